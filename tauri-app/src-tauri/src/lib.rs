@@ -1,5 +1,6 @@
 mod error;
 
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
 use serde::{Deserialize, Serialize};
@@ -9,13 +10,21 @@ use tracing::{debug, error, info, warn};
 use weiback::builder::CoreBuilder;
 use weiback::config::{Config, get_config};
 use weiback::core::{
-    BackupFavoritesOptions, BackupUserPostsOptions, CleanupInvalidPostsOptions, Core,
-    DeletePostOptions, ExportJobOptions, PostQuery, TaskEventListener, TaskRequest,
+    BackupFavoritesOptions, BackupUserPostsOptions, CleanupInvalidPostsOptions, CleanupOptions,
+    CollectGarbageOptions, Core, DeletePostOptions, ExportBundleOptions, ExportJobOptions,
+    FillCoverageGapsOptions, ImportBundleOptions, MaintainDatabaseOptions, MapExportOptions,
+    MentionCount, MergeArchiveOptions, PostQuery, RelocateStorageOptions, ResolveLinksOptions,
+    RestoreQuarantineOptions, StatsExportOptions, TaskEventListener, TaskRequest, TopicCount,
+    VerifyArchiveOptions,
     task::{BackupType, CleanupPicturesOptions, PaginatedPostInfo},
     task_manager::{Task, TaskError},
 };
+use weiback::coverage::UserCoverage;
 use weiback::media_downloader::{DownloaderStatus, MediaDownloaderStatusListener};
 use weiback::models::User;
+use weiback::quarantine::QuarantineBatch;
+use weiback::stats::ArchiveStats;
+use weiback::storage::database::DatabaseBackup;
 
 use error::{Error, Result};
 
@@ -110,6 +119,8 @@ fn perform_init_backend(app_handle: &AppHandle, state: &BackendState) -> Backend
 
             let core_clone = core.clone();
             tauri::async_runtime::spawn(async move { core_clone.login_with_session().await });
+            let core_clone = core.clone();
+            tauri::async_runtime::spawn(async move { core_clone.run_maintenance_schedule().await });
 
             app_handle.manage(core);
             *status_guard = BackendStatus::Running { warning };
@@ -235,6 +246,12 @@ async fn export_posts(core: State<'_, Arc<Core>>, options: ExportJobOptions) ->
     Ok(core.export_posts(TaskRequest::Export(options)).await?)
 }
 
+#[tauri::command]
+async fn export_map(core: State<'_, Arc<Core>>, options: MapExportOptions) -> Result<()> {
+    info!("export_map called with options: {options:?}");
+    Ok(core.export_map(options).await?)
+}
+
 #[tauri::command]
 async fn query_local_posts(
     core: State<'_, Arc<Core>>,
@@ -290,6 +307,22 @@ async fn rebackup_missing_images(core: State<'_, Arc<Core>>, query: PostQuery) -
         .await?)
 }
 
+#[tauri::command]
+async fn upgrade_pictures(core: State<'_, Arc<Core>>, query: PostQuery) -> Result<()> {
+    info!("upgrade_pictures called with query: {query:?}");
+    Ok(core
+        .upgrade_pictures(TaskRequest::UpgradePictures(query))
+        .await?)
+}
+
+#[tauri::command]
+async fn reprocess_posts(core: State<'_, Arc<Core>>, query: PostQuery) -> Result<()> {
+    info!("reprocess_posts called with query: {query:?}");
+    Ok(core
+        .reprocess_posts(TaskRequest::ReprocessPosts(query))
+        .await?)
+}
+
 #[tauri::command]
 async fn get_username_by_id(core: State<'_, Arc<Core>>, uid: WeiboId) -> Result<Option<String>> {
     core.get_username_by_id(uid.into())
@@ -320,9 +353,12 @@ async fn cleanup_pictures(
 }
 
 #[tauri::command]
-async fn cleanup_outdated_avatars(core: State<'_, Arc<Core>>) -> Result<()> {
-    info!("cleanup_invalid_avatars called");
-    Ok(core.cleanup_outdated_avatars().await?)
+async fn cleanup_outdated_avatars(
+    core: State<'_, Arc<Core>>,
+    options: CleanupOptions,
+) -> Result<()> {
+    info!("cleanup_invalid_avatars called with options: {options:?}");
+    Ok(core.cleanup_outdated_avatars(options).await?)
 }
 
 #[tauri::command]
@@ -337,13 +373,181 @@ async fn cleanup_invalid_posts(
 }
 
 #[tauri::command]
-async fn cleanup_invalid_pictures(core: State<'_, Arc<Core>>) -> Result<()> {
-    info!("cleanup_invalid_pictures called");
+async fn cleanup_invalid_pictures(
+    core: State<'_, Arc<Core>>,
+    options: CleanupOptions,
+) -> Result<()> {
+    info!("cleanup_invalid_pictures called with options: {options:?}");
     Ok(core
-        .cleanup_invalid_pictures(TaskRequest::CleanupInvalidPictures)
+        .cleanup_invalid_pictures(TaskRequest::CleanupInvalidPictures(options))
         .await?)
 }
 
+#[tauri::command]
+async fn list_quarantine_batches(core: State<'_, Arc<Core>>) -> Result<Vec<QuarantineBatch>> {
+    info!("list_quarantine_batches called");
+    Ok(core.list_quarantine_batches().await?)
+}
+
+#[tauri::command]
+async fn restore_quarantine(
+    core: State<'_, Arc<Core>>,
+    options: RestoreQuarantineOptions,
+) -> Result<()> {
+    info!("restore_quarantine called with options: {options:?}");
+    Ok(core.restore_quarantine(options).await?)
+}
+
+#[tauri::command]
+async fn backup_database(core: State<'_, Arc<Core>>) -> Result<DatabaseBackup> {
+    info!("backup_database called");
+    Ok(core.backup_database().await?)
+}
+
+#[tauri::command]
+async fn list_database_backups(core: State<'_, Arc<Core>>) -> Result<Vec<DatabaseBackup>> {
+    info!("list_database_backups called");
+    Ok(core.list_database_backups().await?)
+}
+
+#[tauri::command]
+async fn restore_database(core: State<'_, Arc<Core>>, path: PathBuf) -> Result<i64> {
+    info!("restore_database called with path: {path:?}");
+    Ok(core.restore_database(path).await?)
+}
+
+#[tauri::command]
+async fn rebuild_search_index(core: State<'_, Arc<Core>>) -> Result<()> {
+    info!("rebuild_search_index called");
+    Ok(core.rebuild_search_index().await?)
+}
+
+#[tauri::command]
+async fn get_script_folding(core: State<'_, Arc<Core>>) -> Result<bool> {
+    info!("get_script_folding called");
+    Ok(core.get_script_folding().await?)
+}
+
+#[tauri::command]
+async fn set_script_folding(core: State<'_, Arc<Core>>, enabled: bool) -> Result<()> {
+    info!("set_script_folding called with enabled: {enabled}");
+    Ok(core.set_script_folding(enabled).await?)
+}
+
+#[tauri::command]
+async fn reindex_mentions_and_topics(core: State<'_, Arc<Core>>) -> Result<()> {
+    info!("reindex_mentions_and_topics called");
+    Ok(core.reindex_mentions_and_topics().await?)
+}
+
+#[tauri::command]
+async fn get_top_mentions(
+    core: State<'_, Arc<Core>>,
+    uid: Option<WeiboId>,
+    limit: u64,
+) -> Result<Vec<MentionCount>> {
+    info!("get_top_mentions called with uid: {uid:?}, limit: {limit}");
+    Ok(core.get_top_mentions(uid.map(Into::into), limit).await?)
+}
+
+#[tauri::command]
+async fn get_top_topics(
+    core: State<'_, Arc<Core>>,
+    uid: Option<WeiboId>,
+    limit: u64,
+) -> Result<Vec<TopicCount>> {
+    info!("get_top_topics called with uid: {uid:?}, limit: {limit}");
+    Ok(core.get_top_topics(uid.map(Into::into), limit).await?)
+}
+
+#[tauri::command]
+async fn get_archive_stats(
+    core: State<'_, Arc<Core>>,
+    uid: Option<WeiboId>,
+) -> Result<ArchiveStats> {
+    info!("get_archive_stats called with uid: {uid:?}");
+    Ok(core.get_archive_stats(uid.map(Into::into)).await?)
+}
+
+#[tauri::command]
+async fn export_archive_stats(
+    core: State<'_, Arc<Core>>,
+    options: StatsExportOptions,
+) -> Result<()> {
+    info!("export_archive_stats called with options: {options:?}");
+    Ok(core.export_archive_stats(options).await?)
+}
+
+#[tauri::command]
+async fn verify_archive(core: State<'_, Arc<Core>>, options: VerifyArchiveOptions) -> Result<()> {
+    info!("verify_archive called with options: {options:?}");
+    Ok(core.verify_archive(options).await?)
+}
+
+#[tauri::command]
+async fn collect_garbage(core: State<'_, Arc<Core>>, options: CollectGarbageOptions) -> Result<()> {
+    info!("collect_garbage called with options: {options:?}");
+    Ok(core.collect_garbage(options).await?)
+}
+
+#[tauri::command]
+async fn merge_archive(core: State<'_, Arc<Core>>, options: MergeArchiveOptions) -> Result<()> {
+    info!("merge_archive called with options: {options:?}");
+    Ok(core.merge_archive(options).await?)
+}
+
+#[tauri::command]
+async fn export_bundle(core: State<'_, Arc<Core>>, options: ExportBundleOptions) -> Result<()> {
+    info!("export_bundle called with options: {options:?}");
+    Ok(core.export_bundle(options).await?)
+}
+
+#[tauri::command]
+async fn import_bundle(core: State<'_, Arc<Core>>, options: ImportBundleOptions) -> Result<()> {
+    info!("import_bundle called with options: {options:?}");
+    Ok(core.import_bundle(options).await?)
+}
+
+#[tauri::command]
+async fn relocate_storage(
+    core: State<'_, Arc<Core>>,
+    options: RelocateStorageOptions,
+) -> Result<()> {
+    info!("relocate_storage called with options: {options:?}");
+    Ok(core.relocate_storage(options).await?)
+}
+
+#[tauri::command]
+async fn maintain_database(
+    core: State<'_, Arc<Core>>,
+    options: MaintainDatabaseOptions,
+) -> Result<()> {
+    info!("maintain_database called with options: {options:?}");
+    Ok(core.maintain_database(options).await?)
+}
+
+#[tauri::command]
+async fn get_backup_coverage(
+    core: State<'_, Arc<Core>>,
+    uid: Option<WeiboId>,
+) -> Result<Vec<UserCoverage>> {
+    info!("get_backup_coverage called with uid: {uid:?}");
+    Ok(core.get_backup_coverage(uid.map(Into::into)).await?)
+}
+
+#[tauri::command]
+async fn fill_coverage_gaps(core: State<'_, Arc<Core>>, uid: WeiboId) -> Result<()> {
+    info!("fill_coverage_gaps called with uid: {uid:?}");
+    let options = FillCoverageGapsOptions { uid: uid.into() };
+    Ok(core.fill_coverage_gaps(options).await?)
+}
+
+#[tauri::command]
+async fn resolve_links(core: State<'_, Arc<Core>>, options: ResolveLinksOptions) -> Result<()> {
+    info!("resolve_links called with options: {options:?}");
+    Ok(core.resolve_links(options).await?)
+}
+
 pub fn run() -> Result<()> {
     info!("Starting application");
 
@@ -359,6 +563,7 @@ pub fn run() -> Result<()> {
             backup_favorites,
             unfavorite_posts,
             export_posts,
+            export_map,
             query_local_posts,
             get_sms_code,
             login,
@@ -373,12 +578,37 @@ pub fn run() -> Result<()> {
             rebackup_post,
             rebackup_posts,
             rebackup_missing_images,
+            upgrade_pictures,
+            reprocess_posts,
             get_current_task_status,
             get_and_clear_task_errors,
             cleanup_pictures,
             cleanup_outdated_avatars,
             cleanup_invalid_posts,
-            cleanup_invalid_pictures
+            cleanup_invalid_pictures,
+            rebuild_search_index,
+            get_script_folding,
+            set_script_folding,
+            reindex_mentions_and_topics,
+            get_top_mentions,
+            get_top_topics,
+            resolve_links,
+            get_archive_stats,
+            export_archive_stats,
+            get_backup_coverage,
+            fill_coverage_gaps,
+            verify_archive,
+            collect_garbage,
+            list_quarantine_batches,
+            restore_quarantine,
+            backup_database,
+            list_database_backups,
+            restore_database,
+            merge_archive,
+            export_bundle,
+            import_bundle,
+            relocate_storage,
+            maintain_database
         ])
         .build(tauri::generate_context!())
         .expect("tauri app build failed")
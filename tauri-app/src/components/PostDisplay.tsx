@@ -602,6 +602,17 @@ const PostDisplay: React.FC<PostDisplayProps> = ({
           sx={{ pb: 0 }}
         />
         <CardContent>
+          {postInfo.snippet && postInfo.snippet.length > 0 && (
+            <Typography
+              variant="body2"
+              color="text.secondary"
+              sx={{ mb: 1, pl: 1, borderLeft: 2, borderColor: 'divider' }}
+            >
+              {postInfo.snippet.map((part, index) =>
+                part.highlighted ? <mark key={index}>{part.text}</mark> : part.text
+              )}
+            </Typography>
+          )}
           <ProcessedText
             text={postInfo.post.text}
             emoji_map={postInfo.emoji_map}
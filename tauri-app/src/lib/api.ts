@@ -6,10 +6,27 @@ import {
   PaginatedPostInfo,
   PostQuery,
   ExportJobOptions,
+  MapExportOptions,
+  ExportBundleOptions,
   BackupType,
-  ResolutionPolicy,
   CleanupInvalidPostsOptions,
+  CleanupOptions,
+  CleanupPicturesOptions,
+  QuarantineBatch,
   DeletePostOptions,
+  MentionCount,
+  TopicCount,
+  ResolveLinksOptions,
+  ArchiveStats,
+  StatsExportOptions,
+  UserCoverage,
+  VerifyArchiveOptions,
+  CollectGarbageOptions,
+  DatabaseBackup,
+  MergeArchiveOptions,
+  ImportBundleOptions,
+  RelocateStorageOptions,
+  MaintainDatabaseOptions,
 } from '../types'
 import { Config } from '../types/config'
 
@@ -39,6 +56,10 @@ export const unfavoritePosts = () => invoke('unfavorite_posts')
 export const rebackupPosts = (query: PostQuery) => invoke('rebackup_posts', { query })
 export const rebackupMissingImages = (query: PostQuery) =>
   invoke('rebackup_missing_images', { query })
+export const upgradePictures = (query: PostQuery) =>
+  invoke('upgrade_pictures', { query })
+export const reprocessPosts = (query: PostQuery) =>
+  invoke('reprocess_posts', { query })
 
 // Posts
 export const queryLocalPosts = (query: PostQuery) =>
@@ -53,16 +74,74 @@ export const searchIdByUsernamePrefix = (prefix: string) =>
 
 // Export
 export const exportPosts = (options: ExportJobOptions) => invoke('export_posts', { options })
+export const exportMap = (options: MapExportOptions) => invoke('export_map', { options })
 
 // Pictures
 export const getPictureBlob = (id: string) => invoke<ArrayBuffer>('get_picture_blob', { id })
 export const getVideoBlob = (url: string) => invoke<ArrayBuffer>('get_video_blob', { url })
-export const cleanupPictures = (policy: ResolutionPolicy) =>
-  invoke('cleanup_pictures', { options: { policy } })
-export const cleanupOutdatedAvatars = () => invoke('cleanup_outdated_avatars')
+export const cleanupPictures = (options: CleanupPicturesOptions) =>
+  invoke('cleanup_pictures', { options })
+export const cleanupOutdatedAvatars = (options: CleanupOptions) =>
+  invoke('cleanup_outdated_avatars', { options })
 export const cleanupInvalidPosts = (options: CleanupInvalidPostsOptions) =>
   invoke('cleanup_invalid_posts', { options })
-export const cleanupInvalidPictures = () => invoke('cleanup_invalid_pictures')
+export const cleanupInvalidPictures = (options: CleanupOptions) =>
+  invoke('cleanup_invalid_pictures', { options })
+
+// Quarantine
+export const listQuarantineBatches = () => invoke<QuarantineBatch[]>('list_quarantine_batches')
+export const restoreQuarantine = (batch: string) =>
+  invoke('restore_quarantine', { options: { batch } })
+
+// Database backups
+export const backupDatabase = () => invoke<DatabaseBackup>('backup_database')
+export const listDatabaseBackups = () => invoke<DatabaseBackup[]>('list_database_backups')
+export const restoreDatabase = (path: string) => invoke<number>('restore_database', { path })
+
+// Search
+export const rebuildSearchIndex = () => invoke('rebuild_search_index')
+export const getScriptFolding = () => invoke<boolean>('get_script_folding')
+export const setScriptFolding = (enabled: boolean) => invoke('set_script_folding', { enabled })
+
+// Mentions & topics
+export const reindexMentionsAndTopics = () => invoke('reindex_mentions_and_topics')
+export const getTopMentions = (limit: number, uid?: string) =>
+  invoke<MentionCount[]>('get_top_mentions', { uid: uid ?? null, limit })
+export const getTopTopics = (limit: number, uid?: string) =>
+  invoke<TopicCount[]>('get_top_topics', { uid: uid ?? null, limit })
+
+// Links
+export const resolveLinks = (options: ResolveLinksOptions) => invoke('resolve_links', { options })
+
+// Stats
+export const getArchiveStats = (uid?: string) =>
+  invoke<ArchiveStats>('get_archive_stats', { uid: uid ?? null })
+export const exportArchiveStats = (options: StatsExportOptions) =>
+  invoke('export_archive_stats', { options })
+
+// Verify
+export const verifyArchive = (options: VerifyArchiveOptions) =>
+  invoke('verify_archive', { options })
+
+// Garbage collection
+export const collectGarbage = (options: CollectGarbageOptions) =>
+  invoke('collect_garbage', { options })
+export const mergeArchive = (options: MergeArchiveOptions) => invoke('merge_archive', { options })
+
+// Bundles
+export const exportBundle = (options: ExportBundleOptions) => invoke('export_bundle', { options })
+export const importBundle = (options: ImportBundleOptions) => invoke('import_bundle', { options })
+
+// Storage
+export const relocateStorage = (options: RelocateStorageOptions) =>
+  invoke('relocate_storage', { options })
+export const maintainDatabase = (options: MaintainDatabaseOptions) =>
+  invoke('maintain_database', { options })
+
+// Coverage
+export const getBackupCoverage = (uid?: string) =>
+  invoke<UserCoverage[]>('get_backup_coverage', { uid: uid ?? null })
+export const fillCoverageGaps = (uid: string) => invoke('fill_coverage_gaps', { uid })
 
 // Config
 export const getConfig = () => invoke<Config>('get_config_command')
@@ -0,0 +1,6 @@
+export interface DatabaseBackup {
+  path: string
+  label: string // why the snapshot was taken, e.g. 'manual', 'pre-migration', 'pre-restore'
+  created_at: number // Unix timestamp
+  size: number // bytes
+}
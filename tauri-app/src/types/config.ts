@@ -26,6 +26,10 @@ export interface Config {
   static_html: boolean
   picture_path: string
   video_path: string
+  quarantine_path: string
+  backup_path: string
+  backups_to_keep: number // 0 keeps all snapshots
+  maintenance_interval_days: number // 0 turns scheduled maintenance off
   sdk_config: SdkConfig
   dev_mode_out_dir?: string
 }
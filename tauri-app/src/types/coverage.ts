@@ -0,0 +1,17 @@
+export interface CoverageGap {
+  start_month: string // YYYY-MM
+  end_month: string
+  months: number
+}
+
+export interface UserCoverage {
+  uid: number
+  screen_name: string | null
+  remote_count: number | null
+  remote_count_at: number | null // Unix timestamp
+  local_count: number
+  first_month: string | null
+  last_month: string | null
+  coverage: number | null // 0 to 1
+  gaps: CoverageGap[]
+}
@@ -2,3 +2,7 @@ export * from './posts'
 export * from './tasks'
 export * from './user'
 export * from './config'
+export * from './stats'
+export * from './coverage'
+export * from './quarantine'
+export * from './backup'
@@ -40,6 +40,13 @@ export interface PostInfo {
   emoji_map: Record<string, string>
   standalone_pics: AttachedImage[]
   inline_map: Record<string, string>
+  // Highlighted fragments of the matched text, only present in search results
+  snippet?: SnippetPart[] | null
+}
+
+export interface SnippetPart {
+  text: string
+  highlighted: boolean
 }
 
 // --- From LocalExport ---
@@ -48,7 +55,9 @@ export interface PaginatedPostInfo {
   total_items: number
 }
 
-export type SearchTerm = { Fuzzy: string } | { Strict: string }
+export type SearchTerm = { Fuzzy: string } | { Strict: string } | { Query: string }
+
+export type SearchMode = 'fuzzy' | 'strict' | 'query'
 
 export interface PostFilter {
   startDate: Date | null
@@ -56,10 +65,31 @@ export interface PostFilter {
   isFavorited: boolean
   reverseOrder: boolean
   searchTerm: string
-  searchMode: 'fuzzy' | 'strict'
+  searchMode: SearchMode
   userInput: User | string | null
+  hasPictures: boolean | null
+  hasVideo: boolean | null
+  hasLivephoto: boolean | null
+  isRetweet: boolean | null
+  retweetedUserId: string
+  source: string
+  regionName: string
+  userIds: string
+  excludeUserIds: string
+  mentioned: string
+  topic: string
+  hasLocation: boolean | null
+  place: string
+  sortBy: PostSortBy
 }
 
+export type PostSortBy =
+  | 'CreatedAt'
+  | 'AttitudesCount'
+  | 'CommentsCount'
+  | 'RepostsCount'
+  | 'Relevance'
+
 export interface PostQuery {
   user_id?: number
   start_date?: number // Unix timestamp
@@ -69,6 +99,38 @@ export interface PostQuery {
   reverse_order: boolean
   page: number
   posts_per_page: number
+  has_pictures?: boolean
+  has_video?: boolean
+  has_livephoto?: boolean
+  is_retweet?: boolean
+  retweeted_user_id?: number
+  source?: string
+  region_name?: string
+  user_ids?: number[]
+  exclude_user_ids?: number[]
+  mentioned?: string
+  topic?: string
+  has_location?: boolean
+  bbox?: BoundingBox
+  place?: string
+  sort_by?: PostSortBy
+}
+
+export interface BoundingBox {
+  min_latitude: number
+  min_longitude: number
+  max_latitude: number
+  max_longitude: number
+}
+
+export interface MentionCount {
+  screen_name: string
+  count: number
+}
+
+export interface TopicCount {
+  topic: string
+  count: number
 }
 
 export interface ExportOutputConfig {
@@ -80,3 +142,16 @@ export interface ExportJobOptions {
   query: PostQuery
   output: ExportOutputConfig
 }
+
+export type MapFormat = 'GeoJson' | 'Kml'
+
+export interface MapExportOptions {
+  query: PostQuery
+  format: MapFormat
+  output: ExportOutputConfig
+}
+
+export interface ExportBundleOptions {
+  query: PostQuery
+  output: ExportOutputConfig
+}
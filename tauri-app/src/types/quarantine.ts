@@ -0,0 +1,7 @@
+export interface QuarantineBatch {
+  batch: string // also the name of its folder under the quarantine path
+  task: string
+  created_at: number // Unix timestamp
+  restored_at: number | null // Unix timestamp
+  rows: number
+}
@@ -0,0 +1,50 @@
+import { ExportOutputConfig, TopicCount } from './posts'
+
+export interface MonthlyPostCount {
+  uid: number
+  screen_name: string | null
+  month: string // YYYY-MM
+  originals: number
+  retweets: number
+}
+
+export interface UserMediaStats {
+  uid: number
+  screen_name: string | null
+  pictures: number
+  picture_bytes: number
+  videos: number
+  video_bytes: number
+}
+
+export interface SourceCount {
+  source: string
+  count: number
+}
+
+export interface HourlyPostCount {
+  weekday: number // 0 is Sunday
+  hour: number
+  count: number
+}
+
+export interface ArchiveStats {
+  uid: number | null
+  generated_at: number // Unix timestamp
+  total_posts: number
+  original_posts: number
+  retweet_posts: number
+  monthly_posts: MonthlyPostCount[]
+  user_media: UserMediaStats[]
+  top_topics: TopicCount[]
+  top_sources: SourceCount[]
+  hourly_posts: HourlyPostCount[]
+}
+
+export type StatsFormat = 'Json' | 'Csv'
+
+export interface StatsExportOptions {
+  uid?: number
+  format: StatsFormat
+  output: ExportOutputConfig
+}
@@ -1,3 +1,5 @@
+import { ExportOutputConfig } from './posts'
+
 export enum TaskType {
   BackupUser = 'BackupUser',
   BackupFavorites = 'BackupFavorites',
@@ -9,12 +11,79 @@ export enum TaskType {
   RebackupPosts = 'RebackupPosts',
   RebackupMissingImages = 'RebackupMissingImages',
   CleanupInvalidPictures = 'CleanupInvalidPictures',
+  UpgradePictures = 'UpgradePictures',
+  ReprocessPosts = 'ReprocessPosts',
+  RebuildSearchIndex = 'RebuildSearchIndex',
+  SetScriptFolding = 'SetScriptFolding',
+  ReindexMentionsAndTopics = 'ReindexMentionsAndTopics',
+  ResolveLinks = 'ResolveLinks',
+  ExportMap = 'ExportMap',
+  ExportStats = 'ExportStats',
+  FillCoverageGaps = 'FillCoverageGaps',
+  VerifyArchive = 'VerifyArchive',
+  CollectGarbage = 'CollectGarbage',
+  RestoreQuarantine = 'RestoreQuarantine',
+  MergeArchive = 'MergeArchive',
+  ExportBundle = 'ExportBundle',
+  ImportBundle = 'ImportBundle',
+  RelocateStorage = 'RelocateStorage',
+  MaintainDatabase = 'MaintainDatabase',
+}
+
+// Unless it is a dry run, what a cleanup removes is moved to quarantine
+export interface CleanupOptions {
+  dry_run: boolean
+  output?: ExportOutputConfig
 }
 
-export interface CleanupInvalidPostsOptions {
+export interface CleanupInvalidPostsOptions extends CleanupOptions {
   clean_retweeted_invalid: boolean
 }
 
+export interface RestoreQuarantineOptions {
+  batch: string
+}
+
+export interface ResolveLinksOptions {
+  retry_failed: boolean
+}
+
+export interface VerifyArchiveOptions {
+  repair: boolean
+  output: ExportOutputConfig
+}
+
+export interface MergeArchiveOptions {
+  db_path: string // the weiback.db of the other archive
+  picture_path: string
+  video_path: string
+  output: ExportOutputConfig
+}
+
+export type RelocateMode = 'Move' | 'Copy'
+
+// Unset paths stay where they are; the database is moved on the next start
+export interface RelocateStorageOptions {
+  picture_path?: string
+  video_path?: string
+  db_path?: string
+  mode: RelocateMode
+}
+
+export interface MaintainDatabaseOptions {
+  output?: ExportOutputConfig // the report is only written if set
+}
+
+export interface ImportBundleOptions {
+  path: string // a .weiback bundle file
+  output: ExportOutputConfig
+}
+
+export interface CollectGarbageOptions {
+  dry_run: boolean
+  output: ExportOutputConfig
+}
+
 export interface DeletePostOptions {
   id: string
   deep: boolean
@@ -25,7 +94,7 @@ export enum ResolutionPolicy {
   Lowest = 'Lowest',
 }
 
-export interface CleanupPicturesOptions {
+export interface CleanupPicturesOptions extends CleanupOptions {
   policy: ResolutionPolicy
 }
 
@@ -20,6 +20,7 @@ import {
   Select,
   MenuItem,
   Divider,
+  Autocomplete,
 } from '@mui/material'
 import ExpandMoreIcon from '@mui/icons-material/ExpandMore'
 import { LocalizationProvider, DatePicker } from '@mui/x-date-pickers'
@@ -31,17 +32,83 @@ import {
   User,
   PostQuery,
   ExportJobOptions,
+  MapExportOptions,
+  MapFormat,
+  ExportBundleOptions,
   TaskStatus,
   AttachedImage,
   PostFilter,
+  PostSortBy,
+  SearchMode,
+  SearchTerm,
 } from '../types'
 import PostPreviewModal from '../components/PostPreviewModal'
 import { useTaskStore } from '../stores/taskStore'
 import UserSelector from '../components/UserSelector'
-import { queryLocalPosts, exportPosts, rebackupPosts, rebackupMissingImages } from '../lib/api'
+import {
+  queryLocalPosts,
+  exportPosts,
+  exportMap,
+  exportBundle,
+  rebackupPosts,
+  rebackupMissingImages,
+  upgradePictures,
+  reprocessPosts,
+  getTopMentions,
+  getTopTopics,
+} from '../lib/api'
 import { deepEqual } from '../utils'
 
 const POSTS_PER_PAGE = 12
+const TOP_SUGGESTIONS = 50
+
+const DEFAULT_FILTERS = {
+  startDate: null as Date | null,
+  endDate: null as Date | null,
+  isFavorited: false,
+  reverseOrder: false,
+  searchTerm: '',
+  searchMode: 'fuzzy' as SearchMode,
+  hasPictures: null as boolean | null,
+  hasVideo: null as boolean | null,
+  hasLivephoto: null as boolean | null,
+  isRetweet: null as boolean | null,
+  retweetedUserId: '',
+  source: '',
+  regionName: '',
+  mentioned: '',
+  topic: '',
+  hasLocation: null as boolean | null,
+  place: '',
+  userIds: '',
+  excludeUserIds: '',
+  sortBy: 'CreatedAt' as PostSortBy,
+}
+
+// Parses a comma separated list of uids, ignoring anything that is not a number
+const parseUserIds = (input: string): number[] =>
+  input
+    .split(/[,，\s]+/)
+    .filter(s => /^\d+$/.test(s))
+    .map(s => parseInt(s, 10))
+
+const toTriState = (value: string): boolean | null =>
+  value === 'yes' ? true : value === 'no' ? false : null
+
+const fromTriState = (value: boolean | null): string =>
+  value === true ? 'yes' : value === false ? 'no' : 'any'
+
+const buildSearchTerm = (term: string, mode: SearchMode): SearchTerm | undefined => {
+  if (!term) return undefined
+  switch (mode) {
+    case 'fuzzy':
+      return { Fuzzy: term }
+    case 'strict':
+      return { Strict: term }
+    case 'query':
+      return { Query: term }
+  }
+}
 
 const getUserId = (input: User | string | null): number | undefined => {
   if (!input) return undefined
@@ -70,6 +137,7 @@ const buildQueryFromFilters = (
   }
 
   const userId = getUserId(currentFilters.userInput)
+  const retweetedUserId = currentFilters.retweetedUserId.trim()
 
   return {
     page: isBatchOperation ? 1 : currentPage,
@@ -79,11 +147,21 @@ const buildQueryFromFilters = (
     user_id: userId,
     start_date: startDate ? Math.floor(startDate.getTime() / 1000) : undefined,
     end_date: endDate ? Math.floor(endDate.getTime() / 1000) : undefined,
-    search_term: currentFilters.searchTerm
-      ? currentFilters.searchMode === 'fuzzy'
-        ? { Fuzzy: currentFilters.searchTerm }
-        : { Strict: currentFilters.searchTerm }
-      : undefined,
+    search_term: buildSearchTerm(currentFilters.searchTerm, currentFilters.searchMode),
+    has_pictures: currentFilters.hasPictures ?? undefined,
+    has_video: currentFilters.hasVideo ?? undefined,
+    has_livephoto: currentFilters.hasLivephoto ?? undefined,
+    is_retweet: currentFilters.isRetweet ?? undefined,
+    retweeted_user_id: /^\d+$/.test(retweetedUserId) ? parseInt(retweetedUserId, 10) : undefined,
+    source: currentFilters.source.trim() || undefined,
+    region_name: currentFilters.regionName.trim() || undefined,
+    mentioned: currentFilters.mentioned.trim() || undefined,
+    topic: currentFilters.topic.trim() || undefined,
+    has_location: currentFilters.hasLocation ?? undefined,
+    place: currentFilters.place.trim() || undefined,
+    user_ids: parseUserIds(currentFilters.userIds),
+    exclude_user_ids: parseUserIds(currentFilters.excludeUserIds),
+    sort_by: currentFilters.sortBy,
   }
 }
 
@@ -96,14 +174,7 @@ const ContentExplorerPage: React.FC = () => {
 
   // State for UI controls
   const [userInput, setUserInput] = useState<User | string | null>(null)
-  const [filters, setFilters] = useState({
-    startDate: null as Date | null,
-    endDate: null as Date | null,
-    isFavorited: false,
-    reverseOrder: false,
-    searchTerm: '',
-    searchMode: 'fuzzy' as 'fuzzy' | 'strict',
-  })
+  const [filters, setFilters] = useState(DEFAULT_FILTERS)
   // State to hold the filters that are actually applied
   const [appliedFilters, setAppliedFilters] = useState({ ...filters, userInput })
 
@@ -120,6 +191,19 @@ const ContentExplorerPage: React.FC = () => {
   const [loading, setLoading] = useState(true)
   const [refreshKey, setRefreshKey] = useState(0)
 
+  // Suggestions for the mention and topic filters, most frequent first
+  const [mentionOptions, setMentionOptions] = useState<string[]>([])
+  const [topicOptions, setTopicOptions] = useState<string[]>([])
+
+  useEffect(() => {
+    Promise.all([getTopMentions(TOP_SUGGESTIONS), getTopTopics(TOP_SUGGESTIONS)])
+      .then(([mentions, topics]) => {
+        setMentionOptions(mentions.map(m => m.screen_name))
+        setTopicOptions(topics.map(t => t.topic))
+      })
+      .catch(e => console.error('Failed to load mention and topic suggestions:', e))
+  }, [])
+
   const handleJump = () => {
     const pageNum = parseInt(jumpPage, 10)
     if (!isNaN(pageNum) && pageNum >= 1 && pageNum <= totalPages) {
@@ -186,14 +270,7 @@ const ContentExplorerPage: React.FC = () => {
   }
 
   const handleClearFilters = () => {
-    const clearedFilters = {
-      startDate: null,
-      endDate: null,
-      isFavorited: false,
-      reverseOrder: false,
-      searchTerm: '',
-      searchMode: 'fuzzy' as 'fuzzy' | 'strict',
-    }
+    const clearedFilters = DEFAULT_FILTERS
     const clearedUserInput = null
 
     setUserInput(clearedUserInput)
@@ -240,6 +317,67 @@ const ContentExplorerPage: React.FC = () => {
     }
   }
 
+  const handleExportMap = async (format: MapFormat) => {
+    const selectedPath = await open({
+      directory: true,
+      multiple: false,
+      title: '选择导出目录',
+    })
+
+    if (typeof selectedPath !== 'string' || !selectedPath) {
+      enqueueSnackbar('已取消导出', { variant: 'info' })
+      return
+    }
+
+    try {
+      const query = buildQueryFromFilters(appliedFilters, page, true)
+      const options: MapExportOptions = {
+        query,
+        format,
+        output: {
+          task_name: `weiback_map_${Date.now()}`,
+          export_dir: selectedPath,
+        },
+      }
+
+      await exportMap(options)
+      enqueueSnackbar('地图导出任务已成功启动', { variant: 'success' })
+      fetchCurrentTask()
+    } catch (e) {
+      enqueueSnackbar(`启动地图导出任务失败: ${e}`, { variant: 'error' })
+    }
+  }
+
+  const handleExportBundle = async () => {
+    const selectedPath = await open({
+      directory: true,
+      multiple: false,
+      title: '选择导出目录',
+    })
+
+    if (typeof selectedPath !== 'string' || !selectedPath) {
+      enqueueSnackbar('已取消导出', { variant: 'info' })
+      return
+    }
+
+    try {
+      const query = buildQueryFromFilters(appliedFilters, page, true)
+      const options: ExportBundleOptions = {
+        query,
+        output: {
+          task_name: `weiback_bundle_${Date.now()}`,
+          export_dir: selectedPath,
+        },
+      }
+
+      await exportBundle(options)
+      enqueueSnackbar('归档包导出任务已成功启动', { variant: 'success' })
+      fetchCurrentTask()
+    } catch (e) {
+      enqueueSnackbar(`启动归档包导出任务失败: ${e}`, { variant: 'error' })
+    }
+  }
+
   const handleRebackup = async () => {
     try {
       const query = buildQueryFromFilters(appliedFilters, page, true)
@@ -262,6 +400,28 @@ const ContentExplorerPage: React.FC = () => {
     }
   }
 
+  const handleUpgradePictures = async () => {
+    try {
+      const query = buildQueryFromFilters(appliedFilters, page, true)
+      await upgradePictures(query)
+      enqueueSnackbar('升级图片清晰度任务已成功启动', { variant: 'success' })
+      fetchCurrentTask()
+    } catch (e) {
+      enqueueSnackbar(`启动升级图片清晰度任务失败: ${e}`, { variant: 'error' })
+    }
+  }
+
+  const handleReprocessPosts = async () => {
+    try {
+      const query = buildQueryFromFilters(appliedFilters, page, true)
+      await reprocessPosts(query)
+      enqueueSnackbar('重新解析任务已成功启动', { variant: 'success' })
+      fetchCurrentTask()
+    } catch (e) {
+      enqueueSnackbar(`启动重新解析任务失败: ${e}`, { variant: 'error' })
+    }
+  }
+
   return (
     <LocalizationProvider dateAdapter={AdapterDateFns}>
       <Box sx={{ width: '100%', p: 3 }}>
@@ -288,6 +448,11 @@ const ContentExplorerPage: React.FC = () => {
                     <TextField
                       fullWidth
                       label="搜索正文"
+                      placeholder={
+                        filters.searchMode === 'query'
+                          ? '如 from:123 after:2023-01-01 has:image -广告 "完整短语"'
+                          : undefined
+                      }
                       value={filters.searchTerm}
                       onChange={e =>
                         setFilters(f => ({
@@ -310,7 +475,7 @@ const ContentExplorerPage: React.FC = () => {
                                 onChange={e =>
                                   setFilters(f => ({
                                     ...f,
-                                    searchMode: e.target.value as 'fuzzy' | 'strict',
+                                    searchMode: e.target.value as SearchMode,
                                   }))
                                 }
                                 disableUnderline
@@ -318,6 +483,7 @@ const ContentExplorerPage: React.FC = () => {
                               >
                                 <MenuItem value="fuzzy">模糊</MenuItem>
                                 <MenuItem value="strict">严格</MenuItem>
+                                <MenuItem value="query">高级</MenuItem>
                               </Select>
                             </InputAdornment>
                           ),
@@ -378,6 +544,123 @@ const ContentExplorerPage: React.FC = () => {
                       label="结果逆序"
                     />
                   </Grid>
+                  {(
+                    [
+                      ['hasPictures', '图片', '有图片', '无图片'],
+                      ['hasVideo', '视频', '有视频', '无视频'],
+                      ['hasLivephoto', 'Live Photo', '有 Live Photo', '无 Live Photo'],
+                      ['isRetweet', '类型', '仅转发', '仅原创'],
+                      ['hasLocation', '位置', '有位置', '无位置'],
+                    ] as const
+                  ).map(([key, label, yes, no]) => (
+                    <Grid key={key} size={{ xs: 6, md: 3 }}>
+                      <TextField
+                        select
+                        fullWidth
+                        label={label}
+                        value={fromTriState(filters[key])}
+                        onChange={e =>
+                          setFilters(f => ({ ...f, [key]: toTriState(e.target.value) }))
+                        }
+                      >
+                        <MenuItem value="any">不限</MenuItem>
+                        <MenuItem value="yes">{yes}</MenuItem>
+                        <MenuItem value="no">{no}</MenuItem>
+                      </TextField>
+                    </Grid>
+                  ))}
+                  <Grid size={{ xs: 12, md: 4 }}>
+                    <TextField
+                      fullWidth
+                      label="来源"
+                      placeholder="如 iPhone"
+                      value={filters.source}
+                      onChange={e => setFilters(f => ({ ...f, source: e.target.value }))}
+                    />
+                  </Grid>
+                  <Grid size={{ xs: 12, md: 4 }}>
+                    <TextField
+                      fullWidth
+                      label="发布地区"
+                      placeholder="如 北京"
+                      value={filters.regionName}
+                      onChange={e => setFilters(f => ({ ...f, regionName: e.target.value }))}
+                    />
+                  </Grid>
+                  <Grid size={{ xs: 12, md: 4 }}>
+                    <TextField
+                      fullWidth
+                      label="签到地点"
+                      placeholder="如 三里屯"
+                      value={filters.place}
+                      onChange={e => setFilters(f => ({ ...f, place: e.target.value }))}
+                    />
+                  </Grid>
+                  <Grid size={{ xs: 12, md: 4 }}>
+                    <Autocomplete
+                      freeSolo
+                      options={mentionOptions}
+                      inputValue={filters.mentioned}
+                      onInputChange={(_, value) => setFilters(f => ({ ...f, mentioned: value }))}
+                      renderInput={params => (
+                        <TextField {...params} label="提及用户" placeholder="@ 的用户昵称" />
+                      )}
+                    />
+                  </Grid>
+                  <Grid size={{ xs: 12, md: 4 }}>
+                    <Autocomplete
+                      freeSolo
+                      options={topicOptions}
+                      inputValue={filters.topic}
+                      onInputChange={(_, value) => setFilters(f => ({ ...f, topic: value }))}
+                      renderInput={params => (
+                        <TextField {...params} label="话题" placeholder="# 之间的话题" />
+                      )}
+                    />
+                  </Grid>
+                  <Grid size={{ xs: 12, md: 4 }}>
+                    <TextField
+                      fullWidth
+                      label="被转发者 UID"
+                      value={filters.retweetedUserId}
+                      onChange={e => setFilters(f => ({ ...f, retweetedUserId: e.target.value }))}
+                    />
+                  </Grid>
+                  <Grid size={{ xs: 12, md: 4 }}>
+                    <TextField
+                      fullWidth
+                      label="包含更多用户 UID"
+                      placeholder="多个以逗号分隔"
+                      value={filters.userIds}
+                      onChange={e => setFilters(f => ({ ...f, userIds: e.target.value }))}
+                    />
+                  </Grid>
+                  <Grid size={{ xs: 12, md: 4 }}>
+                    <TextField
+                      fullWidth
+                      label="排除用户 UID"
+                      placeholder="多个以逗号分隔"
+                      value={filters.excludeUserIds}
+                      onChange={e => setFilters(f => ({ ...f, excludeUserIds: e.target.value }))}
+                    />
+                  </Grid>
+                  <Grid size={{ xs: 12, md: 4 }}>
+                    <TextField
+                      select
+                      fullWidth
+                      label="排序"
+                      value={filters.sortBy}
+                      onChange={e =>
+                        setFilters(f => ({ ...f, sortBy: e.target.value as PostSortBy }))
+                      }
+                    >
+                      <MenuItem value="CreatedAt">发布时间</MenuItem>
+                      <MenuItem value="AttitudesCount">点赞数</MenuItem>
+                      <MenuItem value="CommentsCount">评论数</MenuItem>
+                      <MenuItem value="RepostsCount">转发数</MenuItem>
+                      <MenuItem value="Relevance">相关度</MenuItem>
+                    </TextField>
+                  </Grid>
                 </Grid>
                 <Stack direction="row" spacing={2} sx={{ mt: 2 }}>
                   <Button variant="contained" onClick={handleSearch}>
@@ -405,6 +688,30 @@ const ContentExplorerPage: React.FC = () => {
                   >
                     {isTaskRunning ? '任务进行中...' : '导出为 HTML'}
                   </Button>
+                  <Button
+                    variant="contained"
+                    color="secondary"
+                    onClick={() => handleExportMap('GeoJson')}
+                    disabled={isTaskRunning}
+                  >
+                    {isTaskRunning ? '任务进行中...' : '导出地图 (GeoJSON)'}
+                  </Button>
+                  <Button
+                    variant="contained"
+                    color="secondary"
+                    onClick={() => handleExportMap('Kml')}
+                    disabled={isTaskRunning}
+                  >
+                    {isTaskRunning ? '任务进行中...' : '导出地图 (KML)'}
+                  </Button>
+                  <Button
+                    variant="contained"
+                    color="secondary"
+                    onClick={handleExportBundle}
+                    disabled={isTaskRunning}
+                  >
+                    {isTaskRunning ? '任务进行中...' : '导出归档包'}
+                  </Button>
                   <Button
                     variant="contained"
                     color="primary"
@@ -421,6 +728,22 @@ const ContentExplorerPage: React.FC = () => {
                   >
                     {isTaskRunning ? '任务进行中...' : '重新备份缺失图片'}
                   </Button>
+                  <Button
+                    variant="contained"
+                    color="primary"
+                    onClick={handleUpgradePictures}
+                    disabled={isTaskRunning}
+                  >
+                    {isTaskRunning ? '任务进行中...' : '升级图片清晰度'}
+                  </Button>
+                  <Button
+                    variant="contained"
+                    color="primary"
+                    onClick={handleReprocessPosts}
+                    disabled={isTaskRunning}
+                  >
+                    {isTaskRunning ? '任务进行中...' : '从原始数据重新解析'}
+                  </Button>
                 </Stack>
               </Box>
             </Stack>
@@ -1,4 +1,4 @@
-import React, { useState } from 'react'
+import React, { useEffect, useState } from 'react'
 import {
   Box,
   Typography,
@@ -13,17 +13,81 @@ import {
   Alert,
   Grid,
   Checkbox,
+  Switch,
+  Table,
+  TableHead,
+  TableBody,
+  TableRow,
+  TableCell,
+  TableContainer,
 } from '@mui/material'
+import { open, save } from '@tauri-apps/plugin-dialog'
 import { useSnackbar } from 'notistack'
 import { useTaskStore } from '../stores/taskStore'
-import { TaskStatus, ResolutionPolicy } from '../types'
+import {
+  TaskStatus,
+  ResolutionPolicy,
+  StatsFormat,
+  CleanupOptions,
+  QuarantineBatch,
+  DatabaseBackup,
+  RelocateMode,
+} from '../types'
 import {
   cleanupPictures,
   cleanupOutdatedAvatars,
   cleanupInvalidPosts,
   cleanupInvalidPictures,
+  rebuildSearchIndex,
+  getScriptFolding,
+  setScriptFolding,
+  reindexMentionsAndTopics,
+  resolveLinks,
+  exportArchiveStats,
+  verifyArchive,
+  collectGarbage,
+  listQuarantineBatches,
+  restoreQuarantine,
+  backupDatabase,
+  listDatabaseBackups,
+  restoreDatabase,
+  mergeArchive,
+  importBundle,
+  relocateStorage,
+  maintainDatabase,
 } from '../lib/api'
 
+type CleanupKind = 'pictures' | 'avatars' | 'posts' | 'invalidPictures'
+
+const CLEANUP_TASK_NAMES: Record<string, string> = {
+  cleanup_pictures: '图片清晰度去重',
+  cleanup_outdated_avatars: '失效头像清理',
+  cleanup_invalid_posts: '失效微博清理',
+  cleanup_invalid_pictures: '失效图片清理',
+}
+
+const BACKUP_LABELS: Record<string, string> = {
+  manual: '手动备份',
+  'pre-migration': '升级前自动备份',
+  'pre-restore': '恢复前自动备份',
+  'pre-merge': '合并前自动备份',
+}
+
+const DryRunCheckbox: React.FC<{ checked: boolean; onChange: (checked: boolean) => void }> = ({
+  checked,
+  onChange,
+}) => (
+  <Box sx={{ mb: 2 }}>
+    <FormControlLabel
+      control={<Checkbox checked={checked} onChange={e => onChange(e.target.checked)} />}
+      label="仅预览"
+    />
+    <Typography variant="caption" sx={{ display: 'block', color: 'text.secondary', ml: 4 }}>
+      只列出将被移除的内容并保存为 JSON 清单，不做任何改动。
+    </Typography>
+  </Box>
+)
+
 const DataManage: React.FC = () => {
   const { enqueueSnackbar } = useSnackbar()
   const isTaskRunning = useTaskStore(state => state.currentTask?.status === TaskStatus.InProgress)
@@ -31,11 +95,78 @@ const DataManage: React.FC = () => {
 
   const [policy, setPolicy] = useState<ResolutionPolicy>(ResolutionPolicy.Highest)
   const [cleanRetweetedInvalid, setCleanRetweetedInvalid] = useState(false)
+  const [scriptFolding, setScriptFoldingState] = useState(false)
+  const [retryFailedLinks, setRetryFailedLinks] = useState(false)
+  const [statsFormat, setStatsFormat] = useState<StatsFormat>('Json')
+  const [repairArchive, setRepairArchive] = useState(false)
+  const [gcMode, setGcMode] = useState<'list' | 'quarantine'>('list')
+  const [saveMaintenanceReport, setSaveMaintenanceReport] = useState(false)
+  const [relocateMode, setRelocateMode] = useState<RelocateMode>('Move')
+  const [relocateTargets, setRelocateTargets] = useState({
+    pictures: true,
+    videos: true,
+    database: false,
+  })
+  const [dryRuns, setDryRuns] = useState<Record<CleanupKind, boolean>>({
+    pictures: false,
+    avatars: false,
+    posts: false,
+    invalidPictures: false,
+  })
+  const [batches, setBatches] = useState<QuarantineBatch[]>([])
+  const [backups, setBackups] = useState<DatabaseBackup[]>([])
+
+  const setDryRun = (kind: CleanupKind) => (checked: boolean) =>
+    setDryRuns(prev => ({ ...prev, [kind]: checked }))
+
+  const fetchBatches = () => {
+    listQuarantineBatches()
+      .then(setBatches)
+      .catch(e => console.error('Failed to list quarantine batches:', e))
+  }
+
+  const fetchBackups = () => {
+    listDatabaseBackups()
+      .then(setBackups)
+      .catch(e => console.error('Failed to list database backups:', e))
+  }
+
+  useEffect(() => {
+    getScriptFolding()
+      .then(setScriptFoldingState)
+      .catch(e => console.error('Failed to get script folding:', e))
+    fetchBatches()
+    fetchBackups()
+  }, [])
+
+  // A dry run changes nothing, so its list is saved to a folder of the user's choice
+  const getCleanupOptions = async (kind: CleanupKind): Promise<CleanupOptions | null> => {
+    if (!dryRuns[kind]) {
+      return { dry_run: false }
+    }
+    const selectedPath = await open({
+      directory: true,
+      multiple: false,
+      title: '选择清单保存目录',
+    })
+    if (typeof selectedPath !== 'string' || !selectedPath) {
+      enqueueSnackbar('已取消预览', { variant: 'info' })
+      return null
+    }
+    return {
+      dry_run: true,
+      output: { task_name: `weiback_cleanup_${Date.now()}`, export_dir: selectedPath },
+    }
+  }
 
   const handleCleanup = async () => {
+    const options = await getCleanupOptions('pictures')
+    if (!options) return
     try {
-      await cleanupPictures(policy)
-      enqueueSnackbar('图片清理任务已启动', { variant: 'success' })
+      await cleanupPictures({ policy, ...options })
+      enqueueSnackbar(options.dry_run ? '图片清理预览任务已启动' : '图片清理任务已启动', {
+        variant: 'success',
+      })
       fetchCurrentTask()
     } catch (e) {
       enqueueSnackbar(`启动清理任务失败: ${e}`, { variant: 'error' })
@@ -43,9 +174,14 @@ const DataManage: React.FC = () => {
   }
 
   const handleCleanupAvatars = async () => {
+    const options = await getCleanupOptions('avatars')
+    if (!options) return
     try {
-      await cleanupOutdatedAvatars()
-      enqueueSnackbar('失效头像清理任务已启动', { variant: 'success' })
+      await cleanupOutdatedAvatars(options)
+      enqueueSnackbar(
+        options.dry_run ? '失效头像清理预览任务已启动' : '失效头像清理任务已启动',
+        { variant: 'success' }
+      )
       fetchCurrentTask()
     } catch (e) {
       enqueueSnackbar(`启动头像清理失败: ${e}`, { variant: 'error' })
@@ -53,9 +189,14 @@ const DataManage: React.FC = () => {
   }
 
   const handleCleanupInvalidPosts = async () => {
+    const options = await getCleanupOptions('posts')
+    if (!options) return
     try {
-      await cleanupInvalidPosts({ clean_retweeted_invalid: cleanRetweetedInvalid })
-      enqueueSnackbar('失效内容清理任务已启动', { variant: 'success' })
+      await cleanupInvalidPosts({ clean_retweeted_invalid: cleanRetweetedInvalid, ...options })
+      enqueueSnackbar(
+        options.dry_run ? '失效内容清理预览任务已启动' : '失效内容清理任务已启动',
+        { variant: 'success' }
+      )
       fetchCurrentTask()
     } catch (e) {
       enqueueSnackbar(`启动失效内容清理失败: ${e}`, { variant: 'error' })
@@ -63,15 +204,326 @@ const DataManage: React.FC = () => {
   }
 
   const handleCleanupInvalidPictures = async () => {
+    const options = await getCleanupOptions('invalidPictures')
+    if (!options) return
     try {
-      await cleanupInvalidPictures()
-      enqueueSnackbar('失效图片清理任务已启动', { variant: 'success' })
+      await cleanupInvalidPictures(options)
+      enqueueSnackbar(
+        options.dry_run ? '失效图片清理预览任务已启动' : '失效图片清理任务已启动',
+        { variant: 'success' }
+      )
       fetchCurrentTask()
     } catch (e) {
       enqueueSnackbar(`启动失效图片清理失败: ${e}`, { variant: 'error' })
     }
   }
 
+  const handleRestoreQuarantine = async (batch: string) => {
+    try {
+      await restoreQuarantine(batch)
+      enqueueSnackbar('恢复隔离区任务已启动', { variant: 'success' })
+      fetchCurrentTask()
+    } catch (e) {
+      enqueueSnackbar(`启动恢复隔离区失败: ${e}`, { variant: 'error' })
+    }
+  }
+
+  const handleBackupDatabase = async () => {
+    try {
+      const backup = await backupDatabase()
+      enqueueSnackbar(`数据库已备份到 ${backup.path}`, { variant: 'success' })
+      fetchBackups()
+    } catch (e) {
+      enqueueSnackbar(`备份数据库失败: ${e}`, { variant: 'error' })
+    }
+  }
+
+  const handleRestoreDatabase = async (path?: string) => {
+    if (!path) {
+      const selected = await open({
+        multiple: false,
+        title: '选择要恢复的数据库备份',
+        filters: [{ name: 'SQLite', extensions: ['db'] }],
+      })
+      if (typeof selected !== 'string' || !selected) return
+      path = selected
+    }
+    try {
+      await restoreDatabase(path)
+      enqueueSnackbar('备份已通过检查，重启应用后生效。当前数据库已另行备份。', {
+        variant: 'success',
+      })
+      fetchBackups()
+    } catch (e) {
+      enqueueSnackbar(`恢复数据库失败: ${e}`, { variant: 'error' })
+    }
+  }
+
+  const handleRebuildSearchIndex = async () => {
+    try {
+      await rebuildSearchIndex()
+      enqueueSnackbar('重建搜索索引任务已启动', { variant: 'success' })
+      fetchCurrentTask()
+    } catch (e) {
+      enqueueSnackbar(`启动重建搜索索引失败: ${e}`, { variant: 'error' })
+    }
+  }
+
+  const handleReindexMentionsAndTopics = async () => {
+    try {
+      await reindexMentionsAndTopics()
+      enqueueSnackbar('重建提及与话题索引任务已启动', { variant: 'success' })
+      fetchCurrentTask()
+    } catch (e) {
+      enqueueSnackbar(`启动重建提及与话题索引失败: ${e}`, { variant: 'error' })
+    }
+  }
+
+  const handleResolveLinks = async () => {
+    try {
+      await resolveLinks({ retry_failed: retryFailedLinks })
+      enqueueSnackbar('解析短链接任务已启动', { variant: 'success' })
+      fetchCurrentTask()
+    } catch (e) {
+      enqueueSnackbar(`启动解析短链接失败: ${e}`, { variant: 'error' })
+    }
+  }
+
+  const handleExportStats = async () => {
+    const selectedPath = await open({
+      directory: true,
+      multiple: false,
+      title: '选择导出目录',
+    })
+
+    if (typeof selectedPath !== 'string' || !selectedPath) {
+      enqueueSnackbar('已取消导出', { variant: 'info' })
+      return
+    }
+
+    try {
+      await exportArchiveStats({
+        format: statsFormat,
+        output: {
+          task_name: `weiback_stats_${Date.now()}`,
+          export_dir: selectedPath,
+        },
+      })
+      enqueueSnackbar('导出统计报告任务已启动', { variant: 'success' })
+      fetchCurrentTask()
+    } catch (e) {
+      enqueueSnackbar(`启动导出统计报告失败: ${e}`, { variant: 'error' })
+    }
+  }
+
+  const handleVerifyArchive = async () => {
+    const selectedPath = await open({
+      directory: true,
+      multiple: false,
+      title: '选择报告保存目录',
+    })
+
+    if (typeof selectedPath !== 'string' || !selectedPath) {
+      enqueueSnackbar('已取消校验', { variant: 'info' })
+      return
+    }
+
+    try {
+      await verifyArchive({
+        repair: repairArchive,
+        output: {
+          task_name: `weiback_verify_${Date.now()}`,
+          export_dir: selectedPath,
+        },
+      })
+      enqueueSnackbar('校验存档任务已启动', { variant: 'success' })
+      fetchCurrentTask()
+    } catch (e) {
+      enqueueSnackbar(`启动校验存档失败: ${e}`, { variant: 'error' })
+    }
+  }
+
+  const handleCollectGarbage = async () => {
+    const selectedPath = await open({
+      directory: true,
+      multiple: false,
+      title: '选择报告保存目录',
+    })
+
+    if (typeof selectedPath !== 'string' || !selectedPath) {
+      enqueueSnackbar('已取消清理', { variant: 'info' })
+      return
+    }
+
+    try {
+      await collectGarbage({
+        dry_run: gcMode === 'list',
+        output: {
+          task_name: `weiback_gc_${Date.now()}`,
+          export_dir: selectedPath,
+        },
+      })
+      enqueueSnackbar('清理孤立媒体任务已启动', { variant: 'success' })
+      fetchCurrentTask()
+    } catch (e) {
+      enqueueSnackbar(`启动清理孤立媒体失败: ${e}`, { variant: 'error' })
+    }
+  }
+
+  const handleMergeArchive = async () => {
+    const dbPath = await open({
+      multiple: false,
+      title: '选择要合并的 weiback.db',
+      filters: [{ name: 'SQLite', extensions: ['db'] }],
+    })
+    if (typeof dbPath !== 'string' || !dbPath) {
+      enqueueSnackbar('已取消合并', { variant: 'info' })
+      return
+    }
+    const selectDir = async (title: string) => {
+      const selected = await open({ directory: true, multiple: false, title })
+      return typeof selected === 'string' && selected ? selected : null
+    }
+    const picturePath = await selectDir('选择该归档的图片目录')
+    const videoPath = picturePath && (await selectDir('选择该归档的视频目录'))
+    const reportPath = videoPath && (await selectDir('选择合并报告保存目录'))
+    if (!picturePath || !videoPath || !reportPath) {
+      enqueueSnackbar('已取消合并', { variant: 'info' })
+      return
+    }
+
+    try {
+      await mergeArchive({
+        db_path: dbPath,
+        picture_path: picturePath,
+        video_path: videoPath,
+        output: {
+          task_name: `weiback_merge_${Date.now()}`,
+          export_dir: reportPath,
+        },
+      })
+      enqueueSnackbar('合并归档任务已启动', { variant: 'success' })
+      fetchCurrentTask()
+    } catch (e) {
+      enqueueSnackbar(`启动合并归档失败: ${e}`, { variant: 'error' })
+    }
+  }
+
+  const handleImportBundle = async () => {
+    const bundlePath = await open({
+      multiple: false,
+      title: '选择要导入的归档包',
+      filters: [{ name: 'weiback 归档包', extensions: ['weiback'] }],
+    })
+    if (typeof bundlePath !== 'string' || !bundlePath) {
+      enqueueSnackbar('已取消导入', { variant: 'info' })
+      return
+    }
+    const reportPath = await open({
+      directory: true,
+      multiple: false,
+      title: '选择导入报告保存目录',
+    })
+    if (typeof reportPath !== 'string' || !reportPath) {
+      enqueueSnackbar('已取消导入', { variant: 'info' })
+      return
+    }
+
+    try {
+      await importBundle({
+        path: bundlePath,
+        output: {
+          task_name: `weiback_import_${Date.now()}`,
+          export_dir: reportPath,
+        },
+      })
+      enqueueSnackbar('导入归档包任务已启动', { variant: 'success' })
+      fetchCurrentTask()
+    } catch (e) {
+      enqueueSnackbar(`启动导入归档包失败: ${e}`, { variant: 'error' })
+    }
+  }
+
+  const handleRelocateStorage = async () => {
+    const selectDir = async (title: string) => {
+      const selected = await open({ directory: true, multiple: false, title })
+      return typeof selected === 'string' && selected ? selected : undefined
+    }
+    const picturePath = relocateTargets.pictures ? await selectDir('选择新的图片目录') : undefined
+    if (relocateTargets.pictures && !picturePath) {
+      enqueueSnackbar('已取消迁移', { variant: 'info' })
+      return
+    }
+    const videoPath = relocateTargets.videos ? await selectDir('选择新的视频目录') : undefined
+    if (relocateTargets.videos && !videoPath) {
+      enqueueSnackbar('已取消迁移', { variant: 'info' })
+      return
+    }
+    const dbPath = relocateTargets.database
+      ? ((await save({
+          title: '选择新的数据库文件',
+          defaultPath: 'weiback.db',
+          filters: [{ name: 'SQLite', extensions: ['db'] }],
+        })) ?? undefined)
+      : undefined
+    if (relocateTargets.database && !dbPath) {
+      enqueueSnackbar('已取消迁移', { variant: 'info' })
+      return
+    }
+
+    try {
+      await relocateStorage({
+        picture_path: picturePath,
+        video_path: videoPath,
+        db_path: dbPath,
+        mode: relocateMode,
+      })
+      enqueueSnackbar('迁移存储位置任务已启动', { variant: 'success' })
+      fetchCurrentTask()
+    } catch (e) {
+      enqueueSnackbar(`启动迁移存储位置失败: ${e}`, { variant: 'error' })
+    }
+  }
+
+  const handleMaintainDatabase = async () => {
+    let exportDir: string | undefined
+    if (saveMaintenanceReport) {
+      const selectedPath = await open({
+        directory: true,
+        multiple: false,
+        title: '选择报告保存目录',
+      })
+      if (typeof selectedPath !== 'string' || !selectedPath) {
+        enqueueSnackbar('已取消维护', { variant: 'info' })
+        return
+      }
+      exportDir = selectedPath
+    }
+
+    try {
+      await maintainDatabase({
+        output: exportDir
+          ? { task_name: `weiback_maintenance_${Date.now()}`, export_dir: exportDir }
+          : undefined,
+      })
+      enqueueSnackbar('数据库维护任务已启动', { variant: 'success' })
+      fetchCurrentTask()
+    } catch (e) {
+      enqueueSnackbar(`启动数据库维护失败: ${e}`, { variant: 'error' })
+    }
+  }
+
+  const handleScriptFoldingChange = async (enabled: boolean) => {
+    try {
+      await setScriptFolding(enabled)
+      setScriptFoldingState(enabled)
+      enqueueSnackbar(`${enabled ? '开启' : '关闭'}繁简归一搜索任务已启动`, { variant: 'success' })
+      fetchCurrentTask()
+    } catch (e) {
+      enqueueSnackbar(`切换繁简归一搜索失败: ${e}`, { variant: 'error' })
+    }
+  }
+
   return (
     <Box sx={{ p: 3 }}>
       <Typography variant="h4" gutterBottom>
@@ -89,10 +541,12 @@ const DataManage: React.FC = () => {
                 如果同一张微博图片存在多种清晰度（如缩略图和原图），此操作将根据您的选择保留其中一个，并删除多余的文件及数据库记录。
               </Typography>
 
-              <Alert severity="warning" sx={{ mb: 2 }}>
-                此操作不可逆，请在执行前确认备份重要数据。
+              <Alert severity="info" sx={{ mb: 2 }}>
+                移除的文件及数据库记录会移动到隔离区，可在下方“隔离区”中恢复。
               </Alert>
 
+              <DryRunCheckbox checked={dryRuns.pictures} onChange={setDryRun('pictures')} />
+
               <FormControl component="fieldset">
                 <FormLabel component="legend">保留策略</FormLabel>
                 <RadioGroup
@@ -138,9 +592,11 @@ const DataManage: React.FC = () => {
               </Typography>
 
               <Alert severity="info" sx={{ mb: 2 }}>
-                仅清理 user 表中已记录的用户的历史头像。
+                仅清理 user 表中已记录的用户的历史头像。移除的头像会移动到隔离区，可在下方恢复。
               </Alert>
 
+              <DryRunCheckbox checked={dryRuns.avatars} onChange={setDryRun('avatars')} />
+
               <Box sx={{ mt: 3 }}>
                 <Button
                   variant="contained"
@@ -166,10 +622,12 @@ const DataManage: React.FC = () => {
                 清理数据库中的失效微博。这些内容通常由于原作者注销或删除或者不可抗力而无法正常显示。
               </Typography>
 
-              <Alert severity="warning" sx={{ mb: 2 }}>
-                此操作将永久删除失效微博及其关联媒体。
+              <Alert severity="info" sx={{ mb: 2 }}>
+                失效微博及其关联媒体会移动到隔离区，可在下方“隔离区”中恢复。
               </Alert>
 
+              <DryRunCheckbox checked={dryRuns.posts} onChange={setDryRun('posts')} />
+
               <Box sx={{ mb: 2 }}>
                 <FormControlLabel
                   control={
@@ -224,10 +682,15 @@ const DataManage: React.FC = () => {
                 被和谐导致大眼化的图片。
               </Typography>
 
-              <Alert severity="warning" sx={{ mb: 2 }}>
-                此操作将永久删除失效图片文件及其数据库记录。
+              <Alert severity="info" sx={{ mb: 2 }}>
+                识别可能有误，失效图片文件及其数据库记录会移动到隔离区，可在下方“隔离区”中恢复。
               </Alert>
 
+              <DryRunCheckbox
+                checked={dryRuns.invalidPictures}
+                onChange={setDryRun('invalidPictures')}
+              />
+
               <Box sx={{ mt: 3 }}>
                 <Button
                   variant="contained"
@@ -242,6 +705,531 @@ const DataManage: React.FC = () => {
             </CardContent>
           </Card>
         </Grid>
+
+        <Grid size={{ xs: 12, md: 6 }}>
+          <Card>
+            <CardContent>
+              <Typography variant="h6" gutterBottom>
+                重建搜索索引
+              </Typography>
+              <Typography variant="body2" color="text.secondary" sx={{ mb: 2 }}>
+                根据数据库中的微博正文重新生成全文搜索索引。当搜索结果缺失或与实际内容不符时使用。
+              </Typography>
+
+              <Alert severity="info" sx={{ mb: 2 }}>
+                不会修改任何微博数据，微博较多时可能需要一些时间。
+              </Alert>
+
+              <FormControlLabel
+                control={
+                  <Switch
+                    checked={scriptFolding}
+                    onChange={e => handleScriptFoldingChange(e.target.checked)}
+                    disabled={isTaskRunning}
+                  />
+                }
+                label="繁简归一搜索（如搜索“台湾”也能找到“臺灣”，切换后将重建索引）"
+              />
+
+              <Box sx={{ mt: 3 }}>
+                <Button
+                  variant="contained"
+                  color="primary"
+                  fullWidth
+                  onClick={handleRebuildSearchIndex}
+                  disabled={isTaskRunning}
+                >
+                  {isTaskRunning ? '任务进行中...' : '开始重建索引'}
+                </Button>
+              </Box>
+            </CardContent>
+          </Card>
+        </Grid>
+
+        <Grid size={{ xs: 12, md: 6 }}>
+          <Card>
+            <CardContent>
+              <Typography variant="h6" gutterBottom>
+                重建提及与话题索引
+              </Typography>
+              <Typography variant="body2" color="text.secondary" sx={{ mb: 2 }}>
+                从微博正文中重新提取 @提及 与 #话题#，用于按提及用户或话题筛选微博。旧版本备份的微博需执行一次。
+              </Typography>
+
+              <Alert severity="info" sx={{ mb: 2 }}>
+                不会修改任何微博数据，微博较多时可能需要一些时间。
+              </Alert>
+
+              <Box sx={{ mt: 3 }}>
+                <Button
+                  variant="contained"
+                  color="primary"
+                  fullWidth
+                  onClick={handleReindexMentionsAndTopics}
+                  disabled={isTaskRunning}
+                >
+                  {isTaskRunning ? '任务进行中...' : '开始重建索引'}
+                </Button>
+              </Box>
+            </CardContent>
+          </Card>
+        </Grid>
+
+        <Grid size={{ xs: 12, md: 6 }}>
+          <Card>
+            <CardContent>
+              <Typography variant="h6" gutterBottom>
+                解析短链接
+              </Typography>
+              <Typography variant="body2" color="text.secondary" sx={{ mb: 2 }}>
+                访问微博正文中的 t.cn 短链接，记录其最终地址与网页标题。导出的 HTML
+                将直接链接到真实地址，即使短链接日后失效也不受影响。
+              </Typography>
+
+              <Alert severity="info" sx={{ mb: 2 }}>
+                需要联网访问每个短链接，已解析的链接会被跳过。
+              </Alert>
+
+              <FormControlLabel
+                control={
+                  <Checkbox
+                    checked={retryFailedLinks}
+                    onChange={e => setRetryFailedLinks(e.target.checked)}
+                  />
+                }
+                label="重试之前无法访问的链接"
+              />
+
+              <Box sx={{ mt: 3 }}>
+                <Button
+                  variant="contained"
+                  color="primary"
+                  fullWidth
+                  onClick={handleResolveLinks}
+                  disabled={isTaskRunning}
+                >
+                  {isTaskRunning ? '任务进行中...' : '开始解析'}
+                </Button>
+              </Box>
+            </CardContent>
+          </Card>
+        </Grid>
+
+        <Grid size={{ xs: 12, md: 6 }}>
+          <Card>
+            <CardContent>
+              <Typography variant="h6" gutterBottom>
+                导出统计报告
+              </Typography>
+              <Typography variant="body2" color="text.secondary" sx={{ mb: 2 }}>
+                统计存档中每位用户每月的原创与转发数、图片与视频的数量和占用空间、热门话题与发布来源，以及按星期与小时的发博分布，导出后可用于制作图表。
+              </Typography>
+
+              <FormControl component="fieldset">
+                <FormLabel component="legend">导出格式</FormLabel>
+                <RadioGroup
+                  value={statsFormat}
+                  onChange={e => setStatsFormat(e.target.value as StatsFormat)}
+                >
+                  <FormControlLabel value="Json" control={<Radio />} label="JSON (单个文件)" />
+                  <FormControlLabel value="Csv" control={<Radio />} label="CSV (每张表一个文件)" />
+                </RadioGroup>
+              </FormControl>
+
+              <Box sx={{ mt: 3 }}>
+                <Button
+                  variant="contained"
+                  color="primary"
+                  fullWidth
+                  onClick={handleExportStats}
+                  disabled={isTaskRunning}
+                >
+                  {isTaskRunning ? '任务进行中...' : '导出报告'}
+                </Button>
+              </Box>
+            </CardContent>
+          </Card>
+        </Grid>
+
+        <Grid size={{ xs: 12, md: 6 }}>
+          <Card>
+            <CardContent>
+              <Typography variant="h6" gutterBottom>
+                校验存档
+              </Typography>
+              <Typography variant="body2" color="text.secondary" sx={{ mb: 2 }}>
+                检查数据库与图片、视频目录是否一致：文件丢失的媒体记录、没有记录的多余文件、缺少图片的微博、原微博缺失的转发，以及与微博不同步的搜索索引。校验结果保存为
+                JSON 报告。
+              </Typography>
+
+              <FormControlLabel
+                control={
+                  <Checkbox
+                    checked={repairArchive}
+                    onChange={e => setRepairArchive(e.target.checked)}
+                  />
+                }
+                label="自动修复"
+              />
+              <Typography
+                variant="caption"
+                sx={{ display: 'block', color: 'text.secondary', ml: 4 }}
+              >
+                将文件丢失的媒体记录移动到隔离区、重建搜索索引，并联网重新备份缺少图片或原微博的微博。多余文件只报告，不会删除。
+              </Typography>
+
+              <Box sx={{ mt: 3 }}>
+                <Button
+                  variant="contained"
+                  color="primary"
+                  fullWidth
+                  onClick={handleVerifyArchive}
+                  disabled={isTaskRunning}
+                >
+                  {isTaskRunning ? '任务进行中...' : '开始校验'}
+                </Button>
+              </Box>
+            </CardContent>
+          </Card>
+        </Grid>
+
+        <Grid size={{ xs: 12, md: 6 }}>
+          <Card>
+            <CardContent>
+              <Typography variant="h6" gutterBottom>
+                清理孤立媒体文件
+              </Typography>
+              <Typography variant="body2" color="text.secondary" sx={{ mb: 2 }}>
+                删除微博后可能遗留下图片、视频文件及其数据库记录。此操作将找出不再被任何微博引用的媒体，并将清单保存为
+                JSON 报告。建议先仅列出，确认清单后再移动到隔离区。
+              </Typography>
+
+              <Alert severity="info" sx={{ mb: 2 }}>
+                孤立文件及其数据库记录会移动到隔离区，可在下方“隔离区”中恢复。
+              </Alert>
+
+              <FormControl component="fieldset">
+                <FormLabel component="legend">处理方式</FormLabel>
+                <RadioGroup
+                  value={gcMode}
+                  onChange={e => setGcMode(e.target.value as 'list' | 'quarantine')}
+                >
+                  <FormControlLabel value="list" control={<Radio />} label="仅列出 (推荐先执行)" />
+                  <FormControlLabel value="quarantine" control={<Radio />} label="移动到隔离区" />
+                </RadioGroup>
+              </FormControl>
+
+              <Box sx={{ mt: 3 }}>
+                <Button
+                  variant="contained"
+                  color="primary"
+                  fullWidth
+                  onClick={handleCollectGarbage}
+                  disabled={isTaskRunning}
+                >
+                  {isTaskRunning ? '任务进行中...' : '开始清理'}
+                </Button>
+              </Box>
+            </CardContent>
+          </Card>
+        </Grid>
+
+        <Grid size={{ xs: 12, md: 6 }}>
+          <Card>
+            <CardContent>
+              <Typography variant="h6" gutterBottom>
+                合并其他归档
+              </Typography>
+              <Typography variant="body2" color="text.secondary" sx={{ mb: 2 }}>
+                将另一份 weiback 归档（数据库及图片、视频目录）合并到当前归档。两边都有的微博保留更完整的版本：未失效、未删除、编辑次数更多或正文更长者优先。新增的媒体文件会复制过来。
+              </Typography>
+
+              <Alert severity="info" sx={{ mb: 2 }}>
+                合并前会自动备份当前数据库，另一份归档不会被修改。两边不一致的用户与微博会列在 JSON
+                合并报告中。
+              </Alert>
+
+              <Box sx={{ mt: 3 }}>
+                <Button
+                  variant="contained"
+                  color="primary"
+                  fullWidth
+                  onClick={handleMergeArchive}
+                  disabled={isTaskRunning}
+                >
+                  {isTaskRunning ? '任务进行中...' : '选择归档并合并'}
+                </Button>
+              </Box>
+            </CardContent>
+          </Card>
+        </Grid>
+
+        <Grid size={{ xs: 12, md: 6 }}>
+          <Card>
+            <CardContent>
+              <Typography variant="h6" gutterBottom>
+                导入归档包
+              </Typography>
+              <Typography variant="body2" color="text.secondary" sx={{ mb: 2 }}>
+                导入他人在「内容浏览」中导出的 .weiback 归档包。归档包内含所选微博、作者、图片与视频，按合并归档的规则并入当前归档。
+              </Typography>
+
+              <Alert severity="info" sx={{ mb: 2 }}>
+                导入前会自动备份当前数据库。媒体文件写入前会校验 SHA-256，校验失败的文件列在 JSON
+                导入报告中。
+              </Alert>
+
+              <Box sx={{ mt: 3 }}>
+                <Button
+                  variant="contained"
+                  color="primary"
+                  fullWidth
+                  onClick={handleImportBundle}
+                  disabled={isTaskRunning}
+                >
+                  {isTaskRunning ? '任务进行中...' : '选择归档包并导入'}
+                </Button>
+              </Box>
+            </CardContent>
+          </Card>
+        </Grid>
+
+        <Grid size={{ xs: 12, md: 6 }}>
+          <Card>
+            <CardContent>
+              <Typography variant="h6" gutterBottom>
+                迁移存储位置
+              </Typography>
+              <Typography variant="body2" color="text.secondary" sx={{ mb: 2 }}>
+                将图片、视频目录与数据库迁移到新位置，支持跨磁盘。每个文件复制后都会校验 SHA-256，全部就位后才切换设置中的路径；中断后重新执行即可从上次的进度继续。
+              </Typography>
+
+              <Alert severity="info" sx={{ mb: 2 }}>
+                数据库在使用中无法移动，将在下次启动时迁移，请在任务完成后重启应用。
+              </Alert>
+
+              <Box sx={{ mb: 2 }}>
+                {(
+                  [
+                    ['pictures', '图片目录'],
+                    ['videos', '视频目录'],
+                    ['database', '数据库'],
+                  ] as const
+                ).map(([key, label]) => (
+                  <FormControlLabel
+                    key={key}
+                    control={
+                      <Checkbox
+                        checked={relocateTargets[key]}
+                        onChange={e =>
+                          setRelocateTargets(prev => ({ ...prev, [key]: e.target.checked }))
+                        }
+                      />
+                    }
+                    label={label}
+                  />
+                ))}
+              </Box>
+
+              <FormControl component="fieldset">
+                <FormLabel component="legend">迁移方式</FormLabel>
+                <RadioGroup
+                  row
+                  value={relocateMode}
+                  onChange={e => setRelocateMode(e.target.value as RelocateMode)}
+                >
+                  <FormControlLabel value="Move" control={<Radio />} label="移动" />
+                  <FormControlLabel value="Copy" control={<Radio />} label="复制 (保留原文件)" />
+                </RadioGroup>
+              </FormControl>
+
+              <Box sx={{ mt: 3 }}>
+                <Button
+                  variant="contained"
+                  color="primary"
+                  fullWidth
+                  onClick={handleRelocateStorage}
+                  disabled={isTaskRunning || !Object.values(relocateTargets).some(Boolean)}
+                >
+                  {isTaskRunning ? '任务进行中...' : '选择新位置并迁移'}
+                </Button>
+              </Box>
+            </CardContent>
+          </Card>
+        </Grid>
+
+        <Grid size={{ xs: 12, md: 6 }}>
+          <Card>
+            <CardContent>
+              <Typography variant="h6" gutterBottom>
+                数据库维护
+              </Typography>
+              <Typography variant="body2" color="text.secondary" sx={{ mb: 2 }}>
+                检查数据库完整性，更新查询统计信息，合并搜索索引碎片，并压缩数据库文件以回收已删除内容占用的空间。应用启动时会按设置中的间隔自动执行。
+              </Typography>
+
+              <Alert severity="info" sx={{ mb: 2 }}>
+                若完整性检查发现损坏，将停止维护并保持数据库原样，请从数据库备份恢复。
+              </Alert>
+
+              <FormControlLabel
+                control={
+                  <Checkbox
+                    checked={saveMaintenanceReport}
+                    onChange={e => setSaveMaintenanceReport(e.target.checked)}
+                  />
+                }
+                label="保存维护报告"
+              />
+              <Typography
+                variant="caption"
+                sx={{ display: 'block', color: 'text.secondary', ml: 4 }}
+              >
+                将维护前后的数据库大小与完整性检查结果保存为 JSON 报告。
+              </Typography>
+
+              <Box sx={{ mt: 3 }}>
+                <Button
+                  variant="contained"
+                  color="primary"
+                  fullWidth
+                  onClick={handleMaintainDatabase}
+                  disabled={isTaskRunning}
+                >
+                  {isTaskRunning ? '任务进行中...' : '开始维护'}
+                </Button>
+              </Box>
+            </CardContent>
+          </Card>
+        </Grid>
+
+        <Grid size={{ xs: 12 }}>
+          <Card>
+            <CardContent>
+              <Typography variant="h6" gutterBottom>
+                隔离区
+              </Typography>
+              <Typography variant="body2" color="text.secondary" sx={{ mb: 2 }}>
+                清理任务移除的文件与数据库记录按每次清理分批保存在隔离区（可在设置中修改路径）。恢复时，清理后又重新备份的内容以新的为准。
+              </Typography>
+
+              <Button variant="outlined" onClick={fetchBatches} sx={{ mb: 2 }}>
+                刷新
+              </Button>
+
+              {batches.length === 0 ? (
+                <Typography variant="body2" color="text.secondary">
+                  隔离区为空。
+                </Typography>
+              ) : (
+                <TableContainer sx={{ maxHeight: 400 }}>
+                  <Table stickyHeader size="small">
+                    <TableHead>
+                      <TableRow>
+                        <TableCell>清理时间</TableCell>
+                        <TableCell>任务</TableCell>
+                        <TableCell align="right">记录数</TableCell>
+                        <TableCell>文件夹</TableCell>
+                        <TableCell />
+                      </TableRow>
+                    </TableHead>
+                    <TableBody>
+                      {batches.map(b => (
+                        <TableRow key={b.batch}>
+                          <TableCell>{new Date(b.created_at * 1000).toLocaleString()}</TableCell>
+                          <TableCell>{CLEANUP_TASK_NAMES[b.task] ?? b.task}</TableCell>
+                          <TableCell align="right">{b.rows}</TableCell>
+                          <TableCell>{b.batch}</TableCell>
+                          <TableCell>
+                            {b.restored_at ? (
+                              <Typography variant="caption" color="text.secondary">
+                                已于 {new Date(b.restored_at * 1000).toLocaleString()} 恢复
+                              </Typography>
+                            ) : (
+                              <Button
+                                size="small"
+                                onClick={() => handleRestoreQuarantine(b.batch)}
+                                disabled={isTaskRunning}
+                              >
+                                恢复
+                              </Button>
+                            )}
+                          </TableCell>
+                        </TableRow>
+                      ))}
+                    </TableBody>
+                  </Table>
+                </TableContainer>
+              )}
+            </CardContent>
+          </Card>
+        </Grid>
+
+        <Grid size={{ xs: 12 }}>
+          <Card>
+            <CardContent>
+              <Typography variant="h6" gutterBottom>
+                数据库备份
+              </Typography>
+              <Typography variant="body2" color="text.secondary" sx={{ mb: 2 }}>
+                在使用中为数据库创建快照，只保留设置中指定数量的最新快照。升级数据库结构前也会自动创建快照。
+              </Typography>
+
+              <Alert severity="info" sx={{ mb: 2 }}>
+                恢复前会检查备份的完整性与数据库版本，并先备份当前数据库。恢复在重启应用后生效。
+              </Alert>
+
+              <Box sx={{ display: 'flex', gap: 2, mb: 2 }}>
+                <Button variant="contained" onClick={handleBackupDatabase}>
+                  立即备份
+                </Button>
+                <Button variant="outlined" onClick={() => handleRestoreDatabase()}>
+                  从文件恢复
+                </Button>
+                <Button variant="outlined" onClick={fetchBackups}>
+                  刷新
+                </Button>
+              </Box>
+
+              {backups.length === 0 ? (
+                <Typography variant="body2" color="text.secondary">
+                  暂无备份。
+                </Typography>
+              ) : (
+                <TableContainer sx={{ maxHeight: 400 }}>
+                  <Table stickyHeader size="small">
+                    <TableHead>
+                      <TableRow>
+                        <TableCell>备份时间</TableCell>
+                        <TableCell>类型</TableCell>
+                        <TableCell align="right">大小</TableCell>
+                        <TableCell>文件</TableCell>
+                        <TableCell />
+                      </TableRow>
+                    </TableHead>
+                    <TableBody>
+                      {backups.map(b => (
+                        <TableRow key={b.path}>
+                          <TableCell>{new Date(b.created_at * 1000).toLocaleString()}</TableCell>
+                          <TableCell>{BACKUP_LABELS[b.label] ?? b.label}</TableCell>
+                          <TableCell align="right">
+                            {(b.size / 1024 / 1024).toFixed(1)} MB
+                          </TableCell>
+                          <TableCell>{b.path}</TableCell>
+                          <TableCell>
+                            <Button size="small" onClick={() => handleRestoreDatabase(b.path)}>
+                              恢复
+                            </Button>
+                          </TableCell>
+                        </TableRow>
+                      ))}
+                    </TableBody>
+                  </Table>
+                </TableContainer>
+              )}
+            </CardContent>
+          </Card>
+        </Grid>
       </Grid>
     </Box>
   )
@@ -13,12 +13,27 @@ import {
   InputLabel,
   FormControl,
   Grid,
+  Table,
+  TableHead,
+  TableBody,
+  TableRow,
+  TableCell,
+  TableContainer,
 } from '@mui/material'
 import { useTaskStore } from '../stores/taskStore'
 import { useAuthStore } from '../stores/authStore'
-import { User, BackupType, TaskStatus } from '../types'
+import { User, BackupType, TaskStatus, UserCoverage } from '../types'
 import UserSelector from '../components/UserSelector'
-import { getUsernameById, backupUser, backupFavorites, unfavoritePosts } from '../lib/api'
+import {
+  getUsernameById,
+  backupUser,
+  backupFavorites,
+  unfavoritePosts,
+  getBackupCoverage,
+  fillCoverageGaps,
+} from '../lib/api'
+
+const COVERAGE_ROWS = 50
 
 const UserBackupSection: React.FC = () => {
   const { enqueueSnackbar } = useSnackbar()
@@ -189,6 +204,105 @@ const FavoritesBackupSection: React.FC = () => {
   )
 }
 
+const formatCoverage = (coverage: UserCoverage): string =>
+  coverage.coverage === null ? '未知' : `${(coverage.coverage * 100).toFixed(1)}%`
+
+const formatGaps = (coverage: UserCoverage): string => {
+  if (coverage.gaps.length === 0) return '-'
+  const shown = coverage.gaps
+    .slice(0, 3)
+    .map(g => (g.months === 1 ? g.start_month : `${g.start_month} ~ ${g.end_month}`))
+  return coverage.gaps.length > 3 ? `${shown.join(', ')} 等 ${coverage.gaps.length} 处` : shown.join(', ')
+}
+
+const isComplete = (coverage: UserCoverage): boolean =>
+  coverage.remote_count !== null && coverage.local_count >= coverage.remote_count
+
+const CoverageSection: React.FC = () => {
+  const { enqueueSnackbar } = useSnackbar()
+  const [coverages, setCoverages] = useState<UserCoverage[] | null>(null)
+  const [loading, setLoading] = useState(false)
+  const isTaskRunning = useTaskStore(state => state.currentTask?.status === TaskStatus.InProgress)
+  const fetchCurrentTask = useTaskStore(state => state.fetchCurrentTask)
+
+  const handleRefresh = async () => {
+    setLoading(true)
+    try {
+      setCoverages(await getBackupCoverage())
+    } catch (e) {
+      enqueueSnackbar(`获取备份完整度失败: ${e}`, { variant: 'error' })
+    } finally {
+      setLoading(false)
+    }
+  }
+
+  const handleFill = async (uid: number) => {
+    try {
+      await fillCoverageGaps(uid.toString())
+      enqueueSnackbar('补全备份缺口任务已成功启动', { variant: 'success' })
+      fetchCurrentTask()
+    } catch (e) {
+      enqueueSnackbar(`启动补全任务失败: ${e}`, { variant: 'error' })
+    }
+  }
+
+  return (
+    <Card>
+      <CardContent>
+        <Typography variant="h5" component="div" sx={{ mb: 2 }}>
+          备份完整度
+        </Typography>
+        <Typography variant="body2" color="text.secondary" sx={{ mb: 2 }}>
+          对比微博显示的发博数与本地已备份的微博数，并列出有发博记录但本地没有任何微博的月份。发博数取自最近一次备份时的数据。
+        </Typography>
+        <Button variant="outlined" onClick={handleRefresh} disabled={loading} sx={{ mb: 2 }}>
+          {loading ? '统计中...' : coverages ? '刷新' : '检查完整度'}
+        </Button>
+        {coverages && (
+          <TableContainer sx={{ maxHeight: 480 }}>
+            <Table stickyHeader size="small">
+              <TableHead>
+                <TableRow>
+                  <TableCell>用户</TableCell>
+                  <TableCell align="right">微博发博数</TableCell>
+                  <TableCell align="right">本地微博数</TableCell>
+                  <TableCell align="right">完整度</TableCell>
+                  <TableCell>时间跨度</TableCell>
+                  <TableCell>缺口月份</TableCell>
+                  <TableCell />
+                </TableRow>
+              </TableHead>
+              <TableBody>
+                {coverages.slice(0, COVERAGE_ROWS).map(c => (
+                  <TableRow key={c.uid}>
+                    <TableCell>{c.screen_name ?? c.uid}</TableCell>
+                    <TableCell align="right">{c.remote_count ?? '未知'}</TableCell>
+                    <TableCell align="right">{c.local_count}</TableCell>
+                    <TableCell align="right">{formatCoverage(c)}</TableCell>
+                    <TableCell>
+                      {c.first_month ? `${c.first_month} ~ ${c.last_month}` : '-'}
+                    </TableCell>
+                    <TableCell>{formatGaps(c)}</TableCell>
+                    <TableCell>
+                      <Button
+                        size="small"
+                        onClick={() => handleFill(c.uid)}
+                        disabled={isTaskRunning || isComplete(c)}
+                      >
+                        补全
+                      </Button>
+                    </TableCell>
+                  </TableRow>
+                ))}
+              </TableBody>
+            </Table>
+          </TableContainer>
+        )}
+      </CardContent>
+    </Card>
+  )
+}
+
 const OnlineBackupPage: React.FC = () => {
   return (
     <Box sx={{ p: 3 }}>
@@ -202,6 +316,9 @@ const OnlineBackupPage: React.FC = () => {
         <Grid size={{ xs: 12, md: 6 }}>
           <FavoritesBackupSection />
         </Grid>
+        <Grid size={{ xs: 12 }}>
+          <CoverageSection />
+        </Grid>
       </Grid>
     </Box>
   )
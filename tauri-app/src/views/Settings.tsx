@@ -66,11 +66,19 @@ const SettingsPage: React.FC = () => {
     setConfigState(initialConfig)
   }
 
-  const handleSelectPath = async (field: 'picture_path' | 'video_path') => {
+  const handleSelectPath = async (
+    field: 'picture_path' | 'video_path' | 'quarantine_path' | 'backup_path'
+  ) => {
+    const names = {
+      picture_path: '图片',
+      video_path: '视频',
+      quarantine_path: '隔离区',
+      backup_path: '数据库备份',
+    }
     const selected = await open({
       directory: true,
       multiple: false,
-      title: `选择${field === 'picture_path' ? '图片' : '视频'}保存路径`,
+      title: `选择${names[field]}保存路径`,
     })
     if (typeof selected === 'string' && config) {
       handleChange(field, selected)
@@ -294,6 +302,7 @@ const SettingsPage: React.FC = () => {
                             <TextField
                               fullWidth
                               label="图片保存路径"
+                              helperText="仅修改路径不会移动已有文件，可在数据维护页面迁移存储位置"
                               value={config.picture_path}
                               slotProps={{
                                 htmlInput: { readOnly: true },
@@ -313,6 +322,7 @@ const SettingsPage: React.FC = () => {
                             <TextField
                               fullWidth
                               label="视频保存路径"
+                              helperText="仅修改路径不会移动已有文件，可在数据维护页面迁移存储位置"
                               value={config.video_path}
                               slotProps={{
                                 htmlInput: { readOnly: true },
@@ -328,6 +338,73 @@ const SettingsPage: React.FC = () => {
                               }}
                             />
                           </Grid>
+                          <Grid size={{ xs: 12 }}>
+                            <TextField
+                              fullWidth
+                              label="隔离区路径"
+                              helperText="清理任务移除的文件会先移动到这里，可在数据维护页面恢复"
+                              value={config.quarantine_path}
+                              slotProps={{
+                                htmlInput: { readOnly: true },
+                                input: {
+                                  endAdornment: (
+                                    <InputAdornment position="end">
+                                      <Button onClick={() => handleSelectPath('quarantine_path')}>
+                                        选择
+                                      </Button>
+                                    </InputAdornment>
+                                  ),
+                                },
+                              }}
+                            />
+                          </Grid>
+                          <Grid size={{ xs: 12, sm: 8 }}>
+                            <TextField
+                              fullWidth
+                              label="数据库备份路径"
+                              helperText="数据库快照保存在这里，升级数据库结构前也会自动备份"
+                              value={config.backup_path}
+                              slotProps={{
+                                htmlInput: { readOnly: true },
+                                input: {
+                                  endAdornment: (
+                                    <InputAdornment position="end">
+                                      <Button onClick={() => handleSelectPath('backup_path')}>
+                                        选择
+                                      </Button>
+                                    </InputAdornment>
+                                  ),
+                                },
+                              }}
+                            />
+                          </Grid>
+                          <Grid size={{ xs: 12, sm: 4 }}>
+                            <TextField
+                              fullWidth
+                              label="保留备份数量"
+                              helperText="0 表示全部保留"
+                              type="number"
+                              value={config.backups_to_keep}
+                              onChange={e =>
+                                handleChange('backups_to_keep', parseInt(e.target.value, 10))
+                              }
+                            />
+                          </Grid>
+                          <Grid size={{ xs: 12, sm: 4 }}>
+                            <TextField
+                              fullWidth
+                              label="数据库维护间隔 (天)"
+                              helperText="启动时按此间隔自动维护数据库，0 表示关闭"
+                              type="number"
+                              value={config.maintenance_interval_days}
+                              onChange={e =>
+                                handleChange(
+                                  'maintenance_interval_days',
+                                  parseInt(e.target.value, 10)
+                                )
+                              }
+                            />
+                          </Grid>
                           {config.dev_mode_out_dir && (
                             <Grid size={{ xs: 12 }}>
                               <TextField
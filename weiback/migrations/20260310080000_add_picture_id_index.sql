@@ -0,0 +1,3 @@
+-- Index picture(id, definition) so that pictures can be looked up
+-- independently of the sinaimg.cn mirror host they were downloaded from
+CREATE INDEX IF NOT EXISTS idx_picture_id_definition ON picture(id, definition);
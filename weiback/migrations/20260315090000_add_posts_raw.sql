@@ -0,0 +1,9 @@
+-- Keep the raw API JSON of every post so that `posts` rows can be
+-- re-derived offline after the models change. `raw` is gzip-compressed JSON.
+CREATE TABLE
+    posts_raw (id INTEGER PRIMARY KEY, raw BLOB NOT NULL);
+
+-- Drop the raw JSON together with its post
+CREATE TRIGGER posts_raw_ad AFTER DELETE ON posts BEGIN
+  DELETE FROM posts_raw WHERE id = old.id;
+END;
@@ -0,0 +1,13 @@
+-- `created_at` keeps the post's own UTC offset, so comparing it as TEXT
+-- is wrong across offsets. Add a normalized Unix timestamp for filtering
+-- and ordering.
+ALTER TABLE posts
+ADD COLUMN created_at_ts INTEGER;
+
+-- Backfill from the RFC 3339 `created_at`; SQLite applies the offset
+UPDATE posts
+SET
+    created_at_ts = CAST(strftime('%s', created_at) AS INTEGER);
+
+-- Ties are ordered by rowid (`id`), which the index covers implicitly
+CREATE INDEX IF NOT EXISTS idx_posts_created_at_ts ON posts(created_at_ts);
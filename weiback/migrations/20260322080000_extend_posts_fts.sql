@@ -0,0 +1,68 @@
+-- Replace the external-content FTS index on posts.text with a self-contained one that
+-- also covers the author's screen name, url_struct titles, the page_info title and
+-- tag_struct names. The index keeps its own copy of the indexed text, so rows can be
+-- removed by rowid without recomputing the derived columns.
+DROP TRIGGER IF EXISTS posts_ai;
+DROP TRIGGER IF EXISTS posts_ad;
+DROP TRIGGER IF EXISTS posts_au;
+DROP TABLE IF EXISTS posts_fts;
+
+CREATE VIRTUAL TABLE posts_fts USING fts5(
+    text,
+    screen_name,
+    url_titles,
+    page_title,
+    tag_names,
+    tokenize='trigram'
+);
+
+-- The indexed columns of each post, shared by the triggers and index rebuilds
+CREATE VIEW posts_fts_source AS
+SELECT
+    p.id AS id,
+    p.text AS text,
+    u.screen_name AS screen_name,
+    (SELECT group_concat(json_extract(value, '$.url_title'), ' ')
+     FROM json_each(p.url_struct)) AS url_titles,
+    json_extract(p.page_info, '$.page_title') AS page_title,
+    (SELECT group_concat(json_extract(value, '$.tag_name'), ' ')
+     FROM json_each(p.tag_struct)) AS tag_names
+FROM posts AS p
+LEFT JOIN users AS u ON u.id = p.uid;
+
+-- Populate the FTS index with existing data
+INSERT INTO posts_fts(rowid, text, screen_name, url_titles, page_title, tag_names)
+SELECT id, text, screen_name, url_titles, page_title, tag_names FROM posts_fts_source;
+
+-- Triggers to keep the FTS index in sync with the posts and users tables
+
+-- 1. After Insert: Add new post to FTS index
+CREATE TRIGGER posts_ai AFTER INSERT ON posts BEGIN
+  INSERT INTO posts_fts(rowid, text, screen_name, url_titles, page_title, tag_names)
+  SELECT id, text, screen_name, url_titles, page_title, tag_names
+  FROM posts_fts_source WHERE id = new.id;
+END;
+
+-- 2. After Delete: Remove deleted post from FTS index
+CREATE TRIGGER posts_ad AFTER DELETE ON posts BEGIN
+  DELETE FROM posts_fts WHERE rowid = old.id;
+END;
+
+-- 3. After Update: Re-index the post when any indexed column changes
+CREATE TRIGGER posts_au AFTER UPDATE OF text, uid, url_struct, page_info, tag_struct ON posts BEGIN
+  DELETE FROM posts_fts WHERE rowid = old.id;
+  INSERT INTO posts_fts(rowid, text, screen_name, url_titles, page_title, tag_names)
+  SELECT id, text, screen_name, url_titles, page_title, tag_names
+  FROM posts_fts_source WHERE id = new.id;
+END;
+
+-- 4. Users may be saved after their posts and may rename themselves
+CREATE TRIGGER users_fts_ai AFTER INSERT ON users BEGIN
+  UPDATE posts_fts SET screen_name = new.screen_name
+  WHERE rowid IN (SELECT id FROM posts WHERE uid = new.id);
+END;
+
+CREATE TRIGGER users_fts_au AFTER UPDATE OF screen_name ON users BEGIN
+  UPDATE posts_fts SET screen_name = new.screen_name
+  WHERE rowid IN (SELECT id FROM posts WHERE uid = new.id);
+END;
@@ -0,0 +1,254 @@
+-- Optionally fold traditional Chinese characters to simplified ones in the search index,
+-- so that e.g. "台湾" also finds "臺灣". The folded text of each post goes into a shadow
+-- `folded` column of posts_fts; the original columns are kept as they are for display,
+-- snippets and non-folded archives. Search terms are folded the same way at query time.
+
+-- Per-archive settings, as key/value pairs
+CREATE TABLE archive_settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+
+-- Traditional characters and the simplified character they fold to
+CREATE TABLE zh_fold_chars (
+    trad TEXT PRIMARY KEY,
+    simp TEXT NOT NULL
+) WITHOUT ROWID;
+
+INSERT INTO zh_fold_chars (trad, simp) VALUES
+    ('並', '并'), ('乾', '干'), ('亂', '乱'), ('亞', '亚'), ('佈', '布'), ('佔', '占'), ('來', '来'), ('侖', '仑'), ('侶', '侣'), ('係', '系'), ('俠', '侠'), ('倆', '俩'),
+    ('倉', '仓'), ('個', '个'), ('們', '们'), ('倖', '幸'), ('倫', '伦'), ('偉', '伟'), ('側', '侧'), ('偵', '侦'), ('偽', '伪'), ('傑', '杰'), ('傘', '伞'), ('備', '备'),
+    ('傢', '家'), ('傭', '佣'), ('傳', '传'), ('債', '债'), ('傷', '伤'), ('傾', '倾'), ('僅', '仅'), ('僑', '侨'), ('僕', '仆'), ('僥', '侥'), ('僱', '雇'), ('價', '价'),
+    ('儀', '仪'), ('億', '亿'), ('儈', '侩'), ('儉', '俭'), ('償', '偿'), ('優', '优'), ('儲', '储'), ('兌', '兑'), ('兒', '儿'), ('兩', '两'), ('冊', '册'), ('凍', '冻'),
+    ('凜', '凛'), ('凱', '凯'), ('別', '别'), ('則', '则'), ('剛', '刚'), ('剮', '剐'), ('創', '创'), ('剷', '铲'), ('劃', '划'), ('劇', '剧'), ('劉', '刘'), ('劊', '刽'),
+    ('劍', '剑'), ('劑', '剂'), ('勁', '劲'), ('動', '动'), ('務', '务'), ('勝', '胜'), ('勞', '劳'), ('勢', '势'), ('勳', '勋'), ('勵', '励'), ('勸', '劝'), ('勻', '匀'),
+    ('匯', '汇'), ('區', '区'), ('協', '协'), ('卻', '却'), ('厭', '厌'), ('厲', '厉'), ('參', '参'), ('叢', '丛'), ('吳', '吴'), ('呂', '吕'), ('員', '员'), ('問', '问'),
+    ('啓', '启'), ('啞', '哑'), ('啟', '启'), ('喚', '唤'), ('喪', '丧'), ('喫', '吃'), ('喬', '乔'), ('單', '单'), ('喲', '哟'), ('嗆', '呛'), ('嗎', '吗'), ('嗚', '呜'),
+    ('嘆', '叹'), ('嘍', '喽'), ('嘔', '呕'), ('嘗', '尝'), ('嘩', '哗'), ('嘯', '啸'), ('嘰', '叽'), ('噁', '恶'), ('噓', '嘘'), ('噴', '喷'), ('噸', '吨'), ('噹', '当'),
+    ('嚇', '吓'), ('嚕', '噜'), ('嚨', '咙'), ('嚮', '向'), ('嚴', '严'), ('囂', '嚣'), ('囉', '啰'), ('囑', '嘱'), ('囪', '囱'), ('國', '国'), ('圍', '围'), ('園', '园'),
+    ('圓', '圆'), ('圖', '图'), ('團', '团'), ('執', '执'), ('堅', '坚'), ('堯', '尧'), ('報', '报'), ('場', '场'), ('塊', '块'), ('塗', '涂'), ('塢', '坞'), ('塵', '尘'),
+    ('塹', '堑'), ('墊', '垫'), ('墜', '坠'), ('墮', '堕'), ('墳', '坟'), ('墾', '垦'), ('壇', '坛'), ('壓', '压'), ('壘', '垒'), ('壞', '坏'), ('壟', '垄'), ('壩', '坝'),
+    ('壯', '壮'), ('壺', '壶'), ('壽', '寿'), ('夠', '够'), ('夢', '梦'), ('夥', '伙'), ('夾', '夹'), ('奪', '夺'), ('奮', '奋'), ('妝', '妆'), ('娛', '娱'), ('婁', '娄'),
+    ('婦', '妇'), ('媽', '妈'), ('嬌', '娇'), ('嬰', '婴'), ('嬸', '婶'), ('孫', '孙'), ('學', '学'), ('孿', '孪'), ('宮', '宫'), ('寢', '寝'), ('實', '实'), ('寧', '宁'),
+    ('審', '审'), ('寫', '写'), ('寬', '宽'), ('寵', '宠'), ('寶', '宝'), ('將', '将'), ('專', '专'), ('尋', '寻'), ('對', '对'), ('導', '导'), ('屆', '届'), ('屍', '尸'),
+    ('屜', '屉'), ('屢', '屡'), ('層', '层'), ('屬', '属'), ('岡', '冈'), ('峯', '峰'), ('島', '岛'), ('峽', '峡'), ('崗', '岗'), ('嶄', '崭'), ('嶺', '岭'), ('嶼', '屿'),
+    ('嶽', '岳'), ('巋', '岿'), ('巒', '峦'), ('帥', '帅'), ('師', '师'), ('帳', '帐'), ('帶', '带'), ('幀', '帧'), ('幟', '帜'), ('幣', '币'), ('幫', '帮'), ('幹', '干'),
+    ('幾', '几'), ('庫', '库'), ('廁', '厕'), ('廂', '厢'), ('廄', '厩'), ('廈', '厦'), ('廚', '厨'), ('廟', '庙'), ('廠', '厂'), ('廢', '废'), ('廣', '广'), ('廬', '庐'),
+    ('廳', '厅'), ('張', '张'), ('強', '强'), ('彈', '弹'), ('彌', '弥'), ('彎', '弯'), ('彙', '汇'), ('彥', '彦'), ('後', '后'), ('徑', '径'), ('從', '从'), ('復', '复'),
+    ('徹', '彻'), ('恆', '恒'), ('恥', '耻'), ('悅', '悦'), ('悶', '闷'), ('悽', '凄'), ('惡', '恶'), ('惱', '恼'), ('愛', '爱'), ('慘', '惨'), ('慚', '惭'), ('慣', '惯'),
+    ('慫', '怂'), ('慮', '虑'), ('慶', '庆'), ('慼', '戚'), ('憂', '忧'), ('憊', '惫'), ('憐', '怜'), ('憑', '凭'), ('憚', '惮'), ('憤', '愤'), ('憫', '悯'), ('憲', '宪'),
+    ('憶', '忆'), ('懇', '恳'), ('應', '应'), ('懞', '蒙'), ('懲', '惩'), ('懶', '懒'), ('懷', '怀'), ('懸', '悬'), ('懼', '惧'), ('懾', '慑'), ('戀', '恋'), ('戰', '战'),
+    ('戲', '戏'), ('戶', '户'), ('挾', '挟'), ('捨', '舍'), ('捲', '卷'), ('掃', '扫'), ('掄', '抡'), ('掙', '挣'), ('揀', '拣'), ('揚', '扬'), ('換', '换'), ('揮', '挥'),
+    ('損', '损'), ('搖', '摇'), ('搗', '捣'), ('搶', '抢'), ('摟', '搂'), ('摯', '挚'), ('摳', '抠'), ('摺', '折'), ('摻', '掺'), ('撈', '捞'), ('撐', '撑'), ('撓', '挠'),
+    ('撚', '捻'), ('撣', '掸'), ('撥', '拨'), ('撫', '抚'), ('撲', '扑'), ('撻', '挞'), ('撾', '挝'), ('撿', '捡'), ('擁', '拥'), ('擄', '掳'), ('擇', '择'), ('擊', '击'),
+    ('擋', '挡'), ('擔', '担'), ('據', '据'), ('擠', '挤'), ('擡', '抬'), ('擬', '拟'), ('擯', '摈'), ('擰', '拧'), ('擱', '搁'), ('擲', '掷'), ('擴', '扩'), ('擺', '摆'),
+    ('擻', '擞'), ('擾', '扰'), ('攆', '撵'), ('攏', '拢'), ('攔', '拦'), ('攙', '搀'), ('攜', '携'), ('攝', '摄'), ('攢', '攒'), ('攣', '挛'), ('攤', '摊'), ('攪', '搅'),
+    ('攬', '揽'), ('攷', '考'), ('敗', '败'), ('敘', '叙'), ('敵', '敌'), ('數', '数'), ('斂', '敛'), ('斃', '毙'), ('斬', '斩'), ('斷', '断'), ('於', '于'), ('時', '时'),
+    ('晉', '晋'), ('晝', '昼'), ('暈', '晕'), ('暢', '畅'), ('暫', '暂'), ('曆', '历'), ('曉', '晓'), ('曠', '旷'), ('曬', '晒'), ('書', '书'), ('會', '会'), ('東', '东'),
+    ('條', '条'), ('棄', '弃'), ('棗', '枣'), ('棟', '栋'), ('棧', '栈'), ('楊', '杨'), ('楓', '枫'), ('業', '业'), ('極', '极'), ('榮', '荣'), ('構', '构'), ('槍', '枪'),
+    ('槳', '桨'), ('樁', '桩'), ('樂', '乐'), ('樓', '楼'), ('標', '标'), ('樞', '枢'), ('樣', '样'), ('樸', '朴'), ('樹', '树'), ('橋', '桥'), ('機', '机'), ('橢', '椭'),
+    ('橫', '横'), ('檔', '档'), ('檢', '检'), ('檯', '台'), ('檸', '柠'), ('檻', '槛'), ('櫃', '柜'), ('櫥', '橱'), ('櫻', '樱'), ('欄', '栏'), ('權', '权'), ('欽', '钦'),
+    ('歐', '欧'), ('歡', '欢'), ('歲', '岁'), ('歷', '历'), ('歸', '归'), ('殘', '残'), ('殲', '歼'), ('殺', '杀'), ('殼', '壳'), ('毀', '毁'), ('毆', '殴'), ('氈', '毡'),
+    ('氣', '气'), ('氫', '氢'), ('決', '决'), ('沒', '没'), ('況', '况'), ('洶', '汹'), ('涼', '凉'), ('淒', '凄'), ('淚', '泪'), ('淨', '净'), ('淪', '沦'), ('淵', '渊'),
+    ('淺', '浅'), ('渙', '涣'), ('減', '减'), ('渦', '涡'), ('測', '测'), ('渾', '浑'), ('湊', '凑'), ('湧', '涌'), ('湯', '汤'), ('準', '准'), ('溝', '沟'), ('溫', '温'),
+    ('溼', '湿'), ('滄', '沧'), ('滅', '灭'), ('滌', '涤'), ('滬', '沪'), ('滯', '滞'), ('滲', '渗'), ('滾', '滚'), ('滿', '满'), ('漁', '渔'), ('漚', '沤'), ('漢', '汉'),
+    ('漣', '涟'), ('漬', '渍'), ('漲', '涨'), ('漸', '渐'), ('漿', '浆'), ('潑', '泼'), ('潔', '洁'), ('潛', '潜'), ('潤', '润'), ('潰', '溃'), ('澀', '涩'), ('澆', '浇'),
+    ('澇', '涝'), ('澗', '涧'), ('澤', '泽'), ('澱', '淀'), ('濁', '浊'), ('濃', '浓'), ('濕', '湿'), ('濘', '泞'), ('濛', '蒙'), ('濟', '济'), ('濤', '涛'), ('濫', '滥'),
+    ('濰', '潍'), ('濱', '滨'), ('濺', '溅'), ('濾', '滤'), ('瀉', '泻'), ('瀏', '浏'), ('瀕', '濒'), ('瀝', '沥'), ('瀾', '澜'), ('灑', '洒'), ('灘', '滩'), ('灣', '湾'),
+    ('灤', '滦'), ('災', '灾'), ('為', '为'), ('烏', '乌'), ('烴', '烃'), ('無', '无'), ('煉', '炼'), ('煙', '烟'), ('煥', '焕'), ('煩', '烦'), ('熒', '荧'), ('熱', '热'),
+    ('熾', '炽'), ('燈', '灯'), ('燒', '烧'), ('燙', '烫'), ('營', '营'), ('燦', '灿'), ('燭', '烛'), ('燴', '烩'), ('燼', '烬'), ('爍', '烁'), ('爐', '炉'), ('爛', '烂'),
+    ('爭', '争'), ('爲', '为'), ('爺', '爷'), ('爾', '尔'), ('牀', '床'), ('牆', '墙'), ('牽', '牵'), ('犢', '犊'), ('犧', '牺'), ('狀', '状'), ('狹', '狭'), ('猙', '狰'),
+    ('猶', '犹'), ('獄', '狱'), ('獅', '狮'), ('獎', '奖'), ('獨', '独'), ('獰', '狞'), ('獲', '获'), ('獵', '猎'), ('獸', '兽'), ('獺', '獭'), ('獻', '献'), ('現', '现'),
+    ('琺', '珐'), ('瑣', '琐'), ('瑤', '瑶'), ('瑩', '莹'), ('瑪', '玛'), ('環', '环'), ('瓊', '琼'), ('甕', '瓮'), ('產', '产'), ('畝', '亩'), ('畢', '毕'), ('畫', '画'),
+    ('異', '异'), ('當', '当'), ('疇', '畴'), ('疊', '叠'), ('痙', '痉'), ('瘋', '疯'), ('瘍', '疡'), ('瘓', '痪'), ('瘡', '疮'), ('瘧', '疟'), ('療', '疗'), ('癟', '瘪'),
+    ('癡', '痴'), ('癢', '痒'), ('癬', '癣'), ('癰', '痈'), ('癱', '瘫'), ('發', '发'), ('皺', '皱'), ('盜', '盗'), ('盞', '盏'), ('盡', '尽'), ('監', '监'), ('盤', '盘'),
+    ('盧', '卢'), ('眾', '众'), ('睜', '睁'), ('瞞', '瞒'), ('瞭', '了'), ('矇', '蒙'), ('矚', '瞩'), ('矯', '矫'), ('硯', '砚'), ('碩', '硕'), ('確', '确'), ('碼', '码'),
+    ('磚', '砖'), ('礎', '础'), ('礙', '碍'), ('礦', '矿'), ('礫', '砾'), ('礬', '矾'), ('祇', '只'), ('祿', '禄'), ('禍', '祸'), ('禦', '御'), ('禮', '礼'), ('禱', '祷'),
+    ('禿', '秃'), ('稅', '税'), ('稈', '秆'), ('種', '种'), ('稱', '称'), ('穀', '谷'), ('積', '积'), ('穎', '颖'), ('穢', '秽'), ('穩', '稳'), ('窩', '窝'), ('窪', '洼'),
+    ('窮', '穷'), ('窯', '窑'), ('窺', '窥'), ('竄', '窜'), ('竅', '窍'), ('竈', '灶'), ('竊', '窃'), ('競', '竞'), ('筆', '笔'), ('筍', '笋'), ('箋', '笺'), ('節', '节'),
+    ('範', '范'), ('築', '筑'), ('篩', '筛'), ('簍', '篓'), ('簡', '简'), ('簽', '签'), ('簾', '帘'), ('籃', '篮'), ('籌', '筹'), ('籠', '笼'), ('籤', '签'), ('籬', '篱'),
+    ('籮', '箩'), ('籲', '吁'), ('粵', '粤'), ('糞', '粪'), ('糧', '粮'), ('糾', '纠'), ('紀', '纪'), ('約', '约'), ('紅', '红'), ('紉', '纫'), ('紋', '纹'), ('納', '纳'),
+    ('紐', '纽'), ('純', '纯'), ('紗', '纱'), ('紙', '纸'), ('級', '级'), ('紛', '纷'), ('紡', '纺'), ('紮', '扎'), ('細', '细'), ('紳', '绅'), ('紹', '绍'), ('終', '终'),
+    ('絃', '弦'), ('組', '组'), ('絆', '绊'), ('結', '结'), ('絕', '绝'), ('絞', '绞'), ('絡', '络'), ('絢', '绚'), ('給', '给'), ('絨', '绒'), ('統', '统'), ('絲', '丝'),
+    ('絹', '绢'), ('綁', '绑'), ('綏', '绥'), ('經', '经'), ('綜', '综'), ('綠', '绿'), ('綢', '绸'), ('綫', '线'), ('維', '维'), ('綱', '纲'), ('網', '网'), ('綴', '缀'),
+    ('綸', '纶'), ('綻', '绽'), ('綽', '绰'), ('綿', '绵'), ('緊', '紧'), ('緒', '绪'), ('緘', '缄'), ('線', '线'), ('緝', '缉'), ('緞', '缎'), ('締', '缔'), ('緣', '缘'),
+    ('編', '编'), ('緩', '缓'), ('緬', '缅'), ('緯', '纬'), ('練', '练'), ('縛', '缚'), ('縣', '县'), ('縧', '绦'), ('縫', '缝'), ('縮', '缩'), ('縱', '纵'), ('縴', '纤'),
+    ('縷', '缕'), ('總', '总'), ('績', '绩'), ('繃', '绷'), ('織', '织'), ('繕', '缮'), ('繞', '绕'), ('繡', '绣'), ('繩', '绳'), ('繪', '绘'), ('繫', '系'), ('繭', '茧'),
+    ('繳', '缴'), ('繹', '绎'), ('繼', '继'), ('續', '续'), ('纏', '缠'), ('纓', '缨'), ('纔', '才'), ('纖', '纤'), ('纘', '缵'), ('纜', '缆'), ('缽', '钵'), ('罈', '坛'),
+    ('罎', '坛'), ('罰', '罚'), ('罵', '骂'), ('罷', '罢'), ('羅', '罗'), ('羣', '群'), ('羨', '羡'), ('義', '义'), ('習', '习'), ('翹', '翘'), ('聖', '圣'), ('聞', '闻'),
+    ('聯', '联'), ('聰', '聪'), ('聲', '声'), ('聳', '耸'), ('聶', '聂'), ('職', '职'), ('聽', '听'), ('聾', '聋'), ('肅', '肃'), ('脅', '胁'), ('脈', '脉'), ('脫', '脱'),
+    ('脹', '胀'), ('腎', '肾'), ('腦', '脑'), ('腫', '肿'), ('腳', '脚'), ('腸', '肠'), ('膚', '肤'), ('膠', '胶'), ('膩', '腻'), ('膽', '胆'), ('膿', '脓'), ('臉', '脸'),
+    ('臍', '脐'), ('臘', '腊'), ('臟', '脏'), ('臥', '卧'), ('臨', '临'), ('臺', '台'), ('與', '与'), ('興', '兴'), ('舉', '举'), ('舊', '旧'), ('艙', '舱'), ('艦', '舰'),
+    ('艱', '艰'), ('茲', '兹'), ('荊', '荆'), ('莊', '庄'), ('莖', '茎'), ('莢', '荚'), ('華', '华'), ('萊', '莱'), ('萬', '万'), ('葉', '叶'), ('著', '着'), ('葦', '苇'),
+    ('葷', '荤'), ('蒐', '搜'), ('蒼', '苍'), ('蓋', '盖'), ('蓮', '莲'), ('蔔', '卜'), ('蔣', '蒋'), ('蔥', '葱'), ('蔭', '荫'), ('蕩', '荡'), ('蕪', '芜'), ('蕭', '萧'),
+    ('薊', '蓟'), ('薔', '蔷'), ('薦', '荐'), ('薩', '萨'), ('藉', '借'), ('藍', '蓝'), ('藝', '艺'), ('藥', '药'), ('蘆', '芦'), ('蘇', '苏'), ('蘊', '蕴'), ('蘋', '苹'),
+    ('蘭', '兰'), ('蘿', '萝'), ('處', '处'), ('虛', '虚'), ('虜', '虏'), ('號', '号'), ('虧', '亏'), ('蛻', '蜕'), ('蝕', '蚀'), ('蝦', '虾'), ('蝸', '蜗'), ('螞', '蚂'),
+    ('螢', '萤'), ('蟄', '蛰'), ('蟬', '蝉'), ('蟲', '虫'), ('蟻', '蚁'), ('蠅', '蝇'), ('蠟', '蜡'), ('蠱', '蛊'), ('蠶', '蚕'), ('蠻', '蛮'), ('衆', '众'), ('術', '术'),
+    ('衛', '卫'), ('衝', '冲'), ('衹', '只'), ('裊', '袅'), ('裏', '里'), ('補', '补'), ('裝', '装'), ('裡', '里'), ('製', '制'), ('複', '复'), ('褲', '裤'), ('襖', '袄'),
+    ('襪', '袜'), ('襯', '衬'), ('襲', '袭'), ('見', '见'), ('規', '规'), ('覓', '觅'), ('視', '视'), ('親', '亲'), ('覺', '觉'), ('覽', '览'), ('觀', '观'), ('觸', '触'),
+    ('訂', '订'), ('訃', '讣'), ('計', '计'), ('訊', '讯'), ('訓', '训'), ('訖', '讫'), ('託', '托'), ('記', '记'), ('訛', '讹'), ('訝', '讶'), ('訟', '讼'), ('訣', '诀'),
+    ('訪', '访'), ('設', '设'), ('許', '许'), ('訴', '诉'), ('診', '诊'), ('詐', '诈'), ('評', '评'), ('詛', '诅'), ('詞', '词'), ('詠', '咏'), ('詢', '询'), ('詣', '诣'),
+    ('試', '试'), ('詩', '诗'), ('詫', '诧'), ('詭', '诡'), ('話', '话'), ('該', '该'), ('詳', '详'), ('誅', '诛'), ('誇', '夸'), ('誌', '志'), ('認', '认'), ('誕', '诞'),
+    ('誘', '诱'), ('語', '语'), ('誠', '诚'), ('誡', '诫'), ('誣', '诬'), ('誤', '误'), ('誦', '诵'), ('誨', '诲'), ('說', '说'), ('説', '说'), ('誰', '谁'), ('課', '课'),
+    ('誹', '诽'), ('誼', '谊'), ('調', '调'), ('諄', '谆'), ('談', '谈'), ('請', '请'), ('諒', '谅'), ('論', '论'), ('諜', '谍'), ('諧', '谐'), ('諱', '讳'), ('諷', '讽'),
+    ('諸', '诸'), ('諺', '谚'), ('諾', '诺'), ('謀', '谋'), ('謂', '谓'), ('謄', '誊'), ('謅', '诌'), ('謊', '谎'), ('謎', '谜'), ('謗', '谤'), ('謙', '谦'), ('講', '讲'),
+    ('謝', '谢'), ('謠', '谣'), ('謬', '谬'), ('謹', '谨'), ('謾', '谩'), ('證', '证'), ('譏', '讥'), ('識', '识'), ('譚', '谭'), ('譜', '谱'), ('譯', '译'), ('議', '议'),
+    ('譴', '谴'), ('護', '护'), ('譽', '誉'), ('讀', '读'), ('變', '变'), ('讒', '谗'), ('讓', '让'), ('讕', '谰'), ('讚', '赞'), ('豈', '岂'), ('豎', '竖'), ('豐', '丰'),
+    ('豔', '艳'), ('豬', '猪'), ('貓', '猫'), ('貝', '贝'), ('貞', '贞'), ('負', '负'), ('財', '财'), ('貢', '贡'), ('貧', '贫'), ('貨', '货'), ('販', '贩'), ('貪', '贪'),
+    ('貫', '贯'), ('責', '责'), ('貯', '贮'), ('貳', '贰'), ('貴', '贵'), ('貶', '贬'), ('買', '买'), ('貸', '贷'), ('費', '费'), ('貼', '贴'), ('貿', '贸'), ('賀', '贺'),
+    ('賂', '赂'), ('賃', '赁'), ('賄', '贿'), ('資', '资'), ('賈', '贾'), ('賊', '贼'), ('賒', '赊'), ('賓', '宾'), ('賜', '赐'), ('賞', '赏'), ('賠', '赔'), ('賢', '贤'),
+    ('賣', '卖'), ('賤', '贱'), ('賦', '赋'), ('質', '质'), ('賬', '账'), ('賭', '赌'), ('賴', '赖'), ('賺', '赚'), ('購', '购'), ('賽', '赛'), ('贅', '赘'), ('贈', '赠'),
+    ('贊', '赞'), ('贍', '赡'), ('贏', '赢'), ('贓', '赃'), ('贖', '赎'), ('贛', '赣'), ('趕', '赶'), ('趙', '赵'), ('趨', '趋'), ('跡', '迹'), ('踐', '践'), ('踴', '踊'),
+    ('蹟', '迹'), ('蹤', '踪'), ('蹧', '糟'), ('躊', '踌'), ('躍', '跃'), ('躥', '蹿'), ('軀', '躯'), ('車', '车'), ('軋', '轧'), ('軌', '轨'), ('軍', '军'), ('軒', '轩'),
+    ('軟', '软'), ('軸', '轴'), ('較', '较'), ('載', '载'), ('輔', '辅'), ('輕', '轻'), ('輛', '辆'), ('輝', '辉'), ('輥', '辊'), ('輩', '辈'), ('輪', '轮'), ('輯', '辑'),
+    ('輸', '输'), ('輻', '辐'), ('輾', '辗'), ('輿', '舆'), ('轄', '辖'), ('轅', '辕'), ('轉', '转'), ('轍', '辙'), ('轎', '轿'), ('轟', '轰'), ('辦', '办'), ('辭', '辞'),
+    ('辮', '辫'), ('辯', '辩'), ('農', '农'), ('這', '这'), ('連', '连'), ('週', '周'), ('進', '进'), ('遊', '游'), ('運', '运'), ('過', '过'), ('達', '达'), ('違', '违'),
+    ('遙', '遥'), ('遜', '逊'), ('遞', '递'), ('遠', '远'), ('適', '适'), ('遲', '迟'), ('遶', '绕'), ('遷', '迁'), ('選', '选'), ('遺', '遗'), ('遼', '辽'), ('邁', '迈'),
+    ('還', '还'), ('邊', '边'), ('邏', '逻'), ('郵', '邮'), ('鄉', '乡'), ('鄒', '邹'), ('鄖', '郧'), ('鄧', '邓'), ('鄭', '郑'), ('鄰', '邻'), ('鄲', '郸'), ('醃', '腌'),
+    ('醜', '丑'), ('醞', '酝'), ('醫', '医'), ('醬', '酱'), ('釀', '酿'), ('釁', '衅'), ('釋', '释'), ('釘', '钉'), ('針', '针'), ('釣', '钓'), ('釩', '钒'), ('鈉', '钠'),
+    ('鈍', '钝'), ('鈔', '钞'), ('鈕', '钮'), ('鈞', '钧'), ('鈣', '钙'), ('鈴', '铃'), ('鈾', '铀'), ('鉀', '钾'), ('鉑', '铂'), ('鉗', '钳'), ('鉚', '铆'), ('鉛', '铅'),
+    ('鉤', '钩'), ('鉸', '铰'), ('鉻', '铬'), ('銀', '银'), ('銅', '铜'), ('銑', '铣'), ('銘', '铭'), ('銜', '衔'), ('銥', '铱'), ('銳', '锐'), ('銷', '销'), ('銻', '锑'),
+    ('鋁', '铝'), ('鋅', '锌'), ('鋇', '钡'), ('鋒', '锋'), ('鋤', '锄'), ('鋪', '铺'), ('鋸', '锯'), ('鋼', '钢'), ('錄', '录'), ('錐', '锥'), ('錘', '锤'), ('錠', '锭'),
+    ('錢', '钱'), ('錦', '锦'), ('錨', '锚'), ('錫', '锡'), ('錯', '错'), ('錳', '锰'), ('鍁', '锨'), ('鍊', '炼'), ('鍋', '锅'), ('鍍', '镀'), ('鍘', '铡'), ('鍛', '锻'),
+    ('鍬', '锹'), ('鍵', '键'), ('鍺', '锗'), ('鍾', '钟'), ('鎂', '镁'), ('鎊', '镑'), ('鎖', '锁'), ('鎢', '钨'), ('鎬', '镐'), ('鎮', '镇'), ('鎳', '镍'), ('鏈', '链'),
+    ('鏟', '铲'), ('鏡', '镜'), ('鏽', '锈'), ('鐐', '镣'), ('鐘', '钟'), ('鐮', '镰'), ('鐳', '镭'), ('鐵', '铁'), ('鑄', '铸'), ('鑑', '鉴'), ('鑒', '鉴'), ('鑪', '炉'),
+    ('鑰', '钥'), ('鑲', '镶'), ('鑷', '镊'), ('鑼', '锣'), ('鑽', '钻'), ('鑿', '凿'), ('長', '长'), ('門', '门'), ('閃', '闪'), ('閉', '闭'), ('開', '开'), ('閏', '闰'),
+    ('閒', '闲'), ('間', '间'), ('閘', '闸'), ('閡', '阂'), ('閣', '阁'), ('閥', '阀'), ('閨', '闺'), ('閩', '闽'), ('閱', '阅'), ('閹', '阉'), ('閻', '阎'), ('闆', '板'),
+    ('闊', '阔'), ('闌', '阑'), ('闔', '阖'), ('闖', '闯'), ('關', '关'), ('闡', '阐'), ('闢', '辟'), ('陝', '陕'), ('陣', '阵'), ('陰', '阴'), ('陳', '陈'), ('陸', '陆'),
+    ('陽', '阳'), ('隊', '队'), ('階', '阶'), ('隕', '陨'), ('際', '际'), ('隨', '随'), ('險', '险'), ('隱', '隐'), ('隴', '陇'), ('隸', '隶'), ('隻', '只'), ('雖', '虽'),
+    ('雙', '双'), ('雛', '雏'), ('雜', '杂'), ('雞', '鸡'), ('離', '离'), ('難', '难'), ('雲', '云'), ('電', '电'), ('霧', '雾'), ('靈', '灵'), ('靜', '静'), ('鞏', '巩'),
+    ('韋', '韦'), ('韌', '韧'), ('韓', '韩'), ('韻', '韵'), ('響', '响'), ('頁', '页'), ('頂', '顶'), ('頃', '顷'), ('項', '项'), ('順', '顺'), ('須', '须'), ('頌', '颂'),
+    ('預', '预'), ('頑', '顽'), ('頒', '颁'), ('頓', '顿'), ('頗', '颇'), ('領', '领'), ('頤', '颐'), ('頭', '头'), ('頰', '颊'), ('頸', '颈'), ('頹', '颓'), ('頻', '频'),
+    ('顆', '颗'), ('題', '题'), ('額', '额'), ('顏', '颜'), ('願', '愿'), ('類', '类'), ('顧', '顾'), ('顫', '颤'), ('顯', '显'), ('顱', '颅'), ('顴', '颧'), ('風', '风'),
+    ('颱', '台'), ('飄', '飘'), ('飛', '飞'), ('飯', '饭'), ('飲', '饮'), ('飼', '饲'), ('飽', '饱'), ('飾', '饰'), ('餃', '饺'), ('餅', '饼'), ('養', '养'), ('餌', '饵'),
+    ('餒', '馁'), ('餓', '饿'), ('餘', '余'), ('餚', '肴'), ('餞', '饯'), ('餡', '馅'), ('館', '馆'), ('餵', '喂'), ('餾', '馏'), ('饅', '馒'), ('饋', '馈'), ('饑', '饥'),
+    ('饒', '饶'), ('饞', '馋'), ('馬', '马'), ('馭', '驭'), ('馮', '冯'), ('馱', '驮'), ('馳', '驰'), ('馴', '驯'), ('駁', '驳'), ('駐', '驻'), ('駒', '驹'), ('駕', '驾'),
+    ('駛', '驶'), ('駝', '驼'), ('駭', '骇'), ('駱', '骆'), ('駿', '骏'), ('騁', '骋'), ('騎', '骑'), ('騙', '骗'), ('騰', '腾'), ('騷', '骚'), ('騾', '骡'), ('驅', '驱'),
+    ('驕', '骄'), ('驗', '验'), ('驚', '惊'), ('驟', '骤'), ('驢', '驴'), ('髒', '脏'), ('體', '体'), ('髮', '发'), ('鬆', '松'), ('鬍', '胡'), ('鬚', '须'), ('鬥', '斗'),
+    ('鬧', '闹'), ('鬱', '郁'), ('魚', '鱼'), ('魯', '鲁'), ('鮑', '鲍'), ('鮮', '鲜'), ('鯉', '鲤'), ('鯨', '鲸'), ('鰓', '鳃'), ('鱉', '鳖'), ('鱗', '鳞'), ('鳥', '鸟'),
+    ('鳳', '凤'), ('鳴', '鸣'), ('鴉', '鸦'), ('鴕', '鸵'), ('鴛', '鸳'), ('鴦', '鸯'), ('鴨', '鸭'), ('鴻', '鸿'), ('鴿', '鸽'), ('鵑', '鹃'), ('鵝', '鹅'), ('鵬', '鹏'),
+    ('鵲', '鹊'), ('鶴', '鹤'), ('鷗', '鸥'), ('鷹', '鹰'), ('鹵', '卤'), ('鹹', '咸'), ('鹼', '碱'), ('鹽', '盐'), ('麗', '丽'), ('麥', '麦'), ('麪', '面'), ('麵', '面'),
+    ('麼', '么'), ('麽', '么'), ('黃', '黄'), ('點', '点'), ('黨', '党'), ('齊', '齐'), ('齋', '斋'), ('齒', '齿'), ('齡', '龄'), ('齣', '出'), ('齧', '啮'), ('齲', '龋'),
+    ('龍', '龙'), ('龐', '庞'), ('龔', '龚'), ('龜', '龟');
+
+-- posts_fts gains the `folded` column, so the index, its source view and the triggers
+-- writing to it are replaced. posts_ad only removes entries and is kept as it is.
+DROP TRIGGER posts_ai;
+DROP TRIGGER posts_au;
+DROP TRIGGER users_fts_ai;
+DROP TRIGGER users_fts_au;
+DROP VIEW posts_fts_source;
+DROP TABLE posts_fts;
+
+CREATE VIRTUAL TABLE posts_fts USING fts5(
+    text,
+    screen_name,
+    url_titles,
+    page_title,
+    tag_names,
+    folded,
+    tokenize='trigram'
+);
+
+-- The indexed columns of each post, shared by the triggers and index rebuilds.
+-- `folded` holds the other columns, one per line, with every character looked up in
+-- zh_fold_chars. It is only filled in when script folding is enabled for the archive.
+-- The characters are split by walking the UTF-8 bytes of the text, reading the width of
+-- each character from its lead byte, so that folding stays linear in the text length.
+CREATE VIEW posts_fts_source AS
+SELECT
+    id,
+    text,
+    screen_name,
+    url_titles,
+    page_title,
+    tag_names,
+    CASE WHEN (SELECT value FROM archive_settings WHERE key = 'script_folding') = 'simplified'
+    THEN (
+        WITH RECURSIVE chars(pos, width) AS (
+            SELECT 1, CASE
+                WHEN substr(bytes, 1, 1) < x'C0' THEN 1
+                WHEN substr(bytes, 1, 1) < x'E0' THEN 2
+                WHEN substr(bytes, 1, 1) < x'F0' THEN 3
+                ELSE 4 END
+            WHERE length(bytes) > 0
+            UNION ALL
+            SELECT pos + width, CASE
+                WHEN substr(bytes, pos + width, 1) < x'C0' THEN 1
+                WHEN substr(bytes, pos + width, 1) < x'E0' THEN 2
+                WHEN substr(bytes, pos + width, 1) < x'F0' THEN 3
+                ELSE 4 END
+            FROM chars WHERE pos + width <= length(bytes)
+        )
+        SELECT group_concat(COALESCE(f.simp, chars.c), '')
+        FROM (
+            SELECT CAST(substr(bytes, pos, width) AS TEXT) AS c FROM chars ORDER BY pos
+        ) AS chars
+        LEFT JOIN zh_fold_chars AS f ON f.trad = chars.c
+    )
+    END AS folded
+FROM (
+    SELECT
+        *,
+        CAST(
+            COALESCE(text, '') || char(10) || COALESCE(screen_name, '') || char(10) ||
+            COALESCE(url_titles, '') || char(10) || COALESCE(page_title, '') || char(10) ||
+            COALESCE(tag_names, '') AS BLOB
+        ) AS bytes
+    FROM (
+        SELECT
+            p.id AS id,
+            p.text AS text,
+            u.screen_name AS screen_name,
+            (SELECT group_concat(json_extract(value, '$.url_title'), ' ')
+             FROM json_each(p.url_struct)) AS url_titles,
+            json_extract(p.page_info, '$.page_title') AS page_title,
+            (SELECT group_concat(json_extract(value, '$.tag_name'), ' ')
+             FROM json_each(p.tag_struct)) AS tag_names
+        FROM posts AS p
+        LEFT JOIN users AS u ON u.id = p.uid
+    )
+);
+
+-- Populate the FTS index with existing data
+INSERT INTO posts_fts(rowid, text, screen_name, url_titles, page_title, tag_names, folded)
+SELECT id, text, screen_name, url_titles, page_title, tag_names, folded FROM posts_fts_source;
+
+-- Triggers to keep the FTS index in sync with the posts and users tables
+
+-- 1. After Insert: Add new post to FTS index
+CREATE TRIGGER posts_ai AFTER INSERT ON posts BEGIN
+  INSERT INTO posts_fts(rowid, text, screen_name, url_titles, page_title, tag_names, folded)
+  SELECT id, text, screen_name, url_titles, page_title, tag_names, folded
+  FROM posts_fts_source WHERE id = new.id;
+END;
+
+-- 2. After Update: Re-index the post when any indexed column changes. Saving a post
+-- sets all of them even when they did not change, so only an actual change re-indexes.
+CREATE TRIGGER posts_au AFTER UPDATE OF text, uid, url_struct, page_info, tag_struct ON posts
+WHEN old.text IS NOT new.text OR old.uid IS NOT new.uid
+  OR old.url_struct IS NOT new.url_struct OR old.page_info IS NOT new.page_info
+  OR old.tag_struct IS NOT new.tag_struct BEGIN
+  DELETE FROM posts_fts WHERE rowid = old.id;
+  INSERT INTO posts_fts(rowid, text, screen_name, url_titles, page_title, tag_names, folded)
+  SELECT id, text, screen_name, url_titles, page_title, tag_names, folded
+  FROM posts_fts_source WHERE id = new.id;
+END;
+
+-- 3. Users may be saved after their posts and may rename themselves. The screen name is
+-- part of the folded column too, so the posts of the user are re-indexed. Saving a user
+-- sets its screen name even when it did not change, so only a rename re-indexes.
+CREATE TRIGGER users_fts_ai AFTER INSERT ON users BEGIN
+  DELETE FROM posts_fts WHERE rowid IN (SELECT id FROM posts WHERE uid = new.id);
+  INSERT INTO posts_fts(rowid, text, screen_name, url_titles, page_title, tag_names, folded)
+  SELECT id, text, screen_name, url_titles, page_title, tag_names, folded
+  FROM posts_fts_source WHERE id IN (SELECT id FROM posts WHERE uid = new.id);
+END;
+
+CREATE TRIGGER users_fts_au AFTER UPDATE OF screen_name ON users
+WHEN old.screen_name IS NOT new.screen_name BEGIN
+  DELETE FROM posts_fts WHERE rowid IN (SELECT id FROM posts WHERE uid = new.id);
+  INSERT INTO posts_fts(rowid, text, screen_name, url_titles, page_title, tag_names, folded)
+  SELECT id, text, screen_name, url_titles, page_title, tag_names, folded
+  FROM posts_fts_source WHERE id IN (SELECT id FROM posts WHERE uid = new.id);
+END;
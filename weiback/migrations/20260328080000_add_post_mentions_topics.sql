@@ -0,0 +1,25 @@
+-- Index the @-mentions and #topics# of every post's own text. Rows are written when a
+-- post is saved; posts saved before this migration are filled in by a backfill task.
+CREATE TABLE
+    post_mentions (
+        post_id INTEGER NOT NULL,
+        screen_name TEXT NOT NULL,
+        PRIMARY KEY (post_id, screen_name)
+    ) WITHOUT ROWID;
+
+CREATE INDEX idx_post_mentions_screen_name ON post_mentions (screen_name);
+
+CREATE TABLE
+    post_topics (
+        post_id INTEGER NOT NULL,
+        topic TEXT NOT NULL,
+        PRIMARY KEY (post_id, topic)
+    ) WITHOUT ROWID;
+
+CREATE INDEX idx_post_topics_topic ON post_topics (topic);
+
+-- Drop the mentions and topics together with their post
+CREATE TRIGGER post_mentions_topics_ad AFTER DELETE ON posts BEGIN
+  DELETE FROM post_mentions WHERE post_id = old.id;
+  DELETE FROM post_topics WHERE post_id = old.id;
+END;
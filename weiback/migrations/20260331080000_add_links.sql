@@ -0,0 +1,11 @@
+-- Where the short links (t.cn) found in post text lead, so the destinations survive the
+-- shortener. Filled in by the resolve links task.
+CREATE TABLE
+    links (
+        short_url TEXT PRIMARY KEY NOT NULL,
+        final_url TEXT,
+        status_code INTEGER,
+        title TEXT,
+        error TEXT,
+        resolved_at INTEGER NOT NULL
+    );
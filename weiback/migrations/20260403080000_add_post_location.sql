@@ -0,0 +1,41 @@
+-- Parse the location of posts out of the `geo` and `tag_struct` JSON into indexed
+-- columns, so posts can be filtered by area or place and put on a map. New posts get
+-- these columns on save; here the existing posts are filled in.
+ALTER TABLE posts ADD COLUMN latitude REAL;
+
+ALTER TABLE posts ADD COLUMN longitude REAL;
+
+ALTER TABLE posts ADD COLUMN place_name TEXT;
+
+-- Weibo puts the latitude first in `geo.coordinates`
+UPDATE posts
+SET
+    latitude = json_extract(geo, '$.coordinates[0]'),
+    longitude = json_extract(geo, '$.coordinates[1]')
+WHERE
+    json_valid(geo)
+    AND json_type(geo, '$.coordinates[0]') IN ('integer', 'real')
+    AND json_type(geo, '$.coordinates[1]') IN ('integer', 'real')
+    AND abs(json_extract(geo, '$.coordinates[0]')) <= 90
+    AND abs(json_extract(geo, '$.coordinates[1]')) <= 180;
+
+UPDATE posts
+SET
+    place_name = (
+        SELECT
+            trim(json_extract(t.value, '$.tag_name'))
+        FROM
+            json_each(posts.tag_struct) AS t
+        WHERE
+            json_extract(t.value, '$.otype') = 'place'
+            AND trim(json_extract(t.value, '$.tag_name')) <> ''
+        LIMIT
+            1
+    )
+WHERE
+    json_valid(tag_struct)
+    AND json_type(tag_struct) = 'array';
+
+CREATE INDEX idx_posts_location ON posts (latitude, longitude);
+
+CREATE INDEX idx_posts_place_name ON posts (place_name);
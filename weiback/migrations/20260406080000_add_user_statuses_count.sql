@@ -0,0 +1,6 @@
+-- The number of posts Weibo reports for each user, as seen in the last API response
+-- carrying the user, so local archives can be checked for completeness.
+ALTER TABLE users ADD COLUMN statuses_count INTEGER;
+
+-- When `statuses_count` was seen, as a Unix timestamp
+ALTER TABLE users ADD COLUMN statuses_count_at INTEGER;
@@ -0,0 +1,20 @@
+-- Cleanup tasks move what they remove to a dated quarantine folder instead of deleting
+-- it, so they can be undone. Every run is a batch named after its folder.
+CREATE TABLE
+    quarantine_batches (
+        batch TEXT PRIMARY KEY NOT NULL,
+        task TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        restored_at INTEGER
+    );
+
+-- The rows removed by a batch, each as a JSON object of its columns. BLOB columns are
+-- stored as hex strings.
+CREATE TABLE
+    quarantine_rows (
+        batch TEXT NOT NULL,
+        table_name TEXT NOT NULL,
+        row_data TEXT NOT NULL
+    );
+
+CREATE INDEX idx_quarantine_rows_batch ON quarantine_rows (batch, table_name);
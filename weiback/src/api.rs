@@ -11,6 +11,7 @@ pub mod profile_statuses;
 pub mod statuses_show;
 
 use async_trait::async_trait;
+use serde_json::Value;
 use tracing::warn;
 use weibosdk_rs::{ApiClient as SdkApiClient, http_client::HttpClient};
 
@@ -105,6 +106,40 @@ impl<C: HttpClient> ApiClientImpl<C> {
     }
 }
 
+/// Re-derives a `Post` from the raw API JSON kept in storage, without any API call.
+///
+/// Long text embedded in the JSON replaces the truncated text, the same way
+/// [`ApiClientImpl::process_post`] does it when online.
+///
+/// # Arguments
+/// * `raw` - The raw JSON of the post.
+/// * `retweeted` - The re-derived retweeted post. If `None`, the retweeted post
+///   embedded in the JSON is used as is.
+///
+/// # Returns
+/// A `Result` containing the re-derived `Post`.
+pub fn post_from_raw(raw: Value, retweeted: Option<Post>) -> Result<Post> {
+    let mut post: PostInternal = serde_json::from_value(raw)?;
+    if let Some(long_text) = post.long_text.take() {
+        post.text = long_text.content;
+    }
+    if let Some(embedded) = post.retweeted_status.as_mut()
+        && let Some(long_text) = embedded.long_text.take()
+    {
+        embedded.text = long_text.content;
+    }
+    let mut post: Post = post.try_into()?;
+    if let Some(retweeted) = retweeted {
+        post.retweeted_status = Some(Box::new(retweeted));
+    }
+    Ok(post)
+}
+
+/// Extracts the ID of the retweeted post from the raw API JSON of a post.
+pub fn raw_retweeted_id(raw: &Value) -> Option<i64> {
+    raw.get("retweeted_status")?.get("id")?.as_i64()
+}
+
 #[async_trait]
 impl<C: HttpClient> ApiClient for ApiClientImpl<C> {}
 
@@ -113,3 +148,34 @@ pub type DefaultApiClient = ApiClientImpl<weibosdk_rs::Client>;
 /// A type alias for `ApiClientImpl` using `crate::dev_client::DevClient` for development mode.
 #[cfg(feature = "dev-mode")]
 pub type DevApiClient = ApiClientImpl<crate::dev_client::DevClient>;
+
+#[cfg(test)]
+mod local_tests {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn test_post_from_raw() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/statuses_show.json");
+        let raw: Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        let long_text = raw["longText"]["content"].as_str().unwrap().to_owned();
+
+        let post = post_from_raw(raw.clone(), None).unwrap();
+        assert_eq!(post.id, raw["id"].as_i64().unwrap());
+        assert_eq!(post.text, long_text);
+        assert!(post.retweeted_status.is_none());
+        assert_eq!(post.raw.as_ref().unwrap()["mblogtype"], raw["mblogtype"]);
+        assert_eq!(raw_retweeted_id(&raw), None);
+
+        let retweeted = Post {
+            id: 42,
+            ..Default::default()
+        };
+        let mut with_retweet = raw;
+        with_retweet["retweeted_status"] = serde_json::json!({"id": 42});
+        assert_eq!(raw_retweeted_id(&with_retweet), Some(42));
+        let post = post_from_raw(with_retweet, Some(retweeted.clone())).unwrap();
+        assert_eq!(post.retweeted_status.as_deref(), Some(&retweeted));
+    }
+}
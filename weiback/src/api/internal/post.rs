@@ -21,7 +21,10 @@ use crate::models::{MixMediaInfo, PicInfoItem, Post, TagStruct};
 /// due to the varied nature of Weibo API responses. It includes custom deserializers
 /// for specific data types and handles nested structures like `LongText`, `PageInfo`,
 /// `UrlStruct`, and `User`.
+///
+/// The raw JSON object the post was parsed from is kept in `raw`.
 #[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(remote = "Self")]
 pub struct PostInternal {
     pub attitudes_count: Option<i64>,
     #[serde(default)]
@@ -60,6 +63,21 @@ pub struct PostInternal {
     pub url_struct: Option<UrlStructInternal>,
     #[serde(default, deserialize_with = "deserialize_user")]
     pub user: Option<UserInternal>,
+    #[serde(skip)]
+    pub raw: Value,
+}
+
+impl<'de> Deserialize<'de> for PostInternal {
+    /// Deserializes the post through a `Value` so that the raw JSON can be kept.
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = Value::deserialize(deserializer)?;
+        let mut post = PostInternal::deserialize(&raw).map_err(serde::de::Error::custom)?;
+        post.raw = raw;
+        Ok(post)
+    }
 }
 
 /// Represents the extended content of a long Weibo post.
@@ -102,6 +120,7 @@ impl TryFrom<PostInternal> for Post {
             text: value.text,
             url_struct: value.url_struct.map(|u| u.try_into()).transpose()?,
             user: value.user.map(|u| u.into()),
+            raw: (!value.raw.is_null()).then_some(value.raw),
         };
         Ok(res)
     }
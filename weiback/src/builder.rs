@@ -8,6 +8,7 @@
 
 use std::sync::Arc;
 
+use sqlx::SqlitePool;
 use tracing::{debug, info};
 use weibosdk_rs::{ApiClient as SdkApiClient, Client as HttpClient};
 
@@ -62,17 +63,17 @@ impl CoreBuilder {
         let main_config_read_guard = main_config.read()?;
 
         // Get current handle or create a temporary runtime for DB initialization
-        let db_pool = if let Ok(handle) = tokio::runtime::Handle::try_current() {
+        let (db_pool, write_pool) = if let Ok(handle) = tokio::runtime::Handle::try_current() {
             debug!("Using existing tokio handle for DB initialization");
             // We are already in an async context.
             // Use block_in_place to allow blocking the current thread for the async DB setup.
-            tokio::task::block_in_place(|| handle.block_on(database::create_db_pool()))?
+            tokio::task::block_in_place(|| handle.block_on(create_db_pools()))?
         } else {
             debug!("No existing tokio handle, creating temporary runtime for DB initialization");
             let rt = Runtime::new()?;
-            rt.block_on(database::create_db_pool())?
+            rt.block_on(create_db_pools())?
         };
-        let storage = StorageImpl::new(db_pool);
+        let storage = StorageImpl::new(db_pool, write_pool);
         info!("Storage initialized");
 
         let exporter = ExporterImpl::new();
@@ -117,3 +118,10 @@ impl Default for CoreBuilder {
         Self::new()
     }
 }
+
+/// Opens the database, and the pool all writes go through.
+async fn create_db_pools() -> Result<(SqlitePool, SqlitePool)> {
+    let db_pool = database::create_db_pool().await?;
+    let write_pool = database::create_write_pool(&db_pool).await?;
+    Ok((db_pool, write_pool))
+}
@@ -0,0 +1,254 @@
+//! This module handles portable archive bundles: single files holding a selection of
+//! posts with everything needed to read them elsewhere.
+//!
+//! A bundle is a SQLite database with the archive schema, holding the selected posts,
+//! their authors, pictures, videos and short links. It has two tables of its own:
+//! `bundle_manifest`, which describes the bundle, and `bundle_files`, which stores the
+//! media files with their SHA-256 checksums. See [`crate::storage::internal::bundle`] for the
+//! table structures.
+//!
+//! Since a bundle is an archive database, importing one merges it like another archive,
+//! see [`Storage::merge_archive`](crate::storage::Storage::merge_archive), and then writes the
+//! media files of the added rows out of it.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    SqlitePool,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode},
+};
+use tracing::info;
+
+use crate::error::{Error, Result};
+use crate::storage::database::create_db_pool_with_url;
+use crate::storage::internal::bundle;
+use crate::utils::sha256_hex;
+use crate::verify::MediaFile;
+
+/// The version of the bundle layout, stored in the manifest under `format`.
+pub const BUNDLE_FORMAT: &str = "1";
+/// The extension of bundle files.
+pub const BUNDLE_EXTENSION: &str = "weiback";
+
+/// The kind of a media file stored in a bundle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BundleFileKind {
+    Picture,
+    Video,
+}
+
+impl BundleFileKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Picture => "picture",
+            Self::Video => "video",
+        }
+    }
+}
+
+impl fmt::Display for BundleFileKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The rows copied into a bundle.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BundleContents {
+    /// The posts copied, including the posts they retweet.
+    pub posts: u64,
+    pub users: u64,
+    /// The files of the `picture` rows copied, avatars included.
+    pub pictures: Vec<MediaFile>,
+    /// The files of the `video` rows copied.
+    pub videos: Vec<MediaFile>,
+}
+
+/// An open bundle file.
+#[derive(Debug)]
+pub struct Bundle {
+    path: PathBuf,
+    pool: SqlitePool,
+}
+
+impl Bundle {
+    /// Creates a new, empty bundle with the current archive schema.
+    ///
+    /// # Arguments
+    /// * `path` - The bundle file, which must not exist yet.
+    pub async fn create(path: &Path) -> Result<Self> {
+        if path.exists() {
+            return Err(Error::DbError(format!("bundle {path:?} already exists")));
+        }
+        info!("Creating bundle {path:?}");
+        // Archives are opened in WAL mode, but a bundle has to stay a single file
+        create_db_pool_with_url(&path.to_string_lossy())
+            .await?
+            .close()
+            .await;
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .journal_mode(SqliteJournalMode::Delete);
+        let pool = SqlitePool::connect_with(options).await?;
+        bundle::create_tables(&pool).await?;
+        let version: i64 =
+            sqlx::query_scalar("SELECT COALESCE(MAX(version), 0) FROM _sqlx_migrations")
+                .fetch_one(&pool)
+                .await?;
+        bundle::set_manifest(&pool, "format", BUNDLE_FORMAT).await?;
+        bundle::set_manifest(&pool, "schema_version", &version.to_string()).await?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            pool,
+        })
+    }
+
+    /// Opens an existing bundle read-only, checking that its layout is known.
+    ///
+    /// # Arguments
+    /// * `path` - The bundle file.
+    pub async fn open(path: &Path) -> Result<Self> {
+        if !path.is_file() {
+            return Err(Error::DbError(format!("bundle {path:?} not found")));
+        }
+        let options = SqliteConnectOptions::new().filename(path).read_only(true);
+        let pool = SqlitePool::connect_with(options).await?;
+        let bundle = Self {
+            path: path.to_path_buf(),
+            pool,
+        };
+        let format = bundle
+            .manifest()
+            .await
+            .map_err(|_| Error::FormatError(format!("{path:?} is not a weiback bundle")))?
+            .remove("format");
+        match format.as_deref() {
+            Some(BUNDLE_FORMAT) => Ok(bundle),
+            Some(format) => Err(Error::FormatError(format!(
+                "bundle {path:?} has format {format}, which this version of weiback cannot read"
+            ))),
+            None => Err(Error::FormatError(format!(
+                "{path:?} is not a weiback bundle"
+            ))),
+        }
+    }
+
+    /// The path of the bundle file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Sets an entry of the manifest.
+    pub async fn set_manifest(&self, key: &str, value: &str) -> Result<()> {
+        bundle::set_manifest(&self.pool, key, value).await
+    }
+
+    /// Reads all entries of the manifest.
+    pub async fn manifest(&self) -> Result<HashMap<String, String>> {
+        bundle::get_manifest(&self.pool).await
+    }
+
+    /// Stores a media file in the bundle with its checksum.
+    ///
+    /// # Arguments
+    /// * `kind` - Whether it is a picture or a video.
+    /// * `path` - The path of the file, relative to the media directory.
+    /// * `data` - The content of the file.
+    pub async fn add_file(&self, kind: BundleFileKind, path: &str, data: &[u8]) -> Result<()> {
+        bundle::insert_file(&self.pool, kind.as_str(), path, &sha256_hex(data), data).await
+    }
+
+    /// Reads a media file of the bundle, checking it against its checksum.
+    ///
+    /// # Arguments
+    /// * `kind` - Whether it is a picture or a video.
+    /// * `path` - The path of the file, relative to the media directory.
+    ///
+    /// # Returns
+    /// A `Result` containing the content of the file, if it is in the bundle, or an
+    /// error if it does not match its checksum.
+    pub async fn read_file(&self, kind: BundleFileKind, path: &str) -> Result<Option<Vec<u8>>> {
+        let Some((sha256, data)) = bundle::get_file(&self.pool, kind.as_str(), path).await? else {
+            return Ok(None);
+        };
+        if sha256_hex(&data) != sha256 {
+            return Err(Error::FormatError(format!(
+                "{kind} {path} in bundle {:?} does not match its checksum",
+                self.path
+            )));
+        }
+        Ok(Some(data))
+    }
+
+    /// Closes the bundle, so that its file is complete.
+    pub async fn close(self) {
+        self.pool.close().await;
+    }
+}
+
+#[cfg(test)]
+mod local_tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bundle_files_are_checked() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(format!("test.{BUNDLE_EXTENSION}"));
+        let bundle = Bundle::create(&path).await.unwrap();
+        assert!(Bundle::create(&path).await.is_err());
+        bundle
+            .add_file(BundleFileKind::Picture, "a/p1.jpg", b"picture")
+            .await
+            .unwrap();
+        bundle.set_manifest("posts", "1").await.unwrap();
+        sqlx::query("INSERT INTO bundle_files VALUES ('video', 'v.mp4', 5, 'bad', 'video')")
+            .execute(&bundle.pool)
+            .await
+            .unwrap();
+        bundle.close().await;
+
+        let bundle = Bundle::open(&path).await.unwrap();
+        let journal_mode: String = sqlx::query_scalar("PRAGMA journal_mode")
+            .fetch_one(&bundle.pool)
+            .await
+            .unwrap();
+        assert_eq!(journal_mode, "delete");
+        let manifest = bundle.manifest().await.unwrap();
+        assert_eq!(manifest["format"], BUNDLE_FORMAT);
+        assert_eq!(manifest["posts"], "1");
+        assert_eq!(
+            bundle
+                .read_file(BundleFileKind::Picture, "a/p1.jpg")
+                .await
+                .unwrap(),
+            Some(b"picture".to_vec())
+        );
+        assert!(
+            bundle
+                .read_file(BundleFileKind::Picture, "a/p2.jpg")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            bundle
+                .read_file(BundleFileKind::Video, "v.mp4")
+                .await
+                .is_err()
+        );
+        bundle.close().await;
+
+        let archive = dir.path().join("archive.db");
+        create_db_pool_with_url(&archive.to_string_lossy())
+            .await
+            .unwrap()
+            .close()
+            .await;
+        assert!(Bundle::open(&archive).await.is_err());
+    }
+}
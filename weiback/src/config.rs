@@ -80,6 +80,14 @@ pub struct Config {
     pub picture_path: PathBuf,
     /// Base path for storing downloaded videos.
     pub video_path: PathBuf,
+    /// Base path for the quarantine folders of cleanup tasks.
+    pub quarantine_path: PathBuf,
+    /// Directory for the database snapshots.
+    pub backup_path: PathBuf,
+    /// How many database snapshots to keep. `0` keeps all of them.
+    pub backups_to_keep: usize,
+    /// Days between scheduled database maintenance runs. `0` turns them off.
+    pub maintenance_interval_days: u32,
     /// Configuration settings for the Weibo SDK.
     pub sdk_config: SdkConfig,
     /// Output directory for dev mode, if enabled.
@@ -107,6 +115,10 @@ impl Default for Config {
             static_html: false,
             picture_path: data_dir.join("pictures"),
             video_path: data_dir.join("videos"),
+            quarantine_path: data_dir.join("quarantine"),
+            backup_path: data_dir.join("backups"),
+            backups_to_keep: 5,
+            maintenance_interval_days: 30,
             sdk_config: Default::default(),
             #[cfg(feature = "dev-mode")]
             dev_mode_out_dir: dirs::download_dir().map(|dir| dir.join("weiback_records")),
@@ -172,7 +184,7 @@ pub fn get_config() -> Arc<RwLock<Config>> {
 /// Saves the current configuration to the appropriate configuration file.
 ///
 /// If a configuration file already exists in one of the predefined paths, it will be
-/// updated. Otherwise, a new default configuration file will be created in the
+/// replaced atomically. Otherwise, a new default configuration file will be created in the
 /// user's local configuration directory.
 ///
 /// # Arguments
@@ -196,14 +208,19 @@ pub fn save_config(config: &Config) -> Result<()> {
         path
     };
 
+    // Written next to the file and renamed over it, so a crash never leaves it half written
+    let temp_path = config_path.with_extension("toml.tmp");
     fs::write(
-        &config_path,
+        &temp_path,
         toml::to_string_pretty(config).inspect_err(|e| {
             error!("serialize config to toml failed: {e}");
         })?,
     )
     .inspect_err(|e| {
-        error!("write config file {:?} failed: {e}", config_path);
+        error!("write config file {:?} failed: {e}", temp_path);
+    })?;
+    fs::rename(&temp_path, &config_path).inspect_err(|e| {
+        error!("replace config file {:?} failed: {e}", config_path);
     })?;
     debug!("Configuration file saved at: {config_path:?}");
 
@@ -11,15 +11,18 @@
 //! - [`PostProcesser`]: Handles the downloading of media and insertion of posts into storage.
 
 pub mod post_processer;
+pub mod search_query;
 pub mod task;
 pub mod task_handler;
 pub mod task_manager;
 
 use bytes::Bytes;
+use std::path::PathBuf;
 use std::sync::{
     Arc,
     atomic::{AtomicU64, Ordering},
 };
+use std::time::Duration;
 use tokio::spawn;
 use tracing::{error, info, warn};
 use weibosdk_rs::{ApiClient as SdkApiClient, api_client::LoginState, session::Session};
@@ -29,14 +32,23 @@ use crate::api::DefaultApiClient;
 #[cfg(feature = "dev-mode")]
 use crate::api::DevApiClient;
 use crate::config::get_config;
+use crate::coverage::UserCoverage;
 use crate::error::Result;
 use crate::exporter::ExporterImpl;
+use crate::maintenance;
 use crate::media_downloader::MediaDownloaderHandle;
 use crate::models::User;
+use crate::quarantine::QuarantineBatch;
+use crate::stats::ArchiveStats;
 use crate::storage::StorageImpl;
+use crate::storage::database::DatabaseBackup;
 pub use task::{
-    BackupFavoritesOptions, BackupUserPostsOptions, CleanupInvalidPostsOptions, DeletePostOptions,
-    ExportJobOptions, PaginatedPostInfo, PostQuery, TaskContext, TaskRequest, UserPostFilter,
+    BackupFavoritesOptions, BackupUserPostsOptions, CleanupInvalidPostsOptions, CleanupOptions,
+    CollectGarbageOptions, DeletePostOptions, ExportBundleOptions, ExportJobOptions,
+    FillCoverageGapsOptions, ImportBundleOptions, MaintainDatabaseOptions, MapExportOptions,
+    MentionCount, MergeArchiveOptions, PaginatedPostInfo, PostQuery, RelocateStorageOptions,
+    ResolveLinksOptions, RestoreQuarantineOptions, StatsExportOptions, TaskContext, TaskRequest,
+    TopicCount, UserPostFilter, VerifyArchiveOptions,
 };
 pub use task_handler::TaskHandler;
 pub use task_manager::{Task, TaskError, TaskEventListener, TaskManager, TaskType};
@@ -57,6 +69,9 @@ macro_rules! run_short_task {
     }};
 }
 
+/// How often [`Core::run_maintenance_schedule`] checks whether maintenance is due.
+const MAINTENANCE_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
 #[cfg(not(feature = "dev-mode"))]
 type TH = TaskHandler<DefaultApiClient, StorageImpl, ExporterImpl, MediaDownloaderHandle>;
 #[cfg(feature = "dev-mode")]
@@ -126,6 +141,89 @@ impl Core {
             .map(|opt| opt.map(|u| u.screen_name))
     }
 
+    /// Checks whether search folds traditional Chinese to simplified for the open archive.
+    pub async fn get_script_folding(&self) -> Result<bool> {
+        self.task_handler.script_folding().await
+    }
+
+    /// Ranks the screen names mentioned in local posts, optionally only by one author.
+    pub async fn get_top_mentions(
+        &self,
+        uid: Option<i64>,
+        limit: u64,
+    ) -> Result<Vec<MentionCount>> {
+        self.task_handler.get_top_mentions(uid, limit).await
+    }
+
+    /// Ranks the topics of local posts, optionally only by one author.
+    pub async fn get_top_topics(&self, uid: Option<i64>, limit: u64) -> Result<Vec<TopicCount>> {
+        self.task_handler.get_top_topics(uid, limit).await
+    }
+
+    /// Compares the local posts of each user with the number Weibo reports, optionally
+    /// only for one user.
+    pub async fn get_backup_coverage(&self, uid: Option<i64>) -> Result<Vec<UserCoverage>> {
+        run_short_task!(
+            self,
+            "get_backup_coverage",
+            self.task_handler.get_coverage(uid)
+        )
+    }
+
+    /// Lists the quarantine batches left by cleanup tasks, the newest first.
+    pub async fn list_quarantine_batches(&self) -> Result<Vec<QuarantineBatch>> {
+        run_short_task!(
+            self,
+            "list_quarantine_batches",
+            self.task_handler.list_quarantine_batches()
+        )
+    }
+
+    /// Takes a snapshot of the database into the backup directory, keeping only the
+    /// configured number of snapshots. Works while the database is in use.
+    pub async fn backup_database(&self) -> Result<DatabaseBackup> {
+        let ctx = self.create_short_task_context();
+        run_short_task!(
+            self,
+            "backup_database",
+            self.task_handler.backup_database(ctx)
+        )
+    }
+
+    /// Lists the database snapshots in the backup directory, the newest first.
+    pub async fn list_database_backups(&self) -> Result<Vec<DatabaseBackup>> {
+        let ctx = self.create_short_task_context();
+        run_short_task!(
+            self,
+            "list_database_backups",
+            self.task_handler.list_database_backups(ctx)
+        )
+    }
+
+    /// Restores the database from a snapshot after checking its schema version. The
+    /// restore takes effect on the next start.
+    ///
+    /// # Returns
+    /// A `Result` containing the schema version of the snapshot.
+    pub async fn restore_database(&self, path: PathBuf) -> Result<i64> {
+        let ctx = self.create_short_task_context();
+        run_short_task!(
+            self,
+            "restore_database",
+            self.task_handler.restore_database(ctx, &path)
+        )
+    }
+
+    /// Builds a statistics report of the local archive, optionally only of one author.
+    pub async fn get_archive_stats(&self, uid: Option<i64>) -> Result<ArchiveStats> {
+        let ctx = self.create_short_task_context();
+        run_short_task!(
+            self,
+            "get_archive_stats",
+            self.task_handler.get_archive_stats(ctx, uid)
+        )
+    }
+
     /// Searches for users in local storage whose screen name starts with the given prefix.
     pub async fn search_users_by_screen_name_prefix(&self, prefix: &str) -> Result<Vec<User>> {
         self.task_handler
@@ -338,6 +436,72 @@ impl Core {
         }
     }
 
+    /// Starts a long-running task to export the posts with coordinates as a map file.
+    pub async fn export_map(&self, options: MapExportOptions) -> Result<()> {
+        let ctx = self.create_long_task_context();
+        let id = ctx.task_id.unwrap();
+        let request = TaskRequest::ExportMap(options);
+        let total = request.total() as u64;
+        self.task_manager
+            .start_task(id, TaskType::ExportMap, "导出地图".into(), total)?;
+        spawn(handle_task_request(self.task_handler.clone(), ctx, request));
+        Ok(())
+    }
+
+    /// Starts a long-running task to back up the posts missing from a user's archive.
+    pub async fn fill_coverage_gaps(&self, options: FillCoverageGapsOptions) -> Result<()> {
+        let ctx = self.create_long_task_context();
+        let id = ctx.task_id.unwrap();
+        let request = TaskRequest::FillCoverageGaps(options);
+        let total = request.total() as u64;
+        self.task_manager.start_task(
+            id,
+            TaskType::FillCoverageGaps,
+            "补全备份缺口".into(),
+            total,
+        )?;
+        spawn(handle_task_request(self.task_handler.clone(), ctx, request));
+        Ok(())
+    }
+
+    /// Starts a long-running task to check the archive for inconsistencies and write a
+    /// report, optionally repairing them.
+    pub async fn verify_archive(&self, options: VerifyArchiveOptions) -> Result<()> {
+        let ctx = self.create_long_task_context();
+        let id = ctx.task_id.unwrap();
+        let request = TaskRequest::VerifyArchive(options);
+        let total = request.total() as u64;
+        self.task_manager
+            .start_task(id, TaskType::VerifyArchive, "校验存档".into(), total)?;
+        spawn(handle_task_request(self.task_handler.clone(), ctx, request));
+        Ok(())
+    }
+
+    /// Starts a long-running task to list the orphan media of the archive, and move them
+    /// to a quarantine batch unless it is a dry run.
+    pub async fn collect_garbage(&self, options: CollectGarbageOptions) -> Result<()> {
+        let ctx = self.create_long_task_context();
+        let id = ctx.task_id.unwrap();
+        let request = TaskRequest::CollectGarbage(options);
+        let total = request.total() as u64;
+        self.task_manager
+            .start_task(id, TaskType::CollectGarbage, "清理孤立媒体".into(), total)?;
+        spawn(handle_task_request(self.task_handler.clone(), ctx, request));
+        Ok(())
+    }
+
+    /// Starts a long-running task to export a statistics report of the archive.
+    pub async fn export_archive_stats(&self, options: StatsExportOptions) -> Result<()> {
+        let ctx = self.create_long_task_context();
+        let id = ctx.task_id.unwrap();
+        let request = TaskRequest::ExportStats(options);
+        let total = request.total() as u64;
+        self.task_manager
+            .start_task(id, TaskType::ExportStats, "导出统计报告".into(), total)?;
+        spawn(handle_task_request(self.task_handler.clone(), ctx, request));
+        Ok(())
+    }
+
     /// Clean up redundant or low-resolution images.
     pub async fn cleanup_pictures(&self, request: TaskRequest) -> Result<()> {
         let ctx = self.create_long_task_context();
@@ -363,7 +527,7 @@ impl Core {
     }
 
     /// Clean up invalid or outdated avatars.
-    pub async fn cleanup_outdated_avatars(&self) -> Result<()> {
+    pub async fn cleanup_outdated_avatars(&self, options: CleanupOptions) -> Result<()> {
         let ctx = self.create_long_task_context();
         let id = ctx.task_id.unwrap();
         self.task_manager
@@ -371,11 +535,129 @@ impl Core {
         spawn(handle_task_request(
             self.task_handler.clone(),
             ctx,
-            TaskRequest::CleanupOutdatedAvatars,
+            TaskRequest::CleanupOutdatedAvatars(options),
         ));
         Ok(())
     }
 
+    /// Starts a long-running task to put back what a cleanup task moved to quarantine.
+    pub async fn restore_quarantine(&self, options: RestoreQuarantineOptions) -> Result<()> {
+        let ctx = self.create_long_task_context();
+        let id = ctx.task_id.unwrap();
+        let request = TaskRequest::RestoreQuarantine(options);
+        let total = request.total() as u64;
+        self.task_manager.start_task(
+            id,
+            TaskType::RestoreQuarantine,
+            "恢复隔离区".into(),
+            total,
+        )?;
+        spawn(handle_task_request(self.task_handler.clone(), ctx, request));
+        Ok(())
+    }
+
+    /// Starts a long-running task to merge another archive into this one.
+    pub async fn merge_archive(&self, options: MergeArchiveOptions) -> Result<()> {
+        let ctx = self.create_long_task_context();
+        let id = ctx.task_id.unwrap();
+        let request = TaskRequest::MergeArchive(options);
+        let total = request.total() as u64;
+        self.task_manager
+            .start_task(id, TaskType::MergeArchive, "合并归档".into(), total)?;
+        spawn(handle_task_request(self.task_handler.clone(), ctx, request));
+        Ok(())
+    }
+
+    /// Starts a long-running task to export posts as a portable bundle file.
+    pub async fn export_bundle(&self, options: ExportBundleOptions) -> Result<()> {
+        let ctx = self.create_long_task_context();
+        let id = ctx.task_id.unwrap();
+        let request = TaskRequest::ExportBundle(options);
+        let total = request.total() as u64;
+        self.task_manager
+            .start_task(id, TaskType::ExportBundle, "导出归档包".into(), total)?;
+        spawn(handle_task_request(self.task_handler.clone(), ctx, request));
+        Ok(())
+    }
+
+    /// Starts a long-running task to import a bundle file into this archive.
+    pub async fn import_bundle(&self, options: ImportBundleOptions) -> Result<()> {
+        let ctx = self.create_long_task_context();
+        let id = ctx.task_id.unwrap();
+        let request = TaskRequest::ImportBundle(options);
+        let total = request.total() as u64;
+        self.task_manager
+            .start_task(id, TaskType::ImportBundle, "导入归档包".into(), total)?;
+        spawn(handle_task_request(self.task_handler.clone(), ctx, request));
+        Ok(())
+    }
+
+    /// Starts a long-running task to move or copy the data directories to new locations.
+    pub async fn relocate_storage(&self, options: RelocateStorageOptions) -> Result<()> {
+        let ctx = self.create_long_task_context();
+        let id = ctx.task_id.unwrap();
+        let request = TaskRequest::RelocateStorage(options);
+        let total = request.total() as u64;
+        self.task_manager.start_task(
+            id,
+            TaskType::RelocateStorage,
+            "迁移存储位置".into(),
+            total,
+        )?;
+        spawn(handle_task_request(self.task_handler.clone(), ctx, request));
+        Ok(())
+    }
+
+    /// Starts a long-running task maintaining the database: an integrity check,
+    /// `ANALYZE`, `PRAGMA optimize`, search index optimization and `VACUUM`.
+    pub async fn maintain_database(&self, options: MaintainDatabaseOptions) -> Result<()> {
+        let ctx = self.create_long_task_context();
+        let id = ctx.task_id.unwrap();
+        let request = TaskRequest::MaintainDatabase(options);
+        let total = request.total() as u64;
+        self.task_manager
+            .start_task(id, TaskType::MaintainDatabase, "维护数据库".into(), total)?;
+        spawn(handle_task_request(self.task_handler.clone(), ctx, request));
+        Ok(())
+    }
+
+    /// Starts the database maintenance task if it is due according to the configured
+    /// interval, see [`crate::maintenance::is_due`], and no other task is running.
+    ///
+    /// An archive that was never maintained is not due: the schedule starts now.
+    ///
+    /// # Returns
+    /// `true` if the task was started.
+    pub async fn maintain_database_if_due(&self) -> Result<bool> {
+        let now = chrono::Utc::now().timestamp();
+        let Some(last) = self.task_handler.last_maintenance().await? else {
+            info!("Starting the database maintenance schedule");
+            self.task_handler.set_last_maintenance(now).await?;
+            return Ok(false);
+        };
+        let interval_days = get_config().read()?.maintenance_interval_days;
+        if !maintenance::is_due(Some(last), interval_days, now) || !self.task_manager.is_idle()? {
+            return Ok(false);
+        }
+        info!("Scheduled database maintenance is due, last run at {last}");
+        self.maintain_database(MaintainDatabaseOptions::default())
+            .await?;
+        Ok(true)
+    }
+
+    /// Checks every [`MAINTENANCE_CHECK_INTERVAL`] whether database maintenance is due,
+    /// and starts it when the archive is idle. Runs until the application exits.
+    pub async fn run_maintenance_schedule(&self) {
+        let mut interval = tokio::time::interval(MAINTENANCE_CHECK_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.maintain_database_if_due().await {
+                warn!("Scheduled database maintenance could not start: {e}");
+            }
+        }
+    }
+
     /// Clean up invalid posts.
     pub async fn cleanup_invalid_posts(&self, request: TaskRequest) -> Result<()> {
         let ctx = self.create_long_task_context();
@@ -478,6 +760,101 @@ impl Core {
         Ok(())
     }
 
+    /// Starts a long-running task to upgrade pictures stored at a lower definition.
+    pub async fn upgrade_pictures(&self, request: TaskRequest) -> Result<()> {
+        let ctx = self.create_long_task_context();
+        let id = ctx.task_id.unwrap();
+        let total = 0; // Will be updated in task_handler
+        self.task_manager.start_task(
+            id,
+            TaskType::UpgradePictures,
+            "升级低清晰度图片".into(),
+            total,
+        )?;
+        spawn(handle_task_request(self.task_handler.clone(), ctx, request));
+        Ok(())
+    }
+
+    /// Starts a long-running task to re-derive posts from their stored raw API JSON.
+    pub async fn reprocess_posts(&self, request: TaskRequest) -> Result<()> {
+        let ctx = self.create_long_task_context();
+        let id = ctx.task_id.unwrap();
+        let total = 0; // Will be updated in task_handler
+        self.task_manager
+            .start_task(id, TaskType::ReprocessPosts, "重新解析微博".into(), total)?;
+        spawn(handle_task_request(self.task_handler.clone(), ctx, request));
+        Ok(())
+    }
+
+    /// Starts a long-running task to rebuild the full-text search index.
+    pub async fn rebuild_search_index(&self) -> Result<()> {
+        let ctx = self.create_long_task_context();
+        let id = ctx.task_id.unwrap();
+        let total = TaskRequest::RebuildSearchIndex.total() as u64;
+        self.task_manager.start_task(
+            id,
+            TaskType::RebuildSearchIndex,
+            "重建搜索索引".into(),
+            total,
+        )?;
+        spawn(handle_task_request(
+            self.task_handler.clone(),
+            ctx,
+            TaskRequest::RebuildSearchIndex,
+        ));
+        Ok(())
+    }
+
+    /// Starts a long-running task to enable or disable folding traditional Chinese to
+    /// simplified in search, which rebuilds the full-text search index.
+    pub async fn set_script_folding(&self, enabled: bool) -> Result<()> {
+        let ctx = self.create_long_task_context();
+        let id = ctx.task_id.unwrap();
+        let request = TaskRequest::SetScriptFolding(enabled);
+        let total = request.total() as u64;
+        let description = if enabled {
+            "开启繁简归一搜索"
+        } else {
+            "关闭繁简归一搜索"
+        };
+        self.task_manager
+            .start_task(id, TaskType::SetScriptFolding, description.into(), total)?;
+        spawn(handle_task_request(self.task_handler.clone(), ctx, request));
+        Ok(())
+    }
+
+    /// Starts a long-running task to re-extract the mentions and topics of all posts.
+    pub async fn reindex_mentions_and_topics(&self) -> Result<()> {
+        let ctx = self.create_long_task_context();
+        let id = ctx.task_id.unwrap();
+        let total = TaskRequest::ReindexMentionsAndTopics.total() as u64;
+        self.task_manager.start_task(
+            id,
+            TaskType::ReindexMentionsAndTopics,
+            "重建提及与话题索引".into(),
+            total,
+        )?;
+        spawn(handle_task_request(
+            self.task_handler.clone(),
+            ctx,
+            TaskRequest::ReindexMentionsAndTopics,
+        ));
+        Ok(())
+    }
+
+    /// Starts a long-running task to resolve the short links in post text to their
+    /// destinations.
+    pub async fn resolve_links(&self, options: ResolveLinksOptions) -> Result<()> {
+        let ctx = self.create_long_task_context();
+        let id = ctx.task_id.unwrap();
+        let request = TaskRequest::ResolveLinks(options);
+        let total = request.total() as u64;
+        self.task_manager
+            .start_task(id, TaskType::ResolveLinks, "解析短链接".into(), total)?;
+        spawn(handle_task_request(self.task_handler.clone(), ctx, request));
+        Ok(())
+    }
+
     // ========================= context creators =========================
 
     /// Creates a task context for long-running tasks, including a unique task ID.
@@ -517,15 +894,64 @@ async fn handle_task_request(task_handler: Arc<TH>, ctx: Arc<TaskContext>, reque
                 .rebackup_missing_images(ctx.clone(), query)
                 .await
         }
-        TaskRequest::CleanupInvalidPictures => {
-            task_handler.cleanup_invalid_pictures(ctx.clone()).await
+        TaskRequest::CleanupInvalidPictures(options) => {
+            task_handler
+                .cleanup_invalid_pictures(ctx.clone(), options)
+                .await
+        }
+        TaskRequest::UpgradePictures(query) => {
+            task_handler.upgrade_pictures(ctx.clone(), query).await
+        }
+        TaskRequest::ReprocessPosts(query) => {
+            task_handler.reprocess_posts(ctx.clone(), query).await
+        }
+        TaskRequest::RebuildSearchIndex => task_handler.rebuild_search_index(ctx.clone()).await,
+        TaskRequest::SetScriptFolding(enabled) => {
+            task_handler.set_script_folding(ctx.clone(), enabled).await
+        }
+        TaskRequest::ReindexMentionsAndTopics => {
+            task_handler.reindex_mentions_and_topics(ctx.clone()).await
+        }
+        TaskRequest::ResolveLinks(options) => {
+            task_handler.resolve_links(ctx.clone(), options).await
+        }
+        TaskRequest::ExportMap(options) => task_handler.export_map(ctx.clone(), options).await,
+        TaskRequest::ExportStats(options) => task_handler.export_stats(ctx.clone(), options).await,
+        TaskRequest::FillCoverageGaps(options) => {
+            task_handler.fill_coverage_gaps(ctx.clone(), options).await
+        }
+        TaskRequest::VerifyArchive(options) => {
+            task_handler.verify_archive(ctx.clone(), options).await
+        }
+        TaskRequest::CollectGarbage(options) => {
+            task_handler.collect_garbage(ctx.clone(), options).await
+        }
+        TaskRequest::RestoreQuarantine(options) => {
+            task_handler.restore_quarantine(ctx.clone(), options).await
+        }
+        TaskRequest::MergeArchive(options) => {
+            task_handler.merge_archive(ctx.clone(), options).await
+        }
+        TaskRequest::ExportBundle(options) => {
+            task_handler.export_bundle(ctx.clone(), options).await
+        }
+        TaskRequest::ImportBundle(options) => {
+            task_handler.import_bundle(ctx.clone(), options).await
+        }
+        TaskRequest::RelocateStorage(options) => {
+            task_handler.relocate_storage(ctx.clone(), options).await
+        }
+        TaskRequest::MaintainDatabase(options) => {
+            task_handler.maintain_database(ctx.clone(), options).await
         }
         TaskRequest::Export(options) => task_handler.export_posts(ctx.clone(), options).await,
         TaskRequest::CleanupPictures(options) => {
             task_handler.cleanup_pictures(ctx.clone(), options).await
         }
-        TaskRequest::CleanupOutdatedAvatars => {
-            task_handler.cleanup_outdated_avatars(ctx.clone()).await
+        TaskRequest::CleanupOutdatedAvatars(options) => {
+            task_handler
+                .cleanup_outdated_avatars(ctx.clone(), options)
+                .await
         }
         TaskRequest::CleanupInvalidPosts(options) => {
             task_handler
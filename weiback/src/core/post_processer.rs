@@ -4,14 +4,16 @@
 //! 1.  Extracting media metadata (images, videos, emojis, avatars) from posts.
 //! 2.  Downloading media files to local storage using a [`MediaDownloader`].
 //! 3.  Enriching post data (e.g., mapping emojis to local IDs).
-//! 4.  Saving processed posts into the [`Storage`].
+//! 4.  Saving processed posts into the [`Storage`], each batch in one transaction.
 
 use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 
+use futures::future;
 use futures::stream::{self, StreamExt, TryStreamExt};
+use tokio::sync::oneshot;
 use tracing::{debug, info};
 use url::Url;
 
@@ -19,13 +21,14 @@ use super::task::TaskContext;
 use crate::api::ApiClient;
 use crate::core::task::PostInfo;
 use crate::emoji_map::EmojiMap;
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::media_downloader::MediaDownloader;
 use crate::models::{PicInfoType, Picture, PictureDefinition, PictureMeta, Post, VideoMeta};
 use crate::storage::Storage;
 use crate::utils::{
     extract_all_pic_metas, extract_emojis_from_text, extract_inline_pic_ids,
-    extract_standalone_images, extract_standalone_pic_ids, pic_url_to_id,
+    extract_pic_fallback_metas, extract_standalone_images, extract_standalone_pic_ids,
+    pic_url_to_id,
 };
 
 /// A processor that handles media downloading and post data enrichment.
@@ -136,12 +139,14 @@ impl<A: ApiClient, S: Storage, D: MediaDownloader> PostProcesser<A, S, D> {
             emoji_map,
             standalone_pics,
             inline_map,
+            snippet: None,
         })
     }
 
     /// Processes a batch of posts, downloading media and saving them to storage.
     ///
-    /// This is the main entry point for persisting posts fetched from the API.
+    /// This is the main entry point for persisting posts fetched from the API. The posts
+    /// are saved in one transaction once their media are downloaded.
     ///
     /// # Arguments
     /// * `ctx` - The task context.
@@ -158,16 +163,13 @@ impl<A: ApiClient, S: Storage, D: MediaDownloader> PostProcesser<A, S, D> {
         self.handle_livephoto_video(ctx.clone(), &posts).await?;
 
         info!("Finished downloading pictures. Processing posts...");
-        stream::iter(posts)
-            .map(Ok)
-            .try_for_each_concurrent(2, |post| async move {
-                if self.need_insert(&post).await? {
-                    self.storage.save_post(&post).await
-                } else {
-                    Ok(())
-                }
-            })
-            .await?;
+        let mut to_save = Vec::with_capacity(posts.len());
+        for post in posts {
+            if self.need_insert(&post).await? {
+                to_save.push(post);
+            }
+        }
+        self.storage.save_posts(&to_save).await?;
 
         info!("Finished processing posts for task {:?}.", ctx.task_id);
         Ok(())
@@ -178,6 +180,68 @@ impl<A: ApiClient, S: Storage, D: MediaDownloader> PostProcesser<A, S, D> {
         Ok(is_valid_post(post) || self.storage.get_post(post.id).await?.is_none())
     }
 
+    /// Re-downloads the pictures of a post that are only stored at a definition lower
+    /// than the configured one.
+    ///
+    /// Only definitions above the best stored one are tried, following the definition
+    /// fallback chain. Pictures that are missing entirely are left untouched.
+    /// The downloads are all queued first, then waited for together.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context.
+    /// * `post` - The post whose pictures should be upgraded.
+    ///
+    /// # Returns
+    /// The IDs of the pictures that were upgraded.
+    pub async fn upgrade_pictures(
+        &self,
+        ctx: Arc<TaskContext>,
+        post: &Post,
+    ) -> Result<Vec<String>> {
+        let target = ctx.config.picture_definition;
+        let chains = extract_pic_fallback_metas(std::slice::from_ref(post), target);
+
+        let mut pending = Vec::new();
+        for (url, chain) in chains {
+            if self.storage.picture_saved(ctx.clone(), &url).await? {
+                continue;
+            }
+            let id = pic_url_to_id(&url)?;
+            let mut best_saved = None;
+            for info in self.storage.get_pictures_by_id(&id).await? {
+                if let PictureMeta::Attached { definition, .. } = info.meta
+                    && best_saved < Some(definition)
+                    && self
+                        .storage
+                        .picture_saved(ctx.clone(), info.meta.url())
+                        .await?
+                {
+                    best_saved = Some(definition);
+                }
+            }
+            let Some(best_saved) = best_saved else {
+                continue;
+            };
+            let candidates: Vec<_> = chain
+                .into_iter()
+                .filter(|m| matches!(m, PictureMeta::Attached { definition, .. } if *definition > best_saved))
+                .collect();
+            if candidates.is_empty() {
+                continue;
+            }
+            debug!("Upgrading picture {id} from {best_saved:?}");
+            let done = self.download_pic_chain(ctx.clone(), candidates).await?;
+            pending.push((id, done));
+        }
+        let upgraded = future::join_all(
+            pending
+                .into_iter()
+                .map(|(id, done)| async move { done.await.ok().map(|()| id) }),
+        )
+        .await;
+        Ok(upgraded.into_iter().flatten().collect())
+    }
+
     /// Identifies and downloads all unique pictures found in a batch of posts.
     async fn handle_picture(
         &self,
@@ -187,47 +251,80 @@ impl<A: ApiClient, S: Storage, D: MediaDownloader> PostProcesser<A, S, D> {
         emoji_map: Option<&HashMap<String, Url>>,
     ) -> Result<()> {
         let pic_metas = extract_all_pic_metas(posts, pic_quality, emoji_map);
+        let mut fallbacks = extract_pic_fallback_metas(posts, pic_quality);
         info!("Found {} unique pictures to download.", pic_metas.len());
 
-        stream::iter(pic_metas)
+        let chains: Vec<_> = pic_metas
+            .into_iter()
+            .map(|meta| fallbacks.remove(meta.url()).unwrap_or_else(|| vec![meta]))
+            .collect();
+        stream::iter(chains)
             .map(Ok)
-            .try_for_each_concurrent(10, |meta| {
+            .try_for_each_concurrent(10, |chain| {
                 let ctx_clone = ctx.clone();
-                async move { self.download_pic_to_local(ctx_clone, meta).await }
+                async move { self.download_pic_to_local(ctx_clone, chain).await }
             })
             .await?;
         Ok(())
     }
 
     /// Downloads a single picture and saves it to local storage.
-    #[tracing::instrument(skip(self, ctx, pic_meta), fields(url = %pic_meta.url()))]
+    ///
+    /// `chain` holds the same picture at decreasing definitions; the first one that
+    /// can be downloaded is saved. Nothing is downloaded if any of them is stored
+    /// already, e.g. from an earlier run that fell back to a lower definition.
+    #[tracing::instrument(skip(self, ctx, chain), fields(url = %chain[0].url()))]
     async fn download_pic_to_local(
         &self,
         ctx: Arc<TaskContext>,
-        pic_meta: PictureMeta,
+        chain: Vec<PictureMeta>,
     ) -> Result<()> {
-        let url = pic_meta.url().to_owned();
-        if self.storage.picture_saved(ctx.clone(), &url).await? {
-            debug!("Picture {url} already exists in local storage, skipping download.");
-            return Ok(());
+        for meta in chain.iter() {
+            if self.storage.picture_saved(ctx.clone(), meta.url()).await? {
+                debug!(
+                    "Picture {} already exists in local storage, skipping download.",
+                    meta.url()
+                );
+                return Ok(());
+            }
         }
+        let url = chain[0].url();
         debug!("Downloading picture {url} to local storage.");
+        self.download_pic_chain(ctx, chain).await?;
+        Ok(())
+    }
+
+    /// Queues the download of the first available picture in `chain`.
+    ///
+    /// # Returns
+    /// A receiver that resolves once the picture is saved. It is closed without a
+    /// value if every candidate fails.
+    async fn download_pic_chain(
+        &self,
+        ctx: Arc<TaskContext>,
+        chain: Vec<PictureMeta>,
+    ) -> Result<oneshot::Receiver<()>> {
+        let urls: Vec<Url> = chain.iter().map(|m| m.url().clone()).collect();
         let storage = self.storage.clone();
+        let (done_tx, done_rx) = oneshot::channel();
         let callback = Box::new(
-            move |ctx, blob| -> Pin<Box<dyn Future<Output = Result<()>> + Send>> {
+            move |ctx, url: Url, blob| -> Pin<Box<dyn Future<Output = Result<()>> + Send>> {
                 Box::pin(async move {
-                    let pic = Picture {
-                        meta: pic_meta,
-                        blob,
-                    };
+                    let meta = chain.into_iter().find(|m| m.url() == &url).ok_or_else(|| {
+                        Error::InconsistentTask(format!("unexpected picture url {url}"))
+                    })?;
+                    let pic = Picture { meta, blob };
                     storage.save_picture(ctx, &pic).await?;
+                    let _ = done_tx.send(());
                     Ok(())
                 })
             },
         );
 
-        self.downloader.download_media(ctx, &url, callback).await?;
-        Ok(())
+        self.downloader
+            .download_media_with_fallback(ctx, &urls, callback)
+            .await?;
+        Ok(done_rx)
     }
 
     /// Identifies and downloads all unique LivePhoto videos found in a batch of posts.
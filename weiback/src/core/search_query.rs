@@ -0,0 +1,713 @@
+//! A small Twitter-style search language for the local archive.
+//!
+//! A query such as `from:123 before:2023-01-01 has:image -广告 "exact phrase"` is parsed
+//! into a [`SearchQuery`], whose text terms are compiled into FTS5 `MATCH` expressions
+//! for `posts_fts` and whose operators are applied onto a [`PostQuery`].
+//!
+//! `posts_fts` uses the `trigram` tokenizer, which cannot match terms shorter than three
+//! characters, such as most Chinese words. [`TextTerm::fts_query`] returns `None` for
+//! those and the storage layer matches them with `LIKE` instead.
+//!
+//! Supported syntax:
+//!
+//! | Syntax                            | Meaning                                              |
+//! |-----------------------------------|------------------------------------------------------|
+//! | `word`, `"exact phrase"`          | The post text contains the word or phrase.           |
+//! | `a OR b`                          | The post text contains either term.                  |
+//! | `-word`, `-"phrase"`              | The post text does not contain the word or phrase.   |
+//! | `from:<uid>`, `-from:<uid>`       | Posted (or not) by the user.                         |
+//! | `rt:<uid>`                        | Retweets of a post by the user.                      |
+//! | `after:<date>`, `since:<date>`    | Posted on or after the date (`YYYY-MM-DD`).          |
+//! | `before:<date>`, `until:<date>`   | Posted before the date.                              |
+//! | `has:image`, `has:video`, `has:livephoto` | Contains (or with `-` lacks) that media.     |
+//! | `is:retweet`, `is:original`, `is:favorited` | Restricts the kind of post.                |
+//! | `source:<text>`, `region:<text>`  | The source client / region name contains the text.  |
+//!
+//! Operator values may be quoted, e.g. `source:"iPhone 15"`. Dates are taken in China
+//! Standard Time, which is what Weibo reports `created_at` in. Words with an unknown
+//! `key:` prefix, such as URLs, are searched as plain text.
+//!
+//! Like every other clause, `from:` narrows the results: it is intersected with the
+//! author filter of the [`PostQuery`] and with other `from:` clauses, so that e.g.
+//! `from:1` in the view of another user matches nothing.
+//!
+//! Error messages are shown to the user as is and are therefore in Chinese.
+
+use chrono::{FixedOffset, NaiveDate};
+use thiserror::Error;
+
+use super::task::PostQuery;
+
+/// An error encountered while parsing a search query.
+///
+/// `position` is the index of the offending character, counted in `char`s so that it
+/// can be shown to the user directly.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{message}（位置 {position}）")]
+pub struct SearchQueryError {
+    pub position: usize,
+    pub message: String,
+}
+
+/// The shortest term, in `char`s, the trigram tokenizer of `posts_fts` can match.
+pub const MIN_FTS_TERM_CHARS: usize = 3;
+
+/// A text term matched against the full-text index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextTerm {
+    Word(String),
+    Phrase(String),
+}
+
+impl TextTerm {
+    /// The text the post must contain.
+    pub fn text(&self) -> &str {
+        let (TextTerm::Word(text) | TextTerm::Phrase(text)) = self;
+        text
+    }
+
+    /// Compiles the term into an FTS5 `MATCH` expression, quoted so that user input is
+    /// never read as FTS syntax.
+    ///
+    /// Returns `None` if the term is too short for the trigram index and has to be
+    /// matched as a substring instead.
+    pub fn fts_query(&self) -> Option<String> {
+        let text = self.text();
+        (text.chars().count() >= MIN_FTS_TERM_CHARS)
+            .then(|| format!("\"{}\"", text.replace('"', "\"\"")))
+    }
+}
+
+/// The kinds of media `has:` can filter on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaFilter {
+    Pictures,
+    Video,
+    Livephoto,
+}
+
+/// The kinds of posts `is:` can filter on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostKind {
+    Retweet,
+    Original,
+    Favorited,
+}
+
+/// A single clause of a search query. All clauses of a query must hold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Clause {
+    /// The text contains any of the terms.
+    Text(Vec<TextTerm>),
+    /// The text does not contain the term.
+    ExcludeText(TextTerm),
+    From {
+        user_id: i64,
+        negated: bool,
+    },
+    RetweetOf(i64),
+    Before(NaiveDate),
+    After(NaiveDate),
+    Has {
+        media: MediaFilter,
+        negated: bool,
+    },
+    Is(PostKind),
+    Source(String),
+    Region(String),
+}
+
+/// A parsed search query.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    pub clauses: Vec<Clause>,
+}
+
+impl SearchQuery {
+    /// Parses a search query string.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The query as typed by the user.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the parsed `SearchQuery`, or a `SearchQueryError` pointing
+    /// at the first offending character.
+    pub fn parse(input: &str) -> Result<Self, SearchQueryError> {
+        Parser {
+            chars: input.chars().collect(),
+            pos: 0,
+        }
+        .parse()
+    }
+
+    /// Builds a query requiring every whitespace-separated word of `term`.
+    pub fn fuzzy(term: &str) -> Self {
+        let clauses = term
+            .split_whitespace()
+            .map(|word| Clause::Text(vec![TextTerm::Word(word.to_string())]))
+            .collect();
+        Self { clauses }
+    }
+
+    /// Builds a query requiring `term` verbatim.
+    pub fn strict(term: &str) -> Self {
+        let clauses = if term.is_empty() {
+            Vec::new()
+        } else {
+            vec![Clause::Text(vec![TextTerm::Phrase(term.to_string())])]
+        };
+        Self { clauses }
+    }
+
+    /// Rewrites the text of every text term with `fold`, leaving operator values alone.
+    pub fn fold_text(&mut self, fold: impl Fn(&str) -> String) {
+        let fold_term = |term: &mut TextTerm| {
+            let (TextTerm::Word(text) | TextTerm::Phrase(text)) = term;
+            *text = fold(text);
+        };
+        for clause in &mut self.clauses {
+            match clause {
+                Clause::Text(terms) => terms.iter_mut().for_each(fold_term),
+                Clause::ExcludeText(term) => fold_term(term),
+                _ => {}
+            }
+        }
+    }
+
+    /// Compiles the positive text terms the index can match into one FTS5 `MATCH`
+    /// expression matching any of them, used to rank and highlight results.
+    ///
+    /// Returns `None` if there is no such term.
+    pub fn rank_query(&self) -> Option<String> {
+        let terms = self
+            .clauses
+            .iter()
+            .filter_map(|clause| match clause {
+                Clause::Text(terms) => Some(terms),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(TextTerm::fts_query)
+            .collect::<Vec<_>>();
+        (!terms.is_empty()).then(|| terms.join(" OR "))
+    }
+
+    /// Narrows `query` by the operator clauses of this search query.
+    ///
+    /// Text clauses are not applied; they are matched by the storage layer through
+    /// [`TextTerm::fts_query`].
+    ///
+    /// # Returns
+    ///
+    /// `false` if a `from:` clause contradicts the authors `query` is already limited
+    /// to, or another `from:` clause, in which case no post can match.
+    #[must_use]
+    pub fn apply_filters(&self, query: &mut PostQuery) -> bool {
+        let mut satisfiable = true;
+        for clause in &self.clauses {
+            match clause {
+                Clause::Text(_) | Clause::ExcludeText(_) => {}
+                Clause::From {
+                    user_id,
+                    negated: false,
+                } => {
+                    let limited = query.user_id.is_some() || !query.user_ids.is_empty();
+                    if limited
+                        && query.user_id != Some(*user_id)
+                        && !query.user_ids.contains(user_id)
+                    {
+                        satisfiable = false;
+                    }
+                    query.user_id = None;
+                    query.user_ids = vec![*user_id];
+                }
+                Clause::From {
+                    user_id,
+                    negated: true,
+                } => query.exclude_user_ids.push(*user_id),
+                Clause::RetweetOf(user_id) => query.retweeted_user_id = Some(*user_id),
+                Clause::Before(date) => {
+                    let end = day_start(*date) - 1;
+                    query.end_date = Some(query.end_date.map_or(end, |e| e.min(end)));
+                }
+                Clause::After(date) => {
+                    let start = day_start(*date);
+                    query.start_date = Some(query.start_date.map_or(start, |s| s.max(start)));
+                }
+                Clause::Has { media, negated } => {
+                    let filter = match media {
+                        MediaFilter::Pictures => &mut query.has_pictures,
+                        MediaFilter::Video => &mut query.has_video,
+                        MediaFilter::Livephoto => &mut query.has_livephoto,
+                    };
+                    *filter = Some(!negated);
+                }
+                Clause::Is(PostKind::Retweet) => query.is_retweet = Some(true),
+                Clause::Is(PostKind::Original) => query.is_retweet = Some(false),
+                Clause::Is(PostKind::Favorited) => query.is_favorited = true,
+                Clause::Source(source) => query.source = Some(source.clone()),
+                Clause::Region(region) => query.region_name = Some(region.clone()),
+            }
+        }
+        satisfiable
+    }
+}
+
+/// The Unix timestamp of midnight at the start of `date`, China Standard Time.
+fn day_start(date: NaiveDate) -> i64 {
+    let cst = FixedOffset::east_opt(8 * 3600).expect("valid offset");
+    date.and_hms_opt(0, 0, 0)
+        .expect("valid time")
+        .and_local_timezone(cst)
+        .unwrap()
+        .timestamp()
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn parse(mut self) -> Result<SearchQuery, SearchQueryError> {
+        let mut clauses = Vec::new();
+        // position of an `OR` still waiting for its right-hand term
+        let mut pending_or = None;
+        loop {
+            self.skip_whitespace();
+            let Some(c) = self.peek() else {
+                break;
+            };
+            let start = self.pos;
+            let negated = c == '-';
+            if negated {
+                self.pos += 1;
+                if self.peek().is_none_or(char::is_whitespace) {
+                    return Err(error(start, "`-` 后缺少搜索词"));
+                }
+            }
+
+            let clause = if self.peek() == Some('"') {
+                Some(text_clause(TextTerm::Phrase(self.quoted()?), negated))
+            } else {
+                self.word_or_operator(negated)?
+            };
+            let Some(clause) = clause else {
+                // a bare `OR`
+                match clauses.last() {
+                    Some(Clause::Text(_)) if pending_or.is_none() => pending_or = Some(start),
+                    _ => return Err(error(start, "`OR` 必须位于两个搜索词之间")),
+                }
+                continue;
+            };
+
+            match (pending_or.take(), clause) {
+                (None, clause) => clauses.push(clause),
+                (Some(_), Clause::Text(terms)) => match clauses.last_mut() {
+                    Some(Clause::Text(alternatives)) => alternatives.extend(terms),
+                    _ => unreachable!("`OR` always follows a text clause"),
+                },
+                (Some(or), _) => {
+                    return Err(error(or, "`OR` 必须位于两个搜索词之间"));
+                }
+            }
+        }
+        if let Some(or) = pending_or {
+            return Err(error(or, "`OR` 必须位于两个搜索词之间"));
+        }
+        Ok(SearchQuery { clauses })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    /// Reads a double-quoted string starting at the current position. A quote inside the
+    /// string is written as two quotes.
+    fn quoted(&mut self) -> Result<String, SearchQueryError> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(error(start, "引号未闭合")),
+                Some('"') if self.chars.get(self.pos + 1) == Some(&'"') => {
+                    text.push('"');
+                    self.pos += 2;
+                }
+                Some('"') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(c) => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        if text.trim().is_empty() {
+            return Err(error(start, "引号内的短语为空"));
+        }
+        Ok(text)
+    }
+
+    /// Reads characters up to the next whitespace.
+    fn bare(&mut self) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| !c.is_whitespace()) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    /// Reads a plain word or a `key:value` operator. Returns `None` for a bare `OR`.
+    fn word_or_operator(&mut self, negated: bool) -> Result<Option<Clause>, SearchQueryError> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| !c.is_whitespace() && c != ':' && c != '"')
+        {
+            self.pos += 1;
+        }
+        let key = self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .to_lowercase();
+        if self.peek() == Some(':') && is_operator(&key) {
+            self.pos += 1;
+            let value_start = self.pos;
+            let value = if self.peek() == Some('"') {
+                self.quoted()?
+            } else {
+                self.bare()
+            };
+            if value.is_empty() {
+                return Err(error(value_start, &format!("`{key}:` 后缺少值")));
+            }
+            return operator(&key, value, negated, start, value_start).map(Some);
+        }
+
+        self.pos = start;
+        let word = self.bare();
+        if word == "OR" && !negated {
+            return Ok(None);
+        }
+        Ok(Some(text_clause(TextTerm::Word(word), negated)))
+    }
+}
+
+fn text_clause(term: TextTerm, negated: bool) -> Clause {
+    if negated {
+        Clause::ExcludeText(term)
+    } else {
+        Clause::Text(vec![term])
+    }
+}
+
+fn is_operator(key: &str) -> bool {
+    matches!(
+        key,
+        "from" | "rt" | "before" | "until" | "after" | "since" | "has" | "is" | "source" | "region"
+    )
+}
+
+/// Builds the clause for an operator. `start` is the position of the operator (including
+/// any `-`) and `value_start` the position of its value.
+fn operator(
+    key: &str,
+    value: String,
+    negated: bool,
+    start: usize,
+    value_start: usize,
+) -> Result<Clause, SearchQueryError> {
+    let not_negatable = || error(start, &format!("`{key}:` 不能取反"));
+    let clause = match key {
+        "from" => Clause::From {
+            user_id: user_id(&value, value_start)?,
+            negated,
+        },
+        "rt" if !negated => Clause::RetweetOf(user_id(&value, value_start)?),
+        "before" | "until" if !negated => Clause::Before(date(&value, value_start)?),
+        "after" | "since" if !negated => Clause::After(date(&value, value_start)?),
+        "has" => {
+            let media = match value.to_lowercase().as_str() {
+                "image" | "images" | "pic" | "pics" | "picture" | "pictures" => {
+                    MediaFilter::Pictures
+                }
+                "video" | "videos" => MediaFilter::Video,
+                "livephoto" | "live" => MediaFilter::Livephoto,
+                _ => {
+                    return Err(error(
+                        value_start,
+                        "`has:` 后应为 `image`、`video` 或 `livephoto`",
+                    ));
+                }
+            };
+            Clause::Has { media, negated }
+        }
+        "is" => match (value.to_lowercase().as_str(), negated) {
+            ("retweet" | "repost", false) | ("original", true) => Clause::Is(PostKind::Retweet),
+            ("original", false) | ("retweet" | "repost", true) => Clause::Is(PostKind::Original),
+            ("favorited" | "fav", false) => Clause::Is(PostKind::Favorited),
+            ("favorited" | "fav", true) => return Err(not_negatable()),
+            _ => {
+                return Err(error(
+                    value_start,
+                    "`is:` 后应为 `retweet`、`original` 或 `favorited`",
+                ));
+            }
+        },
+        "source" if !negated => Clause::Source(value),
+        "region" if !negated => Clause::Region(value),
+        _ => return Err(not_negatable()),
+    };
+    Ok(clause)
+}
+
+fn user_id(value: &str, position: usize) -> Result<i64, SearchQueryError> {
+    value
+        .parse()
+        .map_err(|_| error(position, "应为数字用户 ID"))
+}
+
+fn date(value: &str, position: usize) -> Result<NaiveDate, SearchQueryError> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| error(position, "应为形如 `2023-01-31` 的日期"))
+}
+
+fn error(position: usize, message: &str) -> SearchQueryError {
+    SearchQueryError {
+        position,
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod local_tests {
+    use super::*;
+
+    fn word(s: &str) -> TextTerm {
+        TextTerm::Word(s.to_string())
+    }
+
+    fn phrase(s: &str) -> TextTerm {
+        TextTerm::Phrase(s.to_string())
+    }
+
+    fn ymd(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let query =
+            SearchQuery::parse(r#"from:123 before:2023-01-01 has:image -广告 "exact phrase""#)
+                .unwrap();
+        assert_eq!(
+            query.clauses,
+            [
+                Clause::From {
+                    user_id: 123,
+                    negated: false
+                },
+                Clause::Before(ymd("2023-01-01")),
+                Clause::Has {
+                    media: MediaFilter::Pictures,
+                    negated: false
+                },
+                Clause::ExcludeText(word("广告")),
+                Clause::Text(vec![phrase("exact phrase")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_or_and_plain_words() {
+        let query = SearchQuery::parse(
+            r#"猫猫 OR "小狗 狗" https://t.cn/abc Source:"iPhone 15" -is:retweet"#,
+        )
+        .unwrap();
+        assert_eq!(
+            query.clauses,
+            [
+                Clause::Text(vec![word("猫猫"), phrase("小狗 狗")]),
+                Clause::Text(vec![word("https://t.cn/abc")]),
+                Clause::Source("iPhone 15".to_string()),
+                Clause::Is(PostKind::Original),
+            ]
+        );
+
+        let query = SearchQuery::parse(r#"say "a ""quoted"" word""#).unwrap();
+        assert_eq!(
+            query.clauses,
+            [
+                Clause::Text(vec![word("say")]),
+                Clause::Text(vec![phrase(r#"a "quoted" word"#)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(
+            phrase(r#"a "quoted" word"#).fts_query().as_deref(),
+            Some(r#""a ""quoted"" word""#)
+        );
+        assert_eq!(word("北京市").fts_query().as_deref(), Some(r#""北京市""#));
+        // too short for the trigram tokenizer
+        assert_eq!(word("北京").fts_query(), None);
+        assert_eq!(word("猫").fts_query(), None);
+
+        assert_eq!(
+            SearchQuery::fuzzy(" 猫  可爱 ").clauses,
+            [
+                Clause::Text(vec![word("猫")]),
+                Clause::Text(vec![word("可爱")])
+            ]
+        );
+        assert_eq!(
+            SearchQuery::strict("猫 可爱").clauses,
+            [Clause::Text(vec![phrase("猫 可爱")])]
+        );
+        assert!(SearchQuery::strict("").clauses.is_empty());
+    }
+
+    #[test]
+    fn test_fold_text() {
+        let mut query = SearchQuery::parse(r#"臺灣 "臺北 101" -颱風 source:臺灣"#).unwrap();
+        query.fold_text(|text| text.replace('臺', "台"));
+        assert_eq!(
+            query,
+            SearchQuery::parse(r#"台灣 "台北 101" -颱風 source:臺灣"#).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_rank_query() {
+        let query = SearchQuery::parse(r#"猫 "exact phrase" OR 北京市 -广告推广 from:1"#).unwrap();
+        assert_eq!(
+            query.rank_query().as_deref(),
+            Some(r#""exact phrase" OR "北京市""#)
+        );
+        assert_eq!(
+            SearchQuery::parse("猫 -广告推广").unwrap().rank_query(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_apply_filters() {
+        let query = SearchQuery::parse(
+            "from:1 -from:3 rt:4 after:2023-01-01 before:2023-02-01 \
+             -has:video has:livephoto is:favorited region:上海",
+        )
+        .unwrap();
+        let mut post_query = PostQuery {
+            end_date: Some(0),
+            ..Default::default()
+        };
+        assert!(query.apply_filters(&mut post_query));
+
+        assert_eq!(post_query.user_ids, [1]);
+        assert_eq!(post_query.exclude_user_ids, [3]);
+        assert_eq!(post_query.retweeted_user_id, Some(4));
+        // 2023-01-01T00:00:00+08:00
+        assert_eq!(post_query.start_date, Some(1_672_502_400));
+        // the earlier of the two end dates wins
+        assert_eq!(post_query.end_date, Some(0));
+        assert_eq!(post_query.has_video, Some(false));
+        assert_eq!(post_query.has_livephoto, Some(true));
+        assert_eq!(post_query.has_pictures, None);
+        assert!(post_query.is_favorited);
+        assert_eq!(post_query.region_name.as_deref(), Some("上海"));
+
+        let mut post_query = PostQuery::default();
+        assert!(
+            SearchQuery::parse("before:2023-01-01")
+                .unwrap()
+                .apply_filters(&mut post_query)
+        );
+        assert_eq!(post_query.end_date, Some(1_672_502_400 - 1));
+    }
+
+    #[test]
+    fn test_from_narrows_authors() {
+        let from_1 = SearchQuery::parse("from:1").unwrap();
+
+        // within the view of the same user
+        let mut post_query = PostQuery {
+            user_id: Some(1),
+            ..Default::default()
+        };
+        assert!(from_1.apply_filters(&mut post_query));
+        assert_eq!(post_query.user_id, None);
+        assert_eq!(post_query.user_ids, [1]);
+
+        // picks one of several authors
+        let mut post_query = PostQuery {
+            user_ids: vec![1, 2],
+            ..Default::default()
+        };
+        assert!(from_1.apply_filters(&mut post_query));
+        assert_eq!(post_query.user_ids, [1]);
+
+        // within the view of another user
+        let mut post_query = PostQuery {
+            user_id: Some(2),
+            ..Default::default()
+        };
+        assert!(!from_1.apply_filters(&mut post_query));
+
+        // two authors at once
+        let mut post_query = PostQuery::default();
+        assert!(
+            !SearchQuery::parse("from:1 from:2")
+                .unwrap()
+                .apply_filters(&mut post_query)
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            SearchQuery::parse("from:abc").unwrap_err().to_string(),
+            "应为数字用户 ID（位置 5）"
+        );
+
+        let cases = [
+            (r#"猫 "未闭合"#, 2, "引号未闭合"),
+            ("from:abc", 5, "应为数字用户 ID"),
+            ("北京 before:2023-13-01", 10, "应为形如 `2023-01-31` 的日期"),
+            (
+                "has:audio",
+                4,
+                "`has:` 后应为 `image`、`video` 或 `livephoto`",
+            ),
+            ("a -", 2, "`-` 后缺少搜索词"),
+            ("OR a", 0, "`OR` 必须位于两个搜索词之间"),
+            ("a OR", 2, "`OR` 必须位于两个搜索词之间"),
+            ("a OR from:1", 2, "`OR` 必须位于两个搜索词之间"),
+            ("a -before:2023-01-01", 2, "`before:` 不能取反"),
+            ("source:", 7, "`source:` 后缺少值"),
+            (r#"a """#, 2, "引号内的短语为空"),
+        ];
+        for (input, position, message) in cases {
+            assert_eq!(
+                SearchQuery::parse(input),
+                Err(SearchQueryError {
+                    position,
+                    message: message.to_string()
+                }),
+                "{input}"
+            );
+        }
+    }
+}
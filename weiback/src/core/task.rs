@@ -13,7 +13,15 @@ use serde::{Deserialize, Serialize};
 use serde_with::{DisplayFromStr, serde_as};
 
 use super::task_manager::TaskManager;
-use crate::{api::ContainerType, config::Config, models::Post};
+use crate::{
+    api::ContainerType,
+    config::Config,
+    geo::{BoundingBox, MapFormat},
+    maintenance::MaintenanceStep,
+    models::Post,
+    relocate::RelocateMode,
+    stats::StatsFormat,
+};
 
 /// Context shared across a single task's execution.
 #[derive(Debug)]
@@ -40,7 +48,7 @@ pub enum TaskRequest {
     /// Clean up redundant images or enforce resolution policies.
     CleanupPictures(CleanupPicturesOptions),
     /// Clean up invalid or outdated user avatars.
-    CleanupOutdatedAvatars,
+    CleanupOutdatedAvatars(CleanupOptions),
     /// Clean up invalid posts (e.g., user is None).
     CleanupInvalidPosts(CleanupInvalidPostsOptions),
     /// Re-backup posts based on a query.
@@ -48,7 +56,42 @@ pub enum TaskRequest {
     /// Re-backup posts that have missing images.
     RebackupMissingImages(PostQuery),
     /// Clean up invalid pictures (e.g., "image deleted" placeholders).
-    CleanupInvalidPictures,
+    CleanupInvalidPictures(CleanupOptions),
+    /// Re-download pictures stored below the configured definition, then clean up duplicates.
+    UpgradePictures(PostQuery),
+    /// Re-derive posts and their pictures from the stored raw API JSON.
+    ReprocessPosts(PostQuery),
+    /// Rebuild the full-text search index of posts.
+    RebuildSearchIndex,
+    /// Enable or disable folding traditional Chinese to simplified in search, then
+    /// rebuild the search index.
+    SetScriptFolding(bool),
+    /// Re-extract the mentions and topics of all posts.
+    ReindexMentionsAndTopics,
+    /// Resolve the short links in post text to their destinations.
+    ResolveLinks(ResolveLinksOptions),
+    /// Export the posts with coordinates as a map file.
+    ExportMap(MapExportOptions),
+    /// Export a statistics report of the archive.
+    ExportStats(StatsExportOptions),
+    /// Back up the posts missing from the archive of a user.
+    FillCoverageGaps(FillCoverageGapsOptions),
+    /// Check that the database and the media directories agree, optionally repairing them.
+    VerifyArchive(VerifyArchiveOptions),
+    /// Move or delete media files and rows that nothing references anymore.
+    CollectGarbage(CollectGarbageOptions),
+    /// Put back what a cleanup task moved to quarantine.
+    RestoreQuarantine(RestoreQuarantineOptions),
+    /// Merge another archive, its database and media files, into this one.
+    MergeArchive(MergeArchiveOptions),
+    /// Export the posts matching a query as a portable bundle file.
+    ExportBundle(ExportBundleOptions),
+    /// Import a bundle file into this archive.
+    ImportBundle(ImportBundleOptions),
+    /// Move or copy the media directories and the database to new locations.
+    RelocateStorage(RelocateStorageOptions),
+    /// Check, analyze, optimize and vacuum the database.
+    MaintainDatabase(MaintainDatabaseOptions),
 }
 
 impl TaskRequest {
@@ -59,11 +102,28 @@ impl TaskRequest {
             TaskRequest::UnfavoritePosts => 1,
             TaskRequest::Export(_) => 1,
             TaskRequest::CleanupPictures(_) => 0,
-            TaskRequest::CleanupOutdatedAvatars => 0,
+            TaskRequest::CleanupOutdatedAvatars(_) => 0,
             TaskRequest::CleanupInvalidPosts(_) => 0,
             TaskRequest::RebackupPosts(_) => 0,
             TaskRequest::RebackupMissingImages(_) => 0,
-            TaskRequest::CleanupInvalidPictures => 0,
+            TaskRequest::CleanupInvalidPictures(_) => 0,
+            TaskRequest::UpgradePictures(_) => 0,
+            TaskRequest::ReprocessPosts(_) => 0,
+            TaskRequest::RebuildSearchIndex => 1,
+            TaskRequest::SetScriptFolding(_) => 1,
+            TaskRequest::ReindexMentionsAndTopics => 0,
+            TaskRequest::ResolveLinks(_) => 0,
+            TaskRequest::ExportMap(_) => 0,
+            TaskRequest::ExportStats(_) => 0,
+            TaskRequest::FillCoverageGaps(_) => 0,
+            TaskRequest::VerifyArchive(_) => 1,
+            TaskRequest::CollectGarbage(_) => 1,
+            TaskRequest::RestoreQuarantine(_) => 1,
+            TaskRequest::MergeArchive(_) => 1,
+            TaskRequest::ExportBundle(_) => 0,
+            TaskRequest::ImportBundle(_) => 1,
+            TaskRequest::RelocateStorage(_) => 0,
+            TaskRequest::MaintainDatabase(_) => MaintenanceStep::ALL.len() as u32,
         }
     }
 }
@@ -72,6 +132,85 @@ impl TaskRequest {
 pub struct CleanupInvalidPostsOptions {
     /// Whether to clean up posts that are valid themselves but their retweeted content is invalid.
     pub clean_retweeted_invalid: bool,
+    #[serde(flatten)]
+    pub cleanup: CleanupOptions,
+}
+
+/// The options shared by cleanup tasks.
+///
+/// Unless it is a dry run, what a cleanup removes is moved to a quarantine batch, see
+/// [`crate::quarantine`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CleanupOptions {
+    /// Only list what would be removed, without touching anything.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Where to write the list of what was removed, as `{task_name}.json`.
+    #[serde(default)]
+    pub output: Option<ExportOutputConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreQuarantineOptions {
+    /// The quarantine batch to restore.
+    pub batch: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeArchiveOptions {
+    /// The `weiback.db` of the other archive.
+    pub db_path: PathBuf,
+    /// The picture directory of the other archive.
+    pub picture_path: PathBuf,
+    /// The video directory of the other archive.
+    pub video_path: PathBuf,
+    /// Where to write the merge report, as `{task_name}.json`.
+    pub output: ExportOutputConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportBundleOptions {
+    /// The posts to put in the bundle.
+    pub query: PostQuery,
+    /// Where to write the bundle, as `{task_name}.weiback`.
+    pub output: ExportOutputConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportBundleOptions {
+    /// The bundle file.
+    pub path: PathBuf,
+    /// Where to write the merge report, as `{task_name}.json`.
+    pub output: ExportOutputConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelocateStorageOptions {
+    /// The new picture directory, if it changes.
+    #[serde(default)]
+    pub picture_path: Option<PathBuf>,
+    /// The new video directory, if it changes.
+    #[serde(default)]
+    pub video_path: Option<PathBuf>,
+    /// The new database file, if it changes. The database is moved on the next start.
+    #[serde(default)]
+    pub db_path: Option<PathBuf>,
+    /// Whether the old media files are removed or kept.
+    #[serde(default)]
+    pub mode: RelocateMode,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MaintainDatabaseOptions {
+    /// Where to write the maintenance report, as `{task_name}.json`.
+    #[serde(default)]
+    pub output: Option<ExportOutputConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolveLinksOptions {
+    /// Whether to retry links whose destination could not be reached before.
+    pub retry_failed: bool,
 }
 
 #[serde_as]
@@ -85,6 +224,31 @@ pub struct DeletePostOptions {
     pub deep: bool,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FillCoverageGapsOptions {
+    /// The user whose archive to complete.
+    pub uid: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyArchiveOptions {
+    /// Whether to repair what can be: drop rows of missing media files, rebuild the
+    /// search index and re-back up posts missing pictures or retweeted posts.
+    #[serde(default)]
+    pub repair: bool,
+    /// Where to write the report, as `{task_name}.json`.
+    pub output: ExportOutputConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectGarbageOptions {
+    /// Only list the orphans, without moving them to quarantine.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Where to write the listing, as `{task_name}.json`.
+    pub output: ExportOutputConfig,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BackupFavoritesOptions {
     pub num_pages: u32,
@@ -108,6 +272,8 @@ pub enum ResolutionPolicy {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CleanupPicturesOptions {
     pub policy: ResolutionPolicy,
+    #[serde(flatten)]
+    pub cleanup: CleanupOptions,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
@@ -153,13 +319,45 @@ pub struct ExportJobOptions {
     pub output: ExportOutputConfig,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MapExportOptions {
+    /// The posts to export; only those with coordinates are put on the map.
+    pub query: PostQuery,
+    #[serde(default)]
+    pub format: MapFormat,
+    pub output: ExportOutputConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsExportOptions {
+    /// Only count posts by this user.
+    #[serde(default)]
+    pub uid: Option<i64>,
+    #[serde(default)]
+    pub format: StatsFormat,
+    pub output: ExportOutputConfig,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SearchTerm {
     Fuzzy(String),
     Strict(String),
+    /// A query in the search language of [`super::search_query`].
+    Query(String),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum PostSortBy {
+    #[default]
+    CreatedAt,
+    AttitudesCount,
+    CommentsCount,
+    RepostsCount,
+    /// bm25 relevance to the search term, best first.
+    Relevance,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct PostQuery {
     pub user_id: Option<i64>,
     pub start_date: Option<i64>, // Unix timestamp
@@ -170,12 +368,78 @@ pub struct PostQuery {
     // for pagination
     pub page: u32,
     pub posts_per_page: u32,
+    // media filters match the post or its retweeted post, `Some(false)` excludes
+    #[serde(default)]
+    pub has_pictures: Option<bool>,
+    #[serde(default)]
+    pub has_video: Option<bool>,
+    #[serde(default)]
+    pub has_livephoto: Option<bool>,
+    /// `Some(true)` selects only retweets, `Some(false)` only original posts.
+    #[serde(default)]
+    pub is_retweet: Option<bool>,
+    /// Author of the retweeted post.
+    #[serde(default)]
+    pub retweeted_user_id: Option<i64>,
+    /// Substring of the source client, e.g. "iPhone".
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Substring of the region name, e.g. "北京".
+    #[serde(default)]
+    pub region_name: Option<String>,
+    /// Authors to include, in addition to `user_id`.
+    #[serde(default)]
+    pub user_ids: Vec<i64>,
+    /// Authors to leave out.
+    #[serde(default)]
+    pub exclude_user_ids: Vec<i64>,
+    /// Only posts whose own text `@`-mentions this screen name.
+    #[serde(default)]
+    pub mentioned: Option<String>,
+    /// Only posts whose own text uses this topic, without the `#`s.
+    #[serde(default)]
+    pub topic: Option<String>,
+    /// `Some(true)` selects only posts with coordinates, `Some(false)` only those without.
+    #[serde(default)]
+    pub has_location: Option<bool>,
+    /// Only posts whose coordinates fall within this area.
+    #[serde(default)]
+    pub bbox: Option<BoundingBox>,
+    /// Substring of the check-in place name, e.g. "三里屯".
+    #[serde(default)]
+    pub place: Option<String>,
+    #[serde(default)]
+    pub sort_by: PostSortBy,
+}
+
+/// A screen name with the number of posts mentioning it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MentionCount {
+    pub screen_name: String,
+    pub count: u64,
+}
+
+/// A topic with the number of posts using it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TopicCount {
+    pub topic: String,
+    pub count: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PaginatedPosts {
     pub posts: Vec<Post>,
     pub total_items: u64,
+    /// Highlighted fragments of the matched text, by post id, when searching.
+    pub snippets: HashMap<i64, Vec<SnippetPart>>,
+}
+
+/// A piece of a search result snippet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnippetPart {
+    pub text: String,
+    /// Whether this piece matched the search term.
+    pub highlighted: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -202,6 +466,8 @@ pub struct PostInfo {
     pub emoji_map: HashMap<String, String>,
     pub standalone_pics: Vec<AttachedImage>,
     pub inline_map: HashMap<String, String>,
+    #[serde(default)]
+    pub snippet: Option<Vec<SnippetPart>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
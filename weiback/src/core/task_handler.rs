@@ -7,40 +7,63 @@
 //! - Exporting saved posts to HTML.
 //! - Cleaning up redundant media or invalid avatars.
 
+use std::collections::HashSet;
 use std::future::Future;
+use std::path::Path;
 use std::pin::Pin;
 use std::sync::Arc;
 
 use bytes::Bytes;
+use chrono::Local;
 use futures::{
     pin_mut,
     stream::{self, StreamExt, TryStreamExt},
 };
 use tokio::{fs, time::sleep};
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, info};
 use url::Url;
 
 use super::post_processer::PostProcesser;
 use super::task::{
-    BackupFavoritesOptions, BackupUserPostsOptions, CleanupInvalidPostsOptions,
-    CleanupPicturesOptions, DeletePostOptions, ExportJobOptions, PaginatedPostInfo, PostQuery,
-    ResolutionPolicy, TaskContext,
+    BackupFavoritesOptions, BackupUserPostsOptions, CleanupInvalidPostsOptions, CleanupOptions,
+    CleanupPicturesOptions, CollectGarbageOptions, DeletePostOptions, ExportBundleOptions,
+    ExportJobOptions, ExportOutputConfig, FillCoverageGapsOptions, ImportBundleOptions,
+    MaintainDatabaseOptions, MapExportOptions, MentionCount, MergeArchiveOptions,
+    PaginatedPostInfo, PostInfo, PostQuery, RelocateStorageOptions, ResolutionPolicy,
+    ResolveLinksOptions, RestoreQuarantineOptions, StatsExportOptions, TaskContext, TopicCount,
+    VerifyArchiveOptions,
 };
 use super::task_manager::{TaskError, TaskErrorType};
+use crate::bundle::{BUNDLE_EXTENSION, Bundle, BundleFileKind};
+use crate::coverage::UserCoverage;
 use crate::emoji_map::EmojiMap;
 use crate::error::{Error, Result};
 use crate::exporter::Exporter;
+use crate::geo::MapWriter;
 use crate::html_generator::HTMLGenerator;
 use crate::image_validator::{ImageStatus, ImageValidator};
+use crate::link_resolver::LinkResolver;
+use crate::maintenance::{MaintenanceReport, MaintenanceStep};
 use crate::media_downloader::MediaDownloader;
-use crate::models::{Picture, PictureMeta, User};
+use crate::merge::{MergeOutcome, MergeReport};
+use crate::models::{Picture, PictureMeta, Post, User};
+use crate::quarantine::{CleanupReport, QuarantineBatch, batch_name, move_to_quarantine};
+use crate::relocate::{self, RelocateMode, RelocateSummary};
+use crate::stats::{ArchiveStats, TOP_LIMIT, render_stats};
 use crate::storage::Storage;
+use crate::storage::database::{self, DatabaseBackup};
 use crate::utils::{make_page_name, pic_url_to_id};
+use crate::verify::{RepairSummary, list_files};
 use crate::{
-    api::{ApiClient, ContainerType},
+    api::{ApiClient, ContainerType, post_from_raw, raw_retweeted_id},
     storage::PictureInfo,
 };
 
+/// The most pages of a timeline walked when filling the gaps in an archive.
+const MAX_FILL_PAGES: u32 = 1000;
+/// The most pages in a row that may fail before filling the gaps in an archive gives up.
+const MAX_FILL_FAILURES: u32 = 3;
+
 /// The primary executor for application tasks.
 ///
 /// `TaskHandler` is responsible for fetching data from the API, processing it
@@ -54,6 +77,7 @@ pub struct TaskHandler<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader
     downloader: D,
     processer: PostProcesser<A, S, D>,
     html_generator: HTMLGenerator<A, S>,
+    link_resolver: LinkResolver,
 }
 
 impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S, E, D> {
@@ -65,6 +89,8 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
 
         let html_generator = HTMLGenerator::new(emoji_map, storage.clone());
 
+        let link_resolver = LinkResolver::new()?;
+
         Ok(TaskHandler {
             api_client,
             storage,
@@ -72,6 +98,7 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
             downloader,
             processer,
             html_generator,
+            link_resolver,
         })
     }
 
@@ -80,6 +107,89 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
         self.storage.get_user(uid).await
     }
 
+    /// Checks whether search folds traditional Chinese to simplified for this archive.
+    pub async fn script_folding(&self) -> Result<bool> {
+        self.storage.script_folding().await
+    }
+
+    /// When the database was last maintained, as a Unix timestamp, if ever.
+    pub async fn last_maintenance(&self) -> Result<Option<i64>> {
+        self.storage.last_maintenance().await
+    }
+
+    /// Records when the database was last maintained, as a Unix timestamp.
+    pub async fn set_last_maintenance(&self, at: i64) -> Result<()> {
+        self.storage.set_last_maintenance(at).await
+    }
+
+    /// Ranks the screen names mentioned in local posts, optionally only by one author.
+    pub async fn get_top_mentions(
+        &self,
+        uid: Option<i64>,
+        limit: u64,
+    ) -> Result<Vec<MentionCount>> {
+        self.storage.get_top_mentions(uid, limit).await
+    }
+
+    /// Ranks the topics of local posts, optionally only by one author.
+    pub async fn get_top_topics(&self, uid: Option<i64>, limit: u64) -> Result<Vec<TopicCount>> {
+        self.storage.get_top_topics(uid, limit).await
+    }
+
+    /// Compares the local posts of each user with the number Weibo reports.
+    pub async fn get_coverage(&self, uid: Option<i64>) -> Result<Vec<UserCoverage>> {
+        self.storage.get_coverage(uid).await
+    }
+
+    /// Lists the quarantine batches left by cleanup tasks, the newest first.
+    pub async fn list_quarantine_batches(&self) -> Result<Vec<QuarantineBatch>> {
+        self.storage.list_quarantine_batches().await
+    }
+
+    /// Takes a snapshot of the database into the backup directory, rotating old ones.
+    pub async fn backup_database(&self, ctx: Arc<TaskContext>) -> Result<DatabaseBackup> {
+        self.storage.backup_database(ctx, "manual").await
+    }
+
+    /// Lists the database snapshots in the backup directory, the newest first.
+    pub async fn list_database_backups(
+        &self,
+        ctx: Arc<TaskContext>,
+    ) -> Result<Vec<DatabaseBackup>> {
+        database::list_backups(&ctx.config.backup_path).await
+    }
+
+    /// Stages a database snapshot to replace the database on the next start.
+    ///
+    /// The snapshot must pass an integrity check and must not have a newer schema than
+    /// this version knows. The current database is snapshotted too, so the restore
+    /// can be undone. It is staged before that, as the rotation may remove it.
+    ///
+    /// # Returns
+    /// A `Result` containing the schema version of the snapshot.
+    pub async fn restore_database(&self, ctx: Arc<TaskContext>, path: &Path) -> Result<i64> {
+        let db_path = &ctx.config.db_path;
+        let version = database::stage_restore(db_path, path).await?;
+        if let Err(e) = self
+            .storage
+            .backup_database(ctx.clone(), "pre-restore")
+            .await
+        {
+            database::cancel_staged_restore(db_path).await?;
+            return Err(e);
+        }
+        Ok(version)
+    }
+
+    /// Builds a statistics report of the local archive, optionally only of one author.
+    pub async fn get_archive_stats(
+        &self,
+        ctx: Arc<TaskContext>,
+        uid: Option<i64>,
+    ) -> Result<ArchiveStats> {
+        self.storage.stats(ctx, uid, TOP_LIMIT).await
+    }
+
     /// Searches for users in local storage by screen name prefix.
     pub async fn search_users_by_screen_name_prefix(&self, prefix: &str) -> Result<Vec<User>> {
         self.storage
@@ -265,6 +375,96 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
         Ok(())
     }
 
+    /// Backs up the posts missing from the archive of a user.
+    ///
+    /// The profile API only pages through a timeline from the newest post, so this walks
+    /// the timeline until it is past the oldest gap in the archive, saving every page.
+    /// When there are no gaps but posts are still missing, they may be anywhere and the
+    /// whole timeline is walked. The walk is bounded by the number of posts Weibo reports.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context.
+    /// * `options` - The user whose archive to complete.
+    #[tracing::instrument(skip(self, ctx), fields(uid = options.uid), level = "info")]
+    pub(super) async fn fill_coverage_gaps(
+        &self,
+        ctx: Arc<TaskContext>,
+        options: FillCoverageGapsOptions,
+    ) -> Result<()> {
+        let uid = options.uid;
+        let coverage = self.storage.get_coverage(Some(uid)).await?.pop();
+        if coverage.as_ref().is_some_and(|c| c.is_complete()) {
+            info!("Archive of user {uid} is complete, nothing to fill");
+            ctx.task_manager.update_progress(1, 1)?;
+            return Ok(());
+        }
+        let target = coverage
+            .as_ref()
+            .and_then(|c| c.fill_target())
+            .map(str::to_string);
+        let count = ctx.config.posts_count;
+        let num_pages = coverage
+            .as_ref()
+            .and_then(|c| c.remote_count)
+            .map_or(MAX_FILL_PAGES, |n| {
+                n.div_ceil(count.max(1) as u64) as u32 + 1
+            })
+            .min(MAX_FILL_PAGES);
+        info!("Filling archive of user {uid} back to {target:?}, at most {num_pages} pages");
+        ctx.task_manager.update_progress(0, num_pages as u64)?;
+
+        let mut failures = 0;
+        for page in 1..=num_pages {
+            if page > 1 {
+                sleep(ctx.config.backup_task_interval).await;
+            }
+            let posts = match self
+                .api_client
+                .profile_statuses(uid, page, ContainerType::Normal, count)
+                .await
+            {
+                Ok(posts) => posts,
+                Err(e) => {
+                    ctx.task_manager.report_task_error(TaskError {
+                        error_type: TaskErrorType::DownloadMedia(format!("page {page}")),
+                        message: e.to_string(),
+                    })?;
+                    ctx.task_manager
+                        .update_progress(page as u64, num_pages as u64)?;
+                    failures += 1;
+                    if failures >= MAX_FILL_FAILURES {
+                        return Err(Error::Context(
+                            format!("{failures} pages of user {uid} failed in a row"),
+                            Box::new(e),
+                        ));
+                    }
+                    continue;
+                }
+            };
+            failures = 0;
+            if posts.is_empty() {
+                info!("Reached the end of the timeline of user {uid} at page {page}");
+                break;
+            }
+            // A pinned post may be older than the rest of the first page
+            let past_target = target.as_deref().is_some_and(|target| {
+                posts
+                    .iter()
+                    .all(|p| p.created_at.format("%Y-%m").to_string().as_str() < target)
+            });
+            self.processer.process(ctx.clone(), posts).await?;
+            ctx.task_manager
+                .update_progress(page as u64, num_pages as u64)?;
+            if past_target {
+                info!("Passed the oldest gap of user {uid} at page {page}");
+                break;
+            }
+        }
+        ctx.task_manager
+            .update_progress(num_pages as u64, num_pages as u64)?;
+        Ok(())
+    }
+
     /// Fetches and processes a single page of posts for a user.
     #[tracing::instrument(skip(self, ctx), fields(uid, page))]
     async fn backup_one_page(
@@ -373,14 +573,9 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
         options: ExportJobOptions,
     ) -> Result<()> {
         let posts_per_page = crate::config::get_config().read()?.posts_per_html;
-        let mut query = options.query.clone();
-        query.posts_per_page = posts_per_page;
 
         // Get total count for progress tracking
-        let mut count_query = options.query.clone();
-        count_query.posts_per_page = 1;
-        count_query.page = 1;
-        let total_items = self.storage.query_posts(count_query).await?.total_items;
+        let total_items = self.storage.count_posts(options.query.clone()).await?;
         let total_pages = total_items.div_ceil(posts_per_page as u64);
         info!(
             "Exporting {} posts total, {} pages",
@@ -388,19 +583,20 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
         );
         ctx.task_manager.update_progress(0, total_pages)?;
 
+        let pages = self
+            .storage
+            .stream_posts(options.query)
+            .chunks(posts_per_page as usize);
+        pin_mut!(pages);
         let mut processed: u64 = 0;
-        for page_index in 1.. {
-            query.page = page_index;
-            let local_posts = self.storage.query_posts(query.clone()).await?;
-            if local_posts.posts.is_empty() {
-                info!("No more posts to export. Exiting loop.");
-                break;
-            }
+        while let Some(page) = pages.next().await {
+            let posts = page.into_iter().collect::<Result<Vec<_>>>()?;
+            let page_index = processed + 1;
 
             let page_name = make_page_name(&options.output.task_name, page_index as i32);
             let html = self
                 .html_generator
-                .generate_html(ctx.clone(), local_posts.posts, &page_name)
+                .generate_html(ctx.clone(), posts, &page_name)
                 .await?;
             self.exporter
                 .export_page(html, &page_name, &options.output.export_dir)
@@ -415,6 +611,252 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
         Ok(())
     }
 
+    /// Exports the posts matching a query that have coordinates as a single GeoJSON or
+    /// KML file, named after the task.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context.
+    /// * `options` - The query, map format and output location.
+    #[tracing::instrument(skip(self, ctx), level = "info")]
+    pub(super) async fn export_map(
+        &self,
+        ctx: Arc<TaskContext>,
+        options: MapExportOptions,
+    ) -> Result<()> {
+        let mut query = options.query;
+        query.has_location = Some(true);
+        let total = self.storage.count_posts(query.clone()).await?;
+        info!("Exporting {total} located posts as {:?}", options.format);
+        ctx.task_manager.update_progress(0, total)?;
+
+        let posts = self.storage.stream_posts(query);
+        pin_mut!(posts);
+        let mut writer = MapWriter::new(options.format);
+        let mut processed: u64 = 0;
+        while let Some(post) = posts.try_next().await? {
+            writer.push(&post)?;
+            processed += 1;
+            if processed.is_multiple_of(200) {
+                ctx.task_manager.update_progress(processed, total)?;
+            }
+        }
+
+        let (content, count) = writer.finish();
+        let file_name = format!(
+            "{}.{}",
+            options.output.task_name,
+            options.format.extension()
+        );
+        self.exporter
+            .export_file(&file_name, content.as_bytes(), &options.output.export_dir)
+            .await?;
+        ctx.task_manager.update_progress(total, total)?;
+        info!("Finished exporting map, {count} posts placed");
+        Ok(())
+    }
+
+    /// Exports a statistics report of the archive as a JSON file, or as CSV files named
+    /// after the task.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context.
+    /// * `options` - The user to report on, the file format and output location.
+    #[tracing::instrument(skip(self, ctx), level = "info")]
+    pub(super) async fn export_stats(
+        &self,
+        ctx: Arc<TaskContext>,
+        options: StatsExportOptions,
+    ) -> Result<()> {
+        ctx.task_manager.update_progress(0, 1)?;
+        let stats = self
+            .storage
+            .stats(ctx.clone(), options.uid, TOP_LIMIT)
+            .await?;
+        let files = render_stats(&stats, options.format, &options.output.task_name)?;
+        for (file_name, content) in files {
+            self.exporter
+                .export_file(&file_name, content.as_bytes(), &options.output.export_dir)
+                .await?;
+        }
+        ctx.task_manager.update_progress(1, 1)?;
+        info!(
+            "Finished exporting stats of {} posts as {:?}",
+            stats.total_posts, options.format
+        );
+        Ok(())
+    }
+
+    /// Checks that the database and the media directories agree and writes the report
+    /// as JSON, repairing what can be repaired first if asked to.
+    ///
+    /// Rows of missing media files are moved to a quarantine batch and the search index
+    /// is rebuilt if it is out of sync. Posts missing pictures or retweeted posts are
+    /// re-backed up, which fetches them from Weibo again. Orphan files are only reported.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context.
+    /// * `options` - Whether to repair, and where to write the report.
+    #[tracing::instrument(skip(self, ctx), fields(repair = options.repair), level = "info")]
+    pub(super) async fn verify_archive(
+        &self,
+        ctx: Arc<TaskContext>,
+        options: VerifyArchiveOptions,
+    ) -> Result<()> {
+        ctx.task_manager.update_progress(0, 1)?;
+        let mut report = self.storage.verify_archive(ctx.clone()).await?;
+        info!("Found {} issues in the archive", report.issue_count());
+
+        let mut total = 1;
+        if options.repair {
+            let mut summary = RepairSummary::default();
+            let picture_urls = report
+                .missing_picture_files
+                .iter()
+                .map(|p| p.url.clone())
+                .collect::<Vec<_>>();
+            let video_urls = report
+                .missing_video_files
+                .iter()
+                .map(|v| v.url.clone())
+                .collect::<Vec<_>>();
+            if !picture_urls.is_empty() || !video_urls.is_empty() {
+                let batch = self
+                    .quarantine_batch("verify_archive", &mut summary.batch)
+                    .await?;
+                summary.removed_media_rows = self
+                    .storage
+                    .quarantine_media_rows(&batch, &picture_urls, &video_urls)
+                    .await?;
+            }
+            if report.fts_out_of_sync() {
+                self.storage.rebuild_search_index().await?;
+                summary.search_index_rebuilt = true;
+            }
+
+            let ids = report.posts_to_rebackup();
+            total += ids.len() as u64;
+            ctx.task_manager.update_progress(1, total)?;
+            for (i, id) in ids.into_iter().enumerate() {
+                if i > 0 {
+                    sleep(ctx.config.backup_task_interval).await;
+                }
+                match self.rebackup_post(ctx.clone(), id).await {
+                    Ok(()) => summary.rebacked_up_posts += 1,
+                    Err(e) => {
+                        summary.failed_posts += 1;
+                        ctx.task_manager.report_task_error(TaskError {
+                            error_type: TaskErrorType::DownloadMedia(format!("rebackup post {id}")),
+                            message: e.to_string(),
+                        })?;
+                    }
+                }
+                ctx.task_manager.update_progress(i as u64 + 2, total)?;
+            }
+            info!("Repaired archive: {summary:?}");
+            report.repair = Some(summary);
+        }
+
+        let content = serde_json::to_string_pretty(&report)?;
+        self.exporter
+            .export_file(
+                &format!("{}.json", options.output.task_name),
+                content.as_bytes(),
+                &options.output.export_dir,
+            )
+            .await?;
+        ctx.task_manager.update_progress(total, total)?;
+        Ok(())
+    }
+
+    /// Lists the media files and rows that nothing references anymore and writes the
+    /// listing as JSON. Unless it is a dry run, the rows and files are then moved to a
+    /// quarantine batch, from which they can be restored.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context.
+    /// * `options` - Whether to only list the orphans, and where to write the listing.
+    #[tracing::instrument(skip(self, ctx), level = "info")]
+    pub(super) async fn collect_garbage(
+        &self,
+        ctx: Arc<TaskContext>,
+        options: CollectGarbageOptions,
+    ) -> Result<()> {
+        ctx.task_manager.update_progress(0, 1)?;
+        let mut report = self.storage.find_orphan_media(ctx.clone()).await?;
+        report.dry_run = options.dry_run;
+        info!(
+            "Found {} orphans taking {} bytes",
+            report.orphan_count(),
+            report.orphan_bytes
+        );
+
+        if !options.dry_run && report.orphan_count() > 0 {
+            let batch = self
+                .quarantine_batch("collect_garbage", &mut report.batch)
+                .await?;
+            let picture_urls = report
+                .orphan_picture_rows
+                .iter()
+                .map(|p| p.url.clone())
+                .collect::<Vec<_>>();
+            let video_urls = report
+                .orphan_video_rows
+                .iter()
+                .map(|v| v.url.clone())
+                .collect::<Vec<_>>();
+            report.removed_rows = self
+                .storage
+                .quarantine_media_rows(&batch, &picture_urls, &video_urls)
+                .await?;
+
+            let dir = ctx.config.quarantine_path.join(&batch);
+            let (picture_dir, video_dir) = (dir.join("pictures"), dir.join("videos"));
+            let files = report
+                .orphan_picture_files
+                .iter()
+                .map(|path| (&ctx.config.picture_path, path, &picture_dir))
+                .chain(
+                    report
+                        .orphan_video_files
+                        .iter()
+                        .map(|path| (&ctx.config.video_path, path, &video_dir)),
+                )
+                .collect::<Vec<_>>();
+            let total = files.len() as u64;
+            let mut removed_files = 0;
+            for (i, (root, path, dir)) in files.into_iter().enumerate() {
+                match move_to_quarantine(root, Path::new(path), dir).await {
+                    Ok(()) => removed_files += 1,
+                    Err(e) => {
+                        ctx.task_manager.report_task_error(TaskError {
+                            error_type: TaskErrorType::DownloadMedia(path.clone()),
+                            message: e.to_string(),
+                        })?;
+                    }
+                }
+                if (i as u64 + 1).is_multiple_of(100) {
+                    ctx.task_manager.update_progress(i as u64 + 1, total)?;
+                }
+            }
+            report.removed_files = removed_files;
+            info!(
+                "Moved {} orphan rows and {} orphan files to quarantine batch {batch}",
+                report.removed_rows, report.removed_files
+            );
+        }
+
+        let content = serde_json::to_string_pretty(&report)?;
+        self.exporter
+            .export_file(
+                &format!("{}.json", options.output.task_name),
+                content.as_bytes(),
+                &options.output.export_dir,
+            )
+            .await?;
+        ctx.task_manager.update_progress(1, 1)?;
+        Ok(())
+    }
+
     /// Queries local posts and enriches them with media/metadata.
     ///
     /// # Arguments
@@ -426,12 +868,15 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
         _ctx: Arc<TaskContext>,
         query: PostQuery,
     ) -> Result<PaginatedPostInfo> {
-        let paginated_posts = self.storage.query_posts(query).await?;
-        let posts_info = stream::iter(paginated_posts.posts)
+        let mut paginated_posts = self.storage.query_posts(query).await?;
+        let mut posts_info: Vec<PostInfo> = stream::iter(paginated_posts.posts)
             .map(|post| self.processer.build_post_info(post))
             .buffered(5)
             .try_collect()
             .await?;
+        for info in &mut posts_info {
+            info.snippet = paginated_posts.snippets.remove(&info.post.id);
+        }
 
         Ok(PaginatedPostInfo {
             posts: posts_info,
@@ -466,14 +911,18 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
         ctx: Arc<TaskContext>,
         query: PostQuery,
     ) -> Result<()> {
-        let ids = self.storage.query_all_post_ids(query).await?;
-        let total = ids.len();
+        let total = self.storage.count_posts(query.clone()).await?;
         info!("Found {} posts to re-backup", total);
-        ctx.task_manager.update_progress(0, total as u64)?;
+        ctx.task_manager.update_progress(0, total)?;
 
         let task_interval = ctx.config.backup_task_interval;
         let mut processed: u64 = 0;
-        for (i, id) in ids.into_iter().enumerate() {
+        let ids = self.storage.stream_post_ids(query);
+        pin_mut!(ids);
+        while let Some(id) = ids.try_next().await? {
+            if processed > 0 {
+                sleep(task_interval).await;
+            }
             let post_result = self.api_client.statuses_show(id).await;
             let process_result = match post_result {
                 Ok(post) => self.processer.process(ctx.clone(), vec![post]).await,
@@ -482,7 +931,7 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
 
             match process_result {
                 Ok(_) => {
-                    info!("re-backed up post {} ({}/{})", id, i + 1, total);
+                    info!("re-backed up post {} ({}/{})", id, processed + 1, total);
                 }
                 Err(e) => {
                     ctx.task_manager.report_task_error(TaskError {
@@ -493,11 +942,7 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
             }
             processed += 1;
             if processed.is_multiple_of(100) {
-                ctx.task_manager.update_progress(processed, total as u64)?;
-            }
-
-            if i < total - 1 {
-                sleep(task_interval).await;
+                ctx.task_manager.update_progress(processed, total)?;
             }
         }
         info!("Finished re-backing up posts.");
@@ -520,9 +965,23 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
             options.policy
         );
         let ids = self.storage.get_duplicate_pic_ids().await?;
+        info!("Found {} duplicate picture IDs", ids.len());
+        self.cleanup_picture_ids(ctx, ids, options).await?;
+        info!("Finished cleanup pictures task");
+        Ok(())
+    }
+
+    /// Keeps one definition of each of the given pictures according to the resolution
+    /// policy, and removes the others.
+    async fn cleanup_picture_ids(
+        &self,
+        ctx: Arc<TaskContext>,
+        ids: Vec<String>,
+        options: CleanupPicturesOptions,
+    ) -> Result<()> {
         let total = ids.len() as u64;
-        info!("Found {} duplicate picture IDs", total);
         ctx.task_manager.update_progress(0, total)?;
+        let mut report = CleanupReport::new("cleanup_pictures", options.cleanup.dry_run);
 
         let mut processed: u64 = 0;
         for id in ids {
@@ -544,11 +1003,10 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
                 _ => std::cmp::Ordering::Equal,
             });
 
-            // Keep the first one, delete the rest
+            // Keep the first one, remove the rest
             for pic in pictures.into_iter().skip(1) {
                 if let Err(e) = self
-                    .storage
-                    .delete_picture(ctx.clone(), pic.meta.url())
+                    .remove_picture(ctx.clone(), &mut report, pic.meta.url())
                     .await
                 {
                     ctx.task_manager.report_task_error(TaskError {
@@ -563,20 +1021,25 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
                 ctx.task_manager.update_progress(processed, total)?;
             }
         }
-        info!("Finished cleanup pictures task");
-        Ok(())
+        self.finish_cleanup(report, &options.cleanup).await
     }
 
     /// Identifies and removes invalid or outdated avatar files.
     ///
     /// # Arguments
     /// * `ctx` - The task context.
+    /// * `options` - Whether to only list the avatars, and where to write the list.
     #[tracing::instrument(skip(self, ctx), level = "info")]
-    pub(super) async fn cleanup_outdated_avatars(&self, ctx: Arc<TaskContext>) -> Result<()> {
+    pub(super) async fn cleanup_outdated_avatars(
+        &self,
+        ctx: Arc<TaskContext>,
+        options: CleanupOptions,
+    ) -> Result<()> {
         info!("Starting cleanup invalid avatars task");
         let duplicate_uids = self.storage.get_users_with_duplicate_avatars().await?;
         let total = duplicate_uids.len() as u64;
         ctx.task_manager.update_progress(0, total)?;
+        let mut report = CleanupReport::new("cleanup_outdated_avatars", options.dry_run);
 
         let users = self.storage.get_users_by_ids(&duplicate_uids).await?;
         let avatar_map: std::collections::HashMap<i64, String> = users
@@ -598,10 +1061,9 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
                         }
                     };
                     if pic_id != *current_id {
-                        info!("Deleting invalid avatar: {} for user {}", pic_id, user_id);
+                        info!("Removing invalid avatar: {} for user {}", pic_id, user_id);
                         if let Err(e) = self
-                            .storage
-                            .delete_picture(ctx.clone(), info.meta.url())
+                            .remove_picture(ctx.clone(), &mut report, info.meta.url())
                             .await
                         {
                             ctx.task_manager.report_task_error(TaskError {
@@ -619,6 +1081,7 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
                 ctx.task_manager.update_progress(processed, total)?;
             }
         }
+        self.finish_cleanup(report, &options).await?;
         info!("Finished cleanup invalid avatars task");
         Ok(())
     }
@@ -638,17 +1101,21 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
             "Starting cleanup invalid posts task with options: {:?}",
             options
         );
-        let ids = self
+        let total = self
             .storage
-            .get_invalid_posts_ids(options.clean_retweeted_invalid)
+            .count_invalid_posts(options.clean_retweeted_invalid)
             .await?;
-        let total = ids.len() as u64;
         info!("Found {} invalid posts to clean up", total);
         ctx.task_manager.update_progress(0, total)?;
+        let mut report = CleanupReport::new("cleanup_invalid_posts", options.cleanup.dry_run);
 
         let mut processed: u64 = 0;
-        for id in ids {
-            if let Err(e) = self.storage.delete_post(ctx.clone(), id, true).await {
+        let ids = self
+            .storage
+            .stream_invalid_posts_ids(options.clean_retweeted_invalid);
+        pin_mut!(ids);
+        while let Some(id) = ids.try_next().await? {
+            if let Err(e) = self.remove_post(ctx.clone(), &mut report, id).await {
                 ctx.task_manager.report_task_error(TaskError {
                     error_type: TaskErrorType::DownloadMedia(format!("delete post {}", id)),
                     message: e.to_string(),
@@ -659,51 +1126,705 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
                 ctx.task_manager.update_progress(processed, total)?;
             }
         }
+        self.finish_cleanup(report, &options.cleanup).await?;
         info!("Finished cleanup invalid posts task");
         Ok(())
     }
 
-    /// Re-fetches a single post from Weibo API and processes it.
+    /// Moves a picture to the quarantine batch of a cleanup, or only lists it in a dry run.
+    async fn remove_picture(
+        &self,
+        ctx: Arc<TaskContext>,
+        report: &mut CleanupReport,
+        url: &Url,
+    ) -> Result<()> {
+        if !report.dry_run {
+            let batch = self
+                .quarantine_batch(&report.task, &mut report.batch)
+                .await?;
+            self.storage.quarantine_picture(ctx, &batch, url).await?;
+        }
+        report.pictures.push(url.to_string());
+        Ok(())
+    }
+
+    /// Moves a post and its retweet tree to the quarantine batch of a cleanup, or only
+    /// lists it in a dry run.
+    async fn remove_post(
+        &self,
+        ctx: Arc<TaskContext>,
+        report: &mut CleanupReport,
+        id: i64,
+    ) -> Result<()> {
+        if !report.dry_run {
+            let batch = self
+                .quarantine_batch(&report.task, &mut report.batch)
+                .await?;
+            self.storage.quarantine_post(ctx, &batch, id).await?;
+        }
+        report.posts.push(id);
+        Ok(())
+    }
+
+    /// The quarantine batch of a cleanup, created when the first item is removed so that
+    /// runs removing nothing leave no batch behind.
+    ///
+    /// # Arguments
+    /// * `task` - The cleanup task, which the batch is named after.
+    /// * `batch` - The batch of the run so far, set once it is created.
+    async fn quarantine_batch(&self, task: &str, batch: &mut Option<String>) -> Result<String> {
+        if let Some(batch) = batch {
+            return Ok(batch.clone());
+        }
+        let name = batch_name(task, Local::now());
+        self.storage.create_quarantine_batch(&name, task).await?;
+        info!("Moving removed items to quarantine batch {name}");
+        *batch = Some(name.clone());
+        Ok(name)
+    }
+
+    /// Writes the report of a cleanup, if asked for.
+    async fn finish_cleanup(&self, report: CleanupReport, options: &CleanupOptions) -> Result<()> {
+        info!(
+            "{} {} pictures and {} posts",
+            if report.dry_run {
+                "Would remove"
+            } else {
+                "Removed"
+            },
+            report.pictures.len(),
+            report.posts.len()
+        );
+        if let Some(output) = &options.output {
+            let content = serde_json::to_string_pretty(&report)?;
+            self.exporter
+                .export_file(
+                    &format!("{}.json", output.task_name),
+                    content.as_bytes(),
+                    &output.export_dir,
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Puts back the rows and files a cleanup task moved to a quarantine batch.
     ///
     /// # Arguments
     /// * `ctx` - The task context.
-    /// * `id` - The post ID to refresh.
+    /// * `options` - The batch to restore.
     #[tracing::instrument(skip(self, ctx), level = "info")]
-    pub async fn rebackup_post(&self, ctx: Arc<TaskContext>, id: i64) -> Result<()> {
-        let post = self.api_client.statuses_show(id).await?;
-        self.processer.process(ctx, vec![post]).await
+    pub(super) async fn restore_quarantine(
+        &self,
+        ctx: Arc<TaskContext>,
+        options: RestoreQuarantineOptions,
+    ) -> Result<()> {
+        ctx.task_manager.update_progress(0, 1)?;
+        let summary = self
+            .storage
+            .restore_quarantine(ctx.clone(), &options.batch)
+            .await?;
+        info!(
+            "Restored {} rows and {} files of quarantine batch {}, kept {} rows and {} files saved since",
+            summary.restored_rows,
+            summary.restored_files,
+            options.batch,
+            summary.skipped_rows,
+            summary.skipped_files
+        );
+        ctx.task_manager.update_progress(1, 1)?;
+        Ok(())
     }
 
-    /// Scans all local posts and re-backups those that have missing images.
+    /// Merges another archive, its database and media files, into this one.
+    ///
+    /// The other database is copied and migrated to the current schema first, so it is
+    /// left untouched, and the current database is snapshotted. The media files of the
+    /// added rows are then copied, and the merge report is written to the output.
     ///
     /// # Arguments
     /// * `ctx` - The task context.
-    /// * `query` - Search criteria for posts to check.
-    pub(super) async fn rebackup_missing_images(
+    /// * `options` - The other archive and where to write the report.
+    #[tracing::instrument(skip(self, ctx), level = "info")]
+    pub(super) async fn merge_archive(
         &self,
         ctx: Arc<TaskContext>,
-        query: PostQuery,
+        options: MergeArchiveOptions,
     ) -> Result<()> {
-        info!("Starting re-backup missing images task");
-        let ids = self.storage.query_all_post_ids(query).await?;
-        let total = ids.len();
-        info!("Scanning {total} posts for missing images");
-        ctx.task_manager.update_progress(0, total as u64)?;
+        ctx.task_manager.update_progress(0, 1)?;
+        let mut outcome = self
+            .merge_database_file(ctx.clone(), &options.db_path)
+            .await?;
 
-        let task_interval = ctx.config.backup_task_interval;
-        let mut processed: u64 = 0;
-        for (i, id) in ids.into_iter().enumerate() {
-            let post_opt = self.storage.get_post(id).await?;
-            let post = match post_opt {
-                Some(p) => p,
-                None => {
-                    processed += 1;
-                    warn!("Post {} not found, skipping ({}/{})", id, i + 1, total);
-                    continue;
+        let pictures = outcome
+            .pictures
+            .iter()
+            .map(|f| (&options.picture_path, &ctx.config.picture_path, &f.path));
+        let videos = outcome
+            .videos
+            .iter()
+            .map(|f| (&options.video_path, &ctx.config.video_path, &f.path));
+        let files = pictures.chain(videos).collect::<Vec<_>>();
+        let total = files.len() as u64;
+        let (mut copied, mut skipped, mut missing) = (0, 0, Vec::new());
+        for (i, (from_root, to_root, path)) in files.into_iter().enumerate() {
+            let from = from_root.join(path);
+            let to = to_root.join(path);
+            if to.exists() {
+                skipped += 1;
+            } else if !from.exists() {
+                missing.push(path.clone());
+            } else {
+                let copy_file = async {
+                    if let Some(parent) = to.parent() {
+                        fs::create_dir_all(parent).await?;
+                    }
+                    fs::copy(&from, &to).await
+                };
+                match copy_file.await {
+                    Ok(_) => copied += 1,
+                    Err(e) => {
+                        ctx.task_manager.report_task_error(TaskError {
+                            error_type: TaskErrorType::DownloadMedia(path.clone()),
+                            message: e.to_string(),
+                        })?;
+                    }
                 }
-            };
-
-            let has_missing = match self
+            }
+            if (i as u64 + 1).is_multiple_of(100) {
+                ctx.task_manager.update_progress(i as u64 + 1, total)?;
+            }
+        }
+        outcome.report.files_copied = copied;
+        outcome.report.files_skipped = skipped;
+        outcome.report.missing_files = missing;
+        let report = outcome.report;
+        info!(
+            "Merged {} new and {} replaced posts, {} conflicts, copied {} files",
+            report.posts_added,
+            report.posts_replaced,
+            report.conflicts.len(),
+            report.files_copied
+        );
+
+        self.write_merge_report(&report, &options.output).await?;
+        ctx.task_manager.update_progress(1, 1)?;
+        Ok(())
+    }
+
+    /// Merges the database of another archive or a bundle, through a temporary copy so
+    /// that the file itself is left untouched.
+    async fn merge_database_file(
+        &self,
+        ctx: Arc<TaskContext>,
+        db_path: &Path,
+    ) -> Result<MergeOutcome> {
+        database::check_backup(db_path).await?;
+        let copy = std::env::temp_dir().join(format!(
+            "weiback_merge_{}.db",
+            chrono::Utc::now().timestamp_millis()
+        ));
+        database::copy_database(db_path, &copy).await?;
+        let result = self.merge_copy(ctx, db_path, &copy).await;
+        if let Err(e) = fs::remove_file(&copy).await {
+            error!("remove merge copy {copy:?} failed: {e}");
+        }
+        result
+    }
+
+    /// Migrates the copy of the other database, snapshots the current one and merges.
+    async fn merge_copy(
+        &self,
+        ctx: Arc<TaskContext>,
+        source: &Path,
+        copy: &Path,
+    ) -> Result<MergeOutcome> {
+        let pool = database::create_db_pool_with_url(&copy.to_string_lossy()).await?;
+        pool.close().await;
+        self.storage.backup_database(ctx, "pre-merge").await?;
+        info!("Merging archive {source:?}");
+        self.storage.merge_archive(copy).await
+    }
+
+    /// Writes the report of a merge as `{task_name}.json`.
+    async fn write_merge_report(
+        &self,
+        report: &MergeReport,
+        output: &ExportOutputConfig,
+    ) -> Result<()> {
+        let content = serde_json::to_string_pretty(report)?;
+        self.exporter
+            .export_file(
+                &format!("{}.json", output.task_name),
+                content.as_bytes(),
+                &output.export_dir,
+            )
+            .await
+    }
+
+    /// Exports the posts matching a query as a bundle file, with the posts they
+    /// retweet, their authors, and all their media files, see [`crate::bundle`].
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context.
+    /// * `options` - The posts to export and where to write the bundle.
+    #[tracing::instrument(skip(self, ctx), level = "info")]
+    pub(super) async fn export_bundle(
+        &self,
+        ctx: Arc<TaskContext>,
+        options: ExportBundleOptions,
+    ) -> Result<()> {
+        info!("Starting export bundle task");
+        let ids = self
+            .storage
+            .query_all_post_ids(options.query.clone())
+            .await?;
+        let path = options
+            .output
+            .export_dir
+            .join(format!("{}.{BUNDLE_EXTENSION}", options.output.task_name));
+        let bundle = Bundle::create(&path).await?;
+        let result = self.write_bundle(ctx, &options, &ids, &bundle).await;
+        bundle.close().await;
+        if result.is_err()
+            && let Err(e) = fs::remove_file(&path).await
+        {
+            error!("remove incomplete bundle {path:?} failed: {e}");
+        }
+        result
+    }
+
+    /// Copies the posts and their media files into a new bundle, then writes its manifest.
+    async fn write_bundle(
+        &self,
+        ctx: Arc<TaskContext>,
+        options: &ExportBundleOptions,
+        ids: &[i64],
+        bundle: &Bundle,
+    ) -> Result<()> {
+        let contents = self.storage.fill_bundle(ids, bundle.path()).await?;
+        info!(
+            "Copied {} posts and {} users into bundle {:?}",
+            contents.posts,
+            contents.users,
+            bundle.path()
+        );
+
+        let pictures = contents
+            .pictures
+            .iter()
+            .map(|f| (BundleFileKind::Picture, &ctx.config.picture_path, &f.path));
+        let videos = contents
+            .videos
+            .iter()
+            .map(|f| (BundleFileKind::Video, &ctx.config.video_path, &f.path));
+        let files = pictures.chain(videos).collect::<Vec<_>>();
+        let total = files.len() as u64;
+        ctx.task_manager.update_progress(0, total)?;
+        let (mut added, mut missing) = (0, Vec::new());
+        for (i, (kind, root, path)) in files.into_iter().enumerate() {
+            match fs::read(root.join(path)).await {
+                Ok(data) => {
+                    bundle.add_file(kind, path, &data).await?;
+                    added += 1;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => missing.push(path.clone()),
+                Err(e) => {
+                    ctx.task_manager.report_task_error(TaskError {
+                        error_type: TaskErrorType::DownloadMedia(path.clone()),
+                        message: e.to_string(),
+                    })?;
+                }
+            }
+            if (i as u64 + 1).is_multiple_of(100) {
+                ctx.task_manager.update_progress(i as u64 + 1, total)?;
+            }
+        }
+
+        let manifest = [
+            ("created_at", chrono::Utc::now().timestamp().to_string()),
+            ("query", serde_json::to_string(&options.query)?),
+            ("posts", contents.posts.to_string()),
+            ("users", contents.users.to_string()),
+            ("files", added.to_string()),
+            ("missing_files", serde_json::to_string(&missing)?),
+        ];
+        for (key, value) in manifest {
+            bundle.set_manifest(key, &value).await?;
+        }
+        info!(
+            "Exported {} posts and {added} media files into bundle {:?}, {} files missing",
+            contents.posts,
+            bundle.path(),
+            missing.len()
+        );
+        ctx.task_manager.update_progress(total, total)?;
+        Ok(())
+    }
+
+    /// Imports a bundle file: its database is merged like another archive, see
+    /// [`Self::merge_archive`], then the media files of the added rows are written out
+    /// of the bundle after checking them against their checksums. The merge report is
+    /// written to the output.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context.
+    /// * `options` - The bundle and where to write the report.
+    #[tracing::instrument(skip(self, ctx), level = "info")]
+    pub(super) async fn import_bundle(
+        &self,
+        ctx: Arc<TaskContext>,
+        options: ImportBundleOptions,
+    ) -> Result<()> {
+        ctx.task_manager.update_progress(0, 1)?;
+        let bundle = Bundle::open(&options.path).await?;
+        let result = self.import_bundle_files(ctx.clone(), &bundle).await;
+        bundle.close().await;
+        let report = result?;
+        info!(
+            "Imported {} new and {} replaced posts, {} conflicts, wrote {} files, {} corrupt",
+            report.posts_added,
+            report.posts_replaced,
+            report.conflicts.len(),
+            report.files_copied,
+            report.corrupt_files.len()
+        );
+
+        self.write_merge_report(&report, &options.output).await?;
+        ctx.task_manager.update_progress(1, 1)?;
+        Ok(())
+    }
+
+    /// Merges the database of an open bundle and writes out the media files of the
+    /// added rows.
+    async fn import_bundle_files(
+        &self,
+        ctx: Arc<TaskContext>,
+        bundle: &Bundle,
+    ) -> Result<MergeReport> {
+        let mut outcome = self.merge_database_file(ctx.clone(), bundle.path()).await?;
+
+        let pictures = outcome
+            .pictures
+            .iter()
+            .map(|f| (BundleFileKind::Picture, &ctx.config.picture_path, &f.path));
+        let videos = outcome
+            .videos
+            .iter()
+            .map(|f| (BundleFileKind::Video, &ctx.config.video_path, &f.path));
+        let files = pictures.chain(videos).collect::<Vec<_>>();
+        let total = files.len() as u64;
+        let report = &mut outcome.report;
+        for (i, (kind, root, path)) in files.into_iter().enumerate() {
+            let to = root.join(path);
+            if to.exists() {
+                report.files_skipped += 1;
+            } else {
+                match bundle.read_file(kind, path).await {
+                    Ok(Some(data)) => {
+                        let write_file = async {
+                            if let Some(parent) = to.parent() {
+                                fs::create_dir_all(parent).await?;
+                            }
+                            fs::write(&to, data).await
+                        };
+                        match write_file.await {
+                            Ok(_) => report.files_copied += 1,
+                            Err(e) => {
+                                ctx.task_manager.report_task_error(TaskError {
+                                    error_type: TaskErrorType::DownloadMedia(path.clone()),
+                                    message: e.to_string(),
+                                })?;
+                            }
+                        }
+                    }
+                    Ok(None) => report.missing_files.push(path.clone()),
+                    Err(e) => {
+                        ctx.task_manager.report_task_error(TaskError {
+                            error_type: TaskErrorType::DownloadMedia(path.clone()),
+                            message: e.to_string(),
+                        })?;
+                        report.corrupt_files.push(path.clone());
+                    }
+                }
+            }
+            if (i as u64 + 1).is_multiple_of(100) {
+                ctx.task_manager.update_progress(i as u64 + 1, total)?;
+            }
+        }
+        Ok(outcome.report)
+    }
+
+    /// Moves or copies the media directories and the database to new locations, see
+    /// [`crate::relocate`]. The configuration is switched to each new media directory
+    /// once all its files are in place, and to the new database file, which the database
+    /// is moved to on the next start. Files saved while copying are picked up by a
+    /// second pass.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context.
+    /// * `options` - The new locations and whether to keep the old files.
+    #[tracing::instrument(skip(self, ctx), level = "info")]
+    pub(super) async fn relocate_storage(
+        &self,
+        ctx: Arc<TaskContext>,
+        options: RelocateStorageOptions,
+    ) -> Result<()> {
+        info!("Starting relocate storage task");
+        let picture_path = options
+            .picture_path
+            .as_ref()
+            .filter(|to| **to != ctx.config.picture_path);
+        let video_path = options
+            .video_path
+            .as_ref()
+            .filter(|to| **to != ctx.config.video_path);
+        let db_path = options
+            .db_path
+            .as_ref()
+            .filter(|to| **to != ctx.config.db_path);
+        let dirs = [
+            (&ctx.config.picture_path, picture_path),
+            (&ctx.config.video_path, video_path),
+        ];
+        for (from, to) in dirs {
+            if let Some(to) = to {
+                relocate::check_locations(from, to)?;
+            }
+        }
+        if let Some(db_path) = db_path
+            && db_path.exists()
+        {
+            return Err(Error::ConfigError(format!(
+                "database {db_path:?} already exists"
+            )));
+        }
+
+        // The directories that have to be copied file by file
+        let mut summaries = [RelocateSummary::default(), RelocateSummary::default()];
+        let mut pending = Vec::new();
+        for (i, (from, to)) in dirs.into_iter().enumerate() {
+            let Some(to) = to else {
+                continue;
+            };
+            // A directory renamed by an interrupted run is already in place
+            if !from.exists() {
+                continue;
+            }
+            if options.mode == RelocateMode::Move && relocate::try_rename(from, to).await? {
+                info!("Renamed {from:?} to {to:?}");
+                summaries[i].renamed = true;
+                continue;
+            }
+            let mut files = list_files(from).await?.into_iter().collect::<Vec<_>>();
+            files.sort();
+            pending.push((i, from, to, files));
+        }
+
+        let mut total: u64 = pending.iter().map(|(.., files)| files.len() as u64).sum();
+        ctx.task_manager.update_progress(0, total)?;
+        let mut processed: u64 = 0;
+        // The number of files of each directory copied so far
+        let mut copied = vec![0; pending.len()];
+        // Files saved while copying, e.g. pictures fetched by a short task, are picked
+        // up by a second pass before the configuration is switched
+        for pass in 0..2 {
+            if pass > 0 {
+                let mut added = 0;
+                for (_, from, _, files) in pending.iter_mut() {
+                    let known = files.iter().cloned().collect::<HashSet<_>>();
+                    let mut new_files = list_files(from.as_path())
+                        .await?
+                        .into_iter()
+                        .filter(|file| !known.contains(file))
+                        .collect::<Vec<_>>();
+                    new_files.sort();
+                    added += new_files.len() as u64;
+                    files.extend(new_files);
+                }
+                if added == 0 {
+                    break;
+                }
+                info!("Found {added} files saved while copying");
+                total += added;
+            }
+            for ((i, from, to, files), copied) in pending.iter().zip(copied.iter_mut()) {
+                let files = &files[*copied..];
+                *copied += files.len();
+                info!("Copying {} files from {from:?} to {to:?}", files.len());
+                for file in files {
+                    let relative = file.to_string_lossy().into_owned();
+                    let result = relocate::relocate_file(
+                        &from.join(file),
+                        &to.join(file),
+                        &mut summaries[*i],
+                    )
+                    .await;
+                    if let Err(e) = result {
+                        ctx.task_manager.report_task_error(TaskError {
+                            error_type: TaskErrorType::DownloadMedia(relative.clone()),
+                            message: e.to_string(),
+                        })?;
+                        summaries[*i].failed.push(relative);
+                    }
+                    processed += 1;
+                    if processed.is_multiple_of(100) {
+                        ctx.task_manager.update_progress(processed, total)?;
+                    }
+                }
+            }
+        }
+
+        let mut config = crate::config::get_config().read()?.clone();
+        if let Some(to) = picture_path
+            && summaries[0].failed.is_empty()
+        {
+            config.picture_path = to.clone();
+        }
+        if let Some(to) = video_path
+            && summaries[1].failed.is_empty()
+        {
+            config.video_path = to.clone();
+        }
+        if let Some(to) = db_path {
+            database::stage_relocation(&ctx.config.db_path, to, options.mode).await?;
+            config.db_path = to.clone();
+        }
+        crate::config::save_config(&config)?;
+        info!("Switched the configuration to the new locations");
+
+        if options.mode == RelocateMode::Move {
+            for (i, from, _, files) in pending.iter() {
+                if !summaries[*i].failed.is_empty() {
+                    continue;
+                }
+                for file in files {
+                    if let Err(e) = fs::remove_file(from.join(file)).await {
+                        error!("remove relocated file {file:?} failed: {e}");
+                    }
+                }
+                relocate::remove_empty_dirs(from).await?;
+            }
+        }
+
+        for ((from, to), summary) in dirs.iter().zip(summaries.iter()) {
+            if let Some(to) = to {
+                info!(
+                    "Relocated {from:?} to {to:?}: renamed {}, {} files copied, {} kept, {} failed",
+                    summary.renamed,
+                    summary.copied,
+                    summary.kept,
+                    summary.failed.len()
+                );
+            }
+        }
+        ctx.task_manager.update_progress(total, total)?;
+        let failed = summaries.iter().map(|s| s.failed.len()).sum::<usize>();
+        if failed > 0 {
+            return Err(Error::Io(std::io::Error::other(format!(
+                "{failed} files could not be relocated, their directories were left in place"
+            ))));
+        }
+        Ok(())
+    }
+
+    /// Maintains the database: checks its integrity, refreshes the statistics of the
+    /// query planner, optimizes the search index and vacuums it, see
+    /// [`crate::maintenance`]. A corrupt database is left untouched after the check.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context.
+    /// * `options` - Where to write the report, if anywhere.
+    #[tracing::instrument(skip(self, ctx), level = "info")]
+    pub(super) async fn maintain_database(
+        &self,
+        ctx: Arc<TaskContext>,
+        options: MaintainDatabaseOptions,
+    ) -> Result<()> {
+        info!("Starting maintain database task");
+        let total = MaintenanceStep::ALL.len() as u64;
+        ctx.task_manager.update_progress(0, total)?;
+        let started = chrono::Utc::now().timestamp();
+        let mut report = MaintenanceReport {
+            started_at: started,
+            size_before: self.storage.database_size().await?,
+            ..Default::default()
+        };
+        for (i, step) in MaintenanceStep::ALL.into_iter().enumerate() {
+            info!("Running maintenance step {step:?}");
+            let problems = self.storage.run_maintenance_step(step).await?;
+            report.steps.push(step);
+            report.integrity_errors.extend(problems);
+            ctx.task_manager.update_progress(i as u64 + 1, total)?;
+            if !report.integrity_errors.is_empty() {
+                break;
+            }
+        }
+        report.size_after = self.storage.database_size().await?;
+        report.duration_secs = chrono::Utc::now().timestamp() - started;
+        info!(
+            "Maintained database: {} bytes before, {} after, {} integrity errors",
+            report.size_before,
+            report.size_after,
+            report.integrity_errors.len()
+        );
+
+        if let Some(output) = &options.output {
+            let content = serde_json::to_string_pretty(&report)?;
+            self.exporter
+                .export_file(
+                    &format!("{}.json", output.task_name),
+                    content.as_bytes(),
+                    &output.export_dir,
+                )
+                .await?;
+        }
+        if !report.integrity_errors.is_empty() {
+            return Err(Error::DbError(format!(
+                "integrity check failed, restore the database from a snapshot: {}",
+                report.integrity_errors.join("; ")
+            )));
+        }
+        self.storage
+            .set_last_maintenance(chrono::Utc::now().timestamp())
+            .await?;
+        ctx.task_manager.update_progress(total, total)?;
+        Ok(())
+    }
+
+    /// Re-fetches a single post from Weibo API and processes it.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context.
+    /// * `id` - The post ID to refresh.
+    #[tracing::instrument(skip(self, ctx), level = "info")]
+    pub async fn rebackup_post(&self, ctx: Arc<TaskContext>, id: i64) -> Result<()> {
+        let post = self.api_client.statuses_show(id).await?;
+        self.processer.process(ctx, vec![post]).await
+    }
+
+    /// Scans all local posts and re-backups those that have missing images.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context.
+    /// * `query` - Search criteria for posts to check.
+    pub(super) async fn rebackup_missing_images(
+        &self,
+        ctx: Arc<TaskContext>,
+        query: PostQuery,
+    ) -> Result<()> {
+        info!("Starting re-backup missing images task");
+        let total = self.storage.count_posts(query.clone()).await?;
+        info!("Scanning {total} posts for missing images");
+        ctx.task_manager.update_progress(0, total)?;
+
+        let task_interval = ctx.config.backup_task_interval;
+        let mut processed: u64 = 0;
+        let posts = self.storage.stream_posts(query);
+        pin_mut!(posts);
+        while let Some(post) = posts.try_next().await? {
+            let id = post.id;
+            let has_missing = match self
                 .processer
                 .is_any_image_missing(ctx.clone(), &post)
                 .await
@@ -718,7 +1839,6 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
                         )),
                         message: e.to_string(),
                     })?;
-                    info!("Scanned post {} ({}/{})", id, i + 1, total);
                     false
                 }
             };
@@ -750,15 +1870,251 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
             }
             processed += 1;
             if processed.is_multiple_of(100) || has_missing {
-                ctx.task_manager.update_progress(processed, total as u64)?;
+                ctx.task_manager.update_progress(processed, total)?;
             }
-            info!("Scanned post {} ({}/{})", id, i + 1, total);
+            info!("Scanned post {} ({}/{})", id, processed, total);
         }
 
         info!("Finished re-backup missing images task");
         Ok(())
     }
 
+    /// Re-downloads pictures that are only stored below the configured definition.
+    ///
+    /// Each picture walks its definition fallback chain from the configured
+    /// definition downwards and stops at the best one already on disk. Once every
+    /// post has been scanned, the lower-definition copies of the upgraded pictures are
+    /// moved to quarantine, as [`Self::cleanup_pictures`] does with
+    /// [`ResolutionPolicy::Highest`].
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context.
+    /// * `query` - Search criteria for posts to upgrade.
+    #[tracing::instrument(skip(self, ctx), level = "info")]
+    pub(super) async fn upgrade_pictures(
+        &self,
+        ctx: Arc<TaskContext>,
+        query: PostQuery,
+    ) -> Result<()> {
+        info!("Starting upgrade pictures task");
+        let total = self.storage.count_posts(query.clone()).await?;
+        info!("Scanning {total} posts for low definition pictures");
+        ctx.task_manager.update_progress(0, total)?;
+
+        let mut processed: u64 = 0;
+        let mut upgraded = Vec::new();
+        let posts = self.storage.stream_posts(query);
+        pin_mut!(posts);
+        while let Some(post) = posts.try_next().await? {
+            match self.processer.upgrade_pictures(ctx.clone(), &post).await {
+                Ok(ids) => upgraded.extend(ids),
+                Err(e) => {
+                    let id = post.id;
+                    error!("Failed to upgrade pictures for post {id}: {e}");
+                    ctx.task_manager.report_task_error(TaskError {
+                        error_type: TaskErrorType::DownloadMedia(format!(
+                            "upgrade pictures for post {id}"
+                        )),
+                        message: e.to_string(),
+                    })?;
+                }
+            }
+            processed += 1;
+            if processed.is_multiple_of(100) {
+                ctx.task_manager.update_progress(processed, total)?;
+            }
+        }
+        ctx.task_manager.update_progress(processed, total)?;
+        info!(
+            "Upgraded {} pictures, removing lower definition copies",
+            upgraded.len()
+        );
+
+        upgraded.sort();
+        upgraded.dedup();
+        self.cleanup_picture_ids(
+            ctx,
+            upgraded,
+            CleanupPicturesOptions {
+                policy: ResolutionPolicy::Highest,
+                cleanup: CleanupOptions::default(),
+            },
+        )
+        .await?;
+        info!("Finished upgrade pictures task");
+        Ok(())
+    }
+
+    /// Re-derives posts from the raw API JSON kept in storage.
+    ///
+    /// Each post is re-parsed with the current models and goes through
+    /// [`PostProcesser::process`] again, so that its row and pictures are refreshed.
+    /// No API call is made; posts without raw JSON are skipped.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context.
+    /// * `query` - Search criteria for posts to reprocess.
+    #[tracing::instrument(skip(self, ctx), level = "info")]
+    pub(super) async fn reprocess_posts(
+        &self,
+        ctx: Arc<TaskContext>,
+        query: PostQuery,
+    ) -> Result<()> {
+        info!("Starting reprocess posts task");
+        let total = self.storage.count_posts(query.clone()).await?;
+        info!("Reprocessing {total} posts");
+        ctx.task_manager.update_progress(0, total)?;
+
+        let mut processed: u64 = 0;
+        let mut skipped = 0;
+        let ids = self.storage.stream_post_ids(query);
+        pin_mut!(ids);
+        while let Some(id) = ids.try_next().await? {
+            let result = match self.reparse_post(id).await {
+                Ok(Some(post)) => self.processer.process(ctx.clone(), vec![post]).await,
+                Ok(None) => {
+                    skipped += 1;
+                    Ok(())
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                error!("Failed to reprocess post {id}: {e}");
+                ctx.task_manager.report_task_error(TaskError {
+                    error_type: TaskErrorType::DownloadMedia(format!("reprocess post {id}")),
+                    message: e.to_string(),
+                })?;
+            }
+            processed += 1;
+            if processed.is_multiple_of(100) {
+                ctx.task_manager.update_progress(processed, total)?;
+            }
+        }
+        ctx.task_manager.update_progress(processed, total)?;
+        info!("Finished reprocess posts task, {skipped} posts without raw JSON skipped");
+        Ok(())
+    }
+
+    /// Rebuilds the full-text search index from the stored posts.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context.
+    #[tracing::instrument(skip(self, ctx), level = "info")]
+    pub(super) async fn rebuild_search_index(&self, ctx: Arc<TaskContext>) -> Result<()> {
+        info!("Starting rebuild search index task");
+        ctx.task_manager.update_progress(0, 1)?;
+        self.storage.rebuild_search_index().await?;
+        ctx.task_manager.update_progress(1, 1)?;
+        info!("Finished rebuild search index task");
+        Ok(())
+    }
+
+    /// Re-extracts the mentions and topics of all posts, filling in posts saved before
+    /// they were indexed.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context.
+    #[tracing::instrument(skip(self, ctx), level = "info")]
+    pub(super) async fn reindex_mentions_and_topics(&self, ctx: Arc<TaskContext>) -> Result<()> {
+        const BATCH_SIZE: usize = 500;
+        info!("Starting reindex mentions and topics task");
+        let total = self.storage.count_posts(PostQuery::default()).await?;
+        ctx.task_manager.update_progress(0, total)?;
+        let mut processed = 0;
+        let ids = self
+            .storage
+            .stream_post_ids(PostQuery::default())
+            .try_chunks(BATCH_SIZE)
+            .map_err(|e| e.1);
+        pin_mut!(ids);
+        while let Some(batch) = ids.try_next().await? {
+            self.storage.reindex_mentions_and_topics(&batch).await?;
+            processed += batch.len() as u64;
+            ctx.task_manager.update_progress(processed, total)?;
+        }
+        info!("Finished reindex mentions and topics task, {total} posts processed");
+        Ok(())
+    }
+
+    /// Resolves the short links in post text to their destinations and saves them, so
+    /// exports can link to the real pages after the shortener is gone.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context.
+    /// * `options` - Whether to retry links that failed before.
+    #[tracing::instrument(skip(self, ctx), level = "info")]
+    pub(super) async fn resolve_links(
+        &self,
+        ctx: Arc<TaskContext>,
+        options: ResolveLinksOptions,
+    ) -> Result<()> {
+        const CONCURRENCY: usize = 4;
+        info!("Starting resolve links task");
+        let short_urls = self
+            .storage
+            .get_unresolved_short_urls(options.retry_failed)
+            .await?;
+        let total = short_urls.len() as u64;
+        info!("Found {total} short links to resolve");
+        ctx.task_manager.update_progress(0, total)?;
+
+        let mut links = stream::iter(short_urls.iter())
+            .map(|url| self.link_resolver.resolve(url))
+            .buffer_unordered(CONCURRENCY);
+        let mut processed = 0;
+        let mut failed = 0;
+        while let Some(link) = links.next().await {
+            if !link.is_resolved() {
+                failed += 1;
+            }
+            self.storage.save_link(&link).await?;
+            processed += 1;
+            ctx.task_manager.update_progress(processed, total)?;
+        }
+        info!("Finished resolve links task. Processed: {total}, unreachable: {failed}");
+        Ok(())
+    }
+
+    /// Enables or disables folding traditional Chinese to simplified in search, which
+    /// rebuilds the full-text search index.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context.
+    /// * `enabled` - Whether search text should be folded.
+    #[tracing::instrument(skip(self, ctx), level = "info")]
+    pub(super) async fn set_script_folding(
+        &self,
+        ctx: Arc<TaskContext>,
+        enabled: bool,
+    ) -> Result<()> {
+        info!("Starting set script folding task");
+        ctx.task_manager.update_progress(0, 1)?;
+        self.storage.set_script_folding(enabled).await?;
+        ctx.task_manager.update_progress(1, 1)?;
+        info!("Finished set script folding task");
+        Ok(())
+    }
+
+    /// Re-parses a post, together with its retweeted post, from the stored raw JSON.
+    ///
+    /// # Returns
+    /// `None` if no raw JSON was kept for the post.
+    async fn reparse_post(&self, id: i64) -> Result<Option<Post>> {
+        let Some(raw) = self.storage.get_post_raw(id).await? else {
+            return Ok(None);
+        };
+        let retweeted = match raw_retweeted_id(&raw) {
+            Some(retweeted_id) => self
+                .storage
+                .get_post_raw(retweeted_id)
+                .await?
+                .map(|raw| post_from_raw(raw, None))
+                .transpose()?,
+            None => None,
+        };
+        post_from_raw(raw, retweeted).map(Some)
+    }
+
     /// Cleans up invalid pictures (e.g., "image deleted" placeholders) from local storage.
     ///
     /// This function:
@@ -766,11 +2122,16 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
     /// 2. Checks file size - if > 15kB, skips (can't be invalid placeholder)
     /// 3. If <= 15kB, tries to parse the image - if can't parse, it's invalid
     /// 4. Otherwise, uses ImageValidator to check if it's invalid
-    /// 5. Deletes invalid images from both filesystem and database
+    /// 5. Moves invalid images to quarantine, or only lists them in a dry run
     ///
     /// # Arguments
     /// * `ctx` - The task context.
-    pub(super) async fn cleanup_invalid_pictures(&self, ctx: Arc<TaskContext>) -> Result<()> {
+    /// * `options` - Whether to only list the pictures, and where to write the list.
+    pub(super) async fn cleanup_invalid_pictures(
+        &self,
+        ctx: Arc<TaskContext>,
+        options: CleanupOptions,
+    ) -> Result<()> {
         info!("Starting cleanup invalid pictures task");
 
         // Get total count first for progress tracking
@@ -781,6 +2142,7 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
 
         let mut deleted_count: u64 = 0;
         let storage = self.storage.clone();
+        let mut report = CleanupReport::new("cleanup_invalid_pictures", options.dry_run);
 
         // Use the lazy stream to iterate over pictures
         let picture_stream = storage.get_all_pictures();
@@ -788,7 +2150,7 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
         while let Some(pic_info_result) = picture_stream.next().await {
             // Process the picture, report error but continue if failed
             let deleted = self
-                .process_picture_for_cleanup(ctx.clone(), pic_info_result)
+                .process_picture_for_cleanup(ctx.clone(), &mut report, pic_info_result)
                 .await?;
 
             if deleted {
@@ -804,16 +2166,18 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
             "Finished cleanup invalid pictures task. Processed: {}, Deleted: {}",
             total, deleted_count
         );
+        self.finish_cleanup(report, &options).await?;
         Ok(())
     }
 
-    /// Processes a single picture for cleanup, checking if it's invalid and deleting if so.
+    /// Processes a single picture for cleanup, checking if it's invalid and removing if so.
     ///
-    /// Returns `Ok(true)` if the picture was deleted, `Ok(false)` if it was kept,
+    /// Returns `Ok(true)` if the picture was removed, `Ok(false)` if it was kept,
     /// or `Err` if an error occurred during processing.
     async fn process_picture_for_cleanup(
         &self,
         ctx: Arc<TaskContext>,
+        report: &mut CleanupReport,
         pic_info_result: Result<PictureInfo>,
     ) -> Result<bool> {
         const MAX_FILE_SIZE: u64 = 15 * 1024; // 15kB
@@ -846,7 +2210,7 @@ impl<A: ApiClient, S: Storage, E: Exporter, D: MediaDownloader> TaskHandler<A, S
             };
             info!("Image is {}: {}", reason, file_path.display());
 
-            self.storage.delete_picture_by_url(ctx, url).await?;
+            self.remove_picture(ctx, report, url).await?;
 
             return Ok(true);
         }
@@ -867,14 +2231,17 @@ mod local_tests {
             task::ExportOutputConfig,
             task_manager::{TaskManager, TaskType},
         },
+        geo::MapFormat,
         mock::MockApi,
         mock::{exporter::MockExporter, media_downloader::MockMediaDownloader},
+        stats::StatsFormat,
         storage::{StorageImpl, database},
     };
 
     async fn create_test_storage() -> StorageImpl {
         let db_pool = database::create_db_pool_with_url(":memory:").await.unwrap();
-        StorageImpl::new(db_pool)
+        let write_pool = database::create_write_pool(&db_pool).await.unwrap();
+        StorageImpl::new(db_pool, write_pool)
     }
 
     fn create_dummy_ctx() -> Arc<TaskContext> {
@@ -911,6 +2278,7 @@ mod local_tests {
                 reverse_order: false,
                 page: 1,
                 posts_per_page: 20,
+                ..Default::default()
             },
             output: ExportOutputConfig {
                 task_name,
@@ -923,4 +2291,318 @@ mod local_tests {
             .await
             .unwrap();
     }
+
+    #[tokio::test]
+    async fn test_export_map() {
+        let client = MockClient::new();
+        let api_client = MockApi::new(client.clone());
+        let storage = create_test_storage().await;
+        for (id, geo) in [
+            (1, Some([39.93, 116.45])),
+            (2, Some([31.24, 121.49])),
+            (3, None),
+        ] {
+            let post = Post {
+                id,
+                mblogid: format!("M{id}"),
+                text: format!("post {id}"),
+                geo: geo.map(|c| serde_json::json!({"type": "Point", "coordinates": c})),
+                ..Default::default()
+            };
+            storage.save_post(&post).await.unwrap();
+        }
+        let exporter = MockExporter::new();
+        let downloader = MockMediaDownloader::new();
+        let task_handler =
+            TaskHandler::new(api_client, storage, exporter.clone(), downloader).unwrap();
+
+        for (task_name, format) in [("trips", MapFormat::Kml), ("points", MapFormat::GeoJson)] {
+            let options = MapExportOptions {
+                query: PostQuery::default(),
+                format,
+                output: ExportOutputConfig {
+                    task_name: task_name.to_string(),
+                    export_dir: Path::new("export_dir").into(),
+                },
+            };
+            task_handler
+                .export_map(create_dummy_ctx(), options)
+                .await
+                .unwrap();
+        }
+
+        let files = exporter.get_exported_files();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].0, "trips.kml");
+        let kml = String::from_utf8_lossy(&files[0].1);
+        assert_eq!(kml.matches("<Placemark ").count(), 2);
+        assert!(kml.contains("<Placemark id=\"post-1\">"));
+        assert!(kml.contains("<coordinates>121.49,31.24</coordinates>"));
+        assert!(!kml.contains("post-3"));
+
+        assert_eq!(files[1].0, "points.geojson");
+        let geojson = serde_json::from_slice::<serde_json::Value>(&files[1].1).unwrap();
+        let mut features = geojson["features"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| {
+                (
+                    f["properties"]["id"].as_str().unwrap().to_string(),
+                    f["geometry"]["coordinates"].clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        features.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            features,
+            [
+                ("1".to_string(), serde_json::json!([116.45, 39.93])),
+                ("2".to_string(), serde_json::json!([121.49, 31.24])),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_stats() {
+        let client = MockClient::new();
+        let api_client = MockApi::new(client.clone());
+        let storage = create_test_storage().await;
+        let exporter = MockExporter::new();
+        let downloader = MockMediaDownloader::new();
+        let task_handler =
+            TaskHandler::new(api_client, storage, exporter.clone(), downloader).unwrap();
+
+        let options = StatsExportOptions {
+            uid: None,
+            format: StatsFormat::Csv,
+            output: ExportOutputConfig {
+                task_name: "stats".to_string(),
+                export_dir: Path::new("export_dir").into(),
+            },
+        };
+        task_handler
+            .export_stats(create_dummy_ctx(), options)
+            .await
+            .unwrap();
+
+        let files = exporter.get_exported_files();
+        assert_eq!(files.len(), 6);
+        assert_eq!(files[0].0, "stats_summary.csv");
+        assert!(String::from_utf8_lossy(&files[0].1).starts_with("uid,generated_at,"));
+    }
+
+    #[tokio::test]
+    async fn test_verify_archive() {
+        let client = MockClient::new();
+        let api_client = MockApi::new(client.clone());
+        let storage = create_test_storage().await;
+        let exporter = MockExporter::new();
+        let downloader = MockMediaDownloader::new();
+        let task_handler =
+            TaskHandler::new(api_client, storage, exporter.clone(), downloader).unwrap();
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let orphan = temp_dir.path().join("pictures/orphan.jpg");
+        fs::create_dir_all(orphan.parent().unwrap()).await.unwrap();
+        fs::write(&orphan, b"orphan").await.unwrap();
+        let task_manager = Arc::new(TaskManager::new());
+        task_manager
+            .start_task(0, TaskType::VerifyArchive, "test".into(), 0)
+            .unwrap();
+        let ctx = Arc::new(TaskContext {
+            task_id: Some(0),
+            config: crate::config::Config {
+                picture_path: temp_dir.path().join("pictures"),
+                video_path: temp_dir.path().join("videos"),
+                ..Default::default()
+            },
+            task_manager,
+        });
+
+        let options = VerifyArchiveOptions {
+            repair: true,
+            output: ExportOutputConfig {
+                task_name: "verify".to_string(),
+                export_dir: Path::new("export_dir").into(),
+            },
+        };
+        task_handler.verify_archive(ctx, options).await.unwrap();
+
+        let files = exporter.get_exported_files();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].0, "verify.json");
+        let report: crate::verify::VerifyReport = serde_json::from_slice(&files[0].1).unwrap();
+        assert_eq!(report.orphan_picture_files, ["orphan.jpg"]);
+        assert_eq!(report.repair, Some(RepairSummary::default()));
+    }
+
+    #[tokio::test]
+    async fn test_collect_garbage() {
+        let client = MockClient::new();
+        let api_client = MockApi::new(client.clone());
+        let storage = create_test_storage().await;
+        let exporter = MockExporter::new();
+        let downloader = MockMediaDownloader::new();
+        let task_handler =
+            TaskHandler::new(api_client, storage, exporter.clone(), downloader).unwrap();
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let orphan = temp_dir.path().join("pictures/a/orphan.jpg");
+        fs::create_dir_all(orphan.parent().unwrap()).await.unwrap();
+        fs::write(&orphan, b"orphan").await.unwrap();
+        let task_manager = Arc::new(TaskManager::new());
+        task_manager
+            .start_task(0, TaskType::CollectGarbage, "test".into(), 0)
+            .unwrap();
+        let ctx = Arc::new(TaskContext {
+            task_id: Some(0),
+            config: crate::config::Config {
+                picture_path: temp_dir.path().join("pictures"),
+                video_path: temp_dir.path().join("videos"),
+                quarantine_path: temp_dir.path().join("quarantine"),
+                ..Default::default()
+            },
+            task_manager,
+        });
+
+        let options = |dry_run, task_name: &str| CollectGarbageOptions {
+            dry_run,
+            output: ExportOutputConfig {
+                task_name: task_name.to_string(),
+                export_dir: Path::new("export_dir").into(),
+            },
+        };
+        task_handler
+            .collect_garbage(ctx.clone(), options(true, "dry"))
+            .await
+            .unwrap();
+        assert!(orphan.exists());
+
+        task_handler
+            .collect_garbage(ctx.clone(), options(false, "gc"))
+            .await
+            .unwrap();
+        assert!(!orphan.exists());
+
+        let files = exporter.get_exported_files();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].0, "dry.json");
+        let report: crate::gc::GcReport = serde_json::from_slice(&files[0].1).unwrap();
+        assert!(report.dry_run);
+        assert_eq!(report.orphan_picture_files, ["a/orphan.jpg"]);
+        assert_eq!(report.orphan_bytes, 6);
+        assert_eq!(report.removed_files, 0);
+        assert_eq!(files[1].0, "gc.json");
+        let report: crate::gc::GcReport = serde_json::from_slice(&files[1].1).unwrap();
+        assert!(!report.dry_run);
+        assert_eq!(report.removed_files, 1);
+        let batch = report.batch.unwrap();
+        assert!(
+            ctx.config
+                .quarantine_path
+                .join(&batch)
+                .join("pictures/a/orphan.jpg")
+                .exists()
+        );
+
+        task_handler
+            .restore_quarantine(ctx, RestoreQuarantineOptions { batch })
+            .await
+            .unwrap();
+        assert!(orphan.exists());
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_pictures_quarantine() {
+        let client = MockClient::new();
+        let api_client = MockApi::new(client.clone());
+        let storage = create_test_storage().await;
+        let exporter = MockExporter::new();
+        let downloader = MockMediaDownloader::new();
+        let task_handler =
+            TaskHandler::new(api_client, storage.clone(), exporter.clone(), downloader).unwrap();
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let task_manager = Arc::new(TaskManager::new());
+        task_manager
+            .start_task(0, TaskType::CleanupPictures, "test".into(), 0)
+            .unwrap();
+        let ctx = Arc::new(TaskContext {
+            task_id: Some(0),
+            config: crate::config::Config {
+                picture_path: temp_dir.path().join("pictures"),
+                video_path: temp_dir.path().join("videos"),
+                quarantine_path: temp_dir.path().join("quarantine"),
+                ..Default::default()
+            },
+            task_manager,
+        });
+        let large = "https://wx1.sinaimg.cn/large/abc.jpg";
+        let thumbnail = "https://wx1.sinaimg.cn/thumbnail/abc.jpg";
+        for (url, definition) in [
+            (large, crate::models::PictureDefinition::Large),
+            (thumbnail, crate::models::PictureDefinition::Thumbnail),
+        ] {
+            let picture = Picture {
+                meta: PictureMeta::attached(url, 1, definition).unwrap(),
+                blob: Bytes::from_static(b"picture"),
+            };
+            storage.save_picture(ctx.clone(), &picture).await.unwrap();
+        }
+
+        let options = |dry_run| CleanupPicturesOptions {
+            policy: ResolutionPolicy::Highest,
+            cleanup: CleanupOptions {
+                dry_run,
+                output: Some(ExportOutputConfig {
+                    task_name: if dry_run { "dry" } else { "cleanup" }.to_string(),
+                    export_dir: Path::new("export_dir").into(),
+                }),
+            },
+        };
+        task_handler
+            .cleanup_pictures(ctx.clone(), options(true))
+            .await
+            .unwrap();
+        assert_eq!(storage.count_pictures().await.unwrap(), 2);
+        assert!(storage.list_quarantine_batches().await.unwrap().is_empty());
+
+        task_handler
+            .cleanup_pictures(ctx.clone(), options(false))
+            .await
+            .unwrap();
+        assert_eq!(storage.count_pictures().await.unwrap(), 1);
+        let batches = storage.list_quarantine_batches().await.unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].rows, 1);
+
+        let files = exporter.get_exported_files();
+        assert_eq!(files.len(), 2);
+        let report: CleanupReport = serde_json::from_slice(&files[0].1).unwrap();
+        assert!(report.dry_run);
+        assert_eq!(report.batch, None);
+        assert_eq!(report.pictures, [thumbnail]);
+        let report: CleanupReport = serde_json::from_slice(&files[1].1).unwrap();
+        assert!(!report.dry_run);
+        assert_eq!(report.batch.as_ref(), Some(&batches[0].batch));
+        assert_eq!(report.pictures, [thumbnail]);
+
+        let options = RestoreQuarantineOptions {
+            batch: batches[0].batch.clone(),
+        };
+        task_handler
+            .restore_quarantine(ctx.clone(), options)
+            .await
+            .unwrap();
+        assert_eq!(storage.count_pictures().await.unwrap(), 2);
+        let thumbnail = Url::parse(thumbnail).unwrap();
+        assert!(
+            storage
+                .get_picture_blob(ctx, &thumbnail)
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
 }
@@ -33,6 +33,40 @@ pub enum TaskType {
     RebackupMissingImages,
     /// Clean up invalid pictures (e.g., "image deleted" placeholders).
     CleanupInvalidPictures,
+    /// Re-download pictures stored below the configured definition.
+    UpgradePictures,
+    /// Re-derive posts from the stored raw API JSON.
+    ReprocessPosts,
+    /// Rebuild the full-text search index.
+    RebuildSearchIndex,
+    /// Toggle Simplified/Traditional Chinese folding in search.
+    SetScriptFolding,
+    /// Re-extract the mentions and topics of all posts.
+    ReindexMentionsAndTopics,
+    /// Resolve the short links in post text to their destinations.
+    ResolveLinks,
+    /// Export posts with coordinates as a GeoJSON or KML map.
+    ExportMap,
+    /// Export a statistics report of the archive as JSON or CSV.
+    ExportStats,
+    /// Back up the posts missing from the archive of a user.
+    FillCoverageGaps,
+    /// Check the archive for inconsistencies between the database and the media files.
+    VerifyArchive,
+    /// Move or delete media files and rows that nothing references anymore.
+    CollectGarbage,
+    /// Put back what a cleanup task moved to quarantine.
+    RestoreQuarantine,
+    /// Merge another archive into this one.
+    MergeArchive,
+    /// Export posts as a portable bundle file.
+    ExportBundle,
+    /// Import a bundle file into this archive.
+    ImportBundle,
+    /// Move or copy the media directories and the database to new locations.
+    RelocateStorage,
+    /// Check, analyze, optimize and vacuum the database.
+    MaintainDatabase,
 }
 
 /// The current execution state of a task.
@@ -272,6 +306,15 @@ impl TaskManager {
     pub fn get_current(&self) -> Result<Option<Task>> {
         Ok(self.current_task.lock()?.clone())
     }
+
+    /// Checks that no task is `InProgress`, so that a new one can be started.
+    pub fn is_idle(&self) -> Result<bool> {
+        Ok(self
+            .current_task
+            .lock()?
+            .as_ref()
+            .is_none_or(|task| task.status != TaskStatus::InProgress))
+    }
 }
 
 #[cfg(test)]
@@ -337,9 +380,11 @@ mod local_tests {
         manager
             .start_task(1, TaskType::BackupUser, "Test task".into(), 10)
             .unwrap();
+        assert!(!manager.is_idle().unwrap());
         manager.finish().unwrap();
         let task = manager.get_current().unwrap().unwrap();
         assert_eq!(task.status, TaskStatus::Completed);
+        assert!(manager.is_idle().unwrap());
     }
 
     #[test]
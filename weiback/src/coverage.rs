@@ -0,0 +1,191 @@
+//! This module checks how completely the posts of each user are archived.
+//!
+//! Weibo reports the number of posts of a user (`statuses_count`) with the user in its
+//! API responses, and the latest number seen during a backup is recorded with the user.
+//! A [`UserCoverage`] compares it with the posts stored locally and, while the archive
+//! may be incomplete, lists the gaps in it: runs of months without any local post
+//! between months that have some, i.e. months in which the user is known to have been
+//! active but nothing was backed up.
+//!
+//! Months are `YYYY-MM` strings in the post's own UTC offset, as in [`crate::stats`].
+
+use serde::{Deserialize, Serialize};
+
+/// How completely the posts of a user are archived.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserCoverage {
+    pub uid: i64,
+    pub screen_name: Option<String>,
+    /// The number of posts Weibo reports for the user, if it has been seen.
+    pub remote_count: Option<u64>,
+    /// When `remote_count` was seen, as a Unix timestamp.
+    pub remote_count_at: Option<i64>,
+    /// The number of posts of the user stored locally.
+    pub local_count: u64,
+    /// The month of the oldest local post.
+    pub first_month: Option<String>,
+    /// The month of the newest local post.
+    pub last_month: Option<String>,
+    /// `local_count / remote_count`, at most 1.
+    pub coverage: Option<f64>,
+    /// Runs of months without local posts, oldest first. Empty if the archive is complete.
+    pub gaps: Vec<CoverageGap>,
+}
+
+/// A run of months without local posts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverageGap {
+    pub start_month: String,
+    pub end_month: String,
+    /// The number of months in the run.
+    pub months: u32,
+}
+
+impl UserCoverage {
+    /// Builds the coverage of a user.
+    ///
+    /// # Arguments
+    /// * `uid` - The ID of the user.
+    /// * `screen_name` - The screen name of the user, if known.
+    /// * `remote` - The number of posts Weibo reports for the user and when it was seen.
+    /// * `monthly` - The number of local posts per month, ordered by month.
+    pub fn new(
+        uid: i64,
+        screen_name: Option<String>,
+        remote: Option<(u64, i64)>,
+        monthly: &[(String, u64)],
+    ) -> Self {
+        let local_count = monthly.iter().map(|(_, count)| count).sum();
+        let mut coverage = Self {
+            uid,
+            screen_name,
+            remote_count: remote.map(|(count, _)| count),
+            remote_count_at: remote.map(|(_, at)| at),
+            local_count,
+            first_month: monthly.first().map(|(month, _)| month.clone()),
+            last_month: monthly.last().map(|(month, _)| month.clone()),
+            coverage: remote.map(|(count, _)| match count {
+                0 => 1.0,
+                count => (local_count as f64 / count as f64).min(1.0),
+            }),
+            gaps: Vec::new(),
+        };
+        if !coverage.is_complete() {
+            let months = monthly
+                .iter()
+                .filter(|(_, count)| *count > 0)
+                .map(|(month, _)| month.as_str())
+                .collect::<Vec<_>>();
+            coverage.gaps = find_gaps(&months);
+        }
+        coverage
+    }
+
+    /// Checks whether at least as many posts are stored as Weibo reports.
+    pub fn is_complete(&self) -> bool {
+        self.remote_count
+            .is_some_and(|count| self.local_count >= count)
+    }
+
+    /// The number of posts Weibo reports beyond those stored locally.
+    pub fn missing(&self) -> Option<u64> {
+        self.remote_count
+            .map(|count| count.saturating_sub(self.local_count))
+    }
+
+    /// The oldest month that filling the gaps has to reach back to, or `None` if the
+    /// whole timeline has to be walked, as the missing posts may be anywhere.
+    pub fn fill_target(&self) -> Option<&str> {
+        self.gaps.first().map(|gap| gap.start_month.as_str())
+    }
+}
+
+/// Finds the runs of months missing between the given months.
+///
+/// # Arguments
+/// * `months` - The months with local posts, as `YYYY-MM`, ordered.
+fn find_gaps(months: &[&str]) -> Vec<CoverageGap> {
+    let indices = months
+        .iter()
+        .filter_map(|month| month_index(month))
+        .collect::<Vec<_>>();
+    indices
+        .windows(2)
+        .filter(|pair| pair[1] - pair[0] > 1)
+        .map(|pair| CoverageGap {
+            start_month: month_name(pair[0] + 1),
+            end_month: month_name(pair[1] - 1),
+            months: (pair[1] - pair[0] - 1) as u32,
+        })
+        .collect()
+}
+
+/// Converts a `YYYY-MM` month into the number of months since year 0.
+fn month_index(month: &str) -> Option<i32> {
+    let (year, month) = month.split_once('-')?;
+    let (year, month) = (year.parse::<i32>().ok()?, month.parse::<i32>().ok()?);
+    (1..=12).contains(&month).then_some(year * 12 + month - 1)
+}
+
+fn month_name(index: i32) -> String {
+    format!(
+        "{:04}-{:02}",
+        index.div_euclid(12),
+        index.rem_euclid(12) + 1
+    )
+}
+
+#[cfg(test)]
+mod local_tests {
+    use super::*;
+
+    fn monthly(months: &[(&str, u64)]) -> Vec<(String, u64)> {
+        months.iter().map(|(m, c)| (m.to_string(), *c)).collect()
+    }
+
+    #[test]
+    fn test_find_gaps() {
+        assert_eq!(
+            find_gaps(&["2023-11", "2024-02", "2024-03", "2024-05"]),
+            [
+                CoverageGap {
+                    start_month: "2023-12".to_string(),
+                    end_month: "2024-01".to_string(),
+                    months: 2,
+                },
+                CoverageGap {
+                    start_month: "2024-04".to_string(),
+                    end_month: "2024-04".to_string(),
+                    months: 1,
+                },
+            ]
+        );
+        assert!(find_gaps(&["2024-01", "2024-02"]).is_empty());
+        assert!(find_gaps(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_user_coverage() {
+        let months = monthly(&[("2024-01", 3), ("2024-04", 1)]);
+
+        let coverage = UserCoverage::new(1, None, Some((8, 1_700_000_000)), &months);
+        assert_eq!(coverage.local_count, 4);
+        assert_eq!(coverage.coverage, Some(0.5));
+        assert_eq!(coverage.missing(), Some(4));
+        assert_eq!(coverage.first_month.as_deref(), Some("2024-01"));
+        assert_eq!(coverage.last_month.as_deref(), Some("2024-04"));
+        assert_eq!(coverage.gaps.len(), 1);
+        assert_eq!(coverage.fill_target(), Some("2024-02"));
+        assert!(!coverage.is_complete());
+
+        // Quiet months are not gaps once every post is archived
+        let coverage = UserCoverage::new(1, None, Some((4, 1_700_000_000)), &months);
+        assert!(coverage.is_complete());
+        assert!(coverage.gaps.is_empty());
+
+        // Without a known count the archive may be incomplete
+        let coverage = UserCoverage::new(1, None, None, &months);
+        assert_eq!(coverage.coverage, None);
+        assert_eq!(coverage.gaps.len(), 1);
+    }
+}
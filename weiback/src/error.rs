@@ -230,6 +230,19 @@ impl From<chrono::ParseError> for Error {
     }
 }
 
+impl From<crate::core::search_query::SearchQueryError> for Error {
+    /// Converts a search query parse error into the application's `Error::FormatError` variant.
+    ///
+    /// # Arguments
+    /// * `err` - The `SearchQueryError` to convert.
+    ///
+    /// # Returns
+    /// An `Error::FormatError` variant, keeping the position of the error in its message.
+    fn from(err: crate::core::search_query::SearchQueryError) -> Self {
+        Self::FormatError(format!("搜索语句有误：{err}"))
+    }
+}
+
 impl From<sqlx::Error> for Error {
     /// Converts a `sqlx::Error` into the application's `Error::DbError` variant.
     ///
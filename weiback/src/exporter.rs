@@ -4,7 +4,8 @@
 //! The main entry point is the `Exporter` trait, which defines the `export_page`
 //! method. `ExporterImpl` provides the concrete logic for creating directories,
 //! writing the HTML file, and copying all necessary image files from the local
-//! cache to a resource sub-folder.
+//! cache to a resource sub-folder. Single-file exports, such as maps, go through
+//! `export_file`.
 
 use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
@@ -30,6 +31,14 @@ pub trait Exporter: Send + Sync {
     /// * `page_name` - The base name for the output file (e.g., "page-1").
     /// * `export_dir` - The top-level directory where the export should be saved.
     async fn export_page(&self, page: HTMLPage, page_name: &str, export_dir: &Path) -> Result<()>;
+
+    /// Exports a single file.
+    ///
+    /// # Arguments
+    /// * `file_name` - The name of the output file, including its extension.
+    /// * `content` - The content of the file.
+    /// * `export_dir` - The top-level directory where the export should be saved.
+    async fn export_file(&self, file_name: &str, content: &[u8], export_dir: &Path) -> Result<()>;
 }
 
 /// Represents a single picture to be exported.
@@ -80,19 +89,7 @@ where {
         info!("Exporting page for task '{page_name}' to {export_dir:?}",);
         let mut dir_builder = DirBuilder::new();
         dir_builder.recursive(true);
-        if !export_dir.exists() {
-            debug!("Creating export directory at {export_dir:?}",);
-            dir_builder.create(export_dir).await.inspect_err(|e| {
-                error!("create export directory {:?} failed: {e}", export_dir);
-            })?
-        } else if !export_dir.is_dir() {
-            error!("Export path {} is not a directory", export_dir.display());
-            return Err(std::io::Error::new(
-                ErrorKind::AlreadyExists,
-                "export folder is a already exist file",
-            )
-            .into());
-        }
+        ensure_export_dir(export_dir).await?;
         let html_file_name = make_html_file_name(page_name);
         let html_file_path = export_dir.join(html_file_name);
         debug!("Writing HTML to file: {html_file_path:?}");
@@ -151,6 +148,50 @@ where {
         info!("Finished exporting page for task '{page_name}'");
         Ok(())
     }
+
+    /// Writes the content to `export_dir/file_name`, creating the directory if needed.
+    ///
+    /// # Errors
+    /// Returns an `Error::Io` if the `export_dir` path exists but is a file, or if
+    /// the file cannot be written.
+    async fn export_file(&self, file_name: &str, content: &[u8], export_dir: &Path) -> Result<()> {
+        ensure_export_dir(export_dir).await?;
+        let file_path = export_dir.join(file_name);
+        debug!("Writing {} bytes to {file_path:?}", content.len());
+        let mut file = File::create(&file_path).await.inspect_err(|e| {
+            error!("create file {:?} failed: {e}", file_path);
+        })?;
+        file.write_all(content).await.inspect_err(|e| {
+            error!("write content to {:?} failed: {e}", file_path);
+        })?;
+        info!("Exported {file_path:?}");
+        Ok(())
+    }
+}
+
+/// Creates the export directory if it does not exist yet.
+///
+/// # Errors
+/// Returns an `Error::Io` if the path exists but is not a directory.
+async fn ensure_export_dir(export_dir: &Path) -> Result<()> {
+    if !export_dir.exists() {
+        debug!("Creating export directory at {export_dir:?}",);
+        DirBuilder::new()
+            .recursive(true)
+            .create(export_dir)
+            .await
+            .inspect_err(|e| {
+                error!("create export directory {:?} failed: {e}", export_dir);
+            })?
+    } else if !export_dir.is_dir() {
+        error!("Export path {} is not a directory", export_dir.display());
+        return Err(std::io::Error::new(
+            ErrorKind::AlreadyExists,
+            "export folder is a already exist file",
+        )
+        .into());
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -304,4 +345,21 @@ mod local_tests {
             panic!("Expected Io error, but got {:?}", result);
         }
     }
+
+    #[tokio::test]
+    async fn test_export_file() {
+        let temp_dir = tempdir().unwrap();
+        let export_dir = temp_dir.path().join("maps");
+
+        let exporter = ExporterImpl::new();
+        exporter
+            .export_file("posts.geojson", b"{}", &export_dir)
+            .await
+            .unwrap();
+
+        let content = fs::read_to_string(export_dir.join("posts.geojson"))
+            .await
+            .unwrap();
+        assert_eq!(content, "{}");
+    }
 }
@@ -0,0 +1,94 @@
+//! This module defines the garbage collection of orphan media files.
+//!
+//! Deleting posts, replacing avatars or interrupted downloads can leave media behind:
+//! - `picture`/`video` rows attached to posts that are no longer stored,
+//! - files under the media directories that no stored row points to.
+//!
+//! The orphans are found by [`Storage::find_orphan_media`](crate::storage::Storage::find_orphan_media),
+//! listed in a [`GcReport`], and then either left alone (dry run) or moved to a
+//! quarantine batch, see [`crate::quarantine`], from which they can be restored.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::verify::MediaFile;
+
+/// The orphan media of an archive, and what was done with them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GcReport {
+    /// When the orphans were listed, as a Unix timestamp.
+    pub collected_at: i64,
+    /// Whether the orphans were only listed.
+    pub dry_run: bool,
+    /// Picture rows attached to posts that are no longer stored.
+    pub orphan_picture_rows: Vec<MediaFile>,
+    /// Video rows attached to posts that are no longer stored.
+    pub orphan_video_rows: Vec<MediaFile>,
+    /// Files under the picture directory no stored row points to, relative to it.
+    pub orphan_picture_files: Vec<String>,
+    /// Files under the video directory no stored row points to, relative to it.
+    pub orphan_video_files: Vec<String>,
+    /// The total size of the orphan files.
+    pub orphan_bytes: u64,
+    /// The quarantine batch the orphans were moved to, unless nothing was removed.
+    pub batch: Option<String>,
+    /// The number of rows moved to the quarantine batch.
+    pub removed_rows: u64,
+    /// The number of files moved to the quarantine batch.
+    pub removed_files: u64,
+}
+
+impl GcReport {
+    /// The number of orphan rows and files found.
+    pub fn orphan_count(&self) -> usize {
+        self.orphan_picture_rows.len()
+            + self.orphan_video_rows.len()
+            + self.orphan_picture_files.len()
+            + self.orphan_video_files.len()
+    }
+}
+
+/// Moves a media file to another directory.
+///
+/// # Arguments
+/// * `root` - The media directory the file is in.
+/// * `relative` - The path of the file, relative to `root`.
+/// * `move_to` - The directory to move the file to, keeping its relative path.
+pub async fn move_file(root: &Path, relative: &str, move_to: &Path) -> Result<()> {
+    let source = root.join(relative);
+    let target = move_to.join(relative);
+    if let Some(parent) = target.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    // A rename fails across file systems, fall back to copying
+    if tokio::fs::rename(&source, &target).await.is_err() {
+        tokio::fs::copy(&source, &target).await?;
+        tokio::fs::remove_file(&source).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod local_tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_move_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("pictures");
+        let trash = temp_dir.path().join("trash");
+        tokio::fs::create_dir_all(root.join("a")).await.unwrap();
+        tokio::fs::write(root.join("a/1.jpg"), b"1").await.unwrap();
+        tokio::fs::write(root.join("a/2.jpg"), b"2").await.unwrap();
+
+        move_file(&root, "a/1.jpg", &trash).await.unwrap();
+        assert!(!root.join("a/1.jpg").exists());
+        assert_eq!(tokio::fs::read(trash.join("a/1.jpg")).await.unwrap(), b"1");
+        assert!(root.join("a/2.jpg").exists());
+        assert!(move_file(&root, "a/1.jpg", &trash).await.is_err());
+    }
+}
@@ -0,0 +1,444 @@
+//! This module extracts the location of posts and renders located posts as maps.
+//!
+//! A post can carry a location in two places:
+//! - `geo`: a GeoJSON-like point, `{"type": "Point", "coordinates": [lat, lon]}`. Note that
+//!   Weibo puts the latitude first, unlike GeoJSON.
+//! - `tag_struct`: a tag with `otype` `"place"`, whose `tag_name` is the name of the place
+//!   the post was checked in at, e.g. `"北京·三里屯"`.
+//!
+//! These are parsed into the indexed `latitude`, `longitude` and `place_name` columns of
+//! `posts` on save, and [`MapWriter`] writes posts with coordinates as GeoJSON or KML.
+//!
+//! Within mainland China, Weibo gives coordinates in GCJ-02, the datum Chinese maps are
+//! required to use, which is offset by up to a few hundred meters from WGS-84. They are
+//! stored and filtered on as given, but GeoJSON and KML are defined in WGS-84, so
+//! [`MapWriter`] converts them with [`Coordinates::gcj02_to_wgs84`].
+
+use std::f64::consts::PI;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::error::Result;
+use crate::models::Post;
+
+/// A point on the map, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Coordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl Coordinates {
+    /// Creates coordinates if they are finite and within range.
+    pub fn new(latitude: f64, longitude: f64) -> Option<Self> {
+        let valid = latitude.is_finite()
+            && longitude.is_finite()
+            && (-90.0..=90.0).contains(&latitude)
+            && (-180.0..=180.0).contains(&longitude);
+        valid.then_some(Self {
+            latitude,
+            longitude,
+        })
+    }
+
+    /// Converts GCJ-02 coordinates to WGS-84. Points outside mainland China, where GCJ-02
+    /// is not applied, are returned as is.
+    ///
+    /// GCJ-02 has no closed-form inverse, so this refines the WGS-84 point until it maps
+    /// back onto `self`, which takes a few iterations to get within a millimeter.
+    pub fn gcj02_to_wgs84(self) -> Self {
+        if self.is_outside_china() {
+            return self;
+        }
+        let mut wgs84 = self;
+        for _ in 0..GCJ02_MAX_ITERATIONS {
+            let gcj02 = wgs84.wgs84_to_gcj02();
+            let d_latitude = gcj02.latitude - self.latitude;
+            let d_longitude = gcj02.longitude - self.longitude;
+            wgs84.latitude -= d_latitude;
+            wgs84.longitude -= d_longitude;
+            if d_latitude.abs() < GCJ02_PRECISION && d_longitude.abs() < GCJ02_PRECISION {
+                break;
+            }
+        }
+        wgs84
+    }
+
+    /// Converts WGS-84 coordinates to GCJ-02.
+    fn wgs84_to_gcj02(self) -> Self {
+        if self.is_outside_china() {
+            return self;
+        }
+        let x = self.longitude - 105.0;
+        let y = self.latitude - 35.0;
+        let mut d_latitude =
+            -100.0 + 2.0 * x + 3.0 * y + 0.2 * y * y + 0.1 * x * y + 0.2 * x.abs().sqrt();
+        d_latitude += (20.0 * (6.0 * x * PI).sin() + 20.0 * (2.0 * x * PI).sin()) * 2.0 / 3.0;
+        d_latitude += (20.0 * (y * PI).sin() + 40.0 * (y / 3.0 * PI).sin()) * 2.0 / 3.0;
+        d_latitude += (160.0 * (y / 12.0 * PI).sin() + 320.0 * (y / 30.0 * PI).sin()) * 2.0 / 3.0;
+        let mut d_longitude =
+            300.0 + x + 2.0 * y + 0.1 * x * x + 0.1 * x * y + 0.1 * x.abs().sqrt();
+        d_longitude += (20.0 * (6.0 * x * PI).sin() + 20.0 * (2.0 * x * PI).sin()) * 2.0 / 3.0;
+        d_longitude += (20.0 * (x * PI).sin() + 40.0 * (x / 3.0 * PI).sin()) * 2.0 / 3.0;
+        d_longitude += (150.0 * (x / 12.0 * PI).sin() + 300.0 * (x / 30.0 * PI).sin()) * 2.0 / 3.0;
+
+        let rad_latitude = self.latitude.to_radians();
+        let magic = 1.0 - GCJ02_EE * rad_latitude.sin().powi(2);
+        let sqrt_magic = magic.sqrt();
+        d_latitude = d_latitude * 180.0 / (GCJ02_A * (1.0 - GCJ02_EE) / (magic * sqrt_magic) * PI);
+        d_longitude = d_longitude * 180.0 / (GCJ02_A / sqrt_magic * rad_latitude.cos() * PI);
+        Self {
+            latitude: self.latitude + d_latitude,
+            longitude: self.longitude + d_longitude,
+        }
+    }
+
+    /// Whether the point is outside the rough bounds of mainland China that GCJ-02 covers.
+    fn is_outside_china(&self) -> bool {
+        !(72.004..=137.8347).contains(&self.longitude)
+            || !(0.8293..=55.8271).contains(&self.latitude)
+    }
+}
+
+/// The semi-major axis of the Krasovsky 1940 ellipsoid GCJ-02 is based on, in meters.
+const GCJ02_A: f64 = 6_378_245.0;
+/// The squared eccentricity of the Krasovsky 1940 ellipsoid.
+const GCJ02_EE: f64 = 0.006_693_421_622_965_943;
+/// The precision, in degrees, [`Coordinates::gcj02_to_wgs84`] refines to.
+const GCJ02_PRECISION: f64 = 1e-9;
+/// The most iterations [`Coordinates::gcj02_to_wgs84`] takes.
+const GCJ02_MAX_ITERATIONS: usize = 10;
+
+/// A rectangular area of the map, bounds included.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub min_latitude: f64,
+    pub min_longitude: f64,
+    pub max_latitude: f64,
+    pub max_longitude: f64,
+}
+
+/// The file format of a map export.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MapFormat {
+    #[default]
+    GeoJson,
+    Kml,
+}
+
+impl MapFormat {
+    /// The file extension for this format, without the dot.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            MapFormat::GeoJson => "geojson",
+            MapFormat::Kml => "kml",
+        }
+    }
+}
+
+/// Parses the coordinates of a post's `geo` JSON.
+pub fn parse_coordinates(geo: &Value) -> Option<Coordinates> {
+    let coordinates = geo.get("coordinates")?.as_array()?;
+    match coordinates.as_slice() {
+        [latitude, longitude, ..] => Coordinates::new(latitude.as_f64()?, longitude.as_f64()?),
+        _ => None,
+    }
+}
+
+/// Parses the name of the place a post was checked in at from its `tag_struct` JSON.
+pub fn parse_place_name(tag_struct: &Value) -> Option<&str> {
+    tag_struct
+        .as_array()?
+        .iter()
+        .find(|tag| tag.get("otype").and_then(Value::as_str) == Some("place"))
+        .and_then(|tag| tag.get("tag_name")?.as_str())
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+/// Returns the coordinates and place name of a post, if it has them.
+pub fn post_location(post: &Post) -> Result<(Option<Coordinates>, Option<String>)> {
+    let coordinates = post.geo.as_ref().and_then(parse_coordinates);
+    let tag_struct = post
+        .tag_struct
+        .as_ref()
+        .map(serde_json::to_value)
+        .transpose()?;
+    let place_name = tag_struct
+        .as_ref()
+        .and_then(parse_place_name)
+        .map(str::to_string);
+    Ok((coordinates, place_name))
+}
+
+/// A post placed on the map.
+struct Placemark {
+    coordinates: Coordinates,
+    id: i64,
+    url: Option<String>,
+    created_at: String,
+    author: Option<String>,
+    place_name: Option<String>,
+    text: String,
+}
+
+impl Placemark {
+    fn from_post(post: &Post) -> Result<Option<Self>> {
+        let (coordinates, place_name) = post_location(post)?;
+        let Some(coordinates) = coordinates else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            coordinates: coordinates.gcj02_to_wgs84(),
+            id: post.id,
+            url: post
+                .user
+                .as_ref()
+                .map(|u| format!("https://weibo.com/{}/{}", u.id, post.mblogid)),
+            created_at: post.created_at.to_rfc3339(),
+            author: post.user.as_ref().map(|u| u.screen_name.clone()),
+            place_name,
+            text: post.text.clone(),
+        }))
+    }
+}
+
+/// Writes located posts as a map file one post at a time, so that exporting a large
+/// archive does not need to hold all its posts at once. Posts without coordinates are
+/// left out, and the coordinates of the others are converted from GCJ-02 to WGS-84.
+pub struct MapWriter {
+    format: MapFormat,
+    content: String,
+    count: usize,
+}
+
+impl MapWriter {
+    /// Starts a map file of the given format.
+    pub fn new(format: MapFormat) -> Self {
+        let content = match format {
+            MapFormat::GeoJson => {
+                String::from("{\n  \"type\": \"FeatureCollection\",\n  \"features\": [")
+            }
+            MapFormat::Kml => String::from(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                 <kml xmlns=\"http://www.opengis.net/kml/2.2\">\n<Document>\n",
+            ),
+        };
+        Self {
+            format,
+            content,
+            count: 0,
+        }
+    }
+
+    /// Puts a post on the map if it has coordinates.
+    ///
+    /// # Returns
+    /// A `Result` containing whether the post was placed on the map.
+    pub fn push(&mut self, post: &Post) -> Result<bool> {
+        let Some(placemark) = Placemark::from_post(post)? else {
+            return Ok(false);
+        };
+        match self.format {
+            MapFormat::GeoJson => {
+                if self.count > 0 {
+                    self.content.push(',');
+                }
+                self.content.push_str("\n    ");
+                self.content
+                    .push_str(&serde_json::to_string(&geojson_feature(&placemark))?);
+            }
+            MapFormat::Kml => self.content.push_str(&kml_placemark(&placemark)),
+        }
+        self.count += 1;
+        Ok(true)
+    }
+
+    /// Closes the map file.
+    ///
+    /// # Returns
+    /// The file content and the number of posts placed on the map.
+    pub fn finish(mut self) -> (String, usize) {
+        match self.format {
+            MapFormat::GeoJson => self.content.push_str("\n  ]\n}\n"),
+            MapFormat::Kml => self.content.push_str("</Document>\n</kml>\n"),
+        }
+        (self.content, self.count)
+    }
+}
+
+fn geojson_feature(p: &Placemark) -> Value {
+    json!({
+        "type": "Feature",
+        "geometry": {
+            "type": "Point",
+            "coordinates": [p.coordinates.longitude, p.coordinates.latitude],
+        },
+        "properties": {
+            "id": p.id.to_string(),
+            "url": p.url,
+            "created_at": p.created_at,
+            "author": p.author,
+            "place_name": p.place_name,
+            "text": p.text,
+        },
+    })
+}
+
+fn kml_placemark(p: &Placemark) -> String {
+    let name = p
+        .place_name
+        .as_deref()
+        .or(p.author.as_deref())
+        .unwrap_or("");
+    let mut description = format!("{}\n{}", p.created_at, p.text);
+    if let Some(url) = &p.url {
+        description.push('\n');
+        description.push_str(url);
+    }
+    format!(
+        "<Placemark id=\"post-{}\">\n<name>{}</name>\n<description>{}</description>\n\
+         <TimeStamp><when>{}</when></TimeStamp>\n\
+         <Point><coordinates>{},{}</coordinates></Point>\n</Placemark>\n",
+        p.id,
+        escape_xml(name),
+        escape_xml(&description),
+        escape_xml(&p.created_at),
+        p.coordinates.longitude,
+        p.coordinates.latitude,
+    )
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod local_tests {
+    use serde_json::from_str;
+    use url::Url;
+
+    use super::*;
+    use crate::models::User;
+
+    fn located_post(id: i64, geo: Value, tag_struct: Value) -> Post {
+        let avatar = Url::parse("https://tvax1.sinaimg.cn/large/avatar.jpg").unwrap();
+        Post {
+            id,
+            mblogid: format!("M{id}"),
+            text: "在 <三里屯> & 逛街".to_string(),
+            geo: Some(geo),
+            tag_struct: Some(serde_json::from_value(tag_struct).unwrap()),
+            user: Some(User {
+                avatar_hd: avatar.clone(),
+                avatar_large: avatar.clone(),
+                domain: String::new(),
+                following: false,
+                follow_me: false,
+                id: 1,
+                profile_image_url: avatar,
+                screen_name: "测试".to_string(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn place_tag(name: &str) -> Value {
+        json!([{
+            "tag_name": name,
+            "url_type_pic": "https://h5.sinaimg.cn/upload/location.png",
+            "otype": "place",
+        }])
+    }
+
+    #[test]
+    fn test_parse_location() {
+        let geo = json!({"type": "Point", "coordinates": [39.93, 116.45]});
+        assert_eq!(
+            parse_coordinates(&geo),
+            Some(Coordinates {
+                latitude: 39.93,
+                longitude: 116.45
+            })
+        );
+        assert!(parse_coordinates(&json!("")).is_none());
+        assert!(parse_coordinates(&json!({"coordinates": [139.9, 35.6]})).is_none());
+        assert!(parse_coordinates(&json!({"coordinates": ["39.9"]})).is_none());
+
+        assert_eq!(
+            parse_place_name(&place_tag(" 北京·三里屯 ")),
+            Some("北京·三里屯")
+        );
+        let tags = json!([{"tag_name": "超话", "otype": "topic"}]);
+        assert!(parse_place_name(&tags).is_none());
+    }
+
+    #[test]
+    fn test_gcj02_to_wgs84() {
+        // 三里屯, whose GCJ-02 coordinates are about 600 m east of its WGS-84 ones
+        let gcj02 = Coordinates::new(39.93, 116.45).unwrap();
+        let wgs84 = gcj02.gcj02_to_wgs84();
+        assert!((wgs84.latitude - 39.928_633).abs() < 1e-6);
+        assert!((wgs84.longitude - 116.443_804).abs() < 1e-6);
+        let round_trip = wgs84.wgs84_to_gcj02();
+        assert!((round_trip.latitude - gcj02.latitude).abs() < 1e-8);
+        assert!((round_trip.longitude - gcj02.longitude).abs() < 1e-8);
+
+        // GCJ-02 only applies in mainland China
+        let tokyo = Coordinates::new(35.68, 139.76).unwrap();
+        assert_eq!(tokyo.gcj02_to_wgs84(), tokyo);
+    }
+
+    fn render_map(posts: &[Post], format: MapFormat) -> (String, usize) {
+        let mut writer = MapWriter::new(format);
+        for post in posts {
+            writer.push(post).unwrap();
+        }
+        writer.finish()
+    }
+
+    #[test]
+    fn test_render_map() {
+        let posts = [
+            located_post(
+                1,
+                json!({"type": "Point", "coordinates": [39.93, 116.45]}),
+                place_tag("北京·三里屯"),
+            ),
+            located_post(2, json!(""), place_tag("北京")),
+        ];
+
+        let (geojson, count) = render_map(&posts, MapFormat::GeoJson);
+        assert_eq!(count, 1);
+        let geojson = from_str::<Value>(&geojson).unwrap();
+        let feature = &geojson["features"][0];
+        assert_eq!(geojson["features"].as_array().unwrap().len(), 1);
+        let wgs84 = Coordinates::new(39.93, 116.45).unwrap().gcj02_to_wgs84();
+        assert_eq!(
+            feature["geometry"]["coordinates"],
+            json!([wgs84.longitude, wgs84.latitude])
+        );
+        assert_eq!(feature["properties"]["id"], "1");
+        assert_eq!(feature["properties"]["place_name"], "北京·三里屯");
+        assert_eq!(feature["properties"]["url"], "https://weibo.com/1/M1");
+
+        let (empty, count) = render_map(&posts[1..], MapFormat::GeoJson);
+        assert_eq!(count, 0);
+        let empty = from_str::<Value>(&empty).unwrap();
+        assert_eq!(empty["features"], json!([]));
+
+        let (kml, count) = render_map(&posts, MapFormat::Kml);
+        assert_eq!(count, 1);
+        assert!(kml.contains("<name>北京·三里屯</name>"));
+        assert!(kml.contains("在 &lt;三里屯&gt; &amp; 逛街"));
+        assert!(kml.contains(&format!(
+            "<coordinates>{},{}</coordinates>",
+            wgs84.longitude, wgs84.latitude
+        )));
+    }
+}
@@ -6,6 +6,7 @@
 
 pub mod view_model;
 
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use futures::stream::{self, StreamExt};
@@ -18,9 +19,12 @@ use crate::core::task::TaskContext;
 use crate::emoji_map::EmojiMap;
 use crate::error::Result;
 use crate::exporter::{HTMLPage, PictureExport};
-use crate::models::{PictureDefinition, PictureMeta, Post};
+use crate::models::{Link, PictureDefinition, PictureMeta, Post};
 use crate::storage::Storage;
-use crate::utils::{extract_all_pic_metas, make_resource_dir_name, pic_url_to_filename};
+use crate::utils::{
+    extract_all_pic_metas, extract_pic_fallback_metas, extract_short_urls, make_resource_dir_name,
+    pic_url_to_filename,
+};
 use view_model::PostView;
 
 lazy_static! {
@@ -79,10 +83,11 @@ impl<E: EmojiUpdateApi, S: Storage> HTMLGenerator<E, S> {
     ) -> Result<String> {
         let emoji_map = self.emoji_map.get_or_try_init().await.ok();
         info!("Generating page for {} posts", posts.len());
+        let links = self.get_links(&posts).await;
         let pic_folder = make_resource_dir_name(page_name);
         let post_views: Vec<PostView> = posts
             .into_iter()
-            .map(|p| PostView::from_post(p, &pic_folder, pic_quality, emoji_map))
+            .map(|p| PostView::from_post(p, &pic_folder, pic_quality, emoji_map, &links))
             .collect::<Result<Vec<_>>>()?;
 
         let mut posts_context = Context::new();
@@ -134,13 +139,15 @@ impl<E: EmojiUpdateApi, S: Storage> HTMLGenerator<E, S> {
         let emoji_map = self.emoji_map.get_or_try_init().await.ok();
         debug!("Using picture quality: {pic_quality:?}");
         let pic_metas = extract_all_pic_metas(&posts, pic_quality, emoji_map);
+        let mut fallbacks = extract_pic_fallback_metas(&posts, pic_quality);
         info!(
             "Found {} unique pictures for HTML generation.",
             pic_metas.len()
         );
-        let pic_futures = pic_metas
-            .into_iter()
-            .map(|m| self.get_picture_export_info(ctx.clone(), m));
+        let pic_futures = pic_metas.into_iter().map(|m| {
+            let chain = fallbacks.remove(m.url()).unwrap_or_else(|| vec![m]);
+            self.get_picture_export_info(ctx.clone(), chain)
+        });
         let pictures_to_export: Vec<PictureExport> = stream::iter(pic_futures)
             .buffer_unordered(8)
             .filter_map(|result| async move {
@@ -169,34 +176,69 @@ impl<E: EmojiUpdateApi, S: Storage> HTMLGenerator<E, S> {
         })
     }
 
+    /// Loads the resolved short links in the text of the posts and their retweeted posts.
+    ///
+    /// Links are only a nicety for the rendered page, so a failure to load them is logged
+    /// and the short URLs are kept.
+    async fn get_links(&self, posts: &[Post]) -> HashMap<String, Link> {
+        let mut short_urls = posts
+            .iter()
+            .flat_map(|p| std::iter::once(p).chain(p.retweeted_status.as_deref()))
+            .flat_map(|p| extract_short_urls(&p.text))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        short_urls.sort_unstable();
+        short_urls.dedup();
+        match self.storage.get_links(&short_urls).await {
+            Ok(links) => links
+                .into_iter()
+                .map(|l| (l.short_url.clone(), l))
+                .collect(),
+            Err(e) => {
+                warn!("Failed to load resolved links, keeping short URLs: {e}");
+                HashMap::new()
+            }
+        }
+    }
+
     /// Retrieves the necessary information to export a picture from local storage.
     ///
     /// This involves getting the physical path of the image and determining its target filename.
+    /// If the preferred definition is not stored, lower definitions are used in turn, while
+    /// the target filename is always derived from the preferred one so the HTML links stay valid.
     ///
     /// # Arguments
     /// * `ctx` - The task context.
-    /// * `pic_meta` - Metadata about the picture to export.
+    /// * `chain` - The picture at decreasing definitions, the preferred one first.
     ///
     /// # Returns
     /// A `Result` containing `Some(PictureExport)` if the picture is found, or `None` if not.
     async fn get_picture_export_info(
         &self,
         ctx: Arc<TaskContext>,
-        pic_meta: PictureMeta,
+        chain: Vec<PictureMeta>,
     ) -> Result<Option<PictureExport>> {
-        let url = pic_meta.url();
-        if let Some(source_path) = self.storage.get_picture_path(ctx, url).await? {
-            let target_file_name = pic_url_to_filename(url).inspect_err(|e| {
-                error!("get picture filename for url {} failed: {e}", url);
-            })?;
-            Ok(Some(PictureExport {
-                source_path,
-                target_file_name,
-            }))
-        } else {
-            warn!("Picture path not found in storage for url: {}", url);
-            Ok(None)
+        let url = chain[0].url();
+        for meta in chain.iter() {
+            if let Some(source_path) = self
+                .storage
+                .get_picture_path(ctx.clone(), meta.url())
+                .await?
+            {
+                if meta.url() != url {
+                    debug!("Picture {} falls back to {}", url, meta.url());
+                }
+                let target_file_name = pic_url_to_filename(url).inspect_err(|e| {
+                    error!("get picture filename for url {} failed: {e}", url);
+                })?;
+                return Ok(Some(PictureExport {
+                    source_path,
+                    target_file_name,
+                }));
+            }
         }
+        warn!("Picture path not found in storage for url: {}", url);
+        Ok(None)
     }
 }
 
@@ -215,7 +257,8 @@ mod local_tests {
 
     async fn create_test_storage() -> StorageImpl {
         let db_pool = database::create_db_pool_with_url(":memory:").await.unwrap();
-        StorageImpl::new(db_pool)
+        let write_pool = database::create_write_pool(&db_pool).await.unwrap();
+        StorageImpl::new(db_pool, write_pool)
     }
 
     fn create_mock_client() -> MockClient {
@@ -16,7 +16,7 @@ use serde::Serialize;
 use url::Url;
 
 use crate::error::{Error, Result};
-use crate::models::{PictureDefinition, Post, UrlStruct, User};
+use crate::models::{Link, PictureDefinition, Post, UrlStruct, User};
 use crate::utils::{
     AT_EXPR, EMAIL_EXPR, EMOJI_EXPR, NEWLINE_EXPR, TOPIC_EXPR, URL_EXPR,
     generate_standalone_pic_output_paths, pic_url_to_filename,
@@ -62,6 +62,7 @@ impl PostView {
     /// * `pic_folder` - The name of the folder where pictures for this page will reside.
     /// * `pic_quality` - The desired picture definition for rendered images.
     /// * `emoji_map` - A map to resolve emoji text to URLs.
+    /// * `links` - Resolved short links in the post text, keyed by short URL.
     ///
     /// # Returns
     /// A `Result` containing the `PostView` instance.
@@ -70,6 +71,7 @@ impl PostView {
         pic_folder: &str,
         pic_quality: PictureDefinition,
         emoji_map: Option<&HashMap<String, Url>>,
+        links: &HashMap<String, Link>,
     ) -> Result<Self> {
         let pic_folder_path = Path::new(pic_folder);
 
@@ -81,6 +83,7 @@ impl PostView {
                 pic_folder,
                 pic_quality,
                 emoji_map,
+                links,
             )?))
         } else {
             None
@@ -91,7 +94,7 @@ impl PostView {
         let avatar_path = extract_avatar_path(&post, pic_folder_path);
         let pic_paths =
             generate_standalone_pic_output_paths(&post, pic_folder_path, pic_quality).collect();
-        let text = trans_text(&post, pic_folder_path, emoji_map)?;
+        let text = trans_text(&post, pic_folder_path, emoji_map, links)?;
 
         Ok(PostView {
             id: post.id,
@@ -115,6 +118,7 @@ fn trans_text(
     post: &Post,
     pic_folder: &Path,
     emoji_map: Option<&HashMap<String, Url>>,
+    links: &HashMap<String, Link>,
 ) -> Result<String> {
     // find all email suffixes
     let emails_suffixes = EMAIL_EXPR
@@ -131,7 +135,8 @@ fn trans_text(
             .find_iter(&text)
             .fold((Borrowed(""), 0), |(acc, i), m| {
                 (
-                    acc + &text[i..m.start()] + trans_url(post.url_struct.as_ref(), m.as_str()),
+                    acc + &text[i..m.start()]
+                        + trans_url(post.url_struct.as_ref(), links, m.as_str()),
                     m.end(),
                 )
             });
@@ -223,15 +228,27 @@ fn trans_topic(s: &str) -> Cow<'_, str> {
 }
 
 /// Transforms a URL found in post text into a hyperlink with a default or extracted title.
-fn trans_url<'a>(url_struct: Option<&'a UrlStruct>, url: &'a str) -> Cow<'a, str> {
+///
+/// The destination and title come from the post's `url_struct` when present, then from
+/// the resolved short link, and fall back to the URL itself and a generic title.
+fn trans_url<'a>(
+    url_struct: Option<&'a UrlStruct>,
+    links: &'a HashMap<String, Link>,
+    url: &'a str,
+) -> Cow<'a, str> {
     let this_struct = url_struct.and_then(|p| p.0.iter().find(|u| u.short_url.as_str() == url));
-    let url_title = this_struct
-        .map(|u| u.url_title.as_str())
-        .unwrap_or("网页链接");
+    let link = links.get(url).filter(|l| l.is_resolved());
+    let url_title = match (this_struct, link.and_then(|l| l.title.as_deref())) {
+        (Some(u), _) => Borrowed(u.url_title.as_str()),
+        (None, Some(title)) => Owned(escape_html(title)),
+        (None, None) => Borrowed("网页链接"),
+    };
     let url = if let Some(long_url) = this_struct.and_then(|u| u.long_url.as_ref())
         && Url::parse(long_url).is_ok()
     {
         long_url.as_str()
+    } else if let Some(final_url) = link.and_then(|l| l.final_url.as_deref()) {
+        final_url
     } else {
         url
     };
@@ -244,6 +261,14 @@ fn trans_url<'a>(url_struct: Option<&'a UrlStruct>, url: &'a str) -> Cow<'a, str
         + "</a>"
 }
 
+/// Escapes the characters of text that would otherwise be read as HTML markup.
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
 /// Extracts the local file path for a user's avatar from a post.
 fn extract_avatar_path(post: &Post, pic_folder: &Path) -> Option<String> {
     post.user
@@ -264,3 +289,59 @@ fn extract_avatar_path(post: &Post, pic_folder: &Path) -> Option<String> {
         .transpose()
         .unwrap_or_default()
 }
+
+#[cfg(test)]
+mod local_tests {
+    use super::*;
+    use crate::models::UrlStructItem;
+
+    #[test]
+    fn test_trans_url_uses_resolved_links() {
+        let links = HashMap::from([
+            (
+                "http://t.cn/A1".to_string(),
+                Link {
+                    short_url: "http://t.cn/A1".to_string(),
+                    final_url: Some("https://example.com/a".to_string()),
+                    status_code: Some(200),
+                    title: Some("<猫>".to_string()),
+                    ..Default::default()
+                },
+            ),
+            (
+                "http://t.cn/B2".to_string(),
+                Link {
+                    short_url: "http://t.cn/B2".to_string(),
+                    error: Some("timed out".to_string()),
+                    ..Default::default()
+                },
+            ),
+        ]);
+
+        let html = trans_url(None, &links, "http://t.cn/A1");
+        assert!(html.contains(r#"href="https://example.com/a""#));
+        assert!(html.contains("&lt;猫&gt;</a>"));
+
+        let html = trans_url(None, &links, "http://t.cn/B2");
+        assert!(html.contains(r#"href="http://t.cn/B2""#));
+        assert!(html.contains("网页链接</a>"));
+
+        // the post's own url_struct takes precedence
+        let url_struct = UrlStruct(vec![UrlStructItem {
+            short_url: "http://t.cn/A1".to_string(),
+            url_title: "长链接".to_string(),
+            long_url: Some("https://example.com/long".to_string()),
+            ori_url: String::new(),
+            object_type: None,
+            page_id: None,
+            url_type: Default::default(),
+            url_type_pic: None,
+            pic_ids: None,
+            pic_infos: None,
+            vip_gif: None,
+        }]);
+        let html = trans_url(Some(&url_struct), &links, "http://t.cn/A1");
+        assert!(html.contains(r#"href="https://example.com/long""#));
+        assert!(html.contains("长链接</a>"));
+    }
+}
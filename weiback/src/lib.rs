@@ -7,18 +7,29 @@
 
 pub mod api;
 pub mod builder;
+pub mod bundle;
 pub mod config;
 pub mod core;
+pub mod coverage;
 pub mod emoji_map;
 pub mod error;
 pub mod exporter;
+pub mod gc;
+pub mod geo;
 pub mod html_generator;
 pub mod image_validator;
+pub mod link_resolver;
+pub mod maintenance;
 pub mod media_downloader;
+pub mod merge;
 pub mod message;
 pub mod models;
+pub mod quarantine;
+pub mod relocate;
+pub mod stats;
 pub mod storage;
 pub mod utils;
+pub mod verify;
 
 #[cfg(test)]
 pub mod mock;
@@ -0,0 +1,208 @@
+//! This module resolves the short links (e.g. `https://t.cn/xxx`) found in post text to
+//! their final destinations.
+//!
+//! The [`LinkResolver`] follows HTTP redirects from a short link and records where it
+//! ends up, the status code there and the page title, as a [`Link`]. Weibo sends links
+//! to sites it does not trust through an interstitial page (`weibo.cn/sinaurl?u=...`),
+//! which is skipped so the real destination is recorded instead.
+
+use std::time::Duration;
+
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::{Client, Response, header::CONTENT_TYPE, redirect::Policy};
+use tracing::{debug, error, warn};
+use url::Url;
+
+use crate::error::Result;
+use crate::models::Link;
+
+/// How long to wait for a destination before giving up.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+/// The maximum number of redirects followed from a short link.
+const MAX_REDIRECTS: usize = 10;
+/// How much of a destination page is read looking for its title.
+const MAX_TITLE_BYTES: usize = 64 * 1024;
+
+/// Matches the `<title>` element of an HTML page.
+static TITLE_EXPR: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)<title[^>]*>(.*?)</title>")
+        .inspect_err(|e| error!("Regex init failed: {e}"))
+        .unwrap()
+});
+
+/// Resolves short links by following their redirects.
+#[derive(Debug, Clone)]
+pub struct LinkResolver {
+    client: Client,
+}
+
+impl LinkResolver {
+    /// Creates a new `LinkResolver` with its own HTTP client.
+    pub fn new() -> Result<Self> {
+        let client = Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .redirect(Policy::limited(MAX_REDIRECTS))
+            .build()?;
+        Ok(Self { client })
+    }
+
+    /// Resolves a short link to its final destination.
+    ///
+    /// Network failures are recorded in the returned [`Link`] rather than returned as
+    /// errors, since a dead short link is a result worth keeping too.
+    ///
+    /// # Arguments
+    /// * `short_url` - The short URL as found in post text.
+    pub async fn resolve(&self, short_url: &str) -> Link {
+        let mut link = Link {
+            short_url: short_url.to_string(),
+            resolved_at: Utc::now().timestamp(),
+            ..Default::default()
+        };
+        match self.fetch(short_url).await {
+            Ok(response) => {
+                link.final_url = Some(response.url().to_string());
+                link.status_code = Some(response.status().as_u16());
+                link.title = read_title(response).await;
+                debug!("Resolved {short_url} to {:?}", link.final_url);
+            }
+            Err(e) => {
+                warn!("Failed to resolve {short_url}: {e}");
+                link.error = Some(e.to_string());
+            }
+        }
+        link
+    }
+
+    /// Follows the redirects of a URL, skipping Weibo's interstitial page.
+    async fn fetch(&self, url: &str) -> reqwest::Result<Response> {
+        let response = self.client.get(url).send().await?;
+        match unwrap_interstitial(response.url()) {
+            Some(target) => self.client.get(target).send().await,
+            None => Ok(response),
+        }
+    }
+}
+
+/// Extracts the real destination from Weibo's "you are leaving Weibo" interstitial page
+/// URL, e.g. `https://weibo.cn/sinaurl?u=https%3A%2F%2Fexample.com`.
+fn unwrap_interstitial(url: &Url) -> Option<Url> {
+    if !matches!(url.host_str(), Some("weibo.cn" | "m.weibo.cn")) || url.path() != "/sinaurl" {
+        return None;
+    }
+    url.query_pairs()
+        .find(|(key, _)| key == "u" || key == "toasturl")
+        .and_then(|(_, target)| Url::parse(&target).ok())
+}
+
+/// Reads the title of an HTML response, giving up after [`MAX_TITLE_BYTES`].
+async fn read_title(mut response: Response) -> Option<String> {
+    let is_html = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("html"));
+    if !is_html {
+        return None;
+    }
+    let mut body = Vec::new();
+    while body.len() < MAX_TITLE_BYTES {
+        match response.chunk().await {
+            Ok(Some(chunk)) => body.extend_from_slice(&chunk),
+            Ok(None) => break,
+            Err(e) => {
+                debug!("Failed to read page of {}: {e}", response.url());
+                break;
+            }
+        }
+    }
+    extract_title(&String::from_utf8_lossy(&body))
+}
+
+/// Extracts the text of the `<title>` element of an HTML page.
+fn extract_title(html: &str) -> Option<String> {
+    let title = TITLE_EXPR.captures(html)?.get(1)?.as_str();
+    let title = title
+        .replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ");
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!title.is_empty()).then_some(title)
+}
+
+#[cfg(test)]
+mod local_tests {
+    use mockito::Server;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_resolve_follows_redirects() {
+        let mut server = Server::new_async().await;
+        let short = server
+            .mock("GET", "/A6abc")
+            .with_status(302)
+            .with_header("location", "/article")
+            .create_async()
+            .await;
+        let dest = server
+            .mock("GET", "/article")
+            .with_status(200)
+            .with_header("content-type", "text/html; charset=utf-8")
+            .with_body("<html><head><title>\n  猫 &amp; 狗\n</title></head></html>")
+            .create_async()
+            .await;
+
+        let resolver = LinkResolver::new().unwrap();
+        let short_url = format!("{}/A6abc", server.url());
+        let link = resolver.resolve(&short_url).await;
+        assert_eq!(link.short_url, short_url);
+        assert_eq!(link.final_url, Some(format!("{}/article", server.url())));
+        assert_eq!(link.status_code, Some(200));
+        assert_eq!(link.title.as_deref(), Some("猫 & 狗"));
+        assert!(link.error.is_none());
+        assert!(link.is_resolved());
+        short.assert_async().await;
+        dest.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_resolve_dead_link() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/gone")
+            .with_status(404)
+            .create_async()
+            .await;
+
+        let resolver = LinkResolver::new().unwrap();
+        let link = resolver.resolve(&format!("{}/gone", server.url())).await;
+        assert_eq!(link.status_code, Some(404));
+        assert!(link.title.is_none());
+        assert!(!link.is_resolved());
+
+        let link = resolver.resolve("http://127.0.0.1:1/unreachable").await;
+        assert!(link.final_url.is_none());
+        assert!(link.error.is_some());
+        assert!(!link.is_resolved());
+    }
+
+    #[test]
+    fn test_unwrap_interstitial() {
+        let url =
+            Url::parse("https://weibo.cn/sinaurl?u=https%3A%2F%2Fexample.com%2Fa%3Fb%3D1").unwrap();
+        assert_eq!(
+            unwrap_interstitial(&url).unwrap().as_str(),
+            "https://example.com/a?b=1"
+        );
+        let url = Url::parse("https://weibo.com/sinaurl?u=https%3A%2F%2Fexample.com").unwrap();
+        assert!(unwrap_interstitial(&url).is_none());
+        let url = Url::parse("https://example.com/page").unwrap();
+        assert!(unwrap_interstitial(&url).is_none());
+    }
+}
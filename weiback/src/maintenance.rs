@@ -0,0 +1,85 @@
+//! This module defines the maintenance of an archive's database, as done by the
+//! "maintain database" task.
+//!
+//! A run goes through the [`MaintenanceStep`]s in order. The integrity check comes
+//! first: a database found corrupt is left as it is, since rebuilding it with `VACUUM`
+//! could lose the damaged rows for good, and should be restored from a snapshot instead.
+//!
+//! When the last run finished is kept with the archive, so that runs can be scheduled
+//! every [`maintenance_interval_days`](crate::config::Config::maintenance_interval_days)
+//! with [`is_due`]. An archive that was never maintained starts counting from when the
+//! schedule first sees it, rather than being maintained at once.
+
+use serde::{Deserialize, Serialize};
+
+/// A step of a maintenance run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MaintenanceStep {
+    /// `PRAGMA integrity_check`.
+    IntegrityCheck,
+    /// `ANALYZE`.
+    Analyze,
+    /// `PRAGMA optimize`.
+    Optimize,
+    /// Merges the segments of the full-text search index.
+    OptimizeSearchIndex,
+    /// `VACUUM`.
+    Vacuum,
+}
+
+impl MaintenanceStep {
+    /// All the steps, in the order they are run.
+    pub const ALL: [MaintenanceStep; 5] = [
+        MaintenanceStep::IntegrityCheck,
+        MaintenanceStep::Analyze,
+        MaintenanceStep::Optimize,
+        MaintenanceStep::OptimizeSearchIndex,
+        MaintenanceStep::Vacuum,
+    ];
+}
+
+/// What a maintenance run did.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceReport {
+    /// When the run started, as a Unix timestamp.
+    pub started_at: i64,
+    /// How long the run took, in seconds.
+    pub duration_secs: i64,
+    /// The size of the database before the run, in bytes.
+    pub size_before: u64,
+    /// The size of the database after the run, in bytes.
+    pub size_after: u64,
+    /// The steps that were run.
+    pub steps: Vec<MaintenanceStep>,
+    /// The problems found by the integrity check, empty if the database is intact.
+    pub integrity_errors: Vec<String>,
+}
+
+/// Whether a scheduled maintenance run is due.
+///
+/// # Arguments
+/// * `last` - When the database was last maintained, as a Unix timestamp. `None` is
+///   never due: the caller records `now` as the start of the schedule instead.
+/// * `interval_days` - The days between runs. `0` turns scheduled runs off.
+/// * `now` - The current time, as a Unix timestamp.
+pub fn is_due(last: Option<i64>, interval_days: u32, now: i64) -> bool {
+    match last {
+        Some(last) if interval_days > 0 => now - last >= i64::from(interval_days) * 24 * 60 * 60,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod local_tests {
+    use super::*;
+
+    #[test]
+    fn test_is_due() {
+        let day = 24 * 60 * 60;
+        assert!(!is_due(None, 0, 100 * day));
+        assert!(!is_due(None, 7, 100 * day));
+        assert!(!is_due(Some(95 * day), 7, 100 * day));
+        assert!(is_due(Some(93 * day), 7, 100 * day));
+        assert!(!is_due(Some(0), 0, 100 * day));
+    }
+}
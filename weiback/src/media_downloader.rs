@@ -32,7 +32,7 @@ use url::Url;
 
 use super::core::task::TaskContext;
 use super::core::task_manager::{TaskError, TaskErrorType};
-use crate::error::Result;
+use crate::error::{Error, Result};
 
 /// Maximum number of concurrent downloads.
 pub const MAX_CONCURRENT_DOWNLOADS: usize = 5;
@@ -66,6 +66,21 @@ pub trait MediaDownloader: Clone + Send + Sync + 'static {
         url: &Url,
         callback: AsyncDownloadCallback,
     ) -> Result<()>;
+
+    /// Queues a download that tries each URL in order until one succeeds.
+    ///
+    /// Only the failure of the last candidate is reported as a task error.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context for progress and error reporting.
+    /// * `urls` - The candidate URLs, most preferred first. Must not be empty.
+    /// * `callback` - An async closure executed on the URL that succeeded and its data.
+    async fn download_media_with_fallback(
+        &self,
+        ctx: Arc<TaskContext>,
+        urls: &[Url],
+        callback: AsyncFallbackDownloadCallback,
+    ) -> Result<()>;
 }
 
 /// A type alias for the asynchronous callback function executed after a successful download.
@@ -78,11 +93,25 @@ pub type AsyncDownloadCallback = Box<
         + 'static,
 >;
 
+/// A type alias for the callback of [`MediaDownloader::download_media_with_fallback`].
+///
+/// It additionally receives the URL that was actually downloaded.
+pub type AsyncFallbackDownloadCallback = Box<
+    dyn FnOnce(
+            Arc<TaskContext>,
+            Url,
+            Bytes,
+        ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'static>>
+        + Send
+        + 'static,
+>;
+
 /// Internal structure representing a single download request sent to the worker.
 struct DownloadTask {
     ctx: Arc<TaskContext>,
-    url: Url,
-    callback: AsyncDownloadCallback,
+    /// Candidate URLs, most preferred first. Never empty.
+    urls: Vec<Url>,
+    callback: AsyncFallbackDownloadCallback,
 }
 
 /// Internal state for tracking downloader status.
@@ -172,9 +201,33 @@ impl MediaDownloader for MediaDownloaderHandle {
         url: &Url,
         callback: AsyncDownloadCallback,
     ) -> Result<()> {
+        let callback: AsyncFallbackDownloadCallback =
+            Box::new(move |ctx: Arc<TaskContext>, _url: Url, blob: Bytes| callback(ctx, blob));
+        self.download_media_with_fallback(ctx, std::slice::from_ref(url), callback)
+            .await
+    }
+
+    /// Sends a download request with fallback URLs to the background worker.
+    ///
+    /// This method is non-blocking and returns as soon as the request is queued.
+    ///
+    /// # Errors
+    /// Returns an error if `urls` is empty or the internal channel is closed.
+    #[tracing::instrument(skip(self, ctx, callback), fields(urls = ?urls))]
+    async fn download_media_with_fallback(
+        &self,
+        ctx: Arc<TaskContext>,
+        urls: &[Url],
+        callback: AsyncFallbackDownloadCallback,
+    ) -> Result<()> {
+        if urls.is_empty() {
+            return Err(Error::InconsistentTask(
+                "no url given to download".to_string(),
+            ));
+        }
         let task = DownloadTask {
             ctx,
-            url: url.to_owned(),
+            urls: urls.to_vec(),
             callback,
         };
         self.status.queue_length.fetch_add(1, Ordering::Relaxed);
@@ -225,7 +278,7 @@ impl DownloaderWorker {
                     match opt {
                         Some(task) => {
                             self.status.queue_length.fetch_sub(1, Ordering::Relaxed);
-                            let url = task.url.to_string();
+                            let url = task.urls[0].to_string();
                             let ctx = task.ctx.clone();
                             let client = self.client.clone();
                             let url_for_spawn = url.clone();
@@ -239,7 +292,7 @@ impl DownloaderWorker {
 
                             debug!("Starting download: {url}");
                             workers.push(tokio::spawn(async move {
-                                let result = Self::do_process_task(&client, ctx.clone(), &url_for_spawn, task.callback).await;
+                                let result = Self::do_process_task(&client, ctx.clone(), task.urls, task.callback).await;
 
                                 if let Err(err) = &result {
                                     let task_err = TaskError {
@@ -285,17 +338,29 @@ impl DownloaderWorker {
         }
     }
 
-    /// Performs the HTTP request and handles the response.
-    #[tracing::instrument(skip(client, ctx, callback), fields(url = %url))]
+    /// Tries each candidate URL in turn and runs the callback on the first success.
+    #[tracing::instrument(skip(client, ctx, urls, callback), fields(url = %urls[0]))]
     async fn do_process_task(
         client: &Client,
         ctx: Arc<TaskContext>,
-        url: &str,
-        callback: AsyncDownloadCallback,
+        urls: Vec<Url>,
+        callback: AsyncFallbackDownloadCallback,
     ) -> Result<()> {
-        let url = Url::parse(url).inspect_err(|e| {
-            error!("Failed to parse URL {url}: {e}");
-        })?;
+        let mut last_err = None;
+        for url in urls {
+            match Self::fetch(client, &url).await {
+                Ok(body) => return (callback)(ctx, url, body).await,
+                Err(e) => {
+                    debug!("Download from {url} failed, trying next candidate if any");
+                    last_err = Some(e);
+                }
+            }
+        }
+        Err(last_err.expect("download task has at least one url"))
+    }
+
+    /// Performs the HTTP request and returns the response body.
+    async fn fetch(client: &Client, url: &Url) -> Result<Bytes> {
         let response = client
             .get(url.clone())
             .send()
@@ -308,7 +373,7 @@ impl DownloaderWorker {
             error!("Failed to read bytes from response for {url}: {e}");
         })?;
         debug!("Successfully downloaded media file from {url}");
-        (callback)(ctx, body).await
+        Ok(body)
     }
 }
 
@@ -423,6 +488,60 @@ mod local_tests {
         mock.assert_async().await;
     }
 
+    #[tokio::test]
+    async fn test_download_media_with_fallback() {
+        let mut server = Server::new_async().await;
+        let missing_url = Url::parse(&format!("{}/large/pic.jpg", server.url())).unwrap();
+        let fallback_url = Url::parse(&format!("{}/mw2000/pic.jpg", server.url())).unwrap();
+        let missing = server
+            .mock("GET", "/large/pic.jpg")
+            .with_status(404)
+            .create_async()
+            .await;
+        let fallback = server
+            .mock("GET", "/mw2000/pic.jpg")
+            .with_status(200)
+            .with_body("fallback data")
+            .create_async()
+            .await;
+
+        let client = Client::new();
+        let (handle, worker) = create_downloader(1, client);
+
+        let notify = Arc::new(Notify::new());
+        let notify_clone = notify.clone();
+        let expected_url = fallback_url.clone();
+        let callback = Box::new(
+            move |_: Arc<TaskContext>,
+                  url: Url,
+                  data: Bytes|
+                  -> Pin<Box<dyn Future<Output = Result<()>> + Send>> {
+                assert_eq!(url, expected_url);
+                assert_eq!(data, Bytes::from("fallback data"));
+                notify_clone.notify_one();
+                Box::pin(async { Ok(()) })
+            },
+        );
+
+        tokio::spawn(worker.run());
+
+        let task_manager = Arc::new(TaskManager::new());
+        let dummy_context = Arc::new(TaskContext {
+            task_id: Some(1),
+            config: Default::default(),
+            task_manager: task_manager.clone(),
+        });
+        handle
+            .download_media_with_fallback(dummy_context, &[missing_url, fallback_url], callback)
+            .await
+            .unwrap();
+
+        notify.notified().await;
+        assert!(task_manager.get_and_clear_task_errors().unwrap().is_empty());
+        missing.assert_async().await;
+        fallback.assert_async().await;
+    }
+
     #[tokio::test]
     async fn test_download_media_callback_error() {
         let mut server = Server::new_async().await;
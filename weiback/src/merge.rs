@@ -0,0 +1,85 @@
+//! This module defines the report of merging another archive into the current one, as
+//! produced by the "merge archive" task.
+//!
+//! The other archive's database is attached next to the current one, and each table is
+//! merged by its key:
+//! - `users`: new users are added, and known users are updated when the other archive saw
+//!   them more recently (`statuses_count_at`),
+//! - `posts`: new posts are added, and a post in both archives is taken from the other
+//!   one when its version is more complete, see [`Storage::merge_archive`](crate::storage::Storage::merge_archive).
+//!   The raw JSON, mentions and topics of a post always come with it,
+//! - `favorited_posts`, `picture` and `video`: missing rows are added, except media rows
+//!   whose path is not a plain relative path, which are reported as conflicts,
+//! - `links`: the most recent resolution of each short link is kept.
+//!
+//! The media files of the added `picture` and `video` rows are then copied over.
+
+use serde::{Deserialize, Serialize};
+
+use crate::verify::MediaFile;
+
+/// What merging another archive did.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MergeReport {
+    /// When the merge was run, as a Unix timestamp.
+    pub merged_at: i64,
+    /// The database of the other archive.
+    pub source: String,
+    pub users_added: u64,
+    /// Known users updated from the other archive.
+    pub users_updated: u64,
+    pub posts_added: u64,
+    /// Known posts replaced by a more complete version from the other archive.
+    pub posts_replaced: u64,
+    pub favorites_added: u64,
+    pub pictures_added: u64,
+    pub videos_added: u64,
+    /// Short links added or updated with a more recent resolution.
+    pub links_merged: u64,
+    /// Media files copied from the other archive.
+    pub files_copied: u64,
+    /// Media files not copied because a file with the same path is already stored.
+    pub files_skipped: u64,
+    /// Media files of added rows that are missing in the other archive, relative to
+    /// its media directories.
+    pub missing_files: Vec<String>,
+    /// Media files of added rows that do not match their checksum in an imported bundle.
+    #[serde(default)]
+    pub corrupt_files: Vec<String>,
+    /// Users and posts stored differently in both archives, and media rows left out.
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// A user or post stored differently in both archives, or a media row of the other
+/// archive left out because its path would leave the media directories.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MergeConflict {
+    /// `users`, `posts`, `picture` or `video`.
+    pub table: String,
+    /// The ID of the user or post, or the post a media row is attached to.
+    pub id: i64,
+    /// The URL of a media row.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Which version was kept.
+    pub kept: MergeSide,
+}
+
+/// One of the two archives of a merge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MergeSide {
+    /// The current archive.
+    Local,
+    /// The archive merged in.
+    Incoming,
+}
+
+/// The rows a merge added, with the media files to copy.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MergeOutcome {
+    pub report: MergeReport,
+    /// The files of the added `picture` rows.
+    pub pictures: Vec<MediaFile>,
+    /// The files of the added `video` rows.
+    pub videos: Vec<MediaFile>,
+}
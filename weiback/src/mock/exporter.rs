@@ -27,6 +27,8 @@ pub struct MockExporter {
 struct Inner {
     /// List of HTML pages that have been "exported".
     exported_pages: Vec<HTMLPage>,
+    /// Files that have been "exported", as (file name, content).
+    exported_files: Vec<(String, Vec<u8>)>,
     /// Flag to simulate an export failure.
     should_fail: bool,
 }
@@ -42,6 +44,11 @@ impl MockExporter {
         self.inner.lock().unwrap().exported_pages.clone()
     }
 
+    /// Retrieves a clone of all files that have been "exported" by this mock.
+    pub fn get_exported_files(&self) -> Vec<(String, Vec<u8>)> {
+        self.inner.lock().unwrap().exported_files.clone()
+    }
+
     /// Sets whether the mock exporter should simulate a failure on subsequent `export_page` calls.
     ///
     /// # Arguments
@@ -78,4 +85,19 @@ impl Exporter for MockExporter {
         inner.exported_pages.push(page);
         Ok(())
     }
+
+    /// Simulates the export of a single file, adding it to the `exported_files` list.
+    ///
+    /// # Errors
+    /// Returns `Error::InconsistentTask` if `should_fail` is true.
+    async fn export_file(&self, file_name: &str, content: &[u8], _export_dir: &Path) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.should_fail {
+            return Err(Error::InconsistentTask("Mock error".into()));
+        }
+        inner
+            .exported_files
+            .push((file_name.to_string(), content.to_vec()));
+        Ok(())
+    }
 }
@@ -21,7 +21,7 @@ use crate::{
         task_manager::{TaskError, TaskErrorType, TaskManager},
     },
     error::{Error, Result},
-    media_downloader::{AsyncDownloadCallback, MediaDownloader},
+    media_downloader::{AsyncDownloadCallback, AsyncFallbackDownloadCallback, MediaDownloader},
 };
 
 /// A mock implementation of the [`MediaDownloader`] trait.
@@ -101,6 +101,50 @@ impl MediaDownloader for MockMediaDownloader {
 
         Ok(())
     }
+
+    /// Simulates downloading a media file with fallback URLs.
+    ///
+    /// The mocked responses of `urls` are consumed in order until one succeeds.
+    /// If none does, the error of the last candidate is reported for the first URL.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context for reporting errors.
+    /// * `urls` - The candidate URLs, most preferred first.
+    /// * `callback` - The callback to execute with the "downloaded" URL and data.
+    async fn download_media_with_fallback(
+        &self,
+        ctx: Arc<TaskContext>,
+        urls: &[Url],
+        callback: AsyncFallbackDownloadCallback,
+    ) -> Result<()> {
+        let Some(first_url) = urls.first() else {
+            return Err(Error::InconsistentTask(
+                "no url given to download".to_string(),
+            ));
+        };
+        let mut result = Ok(());
+        for url in urls {
+            let response = self.inner.lock().unwrap().responses.remove(url);
+            match response {
+                Some(Ok(data)) => {
+                    result = (callback)(ctx.clone(), url.clone(), data).await;
+                    break;
+                }
+                Some(Err(e)) => result = Err(e),
+                None => result = Err(Error::InconsistentTask(format!("URL not mocked: {}", url))),
+            }
+        }
+
+        if let Err(err) = result {
+            let task_err = TaskError {
+                error_type: TaskErrorType::DownloadMedia(first_url.to_string()),
+                message: err.to_string(),
+            };
+            ctx.task_manager.report_task_error(task_err)?;
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
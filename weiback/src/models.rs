@@ -8,6 +8,7 @@
 
 pub mod common;
 pub mod err_response;
+pub mod link;
 pub mod mix_media_info;
 pub mod page_info;
 pub mod pic_infos;
@@ -22,6 +23,7 @@ mod build_comments;
 
 pub use common::{HugeInfo, Orientation, PicInfoDetail, PicInfoItemSimple, VideoInfo};
 pub use err_response::ErrResponse;
+pub use link::Link;
 pub use mix_media_info::{MixMediaInfo, MixMediaInfoItem};
 pub use page_info::{PageInfo, PagePicInfo};
 pub use pic_infos::{FocusPoint, PicInfoItem, PicInfoType};
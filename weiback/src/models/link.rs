@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+/// The outcome of resolving a short link (e.g. `https://t.cn/xxx`) found in post text.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Link {
+    /// The short URL as it appears in the post text.
+    pub short_url: String,
+    /// The destination after following redirects, if it was reached.
+    pub final_url: Option<String>,
+    /// The HTTP status code of the destination.
+    pub status_code: Option<u16>,
+    /// The `<title>` of the destination page, if it is HTML.
+    pub title: Option<String>,
+    /// Why the destination could not be reached.
+    pub error: Option<String>,
+    /// When the link was resolved, as a Unix timestamp.
+    pub resolved_at: i64,
+}
+
+impl Link {
+    /// Returns `true` if the destination was reached with a successful status code.
+    pub fn is_resolved(&self) -> bool {
+        self.final_url.is_some() && self.status_code.is_some_and(|c| (200..400).contains(&c))
+    }
+}
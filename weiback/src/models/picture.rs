@@ -18,6 +18,27 @@ pub enum PictureDefinition {
     RealOriginal,
 }
 
+impl PictureDefinition {
+    /// All definitions, from the highest to the lowest.
+    pub const ALL_DESC: [PictureDefinition; 7] = [
+        PictureDefinition::RealOriginal,
+        PictureDefinition::Largest,
+        PictureDefinition::Mw2000,
+        PictureDefinition::Original,
+        PictureDefinition::Large,
+        PictureDefinition::Bmiddle,
+        PictureDefinition::Thumbnail,
+    ];
+
+    /// Returns this definition followed by every lower one, best first.
+    ///
+    /// This is the order in which definitions are tried when the preferred one
+    /// is not available, both when downloading and when resolving local files.
+    pub fn fallback_chain(self) -> impl Iterator<Item = PictureDefinition> {
+        Self::ALL_DESC.into_iter().filter(move |d| *d <= self)
+    }
+}
+
 impl From<&str> for PictureDefinition {
     fn from(value: &str) -> Self {
         match value {
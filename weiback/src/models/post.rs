@@ -34,6 +34,9 @@ pub struct Post {
     pub text: String,
     pub url_struct: Option<UrlStruct>,
     pub user: Option<User>,
+    /// The raw API JSON this post was parsed from, if it came from the API.
+    #[serde(skip)]
+    pub raw: Option<Value>,
 }
 
 #[cfg(test)]
@@ -72,7 +75,10 @@ mod local_tests {
             .collect::<Result<Vec<Post>>>()
             .unwrap();
 
-        for post in posts {
+        for mut post in posts {
+            assert!(post.raw.is_some());
+            // The raw JSON is never serialized
+            post.raw = None;
             let value_from_struct =
                 serde_json::to_value(&post).expect("Failed to serialize Post to Value");
 
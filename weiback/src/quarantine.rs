@@ -0,0 +1,117 @@
+//! This module defines the quarantine of cleanup tasks.
+//!
+//! Cleanup tasks used to delete what they found right away, but some of their checks,
+//! like the invalid image detection, have false positives. Now a cleanup run either
+//! only lists what it would remove (dry run), or moves it to a quarantine batch:
+//! - the files go to a dated folder, `{quarantine_path}/{batch}/pictures` and `videos`,
+//!   keeping their paths relative to the media directories,
+//! - the rows are kept in the `quarantine_rows` table.
+//!
+//! A batch can then be restored with [`Storage::restore_quarantine`](crate::storage::Storage::restore_quarantine).
+
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::gc::move_file;
+use crate::verify::path_to_string;
+
+/// What a cleanup task removed, or would remove in a dry run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CleanupReport {
+    /// When the cleanup was run, as a Unix timestamp.
+    pub created_at: i64,
+    /// The cleanup task, e.g. `cleanup_pictures`.
+    pub task: String,
+    /// Whether nothing was removed.
+    pub dry_run: bool,
+    /// The quarantine batch the removed data went to, unless it was a dry run.
+    pub batch: Option<String>,
+    /// The URLs of the pictures removed.
+    pub pictures: Vec<String>,
+    /// The IDs of the posts removed, together with the posts in their retweet trees.
+    pub posts: Vec<i64>,
+}
+
+impl CleanupReport {
+    /// Starts the report of a cleanup task.
+    pub fn new(task: &str, dry_run: bool) -> Self {
+        Self {
+            created_at: Utc::now().timestamp(),
+            task: task.to_string(),
+            dry_run,
+            ..Default::default()
+        }
+    }
+}
+
+/// A cleanup run whose removed data is in quarantine.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuarantineBatch {
+    /// The name of the batch, which is also the name of its folder.
+    pub batch: String,
+    /// The cleanup task that created the batch.
+    pub task: String,
+    /// When the batch was created, as a Unix timestamp.
+    pub created_at: i64,
+    /// When the batch was restored, as a Unix timestamp.
+    pub restored_at: Option<i64>,
+    /// The number of rows kept for the batch.
+    pub rows: i64,
+}
+
+/// What a restore did.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RestoreSummary {
+    /// Rows put back into their tables.
+    pub restored_rows: u64,
+    /// Rows dropped because a row with the same key was saved since.
+    pub skipped_rows: u64,
+    /// Files moved back to the media directories.
+    pub restored_files: u64,
+    /// Files left in quarantine because a file with the same path was saved since.
+    pub skipped_files: u64,
+}
+
+/// Names a new quarantine batch after the time and the cleanup task. The time only has
+/// one-second resolution, so a sequence number is appended to tell apart the batches
+/// created within the same second.
+pub fn batch_name(task: &str, now: DateTime<Local>) -> String {
+    static SEQUENCE: AtomicU32 = AtomicU32::new(0);
+    let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    format!("{}_{task}_{sequence}", now.format("%Y-%m-%d_%H%M%S"))
+}
+
+/// Moves a media file to a quarantine folder, keeping its relative path. A missing file
+/// is skipped.
+///
+/// # Arguments
+/// * `root` - The media directory the file is in.
+/// * `relative` - The path of the file, relative to `root`.
+/// * `dir` - The folder of the batch for this kind of media, e.g. `{batch}/pictures`.
+pub async fn move_to_quarantine(root: &Path, relative: &Path, dir: &Path) -> Result<()> {
+    if !root.join(relative).exists() {
+        return Ok(());
+    }
+    move_file(root, &path_to_string(relative), dir).await
+}
+
+#[cfg(test)]
+mod local_tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_batch_name() {
+        let now = Local.with_ymd_and_hms(2026, 4, 9, 8, 3, 7).unwrap();
+        let first = batch_name("cleanup_pictures", now);
+        let second = batch_name("cleanup_pictures", now);
+        assert!(first.starts_with("2026-04-09_080307_cleanup_pictures_"));
+        assert!(second.starts_with("2026-04-09_080307_cleanup_pictures_"));
+        assert_ne!(first, second);
+    }
+}
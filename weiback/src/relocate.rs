@@ -0,0 +1,199 @@
+//! This module moves or copies the media directories of the archive to new locations,
+//! as done by the "relocate storage" task.
+//!
+//! A directory being moved is renamed when the new location does not exist yet and is
+//! on the same file system. Otherwise each file is copied with [`copy_verified`], which
+//! checks the copy against the SHA-256 checksum of the original, and files left at the
+//! new location by an interrupted run are checked with [`same_content`] and kept.
+//!
+//! The configuration only points to a new location once every file is in place there,
+//! and when moving, the old files are removed after that. An interrupted run leaves the
+//! old directory in use and can simply be run again.
+//!
+//! The database cannot be moved while it is open, its relocation is staged and done on
+//! the next start, see [`stage_relocation`](crate::storage::database::stage_relocation).
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use crate::error::{Error, Result};
+use crate::utils::hex_string;
+
+/// Whether the old files are kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RelocateMode {
+    /// Remove the old files once the new location is in use.
+    #[default]
+    Move,
+    /// Keep the old files.
+    Copy,
+}
+
+/// What relocating a media directory did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RelocateSummary {
+    /// Whether the whole directory was renamed.
+    pub renamed: bool,
+    /// Files copied and verified.
+    pub copied: u64,
+    /// Files found at the new location from an earlier run, with the same content.
+    pub kept: u64,
+    /// Files that could not be relocated, relative to the directory.
+    pub failed: Vec<String>,
+}
+
+/// Checks that a directory can be relocated: neither location may contain the other.
+pub fn check_locations(from: &Path, to: &Path) -> Result<()> {
+    let from = std::path::absolute(from)?;
+    let to = std::path::absolute(to)?;
+    if from.starts_with(&to) || to.starts_with(&from) {
+        return Err(Error::ConfigError(format!(
+            "cannot relocate {from:?} to {to:?}, one contains the other"
+        )));
+    }
+    Ok(())
+}
+
+/// Renames a directory to a new location that does not exist yet, creating its parent.
+///
+/// # Returns
+/// `false` if the directory could not be renamed, e.g. across file systems, and has to
+/// be copied instead.
+pub async fn try_rename(from: &Path, to: &Path) -> Result<bool> {
+    if to.exists() {
+        return Ok(false);
+    }
+    if let Some(parent) = to.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    Ok(tokio::fs::rename(from, to).await.is_ok())
+}
+
+/// The SHA-256 checksum of a file, as lowercase hex.
+pub async fn sha256_file(path: &Path) -> Result<String> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut hasher = Sha256::new();
+        io::copy(&mut std::fs::File::open(&path)?, &mut hasher)?;
+        Ok(hex_string(&hasher.finalize()))
+    })
+    .await
+    .map_err(|e| Error::Io(io::Error::other(e)))?
+}
+
+/// Whether two files have the same content.
+pub async fn same_content(a: &Path, b: &Path) -> Result<bool> {
+    if tokio::fs::metadata(a).await?.len() != tokio::fs::metadata(b).await?.len() {
+        return Ok(false);
+    }
+    Ok(sha256_file(a).await? == sha256_file(b).await?)
+}
+
+/// Copies a file and checks the copy against the original.
+///
+/// The file is copied next to its destination first and only renamed into place once
+/// it matches, so an interrupted copy never leaves a partial file behind.
+pub async fn copy_verified(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let mut name = to.as_os_str().to_owned();
+    name.push(".part");
+    let part = PathBuf::from(name);
+    tokio::fs::copy(from, &part).await?;
+    if !same_content(from, &part).await? {
+        tokio::fs::remove_file(&part).await?;
+        return Err(Error::Io(io::Error::other(format!(
+            "copy of {from:?} does not match the original"
+        ))));
+    }
+    tokio::fs::rename(&part, to).await?;
+    Ok(())
+}
+
+/// Copies a file to its new location, or checks the copy an earlier run left there.
+pub async fn relocate_file(from: &Path, to: &Path, summary: &mut RelocateSummary) -> Result<()> {
+    if !to.exists() {
+        copy_verified(from, to).await?;
+        summary.copied += 1;
+    } else if same_content(from, to).await? {
+        summary.kept += 1;
+    } else {
+        return Err(Error::Io(io::Error::other(format!(
+            "{to:?} already exists with another content"
+        ))));
+    }
+    Ok(())
+}
+
+/// Removes the empty directories under a directory, and the directory itself if it
+/// ends up empty.
+pub async fn remove_empty_dirs(dir: &Path) -> Result<()> {
+    let dir = dir.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        for entry in WalkDir::new(&dir)
+            .contents_first(true)
+            .into_iter()
+            .flatten()
+        {
+            if entry.file_type().is_dir() {
+                // Fails for directories that are not empty, which are kept
+                let _ = std::fs::remove_dir(entry.path());
+            }
+        }
+    })
+    .await
+    .map_err(|e| Error::Io(io::Error::other(e)))
+}
+
+#[cfg(test)]
+mod local_tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_copy_verified_and_cleanup() {
+        let temp_dir = TempDir::new().unwrap();
+        let from = temp_dir.path().join("pictures");
+        let to = temp_dir.path().join("new/pictures");
+        tokio::fs::create_dir_all(from.join("a/b")).await.unwrap();
+        tokio::fs::write(from.join("a/b/1.jpg"), b"1")
+            .await
+            .unwrap();
+
+        assert!(check_locations(&from, &to).is_ok());
+        assert!(check_locations(&from, &from.join("a")).is_err());
+        assert!(check_locations(&from.join("a"), &from).is_err());
+
+        copy_verified(&from.join("a/b/1.jpg"), &to.join("a/b/1.jpg"))
+            .await
+            .unwrap();
+        assert!(!to.join("a/b/1.jpg.part").exists());
+        assert!(
+            same_content(&from.join("a/b/1.jpg"), &to.join("a/b/1.jpg"))
+                .await
+                .unwrap()
+        );
+        tokio::fs::write(to.join("a/2.jpg"), b"2").await.unwrap();
+        assert!(
+            !same_content(&from.join("a/b/1.jpg"), &to.join("a/2.jpg"))
+                .await
+                .unwrap()
+        );
+
+        tokio::fs::remove_file(from.join("a/b/1.jpg"))
+            .await
+            .unwrap();
+        remove_empty_dirs(&from).await.unwrap();
+        assert!(!from.exists());
+        tokio::fs::remove_file(to.join("a/b/1.jpg")).await.unwrap();
+        remove_empty_dirs(&to).await.unwrap();
+        assert!(to.join("a/2.jpg").exists());
+        assert!(!to.join("a/b").exists());
+    }
+}
@@ -0,0 +1,283 @@
+//! This module defines the statistics report of an archive and renders it for dashboards.
+//!
+//! An [`ArchiveStats`] is assembled by [`Storage::stats`](crate::storage::Storage::stats)
+//! from a family of aggregate queries over the database, plus the sizes of the media
+//! files on disk. Times are bucketed in the post's own UTC offset (as Weibo reports it,
+//! usually `+08:00`), so the hours are the ones the author saw on their clock.
+//!
+//! [`render_stats`] writes a report as a single JSON file, or as one CSV file per table.
+
+use serde::{Deserialize, Serialize};
+use serde_json::to_string_pretty;
+
+use crate::core::task::TopicCount;
+use crate::error::Result;
+
+/// The number of topics and sources ranked in a report.
+pub const TOP_LIMIT: u64 = 20;
+
+/// A statistics report of the posts and media in an archive.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveStats {
+    /// Only posts by this user were counted, if set.
+    pub uid: Option<i64>,
+    /// When the report was generated, as a Unix timestamp.
+    pub generated_at: i64,
+    pub total_posts: u64,
+    pub original_posts: u64,
+    pub retweet_posts: u64,
+    /// Posts per user per month, ordered by user and month.
+    pub monthly_posts: Vec<MonthlyPostCount>,
+    /// Media files per user, most bytes first.
+    pub user_media: Vec<UserMediaStats>,
+    pub top_topics: Vec<TopicCount>,
+    pub top_sources: Vec<SourceCount>,
+    /// Posts per weekday and hour, for a posting-hour heatmap. Empty cells are left out.
+    pub hourly_posts: Vec<HourlyPostCount>,
+}
+
+/// The number of posts a user made in a month.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MonthlyPostCount {
+    pub uid: i64,
+    pub screen_name: Option<String>,
+    /// The month, as `YYYY-MM`.
+    pub month: String,
+    pub originals: u64,
+    pub retweets: u64,
+}
+
+/// The media files saved for the posts of a user.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserMediaStats {
+    pub uid: i64,
+    pub screen_name: Option<String>,
+    pub pictures: u64,
+    pub picture_bytes: u64,
+    pub videos: u64,
+    pub video_bytes: u64,
+}
+
+/// A client a post was sent from (e.g. "iPhone客户端") with the number of posts sent from it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceCount {
+    pub source: String,
+    pub count: u64,
+}
+
+/// The number of posts made in an hour of a weekday.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HourlyPostCount {
+    /// The day of the week, 0 being Sunday.
+    pub weekday: u8,
+    /// The hour of the day, 0 to 23.
+    pub hour: u8,
+    pub count: u64,
+}
+
+/// The file format of a statistics export.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatsFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+/// Renders a report as files.
+///
+/// # Arguments
+/// * `stats` - The report to render.
+/// * `format` - The file format to write.
+/// * `name` - The base name of the files, without extension.
+///
+/// # Returns
+/// A `Result` containing the file names and contents. JSON gives `{name}.json`, CSV one
+/// `{name}_{table}.csv` per table, with the totals in `{name}_summary.csv`.
+pub fn render_stats(
+    stats: &ArchiveStats,
+    format: StatsFormat,
+    name: &str,
+) -> Result<Vec<(String, String)>> {
+    if format == StatsFormat::Json {
+        return Ok(vec![(format!("{name}.json"), to_string_pretty(stats)?)]);
+    }
+
+    let opt = |s: &Option<String>| s.clone().unwrap_or_default();
+    let opt_id = |id: Option<i64>| id.map(|id| id.to_string()).unwrap_or_default();
+    let tables = [
+        (
+            "summary",
+            vec![
+                "uid",
+                "generated_at",
+                "total_posts",
+                "original_posts",
+                "retweet_posts",
+            ],
+            vec![vec![
+                opt_id(stats.uid),
+                stats.generated_at.to_string(),
+                stats.total_posts.to_string(),
+                stats.original_posts.to_string(),
+                stats.retweet_posts.to_string(),
+            ]],
+        ),
+        (
+            "monthly_posts",
+            vec!["uid", "screen_name", "month", "originals", "retweets"],
+            stats
+                .monthly_posts
+                .iter()
+                .map(|m| {
+                    vec![
+                        m.uid.to_string(),
+                        opt(&m.screen_name),
+                        m.month.clone(),
+                        m.originals.to_string(),
+                        m.retweets.to_string(),
+                    ]
+                })
+                .collect(),
+        ),
+        (
+            "user_media",
+            vec![
+                "uid",
+                "screen_name",
+                "pictures",
+                "picture_bytes",
+                "videos",
+                "video_bytes",
+            ],
+            stats
+                .user_media
+                .iter()
+                .map(|m| {
+                    vec![
+                        m.uid.to_string(),
+                        opt(&m.screen_name),
+                        m.pictures.to_string(),
+                        m.picture_bytes.to_string(),
+                        m.videos.to_string(),
+                        m.video_bytes.to_string(),
+                    ]
+                })
+                .collect(),
+        ),
+        (
+            "top_topics",
+            vec!["topic", "count"],
+            stats
+                .top_topics
+                .iter()
+                .map(|t| vec![t.topic.clone(), t.count.to_string()])
+                .collect(),
+        ),
+        (
+            "top_sources",
+            vec!["source", "count"],
+            stats
+                .top_sources
+                .iter()
+                .map(|s| vec![s.source.clone(), s.count.to_string()])
+                .collect(),
+        ),
+        (
+            "hourly_posts",
+            vec!["weekday", "hour", "count"],
+            stats
+                .hourly_posts
+                .iter()
+                .map(|h| {
+                    vec![
+                        h.weekday.to_string(),
+                        h.hour.to_string(),
+                        h.count.to_string(),
+                    ]
+                })
+                .collect(),
+        ),
+    ];
+    Ok(tables
+        .into_iter()
+        .map(|(table, header, rows)| (format!("{name}_{table}.csv"), render_csv(&header, &rows)))
+        .collect())
+}
+
+fn render_csv(header: &[&str], rows: &[Vec<String>]) -> String {
+    let mut csv = header.join(",");
+    csv.push_str("\r\n");
+    for row in rows {
+        let fields = row.iter().map(|f| escape_csv(f)).collect::<Vec<_>>();
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod local_tests {
+    use super::*;
+
+    #[test]
+    fn test_render_stats() {
+        let stats = ArchiveStats {
+            generated_at: 1_700_000_000,
+            total_posts: 3,
+            original_posts: 2,
+            retweet_posts: 1,
+            monthly_posts: vec![MonthlyPostCount {
+                uid: 1,
+                screen_name: Some("猫, \"咪\"".to_string()),
+                month: "2024-01".to_string(),
+                originals: 2,
+                retweets: 1,
+            }],
+            top_sources: vec![SourceCount {
+                source: "iPhone客户端".to_string(),
+                count: 3,
+            }],
+            ..Default::default()
+        };
+
+        let files = render_stats(&stats, StatsFormat::Json, "stats").unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].0, "stats.json");
+        let parsed: ArchiveStats = serde_json::from_str(&files[0].1).unwrap();
+        assert_eq!(parsed, stats);
+
+        let files = render_stats(&stats, StatsFormat::Csv, "stats").unwrap();
+        let names = files.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "stats_summary.csv",
+                "stats_monthly_posts.csv",
+                "stats_user_media.csv",
+                "stats_top_topics.csv",
+                "stats_top_sources.csv",
+                "stats_hourly_posts.csv",
+            ]
+        );
+        assert_eq!(
+            files[0].1,
+            "uid,generated_at,total_posts,original_posts,retweet_posts\r\n,1700000000,3,2,1\r\n"
+        );
+        assert_eq!(
+            files[1].1,
+            "uid,screen_name,month,originals,retweets\r\n1,\"猫, \"\"咪\"\"\",2024-01,2,1\r\n"
+        );
+        assert_eq!(
+            files[2].1,
+            "uid,screen_name,pictures,picture_bytes,videos,video_bytes\r\n"
+        );
+    }
+}
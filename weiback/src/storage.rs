@@ -9,33 +9,61 @@ pub mod internal;
 pub mod picture_storage;
 pub mod video_storage;
 
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::sync::Arc;
 
 use async_trait::async_trait;
 use bytes::Bytes;
+use chrono::Utc;
+use database::DatabaseBackup;
 use futures::{
-    Stream, TryFutureExt,
-    stream::{self, StreamExt},
+    Stream, TryFutureExt, pin_mut,
+    stream::{self, StreamExt, TryStreamExt},
 };
 use itertools::Itertools;
 use picture_storage::FileSystemPictureStorage;
-use sqlx::SqlitePool;
+use serde_json::Value;
+use sqlx::{Acquire, ConnectOptions, SqliteConnection, SqlitePool};
+use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
 use url::Url;
 
-use crate::core::task::{PaginatedPosts, PostQuery, TaskContext};
-use crate::models::{Picture, PictureMeta, Post, User, Video};
+use crate::bundle::BundleContents;
+use crate::core::task::{MentionCount, PaginatedPosts, PostQuery, TaskContext, TopicCount};
+use crate::coverage::UserCoverage;
+use crate::gc::{GcReport, move_file};
+use crate::maintenance::MaintenanceStep;
+use crate::merge::{MergeOutcome, MergeReport};
+use crate::models::{Link, Picture, PictureMeta, Post, User, Video};
+use crate::quarantine::{QuarantineBatch, RestoreSummary, move_to_quarantine};
+use crate::stats::{ArchiveStats, UserMediaStats};
 use crate::utils::pic_url_to_db_key;
+use crate::verify::{
+    DanglingRetweet, VerifyReport, find_posts_missing_pictures, list_files, match_files,
+    path_to_string,
+};
 use crate::{
     error::{Error, Result},
     storage::video_storage::FileSystemVideoStorage,
 };
+use internal::bundle;
+use internal::link;
+use internal::maintenance;
+use internal::merge;
 use internal::picture;
 use internal::post::{self, PostInternal};
+use internal::post_raw;
+use internal::post_term;
+use internal::quarantine;
+use internal::script_folding::{self, ScriptFolder};
+use internal::stats;
 use internal::user;
+use internal::verify;
+use internal::video;
 
 /// Represents metadata and the associated file system path for a picture.
 #[derive(Debug, Clone)]
@@ -81,10 +109,29 @@ pub trait Storage: Send + Sync + Clone + 'static {
 
     /// Saves a post to the database.
     ///
+    /// The raw API JSON of the post, if present, is kept alongside it.
+    ///
     /// # Arguments
     /// * `post` - The post model to save.
     async fn save_post(&self, post: &Post) -> Result<()>;
 
+    /// Saves posts to the database in one transaction, e.g. a page fetched from Weibo.
+    ///
+    /// Either all posts, with their retweeted posts and authors, are saved, or none is.
+    ///
+    /// # Arguments
+    /// * `posts` - The post models to save.
+    async fn save_posts(&self, posts: &[Post]) -> Result<()>;
+
+    /// Retrieves the raw API JSON a post was saved from.
+    ///
+    /// # Arguments
+    /// * `id` - The unique identifier of the post.
+    ///
+    /// # Returns
+    /// A `Result` containing `None` if no raw JSON was kept for the post.
+    async fn get_post_raw(&self, id: i64) -> Result<Option<Value>>;
+
     /// Retrieves a post by its ID.
     ///
     /// # Arguments
@@ -112,6 +159,31 @@ pub trait Storage: Send + Sync + Clone + 'static {
     /// A `Result` containing a vector of post IDs.
     async fn query_all_post_ids(&self, query: PostQuery) -> Result<Vec<i64>>;
 
+    /// Counts the posts matching the given criteria, ignoring pagination.
+    ///
+    /// # Arguments
+    /// * `query` - A `PostQuery` object containing search and filter criteria.
+    async fn count_posts(&self, query: PostQuery) -> Result<u64>;
+
+    /// Streams all posts matching the given criteria in the query's order.
+    ///
+    /// Posts are loaded lazily in batches using keyset pagination, so the stream stays
+    /// cheap on large archives and is not disturbed by posts being updated or deleted
+    /// while it is consumed. `page` and `posts_per_page` of the query are ignored.
+    /// Posts that fail to hydrate are skipped, as in [`Storage::query_posts`].
+    ///
+    /// # Arguments
+    /// * `query` - A `PostQuery` object containing search and filter criteria.
+    fn stream_posts(&self, query: PostQuery) -> impl Stream<Item = Result<Post>> + Send + '_;
+
+    /// Streams the IDs of all posts matching the given criteria in the query's order,
+    /// like [`Storage::stream_posts`] without loading the posts, for long tasks that
+    /// only need their IDs.
+    ///
+    /// # Arguments
+    /// * `query` - A `PostQuery` object containing search and filter criteria.
+    fn stream_post_ids(&self, query: PostQuery) -> impl Stream<Item = Result<i64>> + Send + '_;
+
     /// Marks a post as unfavorited in the database.
     ///
     /// # Arguments
@@ -130,6 +202,119 @@ pub trait Storage: Send + Sync + Clone + 'static {
     /// A `Result` containing a vector of post IDs.
     async fn get_posts_id_to_unfavorite(&self) -> Result<Vec<i64>>;
 
+    /// Rebuilds the full-text search index of posts from scratch.
+    async fn rebuild_search_index(&self) -> Result<()>;
+
+    /// Checks whether search folds traditional Chinese to simplified for this archive.
+    async fn script_folding(&self) -> Result<bool>;
+
+    /// Saves the resolution of a short link, replacing any earlier one.
+    ///
+    /// # Arguments
+    /// * `link` - The resolved link.
+    async fn save_link(&self, link: &Link) -> Result<()>;
+
+    /// Retrieves the saved resolutions of the given short links.
+    ///
+    /// # Arguments
+    /// * `short_urls` - The short URLs to look up.
+    async fn get_links(&self, short_urls: &[String]) -> Result<Vec<Link>>;
+
+    /// Collects the short links in post text that have not been resolved yet.
+    ///
+    /// # Arguments
+    /// * `retry_failed` - Also return links whose destination could not be reached before.
+    async fn get_unresolved_short_urls(&self, retry_failed: bool) -> Result<Vec<String>>;
+
+    /// Re-extracts the mentions and topics of the given posts from their text.
+    ///
+    /// # Arguments
+    /// * `ids` - The IDs of the posts to re-index.
+    async fn reindex_mentions_and_topics(&self, ids: &[i64]) -> Result<()>;
+
+    /// Ranks the screen names mentioned in posts by the number of posts mentioning them.
+    ///
+    /// # Arguments
+    /// * `uid` - Only count posts by this user, e.g. to find who they mention most.
+    /// * `limit` - The maximum number of screen names to return.
+    async fn get_top_mentions(&self, uid: Option<i64>, limit: u64) -> Result<Vec<MentionCount>>;
+
+    /// Ranks the topics of posts by the number of posts using them.
+    ///
+    /// # Arguments
+    /// * `uid` - Only count posts by this user.
+    /// * `limit` - The maximum number of topics to return.
+    async fn get_top_topics(&self, uid: Option<i64>, limit: u64) -> Result<Vec<TopicCount>>;
+
+    /// Builds a statistics report of the archive: posts per user per month, media files
+    /// and their sizes per user, the top topics and sources, and posts per hour.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context, for the media storage paths.
+    /// * `uid` - Only count posts by this user.
+    /// * `top_limit` - The number of topics and sources to rank.
+    async fn stats(
+        &self,
+        ctx: Arc<TaskContext>,
+        uid: Option<i64>,
+        top_limit: u64,
+    ) -> Result<ArchiveStats>;
+
+    /// Compares the number of posts Weibo reports for each user with the posts stored
+    /// locally, and finds the gaps in the archive of each user.
+    ///
+    /// # Arguments
+    /// * `uid` - Only report on this user.
+    ///
+    /// # Returns
+    /// A `Result` containing the coverage of each user with local posts, the users with
+    /// the most missing posts first.
+    async fn get_coverage(&self, uid: Option<i64>) -> Result<Vec<UserCoverage>>;
+
+    /// Checks that the database and the media directories agree, and that the search
+    /// index is in sync with the posts. Nothing is changed.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context, for the media storage paths.
+    ///
+    /// # Returns
+    /// A `Result` containing the report, without a repair summary.
+    async fn verify_archive(&self, ctx: Arc<TaskContext>) -> Result<VerifyReport>;
+
+    /// Lists the media rows attached to posts that are no longer stored, and the files
+    /// under the media directories that no other row points to. Nothing is changed.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context, for the media storage paths.
+    ///
+    /// # Returns
+    /// A `Result` containing the listing, with nothing removed yet.
+    async fn find_orphan_media(&self, ctx: Arc<TaskContext>) -> Result<GcReport>;
+
+    /// Moves picture and video rows to a quarantine batch in one transaction, leaving
+    /// the files alone.
+    ///
+    /// # Arguments
+    /// * `batch` - The quarantine batch.
+    /// * `picture_urls` - The URLs of the pictures to remove.
+    /// * `video_urls` - The URLs of the videos to remove.
+    ///
+    /// # Returns
+    /// A `Result` containing the number of removed rows.
+    async fn quarantine_media_rows(
+        &self,
+        batch: &str,
+        picture_urls: &[String],
+        video_urls: &[String],
+    ) -> Result<u64>;
+
+    /// Enables or disables folding traditional Chinese to simplified in search for this
+    /// archive, rebuilding the full-text search index accordingly.
+    ///
+    /// # Arguments
+    /// * `enabled` - Whether search text should be folded.
+    async fn set_script_folding(&self, enabled: bool) -> Result<()>;
+
     /// Deletes a post and all its associated media.
     ///
     /// # Arguments
@@ -146,12 +331,126 @@ pub trait Storage: Send + Sync + Clone + 'static {
     /// - If the post has no children, deletes only the post itself.
     async fn delete_post(&self, ctx: Arc<TaskContext>, id: i64, deep: bool) -> Result<()>;
 
-    /// Retrieves IDs of posts that are invalid (e.g., uid is NULL).
+    /// Counts the posts that are invalid (e.g., uid is NULL).
+    ///
+    /// # Arguments
+    /// * `clean_retweeted_invalid` - Whether to include posts that are valid themselves
+    /// but their retweeted content is invalid.
+    async fn count_invalid_posts(&self, clean_retweeted_invalid: bool) -> Result<u64>;
+
+    /// Streams the IDs of posts that are invalid (e.g., uid is NULL) in ascending order.
+    ///
+    /// The IDs are loaded lazily in batches, so posts can be deleted while the stream
+    /// is consumed.
     ///
     /// # Arguments
     /// * `clean_retweeted_invalid` - Whether to include posts that are valid themselves
     /// but their retweeted content is invalid.
-    async fn get_invalid_posts_ids(&self, clean_retweeted_invalid: bool) -> Result<Vec<i64>>;
+    fn stream_invalid_posts_ids(
+        &self,
+        clean_retweeted_invalid: bool,
+    ) -> impl Stream<Item = Result<i64>> + Send + '_;
+
+    /// Records a new quarantine batch, to move what a cleanup task removes to.
+    ///
+    /// # Arguments
+    /// * `batch` - The name of the batch, see [`crate::quarantine::batch_name`].
+    /// * `task` - The cleanup task creating the batch.
+    async fn create_quarantine_batch(&self, batch: &str, task: &str) -> Result<()>;
+
+    /// Moves a picture to a quarantine batch instead of deleting it: the file goes to
+    /// the folder of the batch and the row to `quarantine_rows`.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context, for the media and quarantine paths.
+    /// * `batch` - The quarantine batch.
+    /// * `url` - The URL of the picture.
+    async fn quarantine_picture(&self, ctx: Arc<TaskContext>, batch: &str, url: &Url)
+    -> Result<()>;
+
+    /// Moves a post to a quarantine batch instead of deleting it, together with the
+    /// posts of its retweet tree and their media, like a deep [`Storage::delete_post`].
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context, for the media and quarantine paths.
+    /// * `batch` - The quarantine batch.
+    /// * `id` - The ID of the post.
+    async fn quarantine_post(&self, ctx: Arc<TaskContext>, batch: &str, id: i64) -> Result<()>;
+
+    /// Lists the quarantine batches, the newest first.
+    async fn list_quarantine_batches(&self) -> Result<Vec<QuarantineBatch>>;
+
+    /// Puts the rows and files of a quarantine batch back. Rows and files saved again
+    /// since the batch was created are kept, and the quarantined ones dropped.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context, for the media and quarantine paths.
+    /// * `batch` - The quarantine batch to restore.
+    ///
+    /// # Returns
+    /// A `Result` containing what was restored.
+    async fn restore_quarantine(
+        &self,
+        ctx: Arc<TaskContext>,
+        batch: &str,
+    ) -> Result<RestoreSummary>;
+
+    /// Takes a snapshot of the database into the configured backup directory, keeping
+    /// only the configured number of snapshots.
+    ///
+    /// # Arguments
+    /// * `ctx` - The task context, for the backup directory and the rotation.
+    /// * `label` - Why the snapshot is taken, which becomes part of its file name.
+    ///
+    /// # Returns
+    /// A `Result` containing the new snapshot.
+    async fn backup_database(&self, ctx: Arc<TaskContext>, label: &str) -> Result<DatabaseBackup>;
+
+    /// Merges another archive's database into this one, in one transaction.
+    ///
+    /// A post stored in both archives is taken from the other one when its version is
+    /// more complete: valid, not deleted, edited more times, with its raw JSON, or with
+    /// a longer text, in that order. The media files are not copied.
+    ///
+    /// # Arguments
+    /// * `source_db` - The other database, migrated to the same schema version.
+    ///
+    /// # Returns
+    /// A `Result` containing the merge report and the media rows added.
+    async fn merge_archive(&self, source_db: &Path) -> Result<MergeOutcome>;
+
+    /// Copies posts into a bundle, with the posts they retweet, their authors and
+    /// everything belonging to them. The media files are not copied.
+    ///
+    /// # Arguments
+    /// * `ids` - The IDs of the posts.
+    /// * `bundle` - The bundle file, created with [`Bundle::create`](crate::bundle::Bundle::create).
+    ///
+    /// # Returns
+    /// A `Result` containing what was copied, with the media files to add.
+    async fn fill_bundle(&self, ids: &[i64], bundle: &Path) -> Result<BundleContents>;
+
+    /// The size of the database, in bytes.
+    async fn database_size(&self) -> Result<u64>;
+
+    /// Runs a step of a database maintenance run, see [`crate::maintenance`].
+    ///
+    /// # Arguments
+    /// * `step` - The step to run.
+    ///
+    /// # Returns
+    /// A `Result` containing the problems found by the integrity check, empty for the
+    /// other steps.
+    async fn run_maintenance_step(&self, step: MaintenanceStep) -> Result<Vec<String>>;
+
+    /// When the database was last maintained, as a Unix timestamp, if ever.
+    async fn last_maintenance(&self) -> Result<Option<i64>>;
+
+    /// Records when the database was last maintained.
+    ///
+    /// # Arguments
+    /// * `at` - The Unix timestamp of the run.
+    async fn set_last_maintenance(&self, at: i64) -> Result<()>;
 
     /// Saves a picture's content to the file system and its metadata to the database.
     ///
@@ -162,6 +461,8 @@ pub trait Storage: Send + Sync + Clone + 'static {
 
     /// Retrieves the absolute file system path for a given picture URL.
     ///
+    /// Pictures saved from another `sinaimg.cn` mirror host are matched as well.
+    ///
     /// # Arguments
     /// * `ctx` - The task context.
     /// * `url` - The URL of the picture.
@@ -289,50 +590,72 @@ pub trait Storage: Send + Sync + Clone + 'static {
 /// The default implementation of the `Storage` trait.
 ///
 /// It uses a SQLite database pool for metadata management and specialized
-/// file system storage handlers for pictures and videos.
+/// file system storage handlers for pictures and videos. Every write to the archive
+/// database goes through a separate write pool, see
+/// [`create_write_pool`](database::create_write_pool), so that writers queue for its
+/// connection instead of timing out on the database lock. Only the reads use the main
+/// pool, including `VACUUM INTO` snapshots, which do not write to the database. Filling a
+/// bundle writes to the bundle only and opens a connection of its own.
+///
+/// The script folder of the archive is loaded on the first search and kept until script
+/// folding is toggled.
 #[derive(Debug, Clone)]
 pub struct StorageImpl {
     db_pool: SqlitePool,
+    write_pool: SqlitePool,
     pic_storage: FileSystemPictureStorage,
     video_storage: FileSystemVideoStorage,
+    script_folder: Arc<RwLock<Option<CachedFolder>>>,
 }
 
+/// The script folder of an archive, `None` if script folding is disabled.
+type CachedFolder = Option<Arc<ScriptFolder>>;
+
 impl StorageImpl {
-    /// Creates a new `StorageImpl` instance with the given database pool.
-    pub fn new(db_pool: SqlitePool) -> Self {
+    /// Creates a new `StorageImpl` instance with the given database pools.
+    ///
+    /// # Arguments
+    ///
+    /// * `db_pool` - The pool the reads go through.
+    /// * `write_pool` - The pool the writes go through, created by
+    ///   [`create_write_pool`](database::create_write_pool) for `db_pool`.
+    pub fn new(db_pool: SqlitePool, write_pool: SqlitePool) -> Self {
         info!("Storage initialized successfully.");
         StorageImpl {
             db_pool,
+            write_pool,
             pic_storage: Default::default(),
             video_storage: Default::default(),
+            script_folder: Default::default(),
         }
     }
 
-    /// Recursively saves a post and its associated user and retweeted status.
-    fn _save_post(&self, post: Post) -> Pin<Box<dyn Future<Output = Result<()>> + Send + '_>> {
-        Box::pin(async move {
-            debug!("Saving post with id: {}", post.id);
-            if let Some(user) = &post.user {
-                user::save_user(&self.db_pool, user)
-                    .await
-                    .inspect_err(|e| {
-                        error!("save_user for uid={} failed: {e}", user.id);
-                    })?;
-            }
-            if let Some(ret_post) = post.retweeted_status.as_deref() {
-                self._save_post(ret_post.clone()).await?;
-            }
-            let post_storage: PostInternal = post.try_into().inspect_err(|e| {
-                error!("convert Post to PostInternal failed: {e}");
-            })?;
-            post::save_post(&self.db_pool, &post_storage)
-                .await
-                .inspect_err(|e| {
-                    error!("save_post id={} failed: {e}", post_storage.id);
-                })?;
-            debug!("Post with id: {} saved successfully", post_storage.id);
-            Ok(())
-        })
+    /// Saves posts in one transaction on the write pool, so that a post is never stored
+    /// without its retweeted post or author. All statements run on the same connection,
+    /// which prepares each of them once for the whole batch.
+    async fn save_posts_in_transaction(&self, posts: &[Post]) -> Result<()> {
+        let mut tx = self.write_pool.begin().await?;
+        for post in posts {
+            save_post_tree(&mut tx, post.clone()).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Returns the script folder of the archive, loading it on first use.
+    async fn script_folder(&self) -> Result<CachedFolder> {
+        if let Some(folder) = self.script_folder.read().await.as_ref() {
+            return Ok(folder.clone());
+        }
+        let mut cached = self.script_folder.write().await;
+        if let Some(folder) = cached.as_ref() {
+            return Ok(folder.clone());
+        }
+        let folder = script_folding::load_folder(&self.db_pool)
+            .await?
+            .map(Arc::new);
+        *cached = Some(folder.clone());
+        Ok(folder)
     }
 
     /// Retrieves a post and hydrates it with user and retweeted post.
@@ -402,6 +725,483 @@ impl StorageImpl {
         }
         posts
     }
+
+    /// Groups the local posts of each user by month and compares them with the recorded
+    /// number of posts of the user.
+    async fn build_coverage(&self, uid: Option<i64>) -> Result<Vec<UserCoverage>> {
+        let remote_counts = user::get_statuses_counts(&self.db_pool, uid)
+            .await?
+            .into_iter()
+            .map(|(id, count, at)| (id, (count.max(0) as u64, at)))
+            .collect::<HashMap<_, _>>();
+        let monthly_posts = stats::get_monthly_post_counts(&self.db_pool, uid).await?;
+
+        let mut coverages = monthly_posts
+            .into_iter()
+            .chunk_by(|m| m.uid)
+            .into_iter()
+            .map(|(owner, months)| {
+                let months = months.collect::<Vec<_>>();
+                let screen_name = months[0].screen_name.clone();
+                let monthly = months
+                    .into_iter()
+                    .map(|m| (m.month, m.originals + m.retweets))
+                    .collect::<Vec<_>>();
+                UserCoverage::new(
+                    owner,
+                    screen_name,
+                    remote_counts.get(&owner).copied(),
+                    &monthly,
+                )
+            })
+            .collect::<Vec<_>>();
+        coverages.sort_by_key(|c| (Reverse(c.missing()), c.uid));
+        Ok(coverages)
+    }
+
+    /// Attaches the other database, merges it in one transaction, and detaches it again
+    /// whether the merge succeeded or not.
+    async fn merge_from(&self, source_db: &Path) -> Result<MergeOutcome> {
+        let mut conn = self.write_pool.acquire().await?;
+        merge::attach(&mut conn, &source_db.to_string_lossy()).await?;
+        let result = async {
+            let mut tx = conn.begin().await?;
+            let mut report = MergeReport {
+                merged_at: Utc::now().timestamp(),
+                source: source_db.to_string_lossy().into_owned(),
+                ..Default::default()
+            };
+            let (added, updated, mut conflicts) = merge::merge_users(&mut tx).await?;
+            report.users_added = added;
+            report.users_updated = updated;
+            let (added, replaced, post_conflicts) = merge::merge_posts(&mut tx).await?;
+            report.posts_added = added;
+            report.posts_replaced = replaced;
+            conflicts.extend(post_conflicts);
+            report.conflicts = conflicts;
+            report.favorites_added = merge::merge_favorites(&mut tx).await?;
+            report.links_merged = merge::merge_links(&mut tx).await?;
+            let (pictures, rejected) = merge::merge_pictures(&mut tx).await?;
+            report.conflicts.extend(rejected);
+            let (videos, rejected) = merge::merge_videos(&mut tx).await?;
+            report.conflicts.extend(rejected);
+            report.pictures_added = pictures.len() as u64;
+            report.videos_added = videos.len() as u64;
+            tx.commit().await?;
+            Ok(MergeOutcome {
+                report,
+                pictures,
+                videos,
+            })
+        }
+        .await;
+        merge::detach(&mut conn).await?;
+        result
+    }
+
+    /// Attaches the bundle, copies the posts into it in one transaction, and detaches it
+    /// again whether the copy succeeded or not.
+    ///
+    /// The copy only writes to the bundle, so it runs on a connection of its own rather
+    /// than holding the write pool of the archive for its whole duration.
+    async fn fill_bundle_from(&self, ids: &[i64], path: &Path) -> Result<BundleContents> {
+        let mut conn = self.db_pool.connect_options().connect().await?;
+        bundle::attach(&mut conn, &path.to_string_lossy()).await?;
+        let result = async {
+            let mut tx = conn.begin().await?;
+            let (posts, users) = bundle::copy_posts(&mut tx, ids).await?;
+            let (pictures, videos) = bundle::get_media_files(&mut tx).await?;
+            tx.commit().await?;
+            Ok(BundleContents {
+                posts,
+                users,
+                pictures,
+                videos,
+            })
+        }
+        .await;
+        bundle::detach(&mut conn).await?;
+        result
+    }
+
+    /// Runs the integrity queries and matches the media rows against the files on disk.
+    async fn build_verify_report(&self, ctx: Arc<TaskContext>) -> Result<VerifyReport> {
+        let pictures = verify::get_picture_files(&self.db_pool).await?;
+        let videos = verify::get_video_files(&self.db_pool).await?;
+        let mut picture_files = list_files(&ctx.config.picture_path).await?;
+        let mut video_files = list_files(&ctx.config.video_path).await?;
+
+        let (pictures, pic_ids): (Vec<_>, Vec<_>) = pictures.into_iter().unzip();
+        let stored_urls = pictures
+            .iter()
+            .zip(pic_ids)
+            .filter_map(|(picture, id)| id.map(|id| (picture.url.clone(), id)))
+            .collect::<Vec<_>>();
+        let missing_picture_files = match_files(pictures, &mut picture_files);
+        let missing_video_files = match_files(videos, &mut video_files);
+        let missing_urls = missing_picture_files
+            .iter()
+            .map(|picture| picture.url.as_str())
+            .collect::<HashSet<_>>();
+        let stored_pic_ids = stored_urls
+            .into_iter()
+            .filter(|(url, _)| !missing_urls.contains(url.as_str()))
+            .map(|(_, id)| id)
+            .collect::<HashSet<_>>();
+
+        let post_pics = verify::get_post_pic_ids(&self.db_pool).await?;
+        let dangling_retweets = verify::get_dangling_retweets(&self.db_pool)
+            .await?
+            .into_iter()
+            .map(|(post_id, retweeted_id)| DanglingRetweet {
+                post_id,
+                retweeted_id,
+            })
+            .collect();
+        let orphans = |files: HashSet<PathBuf>| {
+            files
+                .iter()
+                .map(|path| path_to_string(path))
+                .sorted()
+                .collect::<Vec<_>>()
+        };
+        Ok(VerifyReport {
+            checked_at: Utc::now().timestamp(),
+            missing_picture_files,
+            missing_video_files,
+            orphan_picture_files: orphans(picture_files),
+            orphan_video_files: orphans(video_files),
+            posts_missing_pictures: find_posts_missing_pictures(post_pics, &stored_pic_ids),
+            dangling_retweets,
+            fts_missing: verify::get_fts_missing(&self.db_pool).await?,
+            fts_stale: verify::get_fts_stale(&self.db_pool).await?,
+            fts_outdated: verify::get_fts_outdated(&self.db_pool).await?,
+            repair: None,
+        })
+    }
+
+    /// Copies the row of a picture to a quarantine batch and deletes it in one
+    /// transaction, then moves its file to the folder of the batch.
+    async fn move_picture_to_quarantine(
+        &self,
+        ctx: Arc<TaskContext>,
+        batch: &str,
+        url: &Url,
+    ) -> Result<()> {
+        let Some(path) = picture::get_picture_path(&self.db_pool, url).await? else {
+            return Ok(());
+        };
+        let mut tx = self.write_pool.begin().await?;
+        quarantine::quarantine_rows(&mut *tx, batch, "picture", "url", vec![url.as_str().into()])
+            .await?;
+        picture::delete_picture_by_url(&mut *tx, url).await?;
+        tx.commit().await?;
+
+        let dir = ctx.config.quarantine_path.join(batch).join("pictures");
+        move_to_quarantine(&ctx.config.picture_path, &path, &dir).await
+    }
+
+    /// Copies picture and video rows to a quarantine batch and deletes them in one
+    /// transaction.
+    async fn move_media_rows_to_quarantine(
+        &self,
+        batch: &str,
+        picture_urls: &[String],
+        video_urls: &[String],
+    ) -> Result<u64> {
+        let keys = |urls: &[String]| {
+            urls.iter()
+                .map(|url| sea_query::Value::from(url.as_str()))
+                .collect::<Vec<_>>()
+        };
+        let mut tx = self.write_pool.begin().await?;
+        quarantine::quarantine_rows(&mut *tx, batch, "picture", "url", keys(picture_urls)).await?;
+        quarantine::quarantine_rows(&mut *tx, batch, "video", "url", keys(video_urls)).await?;
+        let removed = picture::delete_pictures_by_urls(&mut *tx, picture_urls).await?
+            + video::delete_videos_by_urls(&mut *tx, video_urls).await?;
+        tx.commit().await?;
+        Ok(removed)
+    }
+
+    /// Copies the rows of a post tree to a quarantine batch and deletes them in one
+    /// transaction, then moves their media files to its folder. Should moving a file
+    /// fail, it is left in place as an orphan file, not referenced by any row.
+    async fn move_post_to_quarantine(
+        &self,
+        ctx: Arc<TaskContext>,
+        batch: &str,
+        id: i64,
+    ) -> Result<()> {
+        let Some(post) = post::get_post(&self.db_pool, id).await? else {
+            return Ok(());
+        };
+        let root_id = post.retweeted_id.unwrap_or(post.id);
+        let mut ids = post::get_retweet_ids(&self.db_pool, root_id).await?;
+        ids.push(root_id);
+
+        let keys = ids
+            .iter()
+            .map(|&id| sea_query::Value::from(id))
+            .collect::<Vec<_>>();
+        let mut tx = self.write_pool.begin().await?;
+        let pictures = picture::get_pictures_by_post_ids(&mut *tx, &ids).await?;
+        let videos = video::get_video_paths_by_post_ids(&mut *tx, &ids).await?;
+        for (table, key) in [
+            ("posts", "id"),
+            ("posts_raw", "id"),
+            ("post_mentions", "post_id"),
+            ("post_topics", "post_id"),
+            ("favorited_posts", "id"),
+            ("picture", "post_id"),
+            ("video", "post_id"),
+        ] {
+            quarantine::quarantine_rows(&mut *tx, batch, table, key, keys.clone()).await?;
+        }
+        picture::delete_pictures_by_post_ids(&mut *tx, &ids).await?;
+        video::delete_videos_by_post_ids(&mut *tx, &ids).await?;
+        post::batch_delete_posts(&mut *tx, &ids).await?;
+        tx.commit().await?;
+
+        let dir = ctx.config.quarantine_path.join(batch);
+        for info in pictures {
+            move_to_quarantine(&ctx.config.picture_path, &info.path, &dir.join("pictures")).await?;
+        }
+        for path in videos {
+            move_to_quarantine(&ctx.config.video_path, &path, &dir.join("videos")).await?;
+        }
+        Ok(())
+    }
+
+    /// Restores the rows of a quarantine batch, then moves its files back.
+    async fn restore_batch(&self, ctx: Arc<TaskContext>, batch: &str) -> Result<RestoreSummary> {
+        let (restored_rows, skipped_rows) =
+            quarantine::restore_rows(&self.write_pool, batch, Utc::now().timestamp()).await?;
+        let mut summary = RestoreSummary {
+            restored_rows,
+            skipped_rows,
+            ..Default::default()
+        };
+
+        let dir = ctx.config.quarantine_path.join(batch);
+        for (name, root) in [
+            ("pictures", &ctx.config.picture_path),
+            ("videos", &ctx.config.video_path),
+        ] {
+            let source = dir.join(name);
+            for path in list_files(&source).await?.into_iter().sorted() {
+                if root.join(&path).exists() {
+                    summary.skipped_files += 1;
+                    continue;
+                }
+                move_file(&source, &path_to_string(&path), root).await?;
+                summary.restored_files += 1;
+            }
+        }
+        if summary.skipped_files == 0 && dir.exists() {
+            tokio::fs::remove_dir_all(&dir).await?;
+        }
+        Ok(summary)
+    }
+
+    /// Lists the orphan media rows, then matches the other rows against the files on disk.
+    async fn build_gc_report(&self, ctx: Arc<TaskContext>) -> Result<GcReport> {
+        let orphan_picture_rows = verify::get_orphan_picture_files(&self.db_pool).await?;
+        let orphan_video_rows = verify::get_orphan_video_files(&self.db_pool).await?;
+        let orphan_urls = orphan_picture_rows
+            .iter()
+            .chain(&orphan_video_rows)
+            .map(|row| row.url.as_str())
+            .collect::<HashSet<_>>();
+        let pictures = verify::get_picture_files(&self.db_pool)
+            .await?
+            .into_iter()
+            .map(|(picture, _)| picture)
+            .filter(|picture| !orphan_urls.contains(picture.url.as_str()))
+            .collect();
+        let videos = verify::get_video_files(&self.db_pool)
+            .await?
+            .into_iter()
+            .filter(|video| !orphan_urls.contains(video.url.as_str()))
+            .collect();
+
+        // Files of orphan rows are left over too, unless another row shares them
+        let mut picture_files = list_files(&ctx.config.picture_path).await?;
+        let mut video_files = list_files(&ctx.config.video_path).await?;
+        match_files(pictures, &mut picture_files);
+        match_files(videos, &mut video_files);
+        let orphan_picture_files = picture_files
+            .iter()
+            .map(|path| path_to_string(path))
+            .sorted()
+            .collect::<Vec<_>>();
+        let orphan_video_files = video_files
+            .iter()
+            .map(|path| path_to_string(path))
+            .sorted()
+            .collect::<Vec<_>>();
+        let orphan_bytes = file_sizes(
+            &ctx.config.picture_path,
+            orphan_picture_files.iter().map(|path| ((), path)),
+        )
+        .chain(file_sizes(
+            &ctx.config.video_path,
+            orphan_video_files.iter().map(|path| ((), path)),
+        ))
+        .fold(0, |total, ((), bytes)| async move { total + bytes })
+        .await;
+        Ok(GcReport {
+            collected_at: Utc::now().timestamp(),
+            orphan_picture_rows,
+            orphan_video_rows,
+            orphan_picture_files,
+            orphan_video_files,
+            orphan_bytes,
+            ..Default::default()
+        })
+    }
+
+    /// Runs the aggregate queries of a statistics report and sizes up the media files.
+    async fn build_stats(
+        &self,
+        ctx: Arc<TaskContext>,
+        uid: Option<i64>,
+        top_limit: u64,
+    ) -> Result<ArchiveStats> {
+        let monthly_posts = stats::get_monthly_post_counts(&self.db_pool, uid).await?;
+        let hourly_posts = stats::get_hourly_post_counts(&self.db_pool, uid).await?;
+        let top_sources = stats::get_top_sources(&self.db_pool, uid, top_limit).await?;
+        let top_topics = post_term::get_top_topics(&self.db_pool, uid, top_limit).await?;
+        let pictures = stats::get_picture_paths_by_user(&self.db_pool, uid).await?;
+        let videos = stats::get_video_paths_by_user(&self.db_pool, uid).await?;
+
+        let mut user_media: HashMap<i64, UserMediaStats> = HashMap::new();
+        let picture_sizes = file_sizes(&ctx.config.picture_path, pictures);
+        pin_mut!(picture_sizes);
+        while let Some((owner, bytes)) = picture_sizes.next().await {
+            let media = user_media.entry(owner).or_default();
+            media.pictures += 1;
+            media.picture_bytes += bytes;
+        }
+        let video_sizes = file_sizes(&ctx.config.video_path, videos);
+        pin_mut!(video_sizes);
+        while let Some((owner, bytes)) = video_sizes.next().await {
+            let media = user_media.entry(owner).or_default();
+            media.videos += 1;
+            media.video_bytes += bytes;
+        }
+        let screen_names = monthly_posts
+            .iter()
+            .map(|m| (m.uid, m.screen_name.clone()))
+            .collect::<HashMap<_, _>>();
+        let mut user_media = user_media
+            .into_iter()
+            .map(|(owner, media)| UserMediaStats {
+                uid: owner,
+                screen_name: screen_names.get(&owner).cloned().flatten(),
+                ..media
+            })
+            .collect::<Vec<_>>();
+        user_media.sort_by_key(|m| (Reverse(m.picture_bytes + m.video_bytes), m.uid));
+
+        let original_posts = monthly_posts.iter().map(|m| m.originals).sum::<u64>();
+        let retweet_posts = monthly_posts.iter().map(|m| m.retweets).sum::<u64>();
+        Ok(ArchiveStats {
+            uid,
+            generated_at: Utc::now().timestamp(),
+            total_posts: original_posts + retweet_posts,
+            original_posts,
+            retweet_posts,
+            monthly_posts,
+            user_media,
+            top_topics,
+            top_sources,
+            hourly_posts,
+        })
+    }
+}
+
+/// Recursively saves a post and its associated user and retweeted status.
+fn save_post_tree(
+    conn: &mut SqliteConnection,
+    mut post: Post,
+) -> Pin<Box<dyn Future<Output = Result<()>> + Send + '_>> {
+    Box::pin(async move {
+        debug!("Saving post with id: {}", post.id);
+        if let Some(user) = &post.user {
+            user::save_user(&mut *conn, user).await.inspect_err(|e| {
+                error!("save_user for uid={} failed: {e}", user.id);
+            })?;
+        }
+        if let Some(ret_post) = post.retweeted_status.as_deref() {
+            save_post_tree(&mut *conn, ret_post.clone()).await?;
+        }
+        let statuses_count = post
+            .user
+            .as_ref()
+            .zip(post.raw.as_ref())
+            .and_then(|(user, raw)| Some((user.id, statuses_count_of(raw, user.id)?)));
+        if let Some((uid, count)) = statuses_count {
+            user::save_statuses_count(&mut *conn, uid, count, Utc::now().timestamp())
+                .await
+                .inspect_err(|e| {
+                    error!("save_statuses_count for uid={uid} failed: {e}");
+                })?;
+        }
+        let raw = post.raw.take();
+        let post_storage: PostInternal = post.try_into().inspect_err(|e| {
+            error!("convert Post to PostInternal failed: {e}");
+        })?;
+        post::save_post(&mut *conn, &post_storage)
+            .await
+            .inspect_err(|e| {
+                error!("save_post id={} failed: {e}", post_storage.id);
+            })?;
+        if let Some(raw) = raw {
+            post_raw::save_post_raw(&mut *conn, post_storage.id, &raw)
+                .await
+                .inspect_err(|e| {
+                    error!("save_post_raw id={} failed: {e}", post_storage.id);
+                })?;
+        }
+        debug!("Post with id: {} saved successfully", post_storage.id);
+        Ok(())
+    })
+}
+
+/// Reads the number of posts Weibo reports for the author of a post from its raw JSON.
+fn statuses_count_of(raw: &Value, uid: i64) -> Option<i64> {
+    let user = raw.get("user")?;
+    if user.get("id")?.as_i64()? != uid {
+        return None;
+    }
+    user.get("statuses_count")?.as_i64()
+}
+
+/// Looks up the sizes of files under `root`, a few at a time, keyed by the first item
+/// of each pair. The sizes come in the order the lookups finish.
+fn file_sizes<'a, K, P>(
+    root: &'a Path,
+    files: impl IntoIterator<Item = (K, P)> + 'a,
+) -> impl Stream<Item = (K, u64)> + 'a
+where
+    K: 'a,
+    P: AsRef<Path> + 'a,
+{
+    const CONCURRENCY: usize = 16;
+    stream::iter(files)
+        .map(move |(key, path)| async move { (key, file_size(&root.join(path)).await) })
+        .buffer_unordered(CONCURRENCY)
+}
+
+/// Returns the size of a file, or 0 if it cannot be read.
+async fn file_size(path: &Path) -> u64 {
+    match tokio::fs::metadata(path).await {
+        Ok(meta) => meta.len(),
+        Err(e) => {
+            debug!("stat {path:?} failed: {e}");
+            0
+        }
+    }
 }
 
 #[async_trait]
@@ -432,39 +1232,157 @@ impl Storage for StorageImpl {
     }
 
     async fn save_post(&self, post: &Post) -> Result<()> {
-        self._save_post(post.clone()).await
+        self.save_posts_in_transaction(std::slice::from_ref(post))
+            .await
+            .inspect_err(|e| error!("save_post(id={}) failed: {e}", post.id))
+    }
+
+    async fn save_posts(&self, posts: &[Post]) -> Result<()> {
+        self.save_posts_in_transaction(posts)
+            .await
+            .inspect_err(|e| error!("save_posts({} posts) failed: {e}", posts.len()))
     }
 
     async fn get_post(&self, id: i64) -> Result<Option<Post>> {
         self.get_post(id).await
     }
 
-    async fn query_posts(&self, query: PostQuery) -> Result<PaginatedPosts> {
-        let (posts_internal, total_items) = post::query_posts(&self.db_pool, query)
+    async fn get_post_raw(&self, id: i64) -> Result<Option<Value>> {
+        post_raw::get_post_raw(&self.db_pool, id)
             .await
             .inspect_err(|e| {
-                error!("query_posts failed: {e}");
-            })?;
+                error!("get_post_raw(id={}) failed: {e}", id);
+            })
+    }
+
+    async fn query_posts(&self, query: PostQuery) -> Result<PaginatedPosts> {
+        let folder = self.script_folder().await?;
+        let (posts_internal, total_items) =
+            post::query_posts(&self.db_pool, query.clone(), folder.as_deref())
+                .await
+                .inspect_err(|e| {
+                    error!("query_posts failed: {e}");
+                })?;
+        let snippets = if query.search_term.is_some() {
+            let ids: Vec<i64> = posts_internal.iter().map(|p| p.id).collect();
+            post::search_snippets(&self.db_pool, &query, folder.as_deref(), &ids)
+                .await
+                .inspect_err(|e| {
+                    error!("search_snippets failed: {e}");
+                })?
+        } else {
+            HashMap::new()
+        };
         let posts = self.hydrate_posts(posts_internal).await;
-        Ok(PaginatedPosts { posts, total_items })
+        Ok(PaginatedPosts {
+            posts,
+            total_items,
+            snippets,
+        })
     }
 
     async fn query_all_post_ids(&self, query: PostQuery) -> Result<Vec<i64>> {
-        post::query_all_post_ids(&self.db_pool, query)
+        let folder = self.script_folder().await?;
+        post::query_all_post_ids(&self.db_pool, query, folder.as_deref())
             .await
             .inspect_err(|e| {
                 error!("query_all_post_ids failed: {e}");
             })
     }
 
-    async fn save_user(&self, user: &User) -> Result<()> {
-        user::save_user(&self.db_pool, user).await.inspect_err(|e| {
-            error!("save_user(uid={}) failed: {e}", user.id);
+    async fn count_posts(&self, query: PostQuery) -> Result<u64> {
+        let folder = self.script_folder().await?;
+        post::count_posts(&self.db_pool, &query, folder.as_deref())
+            .await
+            .inspect_err(|e| {
+                error!("count_posts failed: {e}");
+            })
+    }
+
+    fn stream_posts(&self, query: PostQuery) -> impl Stream<Item = Result<Post>> + Send + '_ {
+        const BATCH_SIZE: u64 = 100;
+        stream::try_unfold(
+            (query, None, false), // state: query, cursor, finished
+            move |(query, after, finished)| async move {
+                if finished {
+                    return Ok::<_, Error>(None);
+                }
+                let folder = self.script_folder().await?;
+                let batch = post::query_posts_after(
+                    &self.db_pool,
+                    &query,
+                    folder.as_deref(),
+                    after,
+                    BATCH_SIZE,
+                )
+                .await
+                .inspect_err(|e| {
+                    error!("query_posts_after(after={after:?}) failed: {e}");
+                })?;
+                let Some(&(_, last)) = batch.last() else {
+                    return Ok(None);
+                };
+                let finished = (batch.len() as u64) < BATCH_SIZE;
+                Ok(Some((
+                    stream::iter(batch.into_iter().map(|(post, _)| Ok(post))),
+                    (query, Some(last), finished),
+                )))
+            },
+        )
+        .try_flatten()
+        .try_filter_map(move |post| async move {
+            let id = post.id;
+            Ok(self
+                .hydrate_post(post)
+                .await
+                .inspect_err(|e| warn!("{id} cons failed: {e}"))
+                .ok())
         })
     }
 
+    fn stream_post_ids(&self, query: PostQuery) -> impl Stream<Item = Result<i64>> + Send + '_ {
+        const BATCH_SIZE: u64 = 500;
+        stream::try_unfold(
+            (query, None, false), // state: query, cursor, finished
+            move |(query, after, finished)| async move {
+                if finished {
+                    return Ok::<_, Error>(None);
+                }
+                let folder = self.script_folder().await?;
+                let batch = post::query_post_ids_after(
+                    &self.db_pool,
+                    &query,
+                    folder.as_deref(),
+                    after,
+                    BATCH_SIZE,
+                )
+                .await
+                .inspect_err(|e| {
+                    error!("query_post_ids_after(after={after:?}) failed: {e}");
+                })?;
+                let Some(&last) = batch.last() else {
+                    return Ok(None);
+                };
+                let finished = (batch.len() as u64) < BATCH_SIZE;
+                Ok(Some((
+                    stream::iter(batch.into_iter().map(|cursor| Ok(cursor.id))),
+                    (query, Some(last), finished),
+                )))
+            },
+        )
+        .try_flatten()
+    }
+
+    async fn save_user(&self, user: &User) -> Result<()> {
+        user::save_user(&self.write_pool, user)
+            .await
+            .inspect_err(|e| {
+                error!("save_user(uid={}) failed: {e}", user.id);
+            })
+    }
+
     async fn mark_post_unfavorited(&self, id: i64) -> Result<()> {
-        post::mark_post_unfavorited(&self.db_pool, id)
+        post::mark_post_unfavorited(&self.write_pool, id)
             .await
             .inspect_err(|e| {
                 error!("mark_post_unfavorited(id={}) failed: {e}", id);
@@ -472,7 +1390,7 @@ impl Storage for StorageImpl {
     }
 
     async fn mark_post_favorited(&self, id: i64) -> Result<()> {
-        post::mark_post_favorited(&self.db_pool, id)
+        post::mark_post_favorited(&self.write_pool, id)
             .await
             .inspect_err(|e| {
                 error!("mark_post_favorited(id={}) failed: {e}", id);
@@ -487,6 +1405,124 @@ impl Storage for StorageImpl {
             })
     }
 
+    async fn rebuild_search_index(&self) -> Result<()> {
+        post::rebuild_fts(&self.write_pool).await.inspect_err(|e| {
+            error!("rebuild_search_index failed: {e}");
+        })
+    }
+
+    async fn script_folding(&self) -> Result<bool> {
+        script_folding::is_enabled(&self.db_pool)
+            .await
+            .inspect_err(|e| {
+                error!("script_folding failed: {e}");
+            })
+    }
+
+    async fn save_link(&self, link: &Link) -> Result<()> {
+        link::save_link(&self.write_pool, link)
+            .await
+            .inspect_err(|e| error!("save_link({}) failed: {e}", link.short_url))
+    }
+
+    async fn get_links(&self, short_urls: &[String]) -> Result<Vec<Link>> {
+        link::get_links(&self.db_pool, short_urls)
+            .await
+            .inspect_err(|e| error!("get_links({} urls) failed: {e}", short_urls.len()))
+    }
+
+    async fn get_unresolved_short_urls(&self, retry_failed: bool) -> Result<Vec<String>> {
+        link::get_unresolved_short_urls(&self.db_pool, retry_failed)
+            .await
+            .inspect_err(|e| {
+                error!("get_unresolved_short_urls(retry_failed={retry_failed}) failed: {e}");
+            })
+    }
+
+    async fn reindex_mentions_and_topics(&self, ids: &[i64]) -> Result<()> {
+        post::reindex_mentions_and_topics(&self.write_pool, ids)
+            .await
+            .inspect_err(|e| {
+                error!("reindex_mentions_and_topics({} ids) failed: {e}", ids.len());
+            })
+    }
+
+    async fn get_top_mentions(&self, uid: Option<i64>, limit: u64) -> Result<Vec<MentionCount>> {
+        post_term::get_top_mentions(&self.db_pool, uid, limit)
+            .await
+            .inspect_err(|e| {
+                error!("get_top_mentions(uid={uid:?}, limit={limit}) failed: {e}");
+            })
+    }
+
+    async fn get_top_topics(&self, uid: Option<i64>, limit: u64) -> Result<Vec<TopicCount>> {
+        post_term::get_top_topics(&self.db_pool, uid, limit)
+            .await
+            .inspect_err(|e| {
+                error!("get_top_topics(uid={uid:?}, limit={limit}) failed: {e}");
+            })
+    }
+
+    async fn stats(
+        &self,
+        ctx: Arc<TaskContext>,
+        uid: Option<i64>,
+        top_limit: u64,
+    ) -> Result<ArchiveStats> {
+        self.build_stats(ctx, uid, top_limit)
+            .await
+            .inspect_err(|e| {
+                error!("stats(uid={uid:?}, top_limit={top_limit}) failed: {e}");
+            })
+    }
+
+    async fn get_coverage(&self, uid: Option<i64>) -> Result<Vec<UserCoverage>> {
+        self.build_coverage(uid).await.inspect_err(|e| {
+            error!("get_coverage(uid={uid:?}) failed: {e}");
+        })
+    }
+
+    async fn verify_archive(&self, ctx: Arc<TaskContext>) -> Result<VerifyReport> {
+        self.build_verify_report(ctx).await.inspect_err(|e| {
+            error!("verify_archive failed: {e}");
+        })
+    }
+
+    async fn find_orphan_media(&self, ctx: Arc<TaskContext>) -> Result<GcReport> {
+        self.build_gc_report(ctx).await.inspect_err(|e| {
+            error!("find_orphan_media failed: {e}");
+        })
+    }
+
+    async fn quarantine_media_rows(
+        &self,
+        batch: &str,
+        picture_urls: &[String],
+        video_urls: &[String],
+    ) -> Result<u64> {
+        self.move_media_rows_to_quarantine(batch, picture_urls, video_urls)
+            .await
+            .inspect_err(|e| {
+                error!(
+                    "quarantine_media_rows(batch={batch}, {} pictures, {} videos) failed: {e}",
+                    picture_urls.len(),
+                    video_urls.len()
+                );
+            })
+    }
+
+    async fn set_script_folding(&self, enabled: bool) -> Result<()> {
+        // Held across the switch, so that no search reloads the old folder meanwhile
+        let mut cached = self.script_folder.write().await;
+        script_folding::set_enabled(&self.write_pool, enabled)
+            .await
+            .inspect_err(|e| {
+                error!("set_script_folding(enabled={enabled}) failed: {e}");
+            })?;
+        *cached = None;
+        Ok(())
+    }
+
     async fn get_picture_blob(&self, ctx: Arc<TaskContext>, url: &Url) -> Result<Option<Bytes>> {
         self.pic_storage
             .get_picture_blob(&ctx.config.picture_path, &self.db_pool, url)
@@ -498,7 +1534,7 @@ impl Storage for StorageImpl {
 
     async fn save_picture(&self, ctx: Arc<TaskContext>, picture: &Picture) -> Result<()> {
         self.pic_storage
-            .save_picture(&ctx.config.picture_path, &self.db_pool, picture)
+            .save_picture(&ctx.config.picture_path, &self.write_pool, picture)
             .await
             .inspect_err(|e| {
                 error!("save_picture(url={}) failed: {e}", picture.meta.url());
@@ -525,7 +1561,7 @@ impl Storage for StorageImpl {
 
     async fn save_video(&self, ctx: Arc<TaskContext>, video: &Video) -> Result<()> {
         self.video_storage
-            .save_video(&ctx.config.video_path, &self.db_pool, video)
+            .save_video(&ctx.config.video_path, &self.write_pool, video)
             .await
             .inspect_err(|e| {
                 error!("save_video(url={}) failed: {e}", video.meta.url());
@@ -543,7 +1579,7 @@ impl Storage for StorageImpl {
 
     async fn get_picture_path(&self, ctx: Arc<TaskContext>, url: &Url) -> Result<Option<PathBuf>> {
         let url = &pic_url_to_db_key(url);
-        let path = picture::get_picture_path(&self.db_pool, url)
+        let path = picture::find_picture_path(&self.db_pool, url)
             .await
             .inspect_err(|e| {
                 error!("get_picture_path(url={}) failed: {e}", url);
@@ -657,7 +1693,7 @@ impl Storage for StorageImpl {
 
     async fn delete_picture(&self, ctx: Arc<TaskContext>, url: &Url) -> Result<()> {
         self.pic_storage
-            .delete_picture(&ctx.config.picture_path, &self.db_pool, url)
+            .delete_picture(&ctx.config.picture_path, &self.write_pool, url)
             .await
             .inspect_err(|e| {
                 error!("delete_picture(url={}) failed: {e}", url);
@@ -681,7 +1717,7 @@ impl Storage for StorageImpl {
             let _ = tokio::fs::remove_file(&absolute_path).await;
         }
         // Delete from database
-        picture::delete_picture_by_url(&self.db_pool, url)
+        picture::delete_picture_by_url(&self.write_pool, url)
             .await
             .inspect_err(|e| {
                 error!("delete_picture_by_url(url={}) failed: {e}", url);
@@ -713,7 +1749,7 @@ impl Storage for StorageImpl {
             ids.push(root_id);
 
             self.pic_storage
-                .batch_delete_posts_pictures(&picture_path, &self.db_pool, &ids)
+                .batch_delete_posts_pictures(&picture_path, &self.write_pool, &ids)
                 .await
                 .inspect_err(|e| {
                     error!(
@@ -722,7 +1758,7 @@ impl Storage for StorageImpl {
                     );
                 })?;
             self.video_storage
-                .batch_delete_posts_videos(&video_path, &self.db_pool, &ids)
+                .batch_delete_posts_videos(&video_path, &self.write_pool, &ids)
                 .await
                 .inspect_err(|e| {
                     error!(
@@ -730,7 +1766,7 @@ impl Storage for StorageImpl {
                         ids
                     );
                 })?;
-            post::batch_delete_posts(&self.db_pool, &ids)
+            post::batch_delete_posts(&self.write_pool, &ids)
                 .await
                 .inspect_err(|e| {
                     error!(
@@ -747,7 +1783,7 @@ impl Storage for StorageImpl {
                 })?
             {
                 // Post has children, only remove from favorited_posts
-                post::mark_post_unfavorited(&self.db_pool, id)
+                post::mark_post_unfavorited(&self.write_pool, id)
                     .await
                     .inspect_err(|e| {
                         error!(
@@ -760,7 +1796,7 @@ impl Storage for StorageImpl {
 
             // Post has no children, delete only itself
             self.pic_storage
-                .delete_post_pictures(&picture_path, &self.db_pool, id)
+                .delete_post_pictures(&picture_path, &self.write_pool, id)
                 .await
                 .inspect_err(|e| {
                     error!(
@@ -769,7 +1805,7 @@ impl Storage for StorageImpl {
                     );
                 })?;
             self.video_storage
-                .delete_post_videos(&video_path, &self.db_pool, id)
+                .delete_post_videos(&video_path, &self.write_pool, id)
                 .await
                 .inspect_err(|e| {
                     error!(
@@ -777,22 +1813,171 @@ impl Storage for StorageImpl {
                         id
                     );
                 })?;
-            post::delete_post(&self.db_pool, id).await.inspect_err(|e| {
-                error!("delete_post shallow delete_post(id={}) failed: {e}", id);
+            post::delete_post(&self.write_pool, id)
+                .await
+                .inspect_err(|e| {
+                    error!("delete_post shallow delete_post(id={}) failed: {e}", id);
+                })
+        }
+    }
+
+    async fn count_invalid_posts(&self, clean_retweeted_invalid: bool) -> Result<u64> {
+        post::count_invalid_posts(&self.db_pool, clean_retweeted_invalid)
+            .await
+            .inspect_err(|e| {
+                error!(
+                    "count_invalid_posts(clean_retweeted_invalid={}) failed: {e}",
+                    clean_retweeted_invalid
+                );
+            })
+    }
+
+    fn stream_invalid_posts_ids(
+        &self,
+        clean_retweeted_invalid: bool,
+    ) -> impl Stream<Item = Result<i64>> + Send + '_ {
+        const BATCH_SIZE: u64 = 500;
+        stream::try_unfold(
+            (None, false), // state: last id, finished
+            move |(after, finished)| async move {
+                if finished {
+                    return Ok::<_, Error>(None);
+                }
+                let batch = post::get_invalid_posts_ids(
+                    &self.db_pool,
+                    clean_retweeted_invalid,
+                    after,
+                    BATCH_SIZE,
+                )
+                .await
+                .inspect_err(|e| {
+                    error!(
+                        "get_invalid_posts_ids(clean_retweeted_invalid={clean_retweeted_invalid}, \
+                         after={after:?}) failed: {e}"
+                    );
+                })?;
+                let Some(&last) = batch.last() else {
+                    return Ok(None);
+                };
+                let finished = (batch.len() as u64) < BATCH_SIZE;
+                Ok(Some((
+                    stream::iter(batch.into_iter().map(Ok)),
+                    (Some(last), finished),
+                )))
+            },
+        )
+        .try_flatten()
+    }
+
+    async fn create_quarantine_batch(&self, batch: &str, task: &str) -> Result<()> {
+        quarantine::create_batch(&self.write_pool, batch, task, Utc::now().timestamp())
+            .await
+            .inspect_err(|e| {
+                error!("create_quarantine_batch(batch={batch}, task={task}) failed: {e}");
+            })
+    }
+
+    async fn quarantine_picture(
+        &self,
+        ctx: Arc<TaskContext>,
+        batch: &str,
+        url: &Url,
+    ) -> Result<()> {
+        self.move_picture_to_quarantine(ctx, batch, url)
+            .await
+            .inspect_err(|e| {
+                error!("quarantine_picture(batch={batch}, url={url}) failed: {e}");
             })
-        }
     }
 
-    async fn get_invalid_posts_ids(&self, clean_retweeted_invalid: bool) -> Result<Vec<i64>> {
-        post::get_invalid_posts_ids(&self.db_pool, clean_retweeted_invalid)
+    async fn quarantine_post(&self, ctx: Arc<TaskContext>, batch: &str, id: i64) -> Result<()> {
+        self.move_post_to_quarantine(ctx, batch, id)
             .await
             .inspect_err(|e| {
-                error!(
-                    "get_invalid_posts_ids(clean_retweeted_invalid={}) failed: {e}",
-                    clean_retweeted_invalid
-                );
+                error!("quarantine_post(batch={batch}, id={id}) failed: {e}");
+            })
+    }
+
+    async fn list_quarantine_batches(&self) -> Result<Vec<QuarantineBatch>> {
+        quarantine::list_batches(&self.db_pool)
+            .await
+            .inspect_err(|e| {
+                error!("list_quarantine_batches failed: {e}");
             })
     }
+
+    async fn restore_quarantine(
+        &self,
+        ctx: Arc<TaskContext>,
+        batch: &str,
+    ) -> Result<RestoreSummary> {
+        self.restore_batch(ctx, batch).await.inspect_err(|e| {
+            error!("restore_quarantine(batch={batch}) failed: {e}");
+        })
+    }
+
+    async fn backup_database(&self, ctx: Arc<TaskContext>, label: &str) -> Result<DatabaseBackup> {
+        database::snapshot_database(
+            &self.db_pool,
+            &ctx.config.backup_path,
+            label,
+            ctx.config.backups_to_keep,
+        )
+        .await
+        .inspect_err(|e| {
+            error!("backup_database(label={label}) failed: {e}");
+        })
+    }
+
+    async fn merge_archive(&self, source_db: &Path) -> Result<MergeOutcome> {
+        self.merge_from(source_db).await.inspect_err(|e| {
+            error!("merge_archive(source_db={source_db:?}) failed: {e}");
+        })
+    }
+
+    async fn fill_bundle(&self, ids: &[i64], bundle: &Path) -> Result<BundleContents> {
+        self.fill_bundle_from(ids, bundle).await.inspect_err(|e| {
+            error!(
+                "fill_bundle(ids={} posts, bundle={bundle:?}) failed: {e}",
+                ids.len()
+            );
+        })
+    }
+
+    async fn database_size(&self) -> Result<u64> {
+        maintenance::database_size(&self.db_pool)
+            .await
+            .inspect_err(|e| error!("database_size failed: {e}"))
+    }
+
+    async fn run_maintenance_step(&self, step: MaintenanceStep) -> Result<Vec<String>> {
+        let result = match step {
+            MaintenanceStep::IntegrityCheck => maintenance::integrity_check(&self.db_pool).await,
+            MaintenanceStep::Analyze => {
+                maintenance::analyze(&self.write_pool).await.map(|_| vec![])
+            }
+            MaintenanceStep::Optimize => maintenance::optimize(&self.write_pool)
+                .await
+                .map(|_| vec![]),
+            MaintenanceStep::OptimizeSearchIndex => maintenance::optimize_fts(&self.write_pool)
+                .await
+                .map(|_| vec![]),
+            MaintenanceStep::Vacuum => maintenance::vacuum(&self.write_pool).await.map(|_| vec![]),
+        };
+        result.inspect_err(|e| error!("run_maintenance_step(step={step:?}) failed: {e}"))
+    }
+
+    async fn last_maintenance(&self) -> Result<Option<i64>> {
+        maintenance::get_last_run(&self.db_pool)
+            .await
+            .inspect_err(|e| error!("last_maintenance failed: {e}"))
+    }
+
+    async fn set_last_maintenance(&self, at: i64) -> Result<()> {
+        maintenance::set_last_run(&self.write_pool, at)
+            .await
+            .inspect_err(|e| error!("set_last_maintenance(at={at}) failed: {e}"))
+    }
 }
 
 #[cfg(test)]
@@ -805,6 +1990,7 @@ mod local_tests {
     use chrono::DateTime;
     use futures::TryStreamExt;
     use itertools::Itertools;
+    use sqlx::AssertSqlSafe;
     use tempfile::TempDir;
     use tokio::fs::read_to_string;
 
@@ -812,6 +1998,7 @@ mod local_tests {
     use crate::{
         api::{favorites::FavoritesSucc, profile_statuses::ProfileStatusesSucc},
         config::Config,
+        core::task::SearchTerm,
         core::task_manager::{TaskManager, TaskType},
         models::{PictureDefinition, Post, VideoMeta},
     };
@@ -819,11 +2006,8 @@ mod local_tests {
     async fn setup_storage() -> StorageImpl {
         let db_pool = SqlitePool::connect(":memory:").await.unwrap();
         sqlx::migrate!().run(&db_pool).await.unwrap();
-        StorageImpl {
-            db_pool,
-            pic_storage: Default::default(),
-            video_storage: Default::default(),
-        }
+        let write_pool = database::create_write_pool(&db_pool).await.unwrap();
+        StorageImpl::new(db_pool, write_pool)
     }
 
     async fn create_test_posts() -> Vec<Post> {
@@ -860,6 +2044,7 @@ mod local_tests {
         let config = Config {
             picture_path,
             video_path,
+            quarantine_path: temp_dir.path().join("quarantine"),
             ..Default::default()
         };
 
@@ -966,6 +2151,7 @@ mod local_tests {
             reverse_order: false,
             page: 1,
             posts_per_page: 1_000_000_000,
+            ..Default::default()
         };
         let paginated_posts = storage.query_posts(query).await.unwrap();
         let fetched_posts = paginated_posts.posts;
@@ -990,6 +2176,98 @@ mod local_tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_save_posts_in_one_transaction() {
+        let storage = setup_storage().await;
+        let posts = create_test_posts().await;
+        let query = PostQuery {
+            page: 1,
+            posts_per_page: 1_000_000,
+            ..Default::default()
+        };
+
+        // A failing post rolls back the whole batch
+        let last = posts.last().unwrap().id;
+        sqlx::query(AssertSqlSafe(format!(
+            "CREATE TRIGGER fail_post BEFORE INSERT ON posts WHEN NEW.id = {last} \
+             BEGIN SELECT RAISE(ABORT, 'fail'); END"
+        )))
+        .execute(&storage.db_pool)
+        .await
+        .unwrap();
+        assert!(storage.save_posts(&posts).await.is_err());
+        let saved = storage.query_posts(query.clone()).await.unwrap();
+        assert_eq!(saved.total_items, 0);
+        let uid = posts.iter().find_map(|p| p.user.as_ref()).unwrap().id;
+        assert!(storage.get_user(uid).await.unwrap().is_none());
+
+        sqlx::query("DROP TRIGGER fail_post")
+            .execute(&storage.db_pool)
+            .await
+            .unwrap();
+        storage.save_posts(&posts).await.unwrap();
+        let mut ids = HashSet::new();
+        for post in posts.iter() {
+            ids.insert(post.id);
+            if let Some(ret) = post.retweeted_status.as_ref() {
+                ids.insert(ret.id);
+            }
+        }
+        let saved = storage.query_posts(query).await.unwrap();
+        assert_eq!(saved.posts.len(), ids.len());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_query_while_saving() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("weiback.db");
+        let db_pool = database::create_db_pool_with_url(db_path.to_str().unwrap())
+            .await
+            .unwrap();
+        let journal_mode: String = sqlx::query_scalar("PRAGMA journal_mode")
+            .fetch_one(&db_pool)
+            .await
+            .unwrap();
+        assert_eq!(journal_mode, "wal");
+        let write_pool = database::create_write_pool(&db_pool).await.unwrap();
+        let storage = StorageImpl::new(db_pool, write_pool);
+        let posts = create_test_posts().await;
+
+        let writers = (0..2)
+            .map(|_| {
+                let storage = storage.clone();
+                let posts = posts.clone();
+                tokio::spawn(async move {
+                    for _ in 0..3 {
+                        for post in posts.iter() {
+                            storage.save_post(post).await?;
+                        }
+                    }
+                    Ok::<_, Error>(())
+                })
+            })
+            .collect::<Vec<_>>();
+        let query = PostQuery {
+            page: 1,
+            posts_per_page: 50,
+            ..Default::default()
+        };
+        let mut queries = 0;
+        while writers.iter().any(|w| !w.is_finished()) {
+            let readers = (0..4).map(|_| storage.query_posts(query.clone()));
+            for result in futures::future::join_all(readers).await {
+                result.unwrap();
+                queries += 1;
+            }
+        }
+        for writer in writers {
+            writer.await.unwrap().unwrap();
+        }
+        assert!(queries > 0);
+        let total = storage.query_posts(query).await.unwrap().total_items;
+        assert!(total >= posts.len() as u64);
+    }
+
     #[tokio::test]
     async fn test_get_posts() {
         let storage = setup_storage().await;
@@ -1012,6 +2290,7 @@ mod local_tests {
             reverse_order: false,
             page: 1,
             posts_per_page: 1_000_000,
+            ..Default::default()
         };
         let fetched_posts = storage.query_posts(query.clone()).await.unwrap();
         assert_eq!(fetched_posts.posts.len(), ids.len());
@@ -1021,6 +2300,118 @@ mod local_tests {
         assert_eq!(fetched_posts_rev.posts.len(), ids.len());
     }
 
+    #[tokio::test]
+    async fn test_stream_posts() {
+        let storage = setup_storage().await;
+        for post in create_test_posts().await.iter() {
+            storage.save_post(post).await.unwrap();
+        }
+
+        let query = PostQuery {
+            user_id: None,
+            start_date: None,
+            end_date: None,
+            search_term: None,
+            is_favorited: false,
+            reverse_order: false,
+            page: 1,
+            posts_per_page: 1_000_000,
+            ..Default::default()
+        };
+        let expected = storage
+            .query_posts(query.clone())
+            .await
+            .unwrap()
+            .posts
+            .into_iter()
+            .map(|p| p.id)
+            .collect::<Vec<_>>();
+        let streamed = storage
+            .stream_posts(query.clone())
+            .map_ok(|p| p.id)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert!(!expected.is_empty());
+        assert_eq!(streamed, expected);
+        let streamed_ids = storage
+            .stream_post_ids(query.clone())
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(streamed_ids, expected);
+        assert_eq!(
+            storage.count_posts(query).await.unwrap(),
+            expected.len() as u64
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_invalid_posts_ids() {
+        let storage = setup_storage().await;
+        let mut post = create_test_posts()
+            .await
+            .into_iter()
+            .find(|p| p.retweeted_status.is_none())
+            .unwrap();
+        post.user = None;
+        let mut ids = Vec::new();
+        for offset in 0..3 {
+            post.id += offset;
+            storage.save_post(&post).await.unwrap();
+            ids.push(post.id);
+        }
+
+        assert_eq!(storage.count_invalid_posts(true).await.unwrap(), 3);
+        let streamed = storage
+            .stream_invalid_posts_ids(true)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(streamed, ids);
+    }
+
+    #[tokio::test]
+    async fn test_script_folding_cache() {
+        let storage = setup_storage().await;
+        let post = Post {
+            id: 1,
+            text: "我愛臺灣".to_string(),
+            ..Default::default()
+        };
+        storage.save_post(&post).await.unwrap();
+        let query = PostQuery {
+            search_term: Some(SearchTerm::Fuzzy("台湾".to_string())),
+            ..Default::default()
+        };
+
+        assert_eq!(storage.count_posts(query.clone()).await.unwrap(), 0);
+        storage.set_script_folding(true).await.unwrap();
+        assert_eq!(storage.count_posts(query.clone()).await.unwrap(), 1);
+        storage.set_script_folding(false).await.unwrap();
+        assert_eq!(storage.count_posts(query).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_save_post_keeps_raw() {
+        let storage = setup_storage().await;
+        let posts = create_test_posts().await;
+        for post in posts.iter() {
+            storage.save_post(post).await.unwrap();
+        }
+
+        for post in posts {
+            let raw = storage.get_post_raw(post.id).await.unwrap().unwrap();
+            assert_eq!(Some(&raw), post.raw.as_ref());
+            if let Some(retweeted) = post.retweeted_status.as_ref() {
+                assert!(storage.get_post_raw(retweeted.id).await.unwrap().is_some());
+            }
+            let reparsed = crate::api::post_from_raw(raw, None).unwrap();
+            assert_eq!(reparsed, post);
+        }
+        assert!(storage.get_post_raw(-1).await.unwrap().is_none());
+    }
+
     #[tokio::test]
     async fn test_get_ones_posts() {
         let storage = setup_storage().await;
@@ -1054,6 +2445,7 @@ mod local_tests {
             reverse_order: false,
             page: 1,
             posts_per_page: ones_post_ids.len() as u32,
+            ..Default::default()
         };
         let fetched_posts = storage.query_posts(query.clone()).await.unwrap();
         assert_eq!(fetched_posts.posts.len(), ones_post_ids.len());
@@ -1088,6 +2480,7 @@ mod local_tests {
             reverse_order: false,
             page: 1,
             posts_per_page: 2,
+            ..Default::default()
         };
         let paginated_posts = storage.query_posts(query).await.unwrap();
         assert_eq!(paginated_posts.total_items, favorited.len() as u64);
@@ -1587,4 +2980,244 @@ mod local_tests {
         assert!(storage.get_post(retweet1.id).await.unwrap().is_none());
         assert!(storage.get_post(retweet2.id).await.unwrap().is_none());
     }
+
+    #[tokio::test]
+    async fn test_stats() {
+        let storage = setup_storage().await;
+        let (ctx, _temp_dir) = setup_task_context().await;
+
+        let posts = create_test_posts().await;
+        for post in &posts {
+            storage.save_post(post).await.unwrap();
+        }
+        let post = posts.iter().find(|p| p.user.is_some()).unwrap();
+        let uid = post.user.as_ref().unwrap().id;
+        storage
+            .save_picture(ctx.clone(), &create_test_picture(post.id, "stats"))
+            .await
+            .unwrap();
+
+        let stats = storage.stats(ctx.clone(), None, 5).await.unwrap();
+        assert!(stats.total_posts > 0);
+        assert_eq!(
+            stats.total_posts,
+            stats.original_posts + stats.retweet_posts
+        );
+        assert!(stats.top_sources.len() <= 5);
+        assert_eq!(
+            stats.hourly_posts.iter().map(|h| h.count).sum::<u64>(),
+            stats.total_posts
+        );
+        let media = stats.user_media.iter().find(|m| m.uid == uid).unwrap();
+        assert_eq!(media.pictures, 1);
+        assert_eq!(media.picture_bytes, b"test_image_data".len() as u64);
+
+        let stats = storage.stats(ctx, Some(uid), 5).await.unwrap();
+        assert_eq!(stats.uid, Some(uid));
+        assert!(stats.monthly_posts.iter().all(|m| m.uid == uid));
+        assert_eq!(stats.user_media.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_coverage() {
+        let storage = setup_storage().await;
+        let posts = create_test_posts().await;
+        for post in &posts {
+            storage.save_post(post).await.unwrap();
+        }
+        let post = posts
+            .iter()
+            .find(|p| p.user.is_some() && p.raw.is_some())
+            .unwrap();
+        let uid = post.user.as_ref().unwrap().id;
+
+        let coverages = storage.get_coverage(Some(uid)).await.unwrap();
+        assert_eq!(coverages.len(), 1);
+        let coverage = &coverages[0];
+        assert_eq!(coverage.uid, uid);
+        // recorded from the raw JSON of the saved posts
+        assert!(coverage.remote_count.is_some());
+        assert!(coverage.remote_count_at.is_some());
+        assert!(coverage.local_count > 0);
+        assert!(coverage.first_month <= coverage.last_month);
+
+        let coverages = storage.get_coverage(None).await.unwrap();
+        assert!(coverages.iter().any(|c| c.uid == uid));
+        assert!(
+            coverages
+                .windows(2)
+                .all(|pair| pair[0].missing() >= pair[1].missing())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verify_archive() {
+        let storage = setup_storage().await;
+        let (ctx, _temp_dir) = setup_task_context().await;
+        let kept = create_test_picture(1, "kept");
+        let lost = create_test_picture(1, "lost");
+        storage.save_picture(ctx.clone(), &kept).await.unwrap();
+        storage.save_picture(ctx.clone(), &lost).await.unwrap();
+        let lost_path = storage
+            .get_picture_path(ctx.clone(), lost.meta.url())
+            .await
+            .unwrap()
+            .unwrap();
+        tokio::fs::remove_file(&lost_path).await.unwrap();
+        let orphan = ctx.config.picture_path.join("orphan/pic.jpg");
+        tokio::fs::create_dir_all(orphan.parent().unwrap())
+            .await
+            .unwrap();
+        tokio::fs::write(&orphan, b"orphan").await.unwrap();
+
+        let report = storage.verify_archive(ctx.clone()).await.unwrap();
+        assert_eq!(report.missing_picture_files.len(), 1);
+        assert_eq!(
+            report.missing_picture_files[0].url,
+            lost.meta.url().as_str()
+        );
+        assert_eq!(report.orphan_picture_files, ["orphan/pic.jpg"]);
+        assert!(report.missing_video_files.is_empty());
+        assert!(report.orphan_video_files.is_empty());
+        assert!(!report.fts_out_of_sync());
+        assert_eq!(report.issue_count(), 2);
+
+        let urls = report
+            .missing_picture_files
+            .iter()
+            .map(|p| p.url.clone())
+            .collect::<Vec<_>>();
+        storage
+            .create_quarantine_batch("repair", "verify_archive")
+            .await
+            .unwrap();
+        assert_eq!(
+            storage
+                .quarantine_media_rows("repair", &urls, &[])
+                .await
+                .unwrap(),
+            1
+        );
+        let report = storage.verify_archive(ctx.clone()).await.unwrap();
+        assert!(report.missing_picture_files.is_empty());
+        let batches = storage.list_quarantine_batches().await.unwrap();
+        assert_eq!(batches[0].rows, 1);
+        assert!(
+            storage
+                .picture_saved(ctx.clone(), kept.meta.url())
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_orphan_media() {
+        let storage = setup_storage().await;
+        let (ctx, _temp_dir) = setup_task_context().await;
+        let post = create_test_posts().await.remove(0);
+        storage.save_post(&post).await.unwrap();
+        let kept = create_test_picture(post.id, "kept");
+        let orphan = create_test_picture(999, "orphan");
+        storage.save_picture(ctx.clone(), &kept).await.unwrap();
+        storage.save_picture(ctx.clone(), &orphan).await.unwrap();
+        let stray = ctx.config.picture_path.join("stray.jpg");
+        tokio::fs::write(&stray, b"stray").await.unwrap();
+
+        let report = storage.find_orphan_media(ctx.clone()).await.unwrap();
+        assert_eq!(report.orphan_picture_rows.len(), 1);
+        assert_eq!(
+            report.orphan_picture_rows[0].url,
+            orphan.meta.url().as_str()
+        );
+        assert_eq!(report.orphan_picture_files.len(), 2);
+        assert!(
+            report
+                .orphan_picture_files
+                .contains(&report.orphan_picture_rows[0].path)
+        );
+        assert!(
+            report
+                .orphan_picture_files
+                .contains(&"stray.jpg".to_string())
+        );
+        assert_eq!(report.orphan_bytes, (b"test_image_data".len() + 5) as u64);
+        assert!(report.orphan_video_rows.is_empty());
+        assert_eq!(report.orphan_count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_quarantine_and_restore() {
+        let storage = setup_storage().await;
+        let (ctx, _temp_dir) = setup_task_context().await;
+        let post = create_test_posts()
+            .await
+            .into_iter()
+            .find(|p| p.retweeted_status.is_none())
+            .unwrap();
+        storage.save_post(&post).await.unwrap();
+        let attached = create_test_picture(post.id, "attached");
+        let single = create_test_picture(999, "single");
+        let video = create_test_video(post.id);
+        storage.save_picture(ctx.clone(), &attached).await.unwrap();
+        storage.save_picture(ctx.clone(), &single).await.unwrap();
+        storage.save_video(ctx.clone(), &video).await.unwrap();
+        let picture_files = list_files(&ctx.config.picture_path).await.unwrap();
+        let video_files = list_files(&ctx.config.video_path).await.unwrap();
+        assert_eq!(picture_files.len(), 2);
+        assert_eq!(video_files.len(), 1);
+
+        storage
+            .create_quarantine_batch("b1", "cleanup_invalid_posts")
+            .await
+            .unwrap();
+        storage
+            .quarantine_post(ctx.clone(), "b1", post.id)
+            .await
+            .unwrap();
+        storage
+            .quarantine_picture(ctx.clone(), "b1", single.meta.url())
+            .await
+            .unwrap();
+        assert!(storage.get_post(post.id).await.unwrap().is_none());
+        assert_eq!(storage.count_pictures().await.unwrap(), 0);
+        assert!(
+            list_files(&ctx.config.picture_path)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(list_files(&ctx.config.video_path).await.unwrap().is_empty());
+        let dir = ctx.config.quarantine_path.join("b1");
+        assert_eq!(
+            list_files(&dir.join("pictures")).await.unwrap(),
+            picture_files
+        );
+        assert_eq!(list_files(&dir.join("videos")).await.unwrap(), video_files);
+        let batches = storage.list_quarantine_batches().await.unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].task, "cleanup_invalid_posts");
+        assert!(batches[0].rows >= 4);
+
+        let summary = storage.restore_quarantine(ctx.clone(), "b1").await.unwrap();
+        assert_eq!(summary.restored_rows, batches[0].rows as u64);
+        assert_eq!(summary.restored_files, 3);
+        assert_eq!(summary.skipped_files, 0);
+        assert_eq!(
+            storage.get_post(post.id).await.unwrap().unwrap().id,
+            post.id
+        );
+        assert_eq!(storage.count_pictures().await.unwrap(), 2);
+        assert_eq!(
+            list_files(&ctx.config.picture_path).await.unwrap(),
+            picture_files
+        );
+        assert_eq!(
+            list_files(&ctx.config.video_path).await.unwrap(),
+            video_files
+        );
+        assert!(!dir.exists());
+        let batches = storage.list_quarantine_batches().await.unwrap();
+        assert!(batches[0].restored_at.is_some());
+        assert_eq!(batches[0].rows, 0);
+    }
 }
@@ -3,24 +3,90 @@
 //!
 //! It provides functions to create a database pool for both default application usage
 //! and for custom database URLs, such as in-memory databases for testing.
+//!
+//! The database is opened in WAL mode, so that browsing the archive does not wait for a
+//! backup writing to it, and the other way round. SQLite still allows one writer at a
+//! time: all writes go through a pool of their own with a single connection,
+//! created by [`create_write_pool`], and queue there rather than on the database lock.
+//!
+//! It also takes snapshots of the database file with `VACUUM INTO`, which works while
+//! the database is in use. A snapshot is taken automatically before new migrations are
+//! applied, and the snapshots in the backup directory are rotated. Restoring a snapshot
+//! stages it next to the database file, and it replaces the database on the next start.
+//! Moving the database to another file is staged the same way.
 
-use sqlx::{Sqlite, SqlitePool, migrate::MigrateDatabase};
-use tracing::{error, info};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    Connection, Sqlite, SqliteConnection, SqlitePool,
+    migrate::{MigrateDatabase, Migrator},
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous},
+};
+use tracing::{error, info, warn};
 
 use crate::config::get_config;
 use crate::error::{Error, Result};
+use crate::relocate::RelocateMode;
+
+static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// How long a connection waits for the database lock held by another one before failing
+/// with `database is locked`. A vacuum or a merge can hold it for a while.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+/// The number of connections of the pool used for reads. In WAL mode readers neither
+/// block each other nor the writer.
+const READ_POOL_SIZE: u32 = 8;
+/// How long a write waits for the connection of the write pool. A vacuum or a merge of a
+/// large archive holds it for as long as it runs, and the writes queued behind it should
+/// wait for it rather than fail after the default 30 seconds.
+const WRITE_ACQUIRE_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A snapshot of the database in the backup directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatabaseBackup {
+    /// The path of the snapshot file.
+    pub path: PathBuf,
+    /// Why the snapshot was taken, e.g. `manual` or `pre-migration`.
+    pub label: String,
+    /// When the snapshot was taken, as a Unix timestamp.
+    pub created_at: i64,
+    /// The size of the snapshot file in bytes.
+    pub size: u64,
+}
 
 /// Creates a database connection pool using the default database path specified in the application configuration.
 ///
 /// This function initializes the database file if it doesn't exist and runs all pending migrations.
+/// A relocation staged by [`stage_relocation`] and a restore staged by [`stage_restore`] are
+/// applied first, and a snapshot is taken into the
+/// configured backup directory before pending migrations run on an existing database.
 ///
 /// # Returns
 ///
 /// A `Result` containing a `SqlitePool` on success, or an `Error` on failure.
 pub async fn create_db_pool() -> Result<SqlitePool> {
-    let db_path = get_config().read()?.db_path.clone();
+    let (db_path, backup_path, backups_to_keep) = {
+        let config = get_config();
+        let config = config.read()?;
+        (
+            config.db_path.clone(),
+            config.backup_path.clone(),
+            config.backups_to_keep,
+        )
+    };
     info!("Initializing database pool at path: {db_path:?}");
-    create_db_pool_with_url(&db_path.to_string_lossy()).await
+    apply_staged_relocation(&db_path).await?;
+    apply_staged_restore(&db_path).await?;
+    connect_and_migrate(
+        &db_path.to_string_lossy(),
+        Some((&backup_path, backups_to_keep)),
+    )
+    .await
 }
 
 /// Creates a database connection pool for a given database URL.
@@ -37,6 +103,12 @@ pub async fn create_db_pool() -> Result<SqlitePool> {
 ///
 /// A `Result` containing a `SqlitePool` on success, or an `Error` on failure.
 pub async fn create_db_pool_with_url(db_url: &str) -> Result<SqlitePool> {
+    connect_and_migrate(db_url, None).await
+}
+
+/// Connects to the database and runs the pending migrations. With a backup directory and
+/// a rotation, an existing database is snapshotted before any pending migration runs.
+async fn connect_and_migrate(db_url: &str, backup: Option<(&Path, usize)>) -> Result<SqlitePool> {
     if db_url != ":memory:" {
         let db_path = std::path::Path::new(db_url);
         if !db_path.exists() {
@@ -58,9 +130,27 @@ pub async fn create_db_pool_with_url(db_url: &str) -> Result<SqlitePool> {
     }
 
     info!("Connecting to database and running migrations...");
-    let db_pool = SqlitePool::connect(db_url).await?;
+    let db_pool = SqlitePoolOptions::new()
+        .max_connections(READ_POOL_SIZE)
+        .connect_with(connect_options(db_url)?)
+        .await?;
+
+    if let Some((backup_dir, keep)) = backup {
+        let applied = applied_migrations(&mut *db_pool.acquire().await?).await?;
+        let pending = MIGRATOR
+            .iter()
+            .filter(|m| !m.migration_type.is_down_migration())
+            .any(|m| !applied.contains_key(&m.version));
+        // A new database has nothing worth a snapshot
+        if !applied.is_empty() && pending {
+            info!("Pending migrations found, taking a snapshot first...");
+            snapshot_database(&db_pool, backup_dir, "pre-migration", keep)
+                .await
+                .inspect_err(|e| error!("Pre-migration snapshot failed: {e}"))?;
+        }
+    }
 
-    sqlx::migrate!().run(&db_pool).await.map_err(|e| {
+    MIGRATOR.run(&db_pool).await.map_err(|e| {
         error!("Database migration failed: {e}");
         Error::DbError(e.to_string())
     })?;
@@ -69,6 +159,356 @@ pub async fn create_db_pool_with_url(db_url: &str) -> Result<SqlitePool> {
     Ok(db_pool)
 }
 
+/// The options every connection to the database is opened with: WAL journaling, which
+/// lets reads go on while a write is in progress, `synchronous=NORMAL`, which is safe in
+/// WAL mode and only syncs at checkpoints, and a generous busy timeout.
+fn connect_options(db_url: &str) -> Result<SqliteConnectOptions> {
+    Ok(SqliteConnectOptions::from_str(db_url)?
+        .journal_mode(SqliteJournalMode::Wal)
+        .synchronous(SqliteSynchronous::Normal)
+        .busy_timeout(BUSY_TIMEOUT))
+}
+
+/// Creates a pool with a single connection to the database of `db_pool`, for writes.
+///
+/// Writers queue for the connection instead of for the database lock, so they never
+/// time out on each other, and a transaction that reads before it writes cannot fail
+/// with `SQLITE_BUSY` because another connection wrote in the meantime.
+///
+/// # Arguments
+///
+/// * `db_pool` - A pool created by [`create_db_pool`] or [`create_db_pool_with_url`].
+///
+/// # Returns
+///
+/// A `Result` containing the new `SqlitePool` on success, or an `Error` on failure.
+pub async fn create_write_pool(db_pool: &SqlitePool) -> Result<SqlitePool> {
+    // The same options, so an in-memory database is shared as well
+    let options = (*db_pool.connect_options()).clone();
+    Ok(SqlitePoolOptions::new()
+        .max_connections(1)
+        .acquire_timeout(WRITE_ACQUIRE_TIMEOUT)
+        .connect_with(options)
+        .await?)
+}
+
+/// Reads the successfully applied migrations of a database, with their checksums.
+/// A database that was never migrated has none.
+async fn applied_migrations(conn: &mut SqliteConnection) -> Result<HashMap<i64, Vec<u8>>> {
+    let exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master \
+         WHERE type = 'table' AND name = '_sqlx_migrations')",
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    if !exists {
+        return Ok(HashMap::new());
+    }
+    let rows: Vec<(i64, Vec<u8>)> =
+        sqlx::query_as("SELECT version, checksum FROM _sqlx_migrations WHERE success = 1")
+            .fetch_all(&mut *conn)
+            .await?;
+    Ok(rows.into_iter().collect())
+}
+
+/// Takes a snapshot of the database into the backup directory, then removes the
+/// oldest snapshots beyond `keep`.
+///
+/// # Arguments
+/// * `db_pool` - The pool of the database, which can be in use.
+/// * `backup_dir` - The directory of the snapshots.
+/// * `label` - Why the snapshot is taken, which becomes part of its file name.
+/// * `keep` - How many snapshots to keep. `0` keeps all of them.
+///
+/// # Returns
+/// A `Result` containing the new snapshot.
+pub async fn snapshot_database(
+    db_pool: &SqlitePool,
+    backup_dir: &Path,
+    label: &str,
+    keep: usize,
+) -> Result<DatabaseBackup> {
+    tokio::fs::create_dir_all(backup_dir).await?;
+    let now = Local::now();
+    let path = backup_dir.join(format!(
+        "weiback_{}_{label}.db",
+        now.format("%Y-%m-%d_%H%M%S")
+    ));
+    if path.exists() {
+        return Err(Error::DbError(format!("snapshot {path:?} already exists")));
+    }
+    info!("Taking a database snapshot into {path:?}");
+    sqlx::query("VACUUM INTO ?")
+        .bind(path.to_string_lossy().into_owned())
+        .execute(db_pool)
+        .await?;
+    let size = tokio::fs::metadata(&path).await?.len();
+
+    if keep > 0 {
+        for old in list_backups(backup_dir).await?.into_iter().skip(keep) {
+            info!("Removing old database snapshot {:?}", old.path);
+            tokio::fs::remove_file(&old.path).await?;
+        }
+    }
+
+    Ok(DatabaseBackup {
+        path,
+        label: label.to_string(),
+        created_at: now.timestamp(),
+        size,
+    })
+}
+
+/// Lists the snapshots in the backup directory, the newest first.
+pub async fn list_backups(backup_dir: &Path) -> Result<Vec<DatabaseBackup>> {
+    let mut backups = Vec::new();
+    if !backup_dir.exists() {
+        return Ok(backups);
+    }
+    let mut entries = tokio::fs::read_dir(backup_dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let Some(stem) = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_prefix("weiback_"))
+            .and_then(|n| n.strip_suffix(".db"))
+        else {
+            continue;
+        };
+        // `{%Y-%m-%d}_{%H%M%S}_{label}`
+        let mut parts = stem.splitn(3, '_');
+        let (Some(date), Some(time), Some(label)) = (parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+        let Ok(taken_at) =
+            NaiveDateTime::parse_from_str(&format!("{date}_{time}"), "%Y-%m-%d_%H%M%S")
+        else {
+            continue;
+        };
+        let created_at = taken_at
+            .and_local_timezone(Local)
+            .earliest()
+            .map(|t| t.timestamp())
+            .unwrap_or_else(|| taken_at.and_utc().timestamp());
+        backups.push(DatabaseBackup {
+            label: label.to_string(),
+            created_at,
+            size: entry.metadata().await?.len(),
+            path,
+        });
+    }
+    backups.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.path.cmp(&a.path)));
+    Ok(backups)
+}
+
+/// Checks that a snapshot can be restored: it must pass `PRAGMA quick_check`, and
+/// every migration applied to it must be one this version knows. An older snapshot
+/// is fine, the missing migrations run when it is opened.
+///
+/// # Returns
+/// A `Result` containing the latest migration version applied to the snapshot.
+pub async fn check_backup(path: &Path) -> Result<i64> {
+    if !path.is_file() {
+        return Err(Error::DbError(format!("snapshot {path:?} not found")));
+    }
+    let options = SqliteConnectOptions::new().filename(path).read_only(true);
+    let mut conn = SqliteConnection::connect_with(&options).await?;
+
+    let check: String = sqlx::query_scalar("PRAGMA quick_check")
+        .fetch_one(&mut conn)
+        .await?;
+    if check != "ok" {
+        return Err(Error::DbError(format!(
+            "snapshot {path:?} is corrupted: {check}"
+        )));
+    }
+
+    let applied = applied_migrations(&mut conn).await?;
+    conn.close().await?;
+    if applied.is_empty() {
+        return Err(Error::DbError(format!(
+            "{path:?} is not a weiback database"
+        )));
+    }
+    for (version, checksum) in applied.iter() {
+        match MIGRATOR.iter().find(|m| m.version == *version) {
+            None => {
+                return Err(Error::DbError(format!(
+                    "snapshot {path:?} has schema version {version}, which is newer than this version of weiback"
+                )));
+            }
+            Some(m) if m.checksum.as_ref() != checksum.as_slice() => {
+                return Err(Error::DbError(format!(
+                    "migration {version} of snapshot {path:?} does not match this version of weiback"
+                )));
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(applied.into_keys().max().unwrap_or_default())
+}
+
+/// Copies a database that may be in use by another program, with `VACUUM INTO` on a
+/// read-only connection, so the copy is consistent and the original is left untouched.
+pub async fn copy_database(source: &Path, dest: &Path) -> Result<()> {
+    if !source.is_file() {
+        return Err(Error::DbError(format!("database {source:?} not found")));
+    }
+    let options = SqliteConnectOptions::new().filename(source).read_only(true);
+    let mut conn = SqliteConnection::connect_with(&options).await?;
+    sqlx::query("VACUUM INTO ?")
+        .bind(dest.to_string_lossy().into_owned())
+        .execute(&mut conn)
+        .await?;
+    conn.close().await?;
+    Ok(())
+}
+
+/// The path a restore is staged at, next to the database file.
+fn staged_restore_path(db_path: &Path) -> PathBuf {
+    let mut name = db_path.as_os_str().to_owned();
+    name.push(".restore");
+    PathBuf::from(name)
+}
+
+/// Checks a snapshot and copies it next to the database file, to replace the
+/// database on the next start. The database cannot be replaced while it is open.
+///
+/// # Returns
+/// A `Result` containing the latest migration version applied to the snapshot.
+pub async fn stage_restore(db_path: &Path, backup: &Path) -> Result<i64> {
+    let version = check_backup(backup).await?;
+    let staged = staged_restore_path(db_path);
+    info!("Staging the restore of {backup:?} at {staged:?}");
+    tokio::fs::copy(backup, &staged).await?;
+    Ok(version)
+}
+
+/// Removes a staged restore, if there is one.
+pub async fn cancel_staged_restore(db_path: &Path) -> Result<()> {
+    let staged = staged_restore_path(db_path);
+    if staged.exists() {
+        info!("Removing staged restore {staged:?}");
+        tokio::fs::remove_file(&staged).await?;
+    }
+    Ok(())
+}
+
+/// Replaces the database file with a staged restore, if there is one. Must run
+/// before the database is opened.
+async fn apply_staged_restore(db_path: &Path) -> Result<()> {
+    let staged = staged_restore_path(db_path);
+    if !staged.exists() {
+        return Ok(());
+    }
+    info!("Replacing database {db_path:?} with staged restore {staged:?}");
+    // The journal files belong to the replaced database
+    remove_journal_files(db_path).await?;
+    tokio::fs::rename(&staged, db_path).await.inspect_err(|e| {
+        error!("replace database with staged restore {staged:?} failed: {e}");
+    })?;
+    Ok(())
+}
+
+/// Removes the journal files of a database that is not open.
+async fn remove_journal_files(db_path: &Path) -> Result<()> {
+    for suffix in ["-wal", "-shm", "-journal"] {
+        let mut name = db_path.as_os_str().to_owned();
+        name.push(suffix);
+        let journal = PathBuf::from(name);
+        if journal.exists() {
+            warn!("Removing journal file {journal:?} of database {db_path:?}");
+            tokio::fs::remove_file(&journal).await?;
+        }
+    }
+    Ok(())
+}
+
+/// A relocation staged by [`stage_relocation`], as written to its marker.
+#[derive(Debug, Serialize, Deserialize)]
+struct StagedRelocation {
+    /// The database to move or copy.
+    source: PathBuf,
+    /// Whether the source is removed once the copy is in place.
+    mode: RelocateMode,
+}
+
+/// The path of the marker of a staged relocation, next to the new database file. It
+/// holds the [`StagedRelocation`] as JSON.
+fn staged_relocation_path(db_path: &Path) -> PathBuf {
+    let mut name = db_path.as_os_str().to_owned();
+    name.push(".relocate");
+    PathBuf::from(name)
+}
+
+/// Stages moving or copying the database to a new file on the next start, since it
+/// cannot be moved while it is open. The configuration must point to the new file
+/// afterwards.
+///
+/// # Arguments
+/// * `db_path` - The current database file.
+/// * `new_path` - The file to move it to, which must not exist yet.
+/// * `mode` - Whether the current database file is removed or kept.
+pub async fn stage_relocation(db_path: &Path, new_path: &Path, mode: RelocateMode) -> Result<()> {
+    if new_path.exists() {
+        return Err(Error::DbError(format!(
+            "database {new_path:?} already exists"
+        )));
+    }
+    if let Some(parent) = new_path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let marker = staged_relocation_path(new_path);
+    info!("Staging the relocation of database {db_path:?} to {new_path:?} ({mode:?})");
+    let staged = StagedRelocation {
+        source: db_path.to_path_buf(),
+        mode,
+    };
+    tokio::fs::write(&marker, serde_json::to_vec(&staged)?).await?;
+    Ok(())
+}
+
+/// Moves the database to its configured file, if a relocation there is staged. Must
+/// run before the database is opened.
+///
+/// The database is copied with `VACUUM INTO`, which works across file systems, and the
+/// old file is only removed, when moving, once the copy is in place, so an interrupted
+/// run is picked up again on the next start.
+async fn apply_staged_relocation(db_path: &Path) -> Result<()> {
+    let marker = staged_relocation_path(db_path);
+    if !marker.exists() {
+        return Ok(());
+    }
+    let StagedRelocation { source, mode } =
+        serde_json::from_slice(&tokio::fs::read(&marker).await?)
+            .map_err(|e| Error::FormatError(format!("staged relocation {marker:?}: {e}")))?;
+    if !db_path.exists() {
+        if !source.exists() {
+            return Err(Error::DbError(format!(
+                "database {source:?} to move to {db_path:?} not found"
+            )));
+        }
+        info!("Moving database {source:?} to {db_path:?}");
+        let mut name = db_path.as_os_str().to_owned();
+        name.push(".part");
+        let part = PathBuf::from(name);
+        if part.exists() {
+            tokio::fs::remove_file(&part).await?;
+        }
+        copy_database(&source, &part).await?;
+        tokio::fs::rename(&part, db_path).await?;
+    }
+    if mode == RelocateMode::Move && source.exists() {
+        info!("Removing relocated database {source:?}");
+        remove_journal_files(&source).await?;
+        tokio::fs::remove_file(&source).await?;
+    }
+    tokio::fs::remove_file(&marker).await?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod local_tests {
     use super::*;
@@ -159,4 +599,148 @@ mod local_tests {
         // Clean up
         dir.close().unwrap();
     }
+
+    #[tokio::test]
+    async fn test_snapshot_database_rotation() {
+        let dir = tempdir().unwrap();
+        let backup_dir = dir.path().join("backups");
+        let db_url = dir.path().join("weiback.db");
+        let pool = create_db_pool_with_url(db_url.to_str().unwrap())
+            .await
+            .unwrap();
+
+        for label in ["a", "b", "c"] {
+            let backup = snapshot_database(&pool, &backup_dir, label, 2)
+                .await
+                .unwrap();
+            assert!(backup.path.exists());
+            assert_eq!(backup.label, label);
+        }
+
+        let backups = list_backups(&backup_dir).await.unwrap();
+        let labels: Vec<_> = backups.iter().map(|b| b.label.as_str()).collect();
+        assert_eq!(labels, ["c", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_check_backup() {
+        let dir = tempdir().unwrap();
+        let db_url = dir.path().join("weiback.db");
+        let pool = create_db_pool_with_url(db_url.to_str().unwrap())
+            .await
+            .unwrap();
+        let backup = snapshot_database(&pool, dir.path(), "manual", 0)
+            .await
+            .unwrap();
+        let latest = MIGRATOR.iter().map(|m| m.version).max().unwrap();
+        assert_eq!(check_backup(&backup.path).await.unwrap(), latest);
+
+        let other = dir.path().join("other.db");
+        Sqlite::create_database(other.to_str().unwrap())
+            .await
+            .unwrap();
+        assert!(check_backup(&other).await.is_err());
+        assert!(check_backup(&dir.path().join("missing.db")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stage_and_apply_restore() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("weiback.db");
+        let pool = create_db_pool_with_url(db_path.to_str().unwrap())
+            .await
+            .unwrap();
+        let backup = snapshot_database(&pool, dir.path(), "manual", 0)
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE marker (x INTEGER)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool.close().await;
+
+        stage_restore(&db_path, &backup.path).await.unwrap();
+        assert!(staged_restore_path(&db_path).exists());
+        apply_staged_restore(&db_path).await.unwrap();
+        assert!(!staged_restore_path(&db_path).exists());
+
+        let pool = create_db_pool_with_url(db_path.to_str().unwrap())
+            .await
+            .unwrap();
+        let marker: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'marker'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(marker, 0);
+    }
+
+    #[tokio::test]
+    async fn test_no_snapshot_for_new_database() {
+        let dir = tempdir().unwrap();
+        let backup_dir = dir.path().join("backups");
+        let db_path = dir.path().join("weiback.db");
+        connect_and_migrate(db_path.to_str().unwrap(), Some((&backup_dir, 5)))
+            .await
+            .unwrap();
+        assert!(list_backups(&backup_dir).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stage_and_apply_relocation() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("weiback.db");
+        let new_path = dir.path().join("moved/weiback.db");
+        let pool = create_db_pool_with_url(db_path.to_str().unwrap())
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE marker (x INTEGER)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        stage_relocation(&db_path, &new_path, RelocateMode::Move)
+            .await
+            .unwrap();
+        assert!(
+            stage_relocation(&db_path, &db_path, RelocateMode::Move)
+                .await
+                .is_err()
+        );
+        pool.close().await;
+        apply_staged_relocation(&new_path).await.unwrap();
+        assert!(!db_path.exists());
+        assert!(!staged_relocation_path(&new_path).exists());
+
+        let pool = create_db_pool_with_url(new_path.to_str().unwrap())
+            .await
+            .unwrap();
+        let marker: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'marker'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(marker, 1);
+    }
+
+    #[tokio::test]
+    async fn test_apply_relocation_copy_keeps_source() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("weiback.db");
+        let new_path = dir.path().join("copied/weiback.db");
+        let pool = create_db_pool_with_url(db_path.to_str().unwrap())
+            .await
+            .unwrap();
+        stage_relocation(&db_path, &new_path, RelocateMode::Copy)
+            .await
+            .unwrap();
+        pool.close().await;
+
+        apply_staged_relocation(&new_path).await.unwrap();
+        assert!(db_path.exists());
+        assert!(new_path.exists());
+        assert!(!staged_relocation_path(&new_path).exists());
+    }
 }
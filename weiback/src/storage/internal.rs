@@ -1,7 +1,18 @@
 //! This module serves as a container for internal storage logic,
 //! re-exporting sub-modules that handle specific data types like pictures, posts, users, and videos.
 
+pub mod archive_setting;
+pub mod bundle;
+pub mod link;
+pub mod maintenance;
+pub mod merge;
 pub mod picture;
 pub mod post;
+pub mod post_raw;
+pub mod post_term;
+pub mod quarantine;
+pub mod script_folding;
+pub mod stats;
 pub mod user;
+pub mod verify;
 pub mod video;
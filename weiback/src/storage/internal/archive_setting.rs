@@ -0,0 +1,115 @@
+//! This module provides functions for interacting with the `archive_settings` table in the
+//! database.
+//!
+//! Unlike [`Config`](crate::config::Config), which belongs to the application, these
+//! settings belong to the archive itself and travel with the database file.
+//!
+//! # Table Structure: `archive_settings`
+//!
+//! | Column  | Type   | Description                                 |
+//! |---------|--------|---------------------------------------------|
+//! | `key`   | `TEXT` | The name of the setting. **Primary Key.**   |
+//! | `value` | `TEXT` | The value of the setting.                   |
+
+use sea_query::{Expr, ExprTrait, Iden, OnConflict, Query, SqliteQueryBuilder};
+use sea_query_sqlx::SqlxBinder;
+use sqlx::{AssertSqlSafe, Executor, Sqlite};
+
+use crate::error::Result;
+
+#[derive(Iden)]
+#[iden = "archive_settings"]
+enum ArchiveSettingIden {
+    Table,
+    Key,
+    Value,
+}
+
+/// Retrieves the value of an archive setting.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `key` - The name of the setting.
+///
+/// # Returns
+///
+/// A `Result` containing `Some(String)` if the setting is set, `None` otherwise.
+pub async fn get_setting<'e, E>(executor: E, key: &str) -> Result<Option<String>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let (sql, values) = Query::select()
+        .column(ArchiveSettingIden::Value)
+        .from(ArchiveSettingIden::Table)
+        .and_where(Expr::col(ArchiveSettingIden::Key).eq(key))
+        .build_sqlx(SqliteQueryBuilder);
+    Ok(
+        sqlx::query_scalar_with::<Sqlite, String, _>(AssertSqlSafe(sql), values)
+            .fetch_optional(executor)
+            .await?,
+    )
+}
+
+/// Sets an archive setting, or removes it if `value` is `None`.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `key` - The name of the setting.
+/// * `value` - The new value of the setting.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn set_setting<'e, E>(executor: E, key: &str, value: Option<&str>) -> Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let (sql, values) = match value {
+        Some(value) => Query::insert()
+            .into_table(ArchiveSettingIden::Table)
+            .columns([ArchiveSettingIden::Key, ArchiveSettingIden::Value])
+            .values([key.into(), value.into()])?
+            .on_conflict(
+                OnConflict::column(ArchiveSettingIden::Key)
+                    .update_column(ArchiveSettingIden::Value)
+                    .to_owned(),
+            )
+            .build_sqlx(SqliteQueryBuilder),
+        None => Query::delete()
+            .from_table(ArchiveSettingIden::Table)
+            .and_where(Expr::col(ArchiveSettingIden::Key).eq(key))
+            .build_sqlx(SqliteQueryBuilder),
+    };
+    sqlx::query_with(AssertSqlSafe(sql), values)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod local_tests {
+    use sqlx::SqlitePool;
+
+    use super::*;
+    use crate::storage::database::create_db_pool_with_url;
+
+    async fn setup_db() -> SqlitePool {
+        create_db_pool_with_url(":memory:").await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_get_and_set_setting() {
+        let db = setup_db().await;
+        assert!(get_setting(&db, "key").await.unwrap().is_none());
+
+        set_setting(&db, "key", Some("a")).await.unwrap();
+        assert_eq!(get_setting(&db, "key").await.unwrap().as_deref(), Some("a"));
+        set_setting(&db, "key", Some("b")).await.unwrap();
+        assert_eq!(get_setting(&db, "key").await.unwrap().as_deref(), Some("b"));
+
+        set_setting(&db, "key", None).await.unwrap();
+        assert!(get_setting(&db, "key").await.unwrap().is_none());
+    }
+}
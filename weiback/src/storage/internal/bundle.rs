@@ -0,0 +1,406 @@
+//! This module provides functions for the tables of archive bundles, and for copying
+//! the rows of selected posts from the archive into a bundle.
+//!
+//! A bundle is a database with the archive schema, see [`crate::bundle`]. To fill
+//! it, it is attached to a connection of the archive under the [`BUNDLE`] schema.
+//!
+//! # Table Structure: `bundle_manifest`
+//!
+//! | Column  | Type   | Description                          |
+//! |---------|--------|--------------------------------------|
+//! | `key`   | `TEXT` | The name of the entry. **Primary Key.** |
+//! | `value` | `TEXT` | The value of the entry.              |
+//!
+//! # Table Structure: `bundle_files`
+//!
+//! | Column   | Type      | Description                                              |
+//! |----------|-----------|----------------------------------------------------------|
+//! | `kind`   | `TEXT`    | `picture` or `video`. Part of the **Primary Key.**       |
+//! | `path`   | `TEXT`    | The path relative to the media directory. Part of the **Primary Key.** |
+//! | `size`   | `INTEGER` | The size of the file in bytes.                           |
+//! | `sha256` | `TEXT`    | The SHA-256 checksum of the file, as lowercase hex.      |
+//! | `data`   | `BLOB`    | The content of the file.                                 |
+
+use std::collections::HashMap;
+
+use sea_query::{Expr, ExprTrait, Iden, OnConflict, Query, SqliteQueryBuilder};
+use sea_query_sqlx::SqlxBinder;
+use sqlx::{AssertSqlSafe, Executor, Sqlite, SqliteConnection};
+
+use super::quarantine::get_columns;
+use crate::error::Result;
+use crate::verify::MediaFile;
+
+/// The schema name a bundle is attached as.
+pub const BUNDLE: &str = "bundle";
+
+#[derive(Iden)]
+#[iden = "bundle_manifest"]
+enum BundleManifestIden {
+    Table,
+    Key,
+    Value,
+}
+
+#[derive(Iden)]
+#[iden = "bundle_files"]
+enum BundleFileIden {
+    Table,
+    Kind,
+    Path,
+    Size,
+    Sha256,
+    Data,
+}
+
+/// Creates the tables of its own in a new bundle.
+pub async fn create_tables<'e, E>(executor: E) -> Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::raw_sql(
+        "CREATE TABLE bundle_manifest (key TEXT PRIMARY KEY NOT NULL, value TEXT NOT NULL);
+         CREATE TABLE bundle_files (
+             kind TEXT NOT NULL,
+             path TEXT NOT NULL,
+             size INTEGER NOT NULL,
+             sha256 TEXT NOT NULL,
+             data BLOB NOT NULL,
+             PRIMARY KEY (kind, path)
+         );",
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Sets an entry of the manifest, replacing its value.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor of the bundle.
+/// * `key` - The name of the entry.
+/// * `value` - The value of the entry.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn set_manifest<'e, E>(executor: E, key: &str, value: &str) -> Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let (sql, values) = Query::insert()
+        .into_table(BundleManifestIden::Table)
+        .columns([BundleManifestIden::Key, BundleManifestIden::Value])
+        .values([key.into(), value.into()])?
+        .on_conflict(
+            OnConflict::column(BundleManifestIden::Key)
+                .update_column(BundleManifestIden::Value)
+                .to_owned(),
+        )
+        .build_sqlx(SqliteQueryBuilder);
+    sqlx::query_with(AssertSqlSafe(sql), values)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Reads all entries of the manifest.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor of the bundle.
+///
+/// # Returns
+///
+/// A `Result` containing the entries by name.
+pub async fn get_manifest<'e, E>(executor: E) -> Result<HashMap<String, String>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let (sql, values) = Query::select()
+        .columns([BundleManifestIden::Key, BundleManifestIden::Value])
+        .from(BundleManifestIden::Table)
+        .build_sqlx(SqliteQueryBuilder);
+    let rows = sqlx::query_as_with::<Sqlite, (String, String), _>(AssertSqlSafe(sql), values)
+        .fetch_all(executor)
+        .await?;
+    Ok(rows.into_iter().collect())
+}
+
+/// Stores a media file in the bundle, replacing a file with the same path.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor of the bundle.
+/// * `kind` - `picture` or `video`.
+/// * `path` - The path of the file, relative to the media directory.
+/// * `sha256` - The SHA-256 checksum of the file, as lowercase hex.
+/// * `data` - The content of the file.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn insert_file<'e, E>(
+    executor: E,
+    kind: &str,
+    path: &str,
+    sha256: &str,
+    data: &[u8],
+) -> Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let (sql, values) = Query::insert()
+        .replace()
+        .into_table(BundleFileIden::Table)
+        .columns([
+            BundleFileIden::Kind,
+            BundleFileIden::Path,
+            BundleFileIden::Size,
+            BundleFileIden::Sha256,
+            BundleFileIden::Data,
+        ])
+        .values([
+            kind.into(),
+            path.into(),
+            (data.len() as i64).into(),
+            sha256.into(),
+            data.to_vec().into(),
+        ])?
+        .build_sqlx(SqliteQueryBuilder);
+    sqlx::query_with(AssertSqlSafe(sql), values)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Reads a media file of the bundle.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor of the bundle.
+/// * `kind` - `picture` or `video`.
+/// * `path` - The path of the file, relative to the media directory.
+///
+/// # Returns
+///
+/// A `Result` containing the checksum recorded for the file and its content, if it is
+/// in the bundle.
+pub async fn get_file<'e, E>(
+    executor: E,
+    kind: &str,
+    path: &str,
+) -> Result<Option<(String, Vec<u8>)>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let (sql, values) = Query::select()
+        .columns([BundleFileIden::Sha256, BundleFileIden::Data])
+        .from(BundleFileIden::Table)
+        .and_where(Expr::col(BundleFileIden::Kind).eq(kind))
+        .and_where(Expr::col(BundleFileIden::Path).eq(path))
+        .build_sqlx(SqliteQueryBuilder);
+    Ok(
+        sqlx::query_as_with::<Sqlite, (String, Vec<u8>), _>(AssertSqlSafe(sql), values)
+            .fetch_optional(executor)
+            .await?,
+    )
+}
+
+/// Attaches a bundle to a connection of the archive. Must not be called in a transaction.
+pub async fn attach(conn: &mut SqliteConnection, path: &str) -> Result<()> {
+    sqlx::query(AssertSqlSafe(format!("ATTACH DATABASE ? AS {BUNDLE}")))
+        .bind(path)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Detaches the bundle from the connection.
+pub async fn detach(conn: &mut SqliteConnection) -> Result<()> {
+    sqlx::query(AssertSqlSafe(format!("DETACH DATABASE {BUNDLE}")))
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Copies the rows of `table` picked by `condition` from the archive into the bundle.
+async fn copy_rows(conn: &mut SqliteConnection, table: &str, condition: &str) -> Result<u64> {
+    let columns = get_columns(conn, table)
+        .await?
+        .into_iter()
+        .map(|(name, _)| format!("\"{name}\""))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Ok(sqlx::query(AssertSqlSafe(format!(
+        "INSERT OR IGNORE INTO {BUNDLE}.{table} ({columns}) SELECT {columns} \
+         FROM main.{table} WHERE {condition}"
+    )))
+    .execute(&mut *conn)
+    .await?
+    .rows_affected())
+}
+
+/// Copies posts from the archive into the attached bundle, with the posts they retweet,
+/// their authors and the rows belonging to them: raw JSON, mentions, topics, favorites,
+/// pictures, videos, the avatars of the authors and the short links in their text.
+///
+/// # Arguments
+///
+/// * `conn` - A connection of the archive with the bundle attached.
+/// * `ids` - The IDs of the posts.
+///
+/// # Returns
+///
+/// A `Result` containing the number of posts and users copied.
+pub async fn copy_posts(conn: &mut SqliteConnection, ids: &[i64]) -> Result<(u64, u64)> {
+    sqlx::query("CREATE TEMP TABLE bundle_posts (id INTEGER PRIMARY KEY)")
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("INSERT OR IGNORE INTO temp.bundle_posts (id) SELECT value FROM json_each(?)")
+        .bind(serde_json::to_string(ids)?)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query(
+        "INSERT OR IGNORE INTO temp.bundle_posts (id) SELECT retweeted_id FROM main.posts \
+         WHERE id IN (SELECT id FROM temp.bundle_posts) AND retweeted_id IS NOT NULL",
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let picked = "IN (SELECT id FROM temp.bundle_posts)";
+    let users = copy_rows(
+        conn,
+        "users",
+        &format!("id IN (SELECT uid FROM main.posts WHERE id {picked})"),
+    )
+    .await?;
+    let posts = copy_rows(conn, "posts", &format!("id {picked}")).await?;
+    copy_rows(conn, "posts_raw", &format!("id {picked}")).await?;
+    copy_rows(conn, "post_mentions", &format!("post_id {picked}")).await?;
+    copy_rows(conn, "post_topics", &format!("post_id {picked}")).await?;
+    copy_rows(conn, "favorited_posts", &format!("id {picked}")).await?;
+    copy_rows(
+        conn,
+        "picture",
+        &format!(
+            "post_id {picked} OR (post_id IS NULL AND user_id IN (SELECT id FROM {BUNDLE}.users))"
+        ),
+    )
+    .await?;
+    copy_rows(conn, "video", &format!("post_id {picked}")).await?;
+    copy_rows(
+        conn,
+        "links",
+        &format!(
+            "EXISTS (SELECT 1 FROM {BUNDLE}.posts AS p WHERE instr(p.text, links.short_url) > 0)"
+        ),
+    )
+    .await?;
+
+    sqlx::query("DROP TABLE temp.bundle_posts")
+        .execute(&mut *conn)
+        .await?;
+    Ok((posts, users))
+}
+
+/// Lists the media files of the picture and video rows in the attached bundle.
+///
+/// # Arguments
+///
+/// * `conn` - A connection with the bundle attached.
+///
+/// # Returns
+///
+/// A `Result` containing the picture files and the video files.
+pub async fn get_media_files(
+    conn: &mut SqliteConnection,
+) -> Result<(Vec<MediaFile>, Vec<MediaFile>)> {
+    let mut files = Vec::with_capacity(2);
+    for table in ["picture", "video"] {
+        let rows = sqlx::query_as::<Sqlite, (String, String, Option<i64>)>(AssertSqlSafe(format!(
+            "SELECT url, path, post_id FROM {BUNDLE}.{table} WHERE path IS NOT NULL"
+        )))
+        .fetch_all(&mut *conn)
+        .await?;
+        files.push(
+            rows.into_iter()
+                .map(|(url, path, post_id)| MediaFile { url, path, post_id })
+                .collect::<Vec<_>>(),
+        );
+    }
+    let videos = files.pop().unwrap_or_default();
+    let pictures = files.pop().unwrap_or_default();
+    Ok((pictures, videos))
+}
+
+#[cfg(test)]
+mod local_tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::storage::database::create_db_pool_with_url;
+
+    #[tokio::test]
+    async fn test_copy_posts_into_bundle() {
+        let dir = TempDir::new().unwrap();
+        let archive = create_db_pool_with_url(":memory:").await.unwrap();
+        let bundle_path = dir.path().join("test.weiback");
+        let bundle = create_db_pool_with_url(bundle_path.to_str().unwrap())
+            .await
+            .unwrap();
+        create_tables(&bundle).await.unwrap();
+        set_manifest(&bundle, "format", "1").await.unwrap();
+        insert_file(&bundle, "picture", "a/p1.jpg", "abc", b"data")
+            .await
+            .unwrap();
+
+        for statement in [
+            "INSERT INTO users (id, screen_name) VALUES (1, 'a'), (2, 'b'), (3, 'c')",
+            "INSERT INTO posts (id, uid, text, retweeted_id) VALUES \
+             (10, 1, 'retweet', 20), (20, 2, 'original', NULL), (30, 3, 'other', NULL)",
+            "INSERT INTO post_topics (post_id, topic) VALUES (10, 't'), (30, 'u')",
+            "INSERT INTO picture (id, url, path, post_id, user_id) VALUES \
+             ('p1', 'u1', 'a/p1.jpg', 20, NULL), ('p3', 'u3', 'a/p3.jpg', 30, NULL), \
+             (NULL, 'avatar1', 'avatar/1.jpg', NULL, 1)",
+        ] {
+            sqlx::query(statement).execute(&archive).await.unwrap();
+        }
+
+        let mut conn = archive.acquire().await.unwrap();
+        attach(&mut conn, bundle_path.to_str().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(copy_posts(&mut conn, &[10]).await.unwrap(), (2, 2));
+        let (pictures, videos) = get_media_files(&mut conn).await.unwrap();
+        detach(&mut conn).await.unwrap();
+
+        let mut paths = pictures.iter().map(|p| p.path.as_str()).collect::<Vec<_>>();
+        paths.sort();
+        assert_eq!(paths, ["a/p1.jpg", "avatar/1.jpg"]);
+        assert!(videos.is_empty());
+        let topics: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM post_topics")
+            .fetch_one(&bundle)
+            .await
+            .unwrap();
+        assert_eq!(topics, 1);
+
+        let manifest = get_manifest(&bundle).await.unwrap();
+        assert_eq!(manifest.get("format").map(String::as_str), Some("1"));
+        let (sha256, data) = get_file(&bundle, "picture", "a/p1.jpg")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (sha256.as_str(), data.as_slice()),
+            ("abc", b"data".as_slice())
+        );
+        assert!(
+            get_file(&bundle, "video", "a/p1.jpg")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
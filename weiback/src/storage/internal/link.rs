@@ -0,0 +1,270 @@
+//! This module provides functions for interacting with the `links` table in the database.
+//!
+//! It records where the short links (e.g. `https://t.cn/xxx`) found in post text lead, so
+//! the destinations are kept even after the shortener stops serving them.
+//!
+//! # Table Structure: `links`
+//!
+//! | Column        | Type      | Description                                          |
+//! |---------------|-----------|------------------------------------------------------|
+//! | `short_url`   | `TEXT`    | The short URL as found in post text. **Primary Key.** |
+//! | `final_url`   | `TEXT`    | The destination after following redirects.           |
+//! | `status_code` | `INTEGER` | The HTTP status code of the destination.             |
+//! | `title`       | `TEXT`    | The `<title>` of the destination page.               |
+//! | `error`       | `TEXT`    | Why the destination could not be reached.            |
+//! | `resolved_at` | `INTEGER` | When the link was resolved, as a Unix timestamp.     |
+
+use std::collections::HashSet;
+
+use futures::TryStreamExt;
+use sea_query::{Asterisk, Expr, ExprTrait, Iden, OnConflict, Query, SqliteQueryBuilder};
+use sea_query_sqlx::SqlxBinder;
+use sqlx::{Acquire, AssertSqlSafe, Executor, FromRow, Sqlite};
+
+use crate::error::Result;
+use crate::models::Link;
+use crate::utils::extract_short_urls;
+
+#[derive(Iden)]
+#[iden = "links"]
+enum LinkIden {
+    Table,
+    ShortUrl,
+    FinalUrl,
+    StatusCode,
+    Title,
+    Error,
+    ResolvedAt,
+}
+
+#[derive(Iden)]
+#[iden = "posts"]
+enum PostIden {
+    Table,
+    Text,
+}
+
+#[derive(Debug, Clone, FromRow)]
+struct LinkInternal {
+    short_url: String,
+    final_url: Option<String>,
+    status_code: Option<i64>,
+    title: Option<String>,
+    error: Option<String>,
+    resolved_at: i64,
+}
+
+impl From<LinkInternal> for Link {
+    fn from(link: LinkInternal) -> Self {
+        Link {
+            short_url: link.short_url,
+            final_url: link.final_url,
+            status_code: link.status_code.and_then(|c| u16::try_from(c).ok()),
+            title: link.title,
+            error: link.error,
+            resolved_at: link.resolved_at,
+        }
+    }
+}
+
+/// Saves the resolution of a short link, replacing any earlier one.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `link` - The resolved link.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn save_link<'e, E>(executor: E, link: &Link) -> Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let (sql, values) = Query::insert()
+        .into_table(LinkIden::Table)
+        .columns([
+            LinkIden::ShortUrl,
+            LinkIden::FinalUrl,
+            LinkIden::StatusCode,
+            LinkIden::Title,
+            LinkIden::Error,
+            LinkIden::ResolvedAt,
+        ])
+        .values([
+            link.short_url.as_str().into(),
+            link.final_url.clone().into(),
+            link.status_code.map(i64::from).into(),
+            link.title.clone().into(),
+            link.error.clone().into(),
+            link.resolved_at.into(),
+        ])?
+        .on_conflict(
+            OnConflict::column(LinkIden::ShortUrl)
+                .update_columns([
+                    LinkIden::FinalUrl,
+                    LinkIden::StatusCode,
+                    LinkIden::Title,
+                    LinkIden::Error,
+                    LinkIden::ResolvedAt,
+                ])
+                .to_owned(),
+        )
+        .build_sqlx(SqliteQueryBuilder);
+    sqlx::query_with(AssertSqlSafe(sql), values)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Retrieves the saved resolutions of the given short links.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `short_urls` - The short URLs to look up.
+///
+/// # Returns
+///
+/// A `Result` containing the links that have been resolved, in no particular order.
+pub async fn get_links<'e, E>(executor: E, short_urls: &[String]) -> Result<Vec<Link>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    if short_urls.is_empty() {
+        return Ok(Vec::new());
+    }
+    let (sql, values) = Query::select()
+        .column(Asterisk)
+        .from(LinkIden::Table)
+        .and_where(Expr::col(LinkIden::ShortUrl).is_in(short_urls.iter().map(String::as_str)))
+        .build_sqlx(SqliteQueryBuilder);
+    let links = sqlx::query_as_with::<Sqlite, LinkInternal, _>(AssertSqlSafe(sql), values)
+        .fetch_all(executor)
+        .await?;
+    Ok(links.into_iter().map(Into::into).collect())
+}
+
+/// Collects the short links in the text of all posts that still need resolving.
+///
+/// # Arguments
+///
+/// * `acquirer` - A database acquirer.
+/// * `retry_failed` - Also return links whose destination could not be reached before.
+///
+/// # Returns
+///
+/// A `Result` containing the short URLs, in order of first appearance.
+pub async fn get_unresolved_short_urls<'c, A>(
+    acquirer: A,
+    retry_failed: bool,
+) -> Result<Vec<String>>
+where
+    A: Acquire<'c, Database = Sqlite>,
+{
+    let mut conn = acquirer.acquire().await?;
+
+    let (sql, values) = Query::select()
+        .column(Asterisk)
+        .from(LinkIden::Table)
+        .build_sqlx(SqliteQueryBuilder);
+    let done = sqlx::query_as_with::<Sqlite, LinkInternal, _>(AssertSqlSafe(sql), values)
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(Link::from)
+        .filter(|link| !retry_failed || link.is_resolved())
+        .map(|link| link.short_url)
+        .collect::<HashSet<_>>();
+
+    let (sql, values) = Query::select()
+        .column(PostIden::Text)
+        .from(PostIden::Table)
+        .build_sqlx(SqliteQueryBuilder);
+    let mut texts =
+        sqlx::query_scalar_with::<Sqlite, String, _>(AssertSqlSafe(sql), values).fetch(&mut *conn);
+    let mut seen = HashSet::new();
+    let mut short_urls = Vec::new();
+    while let Some(text) = texts.try_next().await? {
+        for url in extract_short_urls(&text) {
+            if !done.contains(url) && seen.insert(url.to_string()) {
+                short_urls.push(url.to_string());
+            }
+        }
+    }
+    Ok(short_urls)
+}
+
+#[cfg(test)]
+mod local_tests {
+    use sqlx::SqlitePool;
+
+    use super::*;
+    use crate::storage::database::create_db_pool_with_url;
+
+    async fn setup_db() -> SqlitePool {
+        create_db_pool_with_url(":memory:").await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_save_and_get_links() {
+        let db = setup_db().await;
+        for (id, text) in [
+            (1, "看 http://t.cn/A1 和 https://example.com/page"),
+            (2, "又是 http://t.cn/A1 还有 http://t.cn/B2 http://t.cn/C3"),
+        ] {
+            sqlx::query("INSERT INTO posts (id, uid, text, created_at) VALUES (?, 1, ?, '')")
+                .bind(id)
+                .bind(text)
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            get_unresolved_short_urls(&db, false).await.unwrap(),
+            ["http://t.cn/A1", "http://t.cn/B2", "http://t.cn/C3"]
+        );
+
+        let resolved = Link {
+            short_url: "http://t.cn/A1".to_string(),
+            final_url: Some("https://example.com/a".to_string()),
+            status_code: Some(200),
+            title: Some("A".to_string()),
+            resolved_at: 1_700_000_000,
+            ..Default::default()
+        };
+        let failed = Link {
+            short_url: "http://t.cn/B2".to_string(),
+            error: Some("timed out".to_string()),
+            resolved_at: 1_700_000_000,
+            ..Default::default()
+        };
+        save_link(&db, &resolved).await.unwrap();
+        save_link(&db, &failed).await.unwrap();
+        assert_eq!(
+            get_unresolved_short_urls(&db, false).await.unwrap(),
+            ["http://t.cn/C3"]
+        );
+        assert_eq!(
+            get_unresolved_short_urls(&db, true).await.unwrap(),
+            ["http://t.cn/B2", "http://t.cn/C3"]
+        );
+
+        let updated = Link {
+            final_url: Some("https://example.com/b".to_string()),
+            status_code: Some(404),
+            error: None,
+            ..failed
+        };
+        save_link(&db, &updated).await.unwrap();
+        let mut links = get_links(
+            &db,
+            &["http://t.cn/A1".to_string(), "http://t.cn/B2".to_string()],
+        )
+        .await
+        .unwrap();
+        links.sort_by(|a, b| a.short_url.cmp(&b.short_url));
+        assert_eq!(links, [resolved, updated]);
+        assert!(get_links(&db, &[]).await.unwrap().is_empty());
+    }
+}
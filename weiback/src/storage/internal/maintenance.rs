@@ -0,0 +1,202 @@
+//! This module provides the statements behind the maintenance of an archive's database.
+//!
+//! None of them reads or writes a table of its own: they reclaim free pages, refresh
+//! the statistics of the query planner, merge the segments of the `posts_fts`
+//! full-text index and check the database file for corruption. When the database was
+//! last maintained is kept in the `archive_settings` table.
+
+use sqlx::{Executor, Sqlite};
+
+use super::archive_setting;
+use crate::error::Result;
+
+/// The `archive_settings` key holding when the database was last maintained.
+const LAST_MAINTENANCE_KEY: &str = "last_maintenance";
+
+/// Retrieves when the database was last maintained.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+///
+/// # Returns
+///
+/// A `Result` containing the Unix timestamp of the last run, or `None` if it never ran.
+pub async fn get_last_run<'e, E>(executor: E) -> Result<Option<i64>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let value = archive_setting::get_setting(executor, LAST_MAINTENANCE_KEY).await?;
+    Ok(value.and_then(|v| v.parse().ok()))
+}
+
+/// Records when the database was last maintained.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `at` - The Unix timestamp of the run.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn set_last_run<'e, E>(executor: E, at: i64) -> Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    archive_setting::set_setting(executor, LAST_MAINTENANCE_KEY, Some(&at.to_string())).await
+}
+
+/// The size of the database file, in bytes, as counted by SQLite.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+///
+/// # Returns
+///
+/// A `Result` containing the number of pages times the page size.
+pub async fn database_size<'e, E>(executor: E) -> Result<u64>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let size: i64 = sqlx::query_scalar(
+        "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok(size as u64)
+}
+
+/// Rebuilds the database file, reclaiming the free pages. Must not be called in a
+/// transaction.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn vacuum<'e, E>(executor: E) -> Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query("VACUUM").execute(executor).await?;
+    Ok(())
+}
+
+/// Gathers the statistics of the tables and indexes for the query planner.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn analyze<'e, E>(executor: E) -> Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query("ANALYZE").execute(executor).await?;
+    Ok(())
+}
+
+/// Lets SQLite run the optimizations it deems worthwhile, see `PRAGMA optimize`.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn optimize<'e, E>(executor: E) -> Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query("PRAGMA optimize").execute(executor).await?;
+    Ok(())
+}
+
+/// Merges the segments of the `posts_fts` full-text index into one, which the many
+/// small writes of backups leave fragmented.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn optimize_fts<'e, E>(executor: E) -> Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query("INSERT INTO posts_fts(posts_fts) VALUES('optimize')")
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Checks the whole database file for corruption.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+///
+/// # Returns
+///
+/// A `Result` containing the problems found, empty if the database is intact.
+pub async fn integrity_check<'e, E>(executor: E) -> Result<Vec<String>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let mut problems: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_all(executor)
+        .await?;
+    problems.retain(|p| p != "ok");
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod local_tests {
+    use sqlx::SqlitePool;
+
+    use super::*;
+    use crate::storage::database::create_db_pool_with_url;
+
+    async fn setup_db() -> SqlitePool {
+        create_db_pool_with_url(":memory:").await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_statements() {
+        let db = setup_db().await;
+        for id in 1..=50 {
+            sqlx::query("INSERT INTO posts (id, uid, text) VALUES (?, 1, ?)")
+                .bind(id)
+                .bind("微博正文".repeat(100))
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+        sqlx::query("DELETE FROM posts").execute(&db).await.unwrap();
+        let before = database_size(&db).await.unwrap();
+        assert!(before > 0);
+
+        assert!(integrity_check(&db).await.unwrap().is_empty());
+        analyze(&db).await.unwrap();
+        optimize(&db).await.unwrap();
+        optimize_fts(&db).await.unwrap();
+        vacuum(&db).await.unwrap();
+
+        assert!(database_size(&db).await.unwrap() < before);
+        assert!(integrity_check(&db).await.unwrap().is_empty());
+
+        assert!(get_last_run(&db).await.unwrap().is_none());
+        set_last_run(&db, 1_700_000_000).await.unwrap();
+        assert_eq!(get_last_run(&db).await.unwrap(), Some(1_700_000_000));
+    }
+}
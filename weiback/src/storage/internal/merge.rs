@@ -0,0 +1,532 @@
+//! This module provides functions for merging the tables of another archive's database
+//! into the current one.
+//!
+//! The other database is attached to a connection under the [`INCOMING`] schema, with
+//! the same migrations applied, so both share their columns. The merge functions are
+//! meant to run in one transaction on that connection; see [`crate::merge`] for what
+//! each table keeps.
+
+use std::path::Path;
+
+use itertools::Itertools;
+use sqlx::{AssertSqlSafe, Sqlite, SqliteConnection};
+
+use super::quarantine::get_columns;
+use crate::error::Result;
+use crate::merge::{MergeConflict, MergeSide};
+use crate::utils::is_safe_relative_path;
+use crate::verify::MediaFile;
+
+/// The schema name the other database is attached as.
+pub const INCOMING: &str = "incoming";
+
+/// Attaches the other database to the connection. Must not be called in a transaction.
+pub async fn attach(conn: &mut SqliteConnection, path: &str) -> Result<()> {
+    sqlx::query(AssertSqlSafe(format!("ATTACH DATABASE ? AS {INCOMING}")))
+        .bind(path)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Detaches the other database from the connection.
+pub async fn detach(conn: &mut SqliteConnection) -> Result<()> {
+    sqlx::query(AssertSqlSafe(format!("DETACH DATABASE {INCOMING}")))
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// The quoted column list of a table.
+async fn column_list(conn: &mut SqliteConnection, table: &str) -> Result<String> {
+    Ok(get_columns(conn, table)
+        .await?
+        .into_iter()
+        .map(|(name, _)| format!("\"{name}\""))
+        .join(", "))
+}
+
+/// Adds the users missing locally, and updates the known users the other archive saw
+/// more recently.
+///
+/// # Arguments
+///
+/// * `conn` - A connection with the other database attached.
+///
+/// # Returns
+///
+/// A `Result` containing the number of users added and updated, and the users whose
+/// screen name differs.
+pub async fn merge_users(conn: &mut SqliteConnection) -> Result<(u64, u64, Vec<MergeConflict>)> {
+    let columns = column_list(conn, "users").await?;
+
+    let conflicts = sqlx::query_as::<Sqlite, (i64, bool)>(AssertSqlSafe(format!(
+        "SELECT i.id, COALESCE(i.statuses_count_at, -1) > COALESCE(m.statuses_count_at, -1) \
+         FROM {INCOMING}.users AS i JOIN main.users AS m ON m.id = i.id \
+         WHERE i.screen_name IS NOT m.screen_name ORDER BY i.id"
+    )))
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|(id, incoming)| MergeConflict {
+        table: "users".to_string(),
+        id,
+        url: None,
+        kept: if incoming {
+            MergeSide::Incoming
+        } else {
+            MergeSide::Local
+        },
+    })
+    .collect();
+
+    let updated = sqlx::query(AssertSqlSafe(format!(
+        "UPDATE main.users SET ({columns}) = \
+         (SELECT {columns} FROM {INCOMING}.users AS i WHERE i.id = users.id) \
+         WHERE id IN (SELECT i.id FROM {INCOMING}.users AS i JOIN main.users AS m ON m.id = i.id \
+         WHERE COALESCE(i.statuses_count_at, -1) > COALESCE(m.statuses_count_at, -1))"
+    )))
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+
+    let added = sqlx::query(AssertSqlSafe(format!(
+        "INSERT INTO main.users ({columns}) SELECT {columns} FROM {INCOMING}.users \
+         WHERE id NOT IN (SELECT id FROM main.users)"
+    )))
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+
+    Ok((added, updated, conflicts))
+}
+
+/// Ranks how complete a version of a post is, as a row value: a valid post first, then
+/// one not deleted, edited more times, with its raw JSON, and with a longer text.
+fn post_rank(alias: &str, schema: &str) -> String {
+    format!(
+        "({alias}.uid IS NOT NULL, COALESCE({alias}.deleted, 0) = 0, \
+         COALESCE({alias}.edit_count, 0), \
+         EXISTS (SELECT 1 FROM {schema}.posts_raw AS r WHERE r.id = {alias}.id), \
+         length(COALESCE({alias}.text, '')))"
+    )
+}
+
+/// Adds the posts missing locally, and replaces the known posts whose version in the
+/// other archive is more complete. The raw JSON, mentions and topics of the taken posts
+/// come with them, except that a replaced post keeps its local raw JSON when the other
+/// archive has none.
+///
+/// # Arguments
+///
+/// * `conn` - A connection with the other database attached.
+///
+/// # Returns
+///
+/// A `Result` containing the number of posts added and replaced, and the posts stored
+/// differently in both archives.
+pub async fn merge_posts(conn: &mut SqliteConnection) -> Result<(u64, u64, Vec<MergeConflict>)> {
+    let columns = column_list(conn, "posts").await?;
+
+    sqlx::query(AssertSqlSafe(format!(
+        "CREATE TEMP TABLE merge_posts AS \
+         SELECT i.id AS id, m.id IS NOT NULL AS existing, \
+         m.id IS NULL OR {incoming_rank} > {local_rank} AS take, \
+         m.id IS NOT NULL AND (i.text IS NOT m.text OR i.uid IS NOT m.uid \
+         OR i.deleted IS NOT m.deleted OR i.edit_count IS NOT m.edit_count) AS conflict \
+         FROM {INCOMING}.posts AS i LEFT JOIN main.posts AS m ON m.id = i.id",
+        incoming_rank = post_rank("i", INCOMING),
+        local_rank = post_rank("m", "main"),
+    )))
+    .execute(&mut *conn)
+    .await?;
+
+    let conflicts = sqlx::query_as::<Sqlite, (i64, bool)>(
+        "SELECT id, take FROM temp.merge_posts WHERE conflict ORDER BY id",
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|(id, take)| MergeConflict {
+        table: "posts".to_string(),
+        id,
+        url: None,
+        kept: if take {
+            MergeSide::Incoming
+        } else {
+            MergeSide::Local
+        },
+    })
+    .collect();
+
+    // The delete triggers drop the raw JSON, mentions, topics and index entries too, so
+    // the raw JSON missing from the other archive is set aside to be put back
+    let raw_columns = column_list(conn, "posts_raw").await?;
+    sqlx::query(AssertSqlSafe(format!(
+        "CREATE TEMP TABLE merge_posts_raw AS SELECT {raw_columns} FROM main.posts_raw \
+         WHERE id IN (SELECT id FROM temp.merge_posts WHERE existing AND take) \
+         AND id NOT IN (SELECT id FROM {INCOMING}.posts_raw)"
+    )))
+    .execute(&mut *conn)
+    .await?;
+    let replaced = sqlx::query(
+        "DELETE FROM main.posts WHERE id IN (SELECT id FROM temp.merge_posts WHERE existing AND take)",
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+    let taken = sqlx::query(AssertSqlSafe(format!(
+        "INSERT INTO main.posts ({columns}) SELECT {columns} FROM {INCOMING}.posts \
+         WHERE id IN (SELECT id FROM temp.merge_posts WHERE take)"
+    )))
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+    for (table, key) in [
+        ("posts_raw", "id"),
+        ("post_mentions", "post_id"),
+        ("post_topics", "post_id"),
+    ] {
+        let columns = column_list(conn, table).await?;
+        sqlx::query(AssertSqlSafe(format!(
+            "INSERT OR IGNORE INTO main.{table} ({columns}) SELECT {columns} \
+             FROM {INCOMING}.{table} WHERE {key} IN (SELECT id FROM temp.merge_posts WHERE take)"
+        )))
+        .execute(&mut *conn)
+        .await?;
+    }
+    sqlx::query(AssertSqlSafe(format!(
+        "INSERT OR IGNORE INTO main.posts_raw ({raw_columns}) \
+         SELECT {raw_columns} FROM temp.merge_posts_raw"
+    )))
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query("DROP TABLE temp.merge_posts_raw")
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("DROP TABLE temp.merge_posts")
+        .execute(&mut *conn)
+        .await?;
+    Ok((taken - replaced, replaced, conflicts))
+}
+
+/// Adds the favorites missing locally.
+///
+/// # Returns
+///
+/// A `Result` containing the number of favorites added.
+pub async fn merge_favorites(conn: &mut SqliteConnection) -> Result<u64> {
+    Ok(sqlx::query(AssertSqlSafe(format!(
+        "INSERT OR IGNORE INTO main.favorited_posts (id, unfavorited) \
+         SELECT id, unfavorited FROM {INCOMING}.favorited_posts"
+    )))
+    .execute(&mut *conn)
+    .await?
+    .rows_affected())
+}
+
+/// Adds the short links missing locally, and updates those resolved more recently in
+/// the other archive.
+///
+/// # Returns
+///
+/// A `Result` containing the number of links added or updated.
+pub async fn merge_links(conn: &mut SqliteConnection) -> Result<u64> {
+    let columns = get_columns(conn, "links").await?;
+    let names = columns
+        .iter()
+        .map(|(name, _)| format!("\"{name}\""))
+        .join(", ");
+    let updates = columns
+        .iter()
+        .filter(|(name, _)| name != "short_url")
+        .map(|(name, _)| format!("\"{name}\" = excluded.\"{name}\""))
+        .join(", ");
+    // `WHERE true` keeps the upsert from being parsed as a join
+    Ok(sqlx::query(AssertSqlSafe(format!(
+        "INSERT INTO main.links ({names}) SELECT {names} FROM {INCOMING}.links WHERE true \
+         ON CONFLICT (short_url) DO UPDATE SET {updates} \
+         WHERE excluded.resolved_at > links.resolved_at"
+    )))
+    .execute(&mut *conn)
+    .await?
+    .rows_affected())
+}
+
+/// Adds the pictures missing locally. A picture already stored from another
+/// `sinaimg.cn` mirror host, with the same ID and definition, is not added again.
+///
+/// # Returns
+///
+/// A `Result` containing the files of the added rows, and the rows left out because
+/// their path would leave the picture directory.
+pub async fn merge_pictures(
+    conn: &mut SqliteConnection,
+) -> Result<(Vec<MediaFile>, Vec<MergeConflict>)> {
+    let condition = "url NOT IN (SELECT url FROM main.picture) AND NOT EXISTS \
+                     (SELECT 1 FROM main.picture AS m WHERE m.id = i.id \
+                     AND m.definition IS i.definition)";
+    merge_media(conn, "picture", condition).await
+}
+
+/// Adds the videos missing locally.
+///
+/// # Returns
+///
+/// A `Result` containing the files of the added rows, and the rows left out because
+/// their path would leave the video directory.
+pub async fn merge_videos(
+    conn: &mut SqliteConnection,
+) -> Result<(Vec<MediaFile>, Vec<MergeConflict>)> {
+    merge_media(conn, "video", "url NOT IN (SELECT url FROM main.video)").await
+}
+
+/// Adds the media rows matching the condition, except those whose path is not a plain
+/// relative path, which are reported as conflicts instead: their files would be
+/// read from and written to outside the media directories.
+async fn merge_media(
+    conn: &mut SqliteConnection,
+    table: &str,
+    condition: &str,
+) -> Result<(Vec<MediaFile>, Vec<MergeConflict>)> {
+    let columns = column_list(conn, table).await?;
+    let rows = sqlx::query_as::<Sqlite, (String, Option<String>, Option<i64>)>(AssertSqlSafe(
+        format!("SELECT url, path, post_id FROM {INCOMING}.{table} AS i WHERE {condition}"),
+    ))
+    .fetch_all(&mut *conn)
+    .await?;
+    let (files, rejected): (Vec<_>, Vec<_>) = rows.into_iter().partition(|(_, path, _)| {
+        path.as_deref()
+            .is_none_or(|path| is_safe_relative_path(Path::new(path)))
+    });
+
+    let placeholders = rejected.iter().map(|_| "?").join(", ");
+    let mut insert = sqlx::query(AssertSqlSafe(format!(
+        "INSERT INTO main.{table} ({columns}) SELECT {columns} FROM {INCOMING}.{table} AS i \
+         WHERE {condition} AND url NOT IN ({placeholders})"
+    )));
+    for (url, _, _) in &rejected {
+        insert = insert.bind(url);
+    }
+    insert.execute(&mut *conn).await?;
+
+    let conflicts = rejected
+        .into_iter()
+        .map(|(url, _, post_id)| MergeConflict {
+            table: table.to_string(),
+            id: post_id.unwrap_or_default(),
+            url: Some(url),
+            kept: MergeSide::Local,
+        })
+        .collect();
+    let files = files
+        .into_iter()
+        .filter_map(|(url, path, post_id)| {
+            Some(MediaFile {
+                url,
+                path: path?,
+                post_id,
+            })
+        })
+        .collect();
+    Ok((files, conflicts))
+}
+
+#[cfg(test)]
+mod local_tests {
+    use sqlx::{Acquire, SqlitePool};
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::storage::database::create_db_pool_with_url;
+
+    async fn setup_dbs() -> (SqlitePool, SqlitePool, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let local = create_db_pool_with_url(dir.path().join("local.db").to_str().unwrap())
+            .await
+            .unwrap();
+        let incoming = create_db_pool_with_url(dir.path().join("incoming.db").to_str().unwrap())
+            .await
+            .unwrap();
+        (local, incoming, dir)
+    }
+
+    #[tokio::test]
+    async fn test_merge_users_and_posts() {
+        let (local, incoming, dir) = setup_dbs().await;
+        for (db, statements) in [
+            (
+                &local,
+                [
+                    "INSERT INTO users (id, screen_name, statuses_count_at) VALUES (1, 'old', 10)",
+                    "INSERT INTO posts (id, uid, text) VALUES (100, 1, 'short'), (101, 1, 'kept')",
+                    "INSERT INTO picture (id, definition, url, path, post_id) VALUES \
+                     ('p1', 'Large', 'https://wx1.sinaimg.cn/large/p1.jpg', 'a/p1.jpg', 100)",
+                ],
+            ),
+            (
+                &incoming,
+                [
+                    "INSERT INTO users (id, screen_name, statuses_count_at) VALUES (1, 'new', 20), (2, 'other', NULL)",
+                    "INSERT INTO posts (id, uid, text, edit_count) VALUES (100, 1, 'short', 1), \
+                     (101, NULL, NULL, NULL), (102, 2, 'hello', NULL)",
+                    "INSERT INTO picture (id, definition, url, path, post_id) VALUES \
+                     ('p1', 'Large', 'https://wx2.sinaimg.cn/large/p1.jpg', 'a/p1.jpg', 100), \
+                     ('p2', 'Large', 'https://wx2.sinaimg.cn/large/p2.jpg', 'a/p2.jpg', 102)",
+                ],
+            ),
+        ] {
+            for statement in statements {
+                sqlx::query(statement).execute(db).await.unwrap();
+            }
+        }
+        sqlx::query("INSERT INTO post_topics (post_id, topic) VALUES (102, 'topic')")
+            .execute(&incoming)
+            .await
+            .unwrap();
+        incoming.close().await;
+
+        let mut conn = local.acquire().await.unwrap();
+        attach(&mut conn, dir.path().join("incoming.db").to_str().unwrap())
+            .await
+            .unwrap();
+        let mut tx = conn.begin().await.unwrap();
+
+        let (added, updated, conflicts) = merge_users(&mut tx).await.unwrap();
+        assert_eq!((added, updated), (1, 1));
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].kept, MergeSide::Incoming);
+
+        let (added, replaced, conflicts) = merge_posts(&mut tx).await.unwrap();
+        assert_eq!((added, replaced), (1, 1));
+        let kept = conflicts.iter().map(|c| (c.id, c.kept)).collect::<Vec<_>>();
+        assert_eq!(kept, [(100, MergeSide::Incoming), (101, MergeSide::Local)]);
+
+        let (pictures, rejected) = merge_pictures(&mut tx).await.unwrap();
+        assert!(rejected.is_empty());
+        assert_eq!(pictures.len(), 1);
+        assert_eq!(pictures[0].path, "a/p2.jpg");
+
+        tx.commit().await.unwrap();
+        detach(&mut conn).await.unwrap();
+
+        let text: String = sqlx::query_scalar("SELECT text FROM posts WHERE id = 101")
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(text, "kept");
+        let edit_count: i64 = sqlx::query_scalar("SELECT edit_count FROM posts WHERE id = 100")
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(edit_count, 1);
+        let screen_name: String = sqlx::query_scalar("SELECT screen_name FROM users WHERE id = 1")
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(screen_name, "new");
+        let topics: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM post_topics WHERE post_id = 102")
+                .fetch_one(&mut *conn)
+                .await
+                .unwrap();
+        assert_eq!(topics, 1);
+        let indexed: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM posts_fts WHERE rowid = 102")
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(indexed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_merge_posts_keeps_local_raw() {
+        let (local, incoming, dir) = setup_dbs().await;
+        for statement in [
+            "INSERT INTO posts (id, uid, text, deleted) VALUES (100, 1, 'a', 1), (101, 1, 'b', 1)",
+            "INSERT INTO posts_raw (id, raw) VALUES (100, 'local 100'), (101, 'local 101')",
+        ] {
+            sqlx::query(statement).execute(&local).await.unwrap();
+        }
+        for statement in [
+            "INSERT INTO posts (id, uid, text, deleted) VALUES (100, 1, 'a', 0), (101, 1, 'b', 0)",
+            "INSERT INTO posts_raw (id, raw) VALUES (101, 'incoming 101')",
+        ] {
+            sqlx::query(statement).execute(&incoming).await.unwrap();
+        }
+        incoming.close().await;
+
+        let mut conn = local.acquire().await.unwrap();
+        attach(&mut conn, dir.path().join("incoming.db").to_str().unwrap())
+            .await
+            .unwrap();
+        let mut tx = conn.begin().await.unwrap();
+        let (added, replaced, _) = merge_posts(&mut tx).await.unwrap();
+        assert_eq!((added, replaced), (0, 2));
+        tx.commit().await.unwrap();
+        detach(&mut conn).await.unwrap();
+
+        let raws = sqlx::query_as::<Sqlite, (i64, String)>(
+            "SELECT id, CAST(raw AS TEXT) FROM posts_raw ORDER BY id",
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .unwrap();
+        assert_eq!(
+            raws,
+            [
+                (100, "local 100".to_string()),
+                (101, "incoming 101".to_string())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_media_rejects_unsafe_paths() {
+        let (local, incoming, dir) = setup_dbs().await;
+        for statement in [
+            "INSERT INTO picture (id, definition, url, path, post_id) VALUES \
+             ('p1', 'Large', 'https://wx1.sinaimg.cn/large/p1.jpg', 'a/p1.jpg', 1), \
+             ('p2', 'Large', 'https://wx1.sinaimg.cn/large/p2.jpg', '../../p2.jpg', 1)",
+            "INSERT INTO video (url, path, post_id) VALUES \
+             ('https://v/1.mp4', '/tmp/1.mp4', 2), ('https://v/2.mp4', NULL, 2)",
+        ] {
+            sqlx::query(statement).execute(&incoming).await.unwrap();
+        }
+        incoming.close().await;
+
+        let mut conn = local.acquire().await.unwrap();
+        attach(&mut conn, dir.path().join("incoming.db").to_str().unwrap())
+            .await
+            .unwrap();
+        let mut tx = conn.begin().await.unwrap();
+        let (pictures, rejected) = merge_pictures(&mut tx).await.unwrap();
+        assert_eq!(pictures.len(), 1);
+        assert_eq!(pictures[0].path, "a/p1.jpg");
+        assert_eq!(
+            rejected,
+            [MergeConflict {
+                table: "picture".to_string(),
+                id: 1,
+                url: Some("https://wx1.sinaimg.cn/large/p2.jpg".to_string()),
+                kept: MergeSide::Local,
+            }]
+        );
+        let (videos, rejected) = merge_videos(&mut tx).await.unwrap();
+        assert!(videos.is_empty());
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].url.as_deref(), Some("https://v/1.mp4"));
+        tx.commit().await.unwrap();
+        detach(&mut conn).await.unwrap();
+
+        let urls: Vec<String> =
+            sqlx::query_scalar("SELECT url FROM picture UNION ALL SELECT url FROM video")
+                .fetch_all(&mut *conn)
+                .await
+                .unwrap();
+        assert_eq!(
+            urls,
+            ["https://wx1.sinaimg.cn/large/p1.jpg", "https://v/2.mp4"]
+        );
+    }
+}
@@ -17,10 +17,17 @@
 //! The `url` column serves as the primary key for uniqueness.
 //! The `id` column is derived from the URL and is used for grouping related pictures
 //! (e.g., different definitions of the same image from a post).
+//!
+//! Pictures hosted on `sinaimg.cn` can be served by several mirrors (`wx1`, `wx3`...).
+//! The original URL is always stored, while [`find_picture_path`] falls back to a
+//! host-agnostic lookup on the picture `id` and the path of the URL, so that a picture
+//! downloaded from one mirror is found when queried through another.
 
 use std::path::PathBuf;
 
-use sea_query::{Asterisk, Expr, ExprTrait, Func, OnConflict, Query, SqliteQueryBuilder};
+use sea_query::{
+    Asterisk, Expr, ExprTrait, Func, LikeExpr, OnConflict, Order, Query, SqliteQueryBuilder,
+};
 use sea_query_sqlx::SqlxBinder;
 use sqlx::{AssertSqlSafe, Executor, Sqlite};
 use url::Url;
@@ -28,7 +35,7 @@ use url::Url;
 use crate::error::{Error, Result};
 use crate::models::{PictureDefinition, PictureMeta};
 use crate::storage::PictureInfo;
-use crate::utils::{pic_url_to_db_key, pic_url_to_id};
+use crate::utils::{pic_url_to_db_key, pic_url_to_id, sinaimg_url_to_pic_key};
 
 #[derive(sqlx::FromRow, Debug)]
 struct PictureDbRecord {
@@ -196,6 +203,53 @@ where
     Ok(raw_res.map(PathBuf::from))
 }
 
+/// Retrieves the local path of a picture, falling back across `sinaimg.cn` mirrors.
+///
+/// An exact match on the URL is preferred. Otherwise, a stored picture with the same
+/// picture ID, served from another mirror host under the same path, is returned. The
+/// path holds the definition of the file as its first segment, e.g. `/large/<pic_id>.jpg`,
+/// so only the host differs. Both are matched in one query on the `id` index.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `url` - The URL of the picture to retrieve the path for.
+///
+/// # Returns
+///
+/// A `Result` containing an `Option<PathBuf>`. `Some(PathBuf)` if the picture is found, `None` otherwise.
+pub async fn find_picture_path<'e, E>(executor: E, url: &Url) -> Result<Option<PathBuf>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let key = pic_url_to_db_key(url);
+    let exact = Expr::col(PictureIden::Url).eq(key.as_str());
+    let mut cond = exact.clone();
+    if let Some((id, _)) = sinaimg_url_to_pic_key(&key) {
+        let path = key
+            .path()
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        let mirror = LikeExpr::new(format!("%.sinaimg.cn{path}")).escape('\\');
+        cond = cond.or(Expr::col(PictureIden::Id)
+            .eq(id)
+            .and(Expr::col(PictureIden::Url).like(mirror)));
+    }
+    let (sql, values) = Query::select()
+        .column(PictureIden::Path)
+        .from(PictureIden::Table)
+        .and_where(cond)
+        .and_where(Expr::col(PictureIden::Path).is_not_null())
+        .order_by_expr(exact, Order::Desc)
+        .limit(1)
+        .build_sqlx(SqliteQueryBuilder);
+    let path = sqlx::query_scalar_with::<_, String, _>(AssertSqlSafe(sql), values)
+        .fetch_optional(executor)
+        .await?;
+    Ok(path.map(PathBuf::from))
+}
+
 /// Retrieves a list of user IDs who have more than one avatar entry in the database.
 ///
 /// # Arguments
@@ -431,6 +485,33 @@ where
     Ok(())
 }
 
+/// Deletes the picture entries with the given URLs from the database.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `urls` - A slice of picture URLs.
+///
+/// # Returns
+///
+/// A `Result` containing the number of deleted entries.
+pub async fn delete_pictures_by_urls<'e, E>(executor: E, urls: &[String]) -> Result<u64>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    if urls.is_empty() {
+        return Ok(0);
+    }
+    let (sql, values) = Query::delete()
+        .from_table(PictureIden::Table)
+        .and_where(Expr::col(PictureIden::Url).is_in(urls.iter().cloned()))
+        .build_sqlx(SqliteQueryBuilder);
+    let result = sqlx::query_with(AssertSqlSafe(sql), values)
+        .execute(executor)
+        .await?;
+    Ok(result.rows_affected())
+}
+
 /// Retrieves a list of picture IDs that have more than one entry in the database.
 ///
 /// This indicates multiple pictures (with different URLs) share the same logical ID (e.g., filename without extension).
@@ -583,6 +664,37 @@ mod local_tests {
         assert_eq!(retrieved_path, Some(PathBuf::from(path2)));
     }
 
+    #[tokio::test]
+    async fn test_find_picture_path_across_mirrors() {
+        let db = setup_db().await;
+        let wx1 = Url::parse("https://wx1.sinaimg.cn/large/abc123.jpg").unwrap();
+        let meta = PictureMeta::attached(wx1.as_str(), 1, PictureDefinition::Largest).unwrap();
+        save_picture_meta(&db, &meta, Some("wx1.sinaimg.cn/large/abc123.jpg"))
+            .await
+            .unwrap();
+
+        let wx3 = Url::parse("https://wx3.sinaimg.cn/large/abc123.jpg").unwrap();
+        assert!(get_picture_path(&db, &wx3).await.unwrap().is_none());
+        assert_eq!(
+            find_picture_path(&db, &wx3).await.unwrap(),
+            Some(PathBuf::from("wx1.sinaimg.cn/large/abc123.jpg"))
+        );
+
+        // A different definition of the same picture is not a match
+        let orj = Url::parse("https://wx3.sinaimg.cn/orj360/abc123.jpg").unwrap();
+        assert!(find_picture_path(&db, &orj).await.unwrap().is_none());
+
+        // Exact matches are preferred over mirrors
+        let meta3 = PictureMeta::attached(wx3.as_str(), 1, PictureDefinition::Largest).unwrap();
+        save_picture_meta(&db, &meta3, Some("wx3.sinaimg.cn/large/abc123.jpg"))
+            .await
+            .unwrap();
+        assert_eq!(
+            find_picture_path(&db, &wx3).await.unwrap(),
+            Some(PathBuf::from("wx3.sinaimg.cn/large/abc123.jpg"))
+        );
+    }
+
     #[tokio::test]
     async fn test_picture_record_conversion() {
         let url_str = "http://example.com/image.jpg";
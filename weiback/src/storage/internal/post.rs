@@ -12,11 +12,14 @@
 //! | `attitudes_status` | `INTEGER` | Status of attitudes.                              |
 //! | `comments_count`   | `INTEGER` | Number of comments on the post.                   |
 //! | `created_at`       | `TEXT`    | Timestamp of post creation (RFC3339 format).      |
+//! | `created_at_ts`    | `INTEGER` | `created_at` as a Unix timestamp. **Indexed.**    |
 //! | `deleted`          | `BOOLEAN` | Whether the post has been marked as deleted.      |
 //! | `edit_count`       | `INTEGER` | Number of times the post has been edited.         |
 //! | `favorited`        | `BOOLEAN` | Whether the post is marked as favorited.          |
 //! | `geo`              | `JSON`    | Geographical information as JSON.                 |
 //! | `id`               | `INTEGER` | Unique identifier for the post. **Primary Key.**  |
+//! | `latitude`         | `REAL`    | Latitude parsed from `geo`. **Indexed.**          |
+//! | `longitude`        | `REAL`    | Longitude parsed from `geo`. **Indexed.**         |
 //! | `mblogid`          | `TEXT`    | Microblog ID.                                     |
 //! | `mix_media_ids`    | `JSON`    | Mixed media IDs as JSON array.                    |
 //! | `mix_media_info`   | `JSON`    | Mixed media information as JSON.                  |
@@ -24,6 +27,7 @@
 //! | `pic_ids`          | `JSON`    | Picture IDs as JSON array.                        |
 //! | `pic_infos`        | `JSON`    | Picture information as JSON object.               |
 //! | `pic_num`          | `INTEGER` | Number of pictures in the post.                   |
+//! | `place_name`       | `TEXT`    | Check-in place from `tag_struct`. **Indexed.**    |
 //! | `region_name`      | `TEXT`    | Region name of the post.                          |
 //! | `reposts_count`    | `INTEGER` | Number of reposts.                                |
 //! | `repost_type`      | `INTEGER` | Type of repost.                                   |
@@ -35,6 +39,9 @@
 //! | `url_struct`       | `JSON`    | URL structure as JSON.                            |
 //!
 //! The `id` column serves as the primary key for uniqueness in the `posts` table.
+//! `created_at_ts` is derived from `created_at` on save and is what date filters and
+//! ordering use, since `created_at` keeps the post's own UTC offset. Likewise `latitude`,
+//! `longitude` and `place_name` are parsed on save, see [`crate::geo`].
 //!
 //! # Table Structure: `favorited_posts`
 //!
@@ -42,20 +49,49 @@
 //! |---------------|-----------|---------------------------------------------------|
 //! | `id`          | `INTEGER` | The ID of the favorited post. **Primary Key.**    |
 //! | `unfavorited` | `BOOLEAN` | True if the post has been unfavorited.            |
+//!
+//! # Full-Text Index: `posts_fts`
+//!
+//! An FTS5 trigram index with one entry per post, its `rowid` being the post ID.
+//!
+//! | Column        | Source                                           |
+//! |---------------|--------------------------------------------------|
+//! | `text`        | `posts.text`                                     |
+//! | `screen_name` | `users.screen_name` of the author                |
+//! | `url_titles`  | The `url_title` of every `url_struct` entry      |
+//! | `page_title`  | `page_info.page_title`                           |
+//! | `tag_names`   | The `tag_name` of every `tag_struct` entry       |
+//!
+//! The entries are computed by the `posts_fts_source` view and kept in sync by triggers
+//! on `posts` and `users`.
+//!
+//! # Mentions and Topics
+//!
+//! The `@` mentions and `#` topics in a post's text are extracted on save into the
+//! `post_mentions` and `post_topics` tables, see [`post_term`].
+
+use std::collections::HashMap;
 
 use chrono::DateTime;
 use sea_query::{
-    Asterisk, Expr, ExprTrait, Func, Iden, OnConflict, Order, Query, SqliteQueryBuilder,
+    Asterisk, Expr, ExprTrait, Func, Iden, LikeExpr, OnConflict, Order, Query, SqliteQueryBuilder,
 };
 use sea_query_sqlx::SqlxBinder;
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, from_value, to_value};
-use sqlx::{Acquire, AssertSqlSafe, Executor, FromRow, Sqlite};
-use tracing::{debug, error};
-
-use crate::core::task::{PostQuery, SearchTerm};
+use sqlx::{Acquire, AssertSqlSafe, Executor, FromRow, Sqlite, SqliteConnection};
+use tracing::debug;
+
+use super::post_term::{self, PostTerm};
+use super::script_folding::ScriptFolder;
+use crate::core::{
+    search_query::{Clause, SearchQuery, TextTerm},
+    task::{PostQuery, PostSortBy, SearchTerm, SnippetPart},
+};
 use crate::error::{Error, Result};
+use crate::geo::{parse_coordinates, parse_place_name};
 use crate::models::Post;
+use crate::utils::{extract_mentions, extract_topics};
 
 #[derive(Iden)]
 #[iden = "posts"]
@@ -65,11 +101,14 @@ enum PostIden {
     AttitudesStatus,
     CommentsCount,
     CreatedAt,
+    CreatedAtTs,
     Deleted,
     EditCount,
     Favorited,
     Geo,
     Id,
+    Latitude,
+    Longitude,
     Mblogid,
     MixMediaIds,
     MixMediaInfo,
@@ -77,6 +116,7 @@ enum PostIden {
     PicIds,
     PicInfos,
     PicNum,
+    PlaceName,
     RegionName,
     RepostsCount,
     RepostType,
@@ -178,8 +218,8 @@ impl TryInto<Post> for PostInternal {
     /// Tries to convert a `PostInternal` database representation into a `Post` model.
     /// This conversion can fail due to malformed date strings or invalid JSON data.
     ///
-    /// **Note:** This is a lossy conversion for internal use. The `retweeted_status` and `user`
-    /// fields are always `None` after conversion. The caller is responsible for populating
+    /// **Note:** This is a lossy conversion for internal use. The `retweeted_status`, `user`
+    /// and `raw` fields are always `None` after conversion. The caller is responsible for populating
     /// these fields by fetching them separately from the database if needed.
     fn try_into(self) -> Result<Post> {
         Ok(Post {
@@ -209,6 +249,7 @@ impl TryInto<Post> for PostInternal {
             text: self.text,
             url_struct: self.url_struct.map(from_value).transpose()?,
             user: None,
+            raw: None,
         })
     }
 }
@@ -286,6 +327,9 @@ where
     A: Acquire<'c, Database = Sqlite>,
 {
     use serde_json::to_string;
+    let created_at_ts = DateTime::parse_from_rfc3339(&post.created_at)?.timestamp();
+    let coordinates = post.geo.as_ref().and_then(parse_coordinates);
+    let place_name = post.tag_struct.as_ref().and_then(parse_place_name);
     let mut conn = acquirer.acquire().await?;
     let (sql, values) = Query::insert()
         .into_table(PostIden::Table)
@@ -294,11 +338,14 @@ where
             PostIden::AttitudesStatus,
             PostIden::CommentsCount,
             PostIden::CreatedAt,
+            PostIden::CreatedAtTs,
             PostIden::Deleted,
             PostIden::EditCount,
             PostIden::Favorited,
             PostIden::Geo,
             PostIden::Id,
+            PostIden::Latitude,
+            PostIden::Longitude,
             PostIden::Mblogid,
             PostIden::MixMediaIds,
             PostIden::MixMediaInfo,
@@ -306,6 +353,7 @@ where
             PostIden::PicIds,
             PostIden::PicInfos,
             PostIden::PicNum,
+            PostIden::PlaceName,
             PostIden::RegionName,
             PostIden::RepostsCount,
             PostIden::RepostType,
@@ -321,11 +369,14 @@ where
             post.attitudes_status.into(),
             post.comments_count.into(),
             post.created_at.clone().into(),
+            created_at_ts.into(),
             post.deleted.into(),
             post.edit_count.into(),
             post.favorited.into(),
             post.geo.as_ref().map(to_string).transpose()?.into(),
             post.id.into(),
+            coordinates.map(|c| c.latitude).into(),
+            coordinates.map(|c| c.longitude).into(),
             post.mblogid.clone().into(),
             post.mix_media_ids
                 .as_ref()
@@ -341,6 +392,7 @@ where
             post.pic_ids.as_ref().map(to_string).transpose()?.into(),
             post.pic_infos.as_ref().map(to_string).transpose()?.into(),
             post.pic_num.into(),
+            place_name.into(),
             post.region_name.clone().into(),
             post.reposts_count.into(),
             post.repost_type.into(),
@@ -358,10 +410,13 @@ where
                     PostIden::AttitudesStatus,
                     PostIden::CommentsCount,
                     PostIden::CreatedAt,
+                    PostIden::CreatedAtTs,
                     PostIden::Deleted,
                     PostIden::EditCount,
                     PostIden::Favorited,
                     PostIden::Geo,
+                    PostIden::Latitude,
+                    PostIden::Longitude,
                     PostIden::Mblogid,
                     PostIden::MixMediaIds,
                     PostIden::MixMediaInfo,
@@ -369,6 +424,7 @@ where
                     PostIden::PicIds,
                     PostIden::PicInfos,
                     PostIden::PicNum,
+                    PostIden::PlaceName,
                     PostIden::RegionName,
                     PostIden::RepostsCount,
                     PostIden::RepostType,
@@ -386,6 +442,7 @@ where
     sqlx::query_with(AssertSqlSafe(sql), values)
         .execute(&mut *conn)
         .await?;
+    save_mentions_and_topics(&mut *conn, post.id, &post.text).await?;
 
     if post.favorited {
         mark_post_favorited(&mut *conn, post.id).await?;
@@ -393,6 +450,13 @@ where
     Ok(())
 }
 
+/// Records the mentions and topics of a post's own text in `post_mentions` and
+/// `post_topics`, replacing the previous ones.
+async fn save_mentions_and_topics(conn: &mut SqliteConnection, id: i64, text: &str) -> Result<()> {
+    post_term::save_post_terms(&mut *conn, PostTerm::Mention, id, &extract_mentions(text)).await?;
+    post_term::save_post_terms(&mut *conn, PostTerm::Topic, id, &extract_topics(text)).await
+}
+
 /// Marks a post as unfavorited in the `favorited_posts` table.
 ///
 /// # Arguments
@@ -477,28 +541,8 @@ where
     )
 }
 
-/// Retrieves the IDs of posts that are invalid.
-///
-/// # Arguments
-///
-/// * `executor` - A database executor.
-/// * `clean_retweeted_invalid` - Whether to include posts that are valid themselves but their retweeted content is invalid.
-///
-/// # Returns
-///
-/// A `Result` containing a `Vec<i64>` of invalid post IDs.
-pub async fn get_invalid_posts_ids<'e, E>(
-    executor: E,
-    clean_retweeted_invalid: bool,
-) -> Result<Vec<i64>>
-where
-    E: Executor<'e, Database = Sqlite>,
-{
-    debug!(
-        "query invalid posts, clean_retweeted_invalid: {}",
-        clean_retweeted_invalid
-    );
-
+/// Builds the query selecting the IDs of invalid posts.
+fn invalid_posts_query(clean_retweeted_invalid: bool) -> sea_query::SelectStatement {
     let mut query = Query::select();
     query.column(PostIden::Id).from(PostIden::Table);
 
@@ -520,6 +564,68 @@ where
                 ),
             );
     }
+    query
+}
+
+/// Counts the posts that are invalid.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `clean_retweeted_invalid` - Whether to include posts that are valid themselves but their retweeted content is invalid.
+///
+/// # Returns
+///
+/// A `Result` containing the number of invalid posts.
+pub async fn count_invalid_posts<'e, E>(executor: E, clean_retweeted_invalid: bool) -> Result<u64>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let (sql, values) = Query::select()
+        .expr(Func::count(1))
+        .from_subquery(
+            invalid_posts_query(clean_retweeted_invalid),
+            sea_query::Alias::new("invalid_posts"),
+        )
+        .build_sqlx(SqliteQueryBuilder);
+    Ok(
+        sqlx::query_scalar_with::<Sqlite, i64, _>(AssertSqlSafe(sql), values)
+            .fetch_one(executor)
+            .await? as u64,
+    )
+}
+
+/// Retrieves the next batch of IDs of posts that are invalid, in ascending order.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `clean_retweeted_invalid` - Whether to include posts that are valid themselves but their retweeted content is invalid.
+/// * `after` - The last ID of the previous batch, `None` for the first batch.
+/// * `limit` - The maximum number of IDs to return.
+///
+/// # Returns
+///
+/// A `Result` containing a `Vec<i64>` of invalid post IDs.
+pub async fn get_invalid_posts_ids<'e, E>(
+    executor: E,
+    clean_retweeted_invalid: bool,
+    after: Option<i64>,
+    limit: u64,
+) -> Result<Vec<i64>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    debug!(
+        "query invalid posts, clean_retweeted_invalid: {}, after: {:?}",
+        clean_retweeted_invalid, after
+    );
+
+    let mut query = invalid_posts_query(clean_retweeted_invalid);
+    if let Some(after) = after {
+        query.and_where(Expr::col(PostIden::Id).gt(after));
+    }
+    query.order_by(PostIden::Id, Order::Asc).limit(limit);
 
     let (sql, values) = query.build_sqlx(SqliteQueryBuilder);
     Ok(
@@ -636,81 +742,307 @@ where
     Ok(())
 }
 
+/// Builds a `LIKE` pattern, escaped with a backslash, matching strings that contain `s`.
+fn contains_pattern(s: &str) -> String {
+    let escaped = s
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+/// Builds a `LIKE` expression matching strings that contain `s` literally.
+fn contains(s: &str) -> LikeExpr {
+    LikeExpr::new(contains_pattern(s)).escape('\\')
+}
+
+/// The kinds of media a post can be filtered on.
+#[derive(Debug, Clone, Copy)]
+enum MediaKind {
+    Picture,
+    Video,
+    Livephoto,
+}
+
+impl MediaKind {
+    /// The SQL condition for a `posts` row named `table` to contain this kind of media.
+    ///
+    /// Media lives in the JSON columns `pic_ids`, `pic_infos`, `mix_media_info` and
+    /// `page_info`, so the checks go through SQLite's JSON functions. The condition is
+    /// never `NULL`, so that it can be negated.
+    fn condition(self, table: &str) -> String {
+        let mix_media_item = |path: &str, value: &str| {
+            format!(
+                "EXISTS (SELECT 1 FROM json_each({table}.mix_media_info, '$.items') \
+                 WHERE json_extract(value, '{path}') = '{value}')"
+            )
+        };
+        match self {
+            MediaKind::Picture => format!(
+                "(COALESCE(json_array_length({table}.pic_ids), 0) > 0 OR {})",
+                mix_media_item("$.type", "pic")
+            ),
+            MediaKind::Video => format!(
+                "(COALESCE(json_extract({table}.page_info, '$.object_type') = 'video', 0) OR {})",
+                mix_media_item("$.type", "video")
+            ),
+            MediaKind::Livephoto => format!(
+                "(EXISTS (SELECT 1 FROM json_each({table}.pic_infos) \
+                 WHERE json_extract(value, '$.type') = 'livephoto') OR {})",
+                mix_media_item("$.data.type", "livephoto")
+            ),
+        }
+    }
+
+    /// The condition for a post or its retweeted post to contain this kind of media.
+    fn post_or_retweet_condition(self) -> String {
+        format!(
+            "({} OR EXISTS (SELECT 1 FROM posts AS rt WHERE rt.id = posts.retweeted_id AND {}))",
+            self.condition("posts"),
+            self.condition("rt")
+        )
+    }
+}
+
+/// Selects the ids of posts whose own index entry or retweeted post's entry matches
+/// `fts_query`.
+fn fts_matches(fts_query: String) -> sea_query::SelectStatement {
+    use sea_query::{Alias, JoinType, UnionType};
+
+    let mut subquery_self = Query::select();
+    subquery_self
+        .column((PostIden::Table, PostIden::Id))
+        .from(PostIden::Table)
+        .join(
+            JoinType::InnerJoin,
+            PostFtsIden::Table,
+            Expr::col((PostFtsIden::Table, Alias::new("rowid")))
+                .eq(Expr::col((PostIden::Table, PostIden::Id))),
+        )
+        .and_where(Expr::cust_with_values(
+            "posts_fts MATCH ?",
+            [fts_query.clone()],
+        ));
+
+    let mut subquery_retweet = Query::select();
+    subquery_retweet
+        .column((PostIden::Table, PostIden::Id))
+        .from(PostIden::Table)
+        .join(
+            JoinType::InnerJoin,
+            PostFtsIden::Table,
+            Expr::col((PostFtsIden::Table, Alias::new("rowid")))
+                .eq(Expr::col((PostIden::Table, PostIden::RetweetedId))),
+        )
+        .and_where(Expr::cust_with_values("posts_fts MATCH ?", [fts_query]));
+
+    subquery_self.union(UnionType::All, subquery_retweet.take());
+    subquery_self.take()
+}
+
+/// The columns of `posts_fts`.
+const FTS_COLUMNS: [&str; 6] = [
+    "text",
+    "screen_name",
+    "url_titles",
+    "page_title",
+    "tag_names",
+    "folded",
+];
+
+/// The condition for the index entry of a post or its retweeted post to contain a text
+/// term in any column.
+///
+/// Terms long enough for the trigram index go through `MATCH`; shorter ones are matched
+/// with `LIKE` on the entries of the post, which finds 1–2 character Chinese words.
+/// The condition is never `NULL`, so that it can be negated.
+fn text_condition(term: &TextTerm) -> Expr {
+    if let Some(fts_query) = term.fts_query() {
+        return Expr::col((PostIden::Table, PostIden::Id)).in_subquery(fts_matches(fts_query));
+    }
+    let pattern = contains_pattern(term.text());
+    let any_column = FTS_COLUMNS
+        .iter()
+        .map(|column| format!("posts_fts.{column} LIKE ? ESCAPE '\\'"))
+        .collect::<Vec<_>>()
+        .join(" OR ");
+    Expr::cust_with_values(
+        format!(
+            "EXISTS (SELECT 1 FROM posts_fts \
+             WHERE posts_fts.rowid IN (posts.id, posts.retweeted_id) AND ({any_column}))"
+        ),
+        FTS_COLUMNS.map(|_| pattern.clone()),
+    )
+}
+
+/// Turns the search term of a query into a [`SearchQuery`], folding its text terms with
+/// the script folder of the archive, if any.
+fn search_query(query: &PostQuery, folder: Option<&ScriptFolder>) -> Result<SearchQuery> {
+    let mut search = match &query.search_term {
+        Some(SearchTerm::Fuzzy(term)) => SearchQuery::fuzzy(term),
+        Some(SearchTerm::Strict(term)) => SearchQuery::strict(term),
+        Some(SearchTerm::Query(input)) => SearchQuery::parse(input)?,
+        None => return Ok(SearchQuery::default()),
+    };
+    if let Some(folder) = folder {
+        search.fold_text(|text| folder.fold(text));
+    }
+    Ok(search)
+}
+
 /// Builds the common part of a post query based on the given `PostQuery` criteria.
 /// This includes joins for FTS and all the `WHERE` clause filters.
-fn build_common_query(query: &PostQuery) -> Result<sea_query::SelectStatement> {
-    use sea_query::{Alias, JoinType, UnionType};
+///
+/// `search` is the [`SearchQuery`] of the `SearchTerm`: its text terms become FTS or
+/// `LIKE` matches and its operators are applied onto a copy of `query` before the
+/// filters are built. Operators contradicting `query` make it match nothing.
+fn build_common_query(query: &PostQuery, search: &SearchQuery) -> sea_query::SelectStatement {
+    let mut compiled;
+    let mut satisfiable = true;
+    let query = if search.clauses.is_empty() {
+        query
+    } else {
+        compiled = query.clone();
+        satisfiable = search.apply_filters(&mut compiled);
+        &compiled
+    };
 
     let mut posts_query = Query::select();
     posts_query.from(PostIden::Table);
+    if !satisfiable {
+        posts_query.and_where(Expr::cust("FALSE"));
+    }
 
-    if let Some(search_term) = query.search_term.as_ref() {
-        let fts_query = match search_term {
-            SearchTerm::Fuzzy(term) => term.to_owned(),
-            SearchTerm::Strict(term) => format!("\"{}\"", term.replace('"', "\"\"")),
-        };
+    for clause in &search.clauses {
+        match clause {
+            Clause::Text(terms) => {
+                posts_query.and_where(
+                    terms
+                        .iter()
+                        .map(text_condition)
+                        .reduce(|a, b| a.or(b))
+                        .expect("text clauses are never empty"),
+                );
+            }
+            Clause::ExcludeText(term) => {
+                posts_query.and_where(text_condition(term).not());
+            }
+            _ => {}
+        }
+    }
 
-        let mut subquery_self = Query::select();
-        subquery_self
-            .column((PostIden::Table, PostIden::Id))
-            .from(PostIden::Table)
-            .join(
-                JoinType::InnerJoin,
-                PostFtsIden::Table,
-                Expr::col((PostFtsIden::Table, Alias::new("rowid")))
-                    .eq(Expr::col((PostIden::Table, PostIden::Id))),
-            )
-            .and_where(Expr::cust_with_values(
-                "posts_fts.text MATCH ?",
-                [fts_query.clone()],
-            ));
-
-        let mut subquery_retweet = Query::select();
-        subquery_retweet
-            .column((PostIden::Table, PostIden::Id))
-            .from(PostIden::Table)
-            .join(
-                JoinType::InnerJoin,
-                PostFtsIden::Table,
-                Expr::col((PostFtsIden::Table, Alias::new("rowid")))
-                    .eq(Expr::col((PostIden::Table, PostIden::RetweetedId))),
-            )
-            .and_where(Expr::cust_with_values(
-                "posts_fts.text MATCH ?",
-                [fts_query],
-            ));
+    let user_ids: Vec<i64> = query
+        .user_id
+        .into_iter()
+        .chain(query.user_ids.iter().copied())
+        .collect();
+    if !user_ids.is_empty() {
+        posts_query.and_where(Expr::col((PostIden::Table, PostIden::Uid)).is_in(user_ids));
+    }
+
+    if !query.exclude_user_ids.is_empty() {
+        posts_query.and_where(
+            Expr::col((PostIden::Table, PostIden::Uid))
+                .is_null()
+                .or(Expr::col((PostIden::Table, PostIden::Uid))
+                    .is_not_in(query.exclude_user_ids.iter().copied())),
+        );
+    }
+
+    if let Some(is_retweet) = query.is_retweet {
+        let retweeted_id = Expr::col((PostIden::Table, PostIden::RetweetedId));
+        posts_query.and_where(if is_retweet {
+            retweeted_id.is_not_null()
+        } else {
+            retweeted_id.is_null()
+        });
+    }
 
-        subquery_self.union(UnionType::All, subquery_retweet.take());
+    if let Some(retweeted_user_id) = query.retweeted_user_id {
+        posts_query.and_where(
+            Expr::col((PostIden::Table, PostIden::RetweetedId)).in_subquery(
+                Query::select()
+                    .column(PostIden::Id)
+                    .from(PostIden::Table)
+                    .and_where(Expr::col(PostIden::Uid).eq(retweeted_user_id))
+                    .take(),
+            ),
+        );
+    }
+
+    if let Some(mentioned) = query.mentioned.as_deref() {
+        posts_query.and_where(
+            Expr::col((PostIden::Table, PostIden::Id)).in_subquery(
+                PostTerm::Mention.posts_with(mentioned.trim().trim_start_matches('@')),
+            ),
+        );
+    }
+
+    if let Some(topic) = query.topic.as_deref() {
+        posts_query.and_where(
+            Expr::col((PostIden::Table, PostIden::Id))
+                .in_subquery(PostTerm::Topic.posts_with(topic.trim().trim_matches('#'))),
+        );
+    }
 
+    if let Some(source) = query.source.as_deref() {
+        posts_query
+            .and_where(Expr::col((PostIden::Table, PostIden::Source)).like(contains(source)));
+    }
+
+    if let Some(region_name) = query.region_name.as_deref() {
         posts_query.and_where(
-            Expr::col((PostIden::Table, PostIden::Id)).in_subquery(subquery_self.take()),
+            Expr::col((PostIden::Table, PostIden::RegionName)).like(contains(region_name)),
         );
     }
 
-    if let Some(user_id) = query.user_id {
-        posts_query.and_where(Expr::col((PostIden::Table, PostIden::Uid)).eq(user_id));
+    if let Some(has_location) = query.has_location {
+        let latitude = Expr::col((PostIden::Table, PostIden::Latitude));
+        posts_query.and_where(if has_location {
+            latitude.is_not_null()
+        } else {
+            latitude.is_null()
+        });
+    }
+
+    if let Some(bbox) = query.bbox {
+        posts_query
+            .and_where(
+                Expr::col((PostIden::Table, PostIden::Latitude))
+                    .between(bbox.min_latitude, bbox.max_latitude),
+            )
+            .and_where(
+                Expr::col((PostIden::Table, PostIden::Longitude))
+                    .between(bbox.min_longitude, bbox.max_longitude),
+            );
+    }
+
+    if let Some(place) = query.place.as_deref() {
+        posts_query
+            .and_where(Expr::col((PostIden::Table, PostIden::PlaceName)).like(contains(place)));
+    }
+
+    for (kind, wanted) in [
+        (MediaKind::Picture, query.has_pictures),
+        (MediaKind::Video, query.has_video),
+        (MediaKind::Livephoto, query.has_livephoto),
+    ] {
+        if let Some(wanted) = wanted {
+            let cond = kind.post_or_retweet_condition();
+            posts_query.and_where(Expr::cust(if wanted {
+                cond
+            } else {
+                format!("NOT {cond}")
+            }));
+        }
     }
 
     if let Some(start_date) = query.start_date {
-        let dt = DateTime::from_timestamp(start_date, 0)
-            .ok_or_else(|| {
-                let msg = "无效的开始时间".to_string();
-                error!("{msg}");
-                Error::FormatError(msg)
-            })?
-            .to_rfc3339();
-        posts_query.and_where(Expr::col((PostIden::Table, PostIden::CreatedAt)).gte(dt));
+        posts_query.and_where(Expr::col((PostIden::Table, PostIden::CreatedAtTs)).gte(start_date));
     }
 
     if let Some(end_date) = query.end_date {
-        let dt = DateTime::from_timestamp(end_date, 0)
-            .ok_or_else(|| {
-                let msg = "无效的结束时间".to_string();
-                error!("{msg}");
-                Error::FormatError(msg)
-            })?
-            .to_rfc3339();
-        posts_query.and_where(Expr::col((PostIden::Table, PostIden::CreatedAt)).lte(dt));
+        posts_query.and_where(Expr::col((PostIden::Table, PostIden::CreatedAtTs)).lte(end_date));
     }
 
     if query.is_favorited {
@@ -724,7 +1056,107 @@ fn build_common_query(query: &PostQuery) -> Result<sea_query::SelectStatement> {
         );
     }
 
-    Ok(posts_query)
+    posts_query
+}
+
+/// The position of a post in the (sort key, `id`) ordering used for keyset pagination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PostCursor {
+    pub sort_key: i64,
+    pub id: i64,
+}
+
+/// A post together with its position in the query's ordering.
+#[derive(Debug, FromRow)]
+struct KeyedPost {
+    #[sqlx(flatten)]
+    post: PostInternal,
+    sort_key: i64,
+}
+
+/// The integer expression posts are sorted by, missing counts sorting as zero.
+///
+/// Relevance is the best bm25 score of the post or its retweeted post against the
+/// search terms, negated and scaled to an integer so that larger is better like the
+/// other keys. Without terms the index can rank, relevance falls back to the post time.
+fn sort_key(query: &PostQuery, search: &SearchQuery) -> Expr {
+    let column = match query.sort_by {
+        PostSortBy::CreatedAt => return Expr::col((PostIden::Table, PostIden::CreatedAtTs)),
+        PostSortBy::AttitudesCount => PostIden::AttitudesCount,
+        PostSortBy::CommentsCount => PostIden::CommentsCount,
+        PostSortBy::RepostsCount => PostIden::RepostsCount,
+        PostSortBy::Relevance => {
+            let Some(rank_query) = search.rank_query() else {
+                return Expr::col((PostIden::Table, PostIden::CreatedAtTs));
+            };
+            return Expr::cust_with_values(
+                "COALESCE((SELECT CAST(-MIN(bm25(posts_fts)) * 1000000 AS INTEGER) \
+                 FROM posts_fts WHERE posts_fts MATCH ? \
+                 AND posts_fts.rowid IN (posts.id, posts.retweeted_id)), 0)",
+                [rank_query],
+            );
+        }
+    };
+    Func::coalesce([Expr::col((PostIden::Table, column)), Expr::val(0)]).into()
+}
+
+/// Orders posts by (sort key, `id`), largest first unless `reverse_order` is set.
+fn order_posts(
+    posts_query: &mut sea_query::SelectStatement,
+    query: &PostQuery,
+    search: &SearchQuery,
+) {
+    let order = if query.reverse_order {
+        Order::Asc
+    } else {
+        Order::Desc
+    };
+    posts_query
+        .order_by_expr(sort_key(query, search), order.clone())
+        .order_by((PostIden::Table, PostIden::Id), order);
+}
+
+/// Counts the posts matching `query` whose search term was compiled into `search`.
+async fn count_matching(
+    conn: &mut SqliteConnection,
+    query: &PostQuery,
+    search: &SearchQuery,
+) -> Result<u64> {
+    let mut count_query = build_common_query(query, search);
+    count_query.expr(if query.search_term.is_some() {
+        Expr::col((PostIden::Table, PostIden::Id)).count_distinct()
+    } else {
+        Func::count(1).into()
+    });
+
+    let (sql, values) = count_query.build_sqlx(SqliteQueryBuilder);
+    Ok(sqlx::query_scalar_with(AssertSqlSafe(sql), values)
+        .fetch_one(conn)
+        .await?)
+}
+
+/// Counts the posts matching the given criteria, ignoring pagination.
+///
+/// # Arguments
+///
+/// * `acquirer` - A database acquirer.
+/// * `query` - A `PostQuery` struct specifying the filters.
+/// * `folder` - The script folder of the archive, `None` if folding is disabled.
+///
+/// # Returns
+///
+/// A `Result` containing the number of matching posts.
+pub async fn count_posts<'c, A>(
+    acquirer: A,
+    query: &PostQuery,
+    folder: Option<&ScriptFolder>,
+) -> Result<u64>
+where
+    A: Acquire<'c, Database = Sqlite>,
+{
+    let mut conn = acquirer.acquire().await?;
+    let search = search_query(query, folder)?;
+    count_matching(&mut conn, query, &search).await
 }
 
 /// Queries posts from the database based on various criteria.
@@ -736,38 +1168,28 @@ fn build_common_query(query: &PostQuery) -> Result<sea_query::SelectStatement> {
 ///
 /// * `acquirer` - A database acquirer.
 /// * `query` - A `PostQuery` struct specifying the query parameters.
+/// * `folder` - The script folder of the archive, `None` if folding is disabled.
 ///
 /// # Returns
 ///
 /// A `Result` containing a tuple of `(Vec<PostInternal>, u64)`. The vector contains the
 /// matching posts, and `u64` is the total count of items matching the query without pagination.
-pub async fn query_posts<'c, A>(acquirer: A, query: PostQuery) -> Result<(Vec<PostInternal>, u64)>
+pub async fn query_posts<'c, A>(
+    acquirer: A,
+    query: PostQuery,
+    folder: Option<&ScriptFolder>,
+) -> Result<(Vec<PostInternal>, u64)>
 where
     A: Acquire<'c, Database = Sqlite>,
 {
-    let mut posts_query = build_common_query(&query)?;
-
-    let mut count_query = posts_query.clone();
-    count_query.expr(if query.search_term.is_some() {
-        Expr::col((PostIden::Table, PostIden::Id)).count_distinct()
-    } else {
-        Func::count(1).into()
-    });
-
-    let (sql, values) = count_query.build_sqlx(SqliteQueryBuilder);
     let mut conn = acquirer.acquire().await?;
-    let total_items: u64 = sqlx::query_scalar_with(AssertSqlSafe(sql), values)
-        .fetch_one(&mut *conn)
-        .await?;
+    let search = search_query(&query, folder)?;
+    let total_items = count_matching(&mut conn, &query, &search).await?;
 
-    let order = if query.reverse_order {
-        Order::Asc
-    } else {
-        Order::Desc
-    };
+    let mut posts_query = build_common_query(&query, &search);
+    posts_query.column((PostIden::Table, Asterisk));
+    order_posts(&mut posts_query, &query, &search);
     posts_query
-        .column((PostIden::Table, Asterisk))
-        .order_by((PostIden::Table, PostIden::Id), order)
         .limit(query.posts_per_page as u64)
         .offset((query.page.saturating_sub(1) * query.posts_per_page) as u64);
 
@@ -779,50 +1201,364 @@ where
     Ok((posts, total_items))
 }
 
-/// Queries all post IDs from the database based on various criteria, without pagination.
+/// Queries the next batch of posts after a cursor, using keyset pagination.
+///
+/// Unlike [`query_posts`], the cost of a batch does not grow with its position and no
+/// count is performed. `page` and `posts_per_page` of the query are ignored.
 ///
 /// # Arguments
 ///
 /// * `acquirer` - A database acquirer.
-/// * `query` - A `PostQuery` struct specifying the query parameters.
+/// * `query` - A `PostQuery` struct specifying the filters and ordering.
+/// * `folder` - The script folder of the archive, `None` if folding is disabled.
+/// * `after` - The cursor of the last post of the previous batch, `None` for the first batch.
+/// * `limit` - The maximum number of posts to return.
 ///
 /// # Returns
 ///
-/// A `Result` containing a vector of `i64` representing the post IDs.
-pub async fn query_all_post_ids<'c, A>(acquirer: A, query: PostQuery) -> Result<Vec<i64>>
+/// A `Result` containing the posts following `after` in the query's order, each with
+/// its own cursor.
+pub async fn query_posts_after<'c, A>(
+    acquirer: A,
+    query: &PostQuery,
+    folder: Option<&ScriptFolder>,
+    after: Option<PostCursor>,
+    limit: u64,
+) -> Result<Vec<(PostInternal, PostCursor)>>
 where
     A: Acquire<'c, Database = Sqlite>,
 {
-    let mut posts_query = build_common_query(&query)?;
-
-    posts_query.column((PostIden::Table, PostIden::Id));
+    let mut conn = acquirer.acquire().await?;
+    let mut posts_query = build_keyset_query(query, folder, after, limit)?;
+    posts_query.column((PostIden::Table, Asterisk));
 
     let (sql, values) = posts_query.build_sqlx(SqliteQueryBuilder);
-    let mut conn = acquirer.acquire().await?;
-    let ids = sqlx::query_scalar_with::<Sqlite, i64, _>(AssertSqlSafe(sql), values)
+    let posts = sqlx::query_as_with::<Sqlite, KeyedPost, _>(AssertSqlSafe(sql), values)
         .fetch_all(&mut *conn)
         .await?;
-
-    Ok(ids)
+    Ok(posts
+        .into_iter()
+        .map(|KeyedPost { post, sort_key }| {
+            let cursor = PostCursor {
+                sort_key,
+                id: post.id,
+            };
+            (post, cursor)
+        })
+        .collect())
 }
 
-#[cfg(test)]
-mod local_tests {
-    use std::collections::HashSet;
-    use std::path::Path;
-
-    use sqlx::SqlitePool;
-    use tokio::fs::read_to_string;
+/// Queries the IDs of the next batch of posts after a cursor, like [`query_posts_after`]
+/// without loading the posts themselves.
+///
+/// # Arguments
+///
+/// * `acquirer` - A database acquirer.
+/// * `query` - A `PostQuery` struct specifying the filters and ordering.
+/// * `folder` - The script folder of the archive, `None` if folding is disabled.
+/// * `after` - The cursor of the last post of the previous batch, `None` for the first batch.
+/// * `limit` - The maximum number of posts to return.
+///
+/// # Returns
+///
+/// A `Result` containing the cursors of the posts following `after` in the query's
+/// order, whose `id` is the post ID.
+pub async fn query_post_ids_after<'c, A>(
+    acquirer: A,
+    query: &PostQuery,
+    folder: Option<&ScriptFolder>,
+    after: Option<PostCursor>,
+    limit: u64,
+) -> Result<Vec<PostCursor>>
+where
+    A: Acquire<'c, Database = Sqlite>,
+{
+    let mut conn = acquirer.acquire().await?;
+    let mut posts_query = build_keyset_query(query, folder, after, limit)?;
+    posts_query.column((PostIden::Table, PostIden::Id));
 
-    use super::*;
-    use crate::api::{favorites::FavoritesSucc, profile_statuses::ProfileStatusesSucc};
+    let (sql, values) = posts_query.build_sqlx(SqliteQueryBuilder);
+    let rows = sqlx::query_as_with::<Sqlite, (i64, i64), _>(AssertSqlSafe(sql), values)
+        .fetch_all(&mut *conn)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(sort_key, id)| PostCursor { sort_key, id })
+        .collect())
+}
 
-    async fn setup_db() -> SqlitePool {
-        let pool = SqlitePool::connect(":memory:").await.unwrap();
-        sqlx::migrate!().run(&pool).await.unwrap();
-        pool
+/// Builds the query of the posts following a cursor in the query's order, selecting
+/// their sort key as `sort_key` before any other column.
+fn build_keyset_query(
+    query: &PostQuery,
+    folder: Option<&ScriptFolder>,
+    after: Option<PostCursor>,
+    limit: u64,
+) -> Result<sea_query::SelectStatement> {
+    use sea_query::Alias;
+
+    let search = search_query(query, folder)?;
+    let mut posts_query = build_common_query(query, &search);
+    let key = sort_key(query, &search);
+    if let Some(after) = after {
+        let id = Expr::col((PostIden::Table, PostIden::Id));
+        posts_query.and_where(if query.reverse_order {
+            key.clone()
+                .gt(after.sort_key)
+                .or(key.eq(after.sort_key).and(id.gt(after.id)))
+        } else {
+            key.clone()
+                .lt(after.sort_key)
+                .or(key.eq(after.sort_key).and(id.lt(after.id)))
+        });
     }
-
+    posts_query.expr_as(key, Alias::new("sort_key"));
+    order_posts(&mut posts_query, query, &search);
+    posts_query.limit(limit);
+    Ok(posts_query)
+}
+
+/// Queries all post IDs from the database based on various criteria, without pagination.
+///
+/// # Arguments
+///
+/// * `acquirer` - A database acquirer.
+/// * `query` - A `PostQuery` struct specifying the query parameters.
+/// * `folder` - The script folder of the archive, `None` if folding is disabled.
+///
+/// # Returns
+///
+/// A `Result` containing a vector of `i64` representing the post IDs.
+pub async fn query_all_post_ids<'c, A>(
+    acquirer: A,
+    query: PostQuery,
+    folder: Option<&ScriptFolder>,
+) -> Result<Vec<i64>>
+where
+    A: Acquire<'c, Database = Sqlite>,
+{
+    let mut conn = acquirer.acquire().await?;
+    let search = search_query(&query, folder)?;
+    let mut posts_query = build_common_query(&query, &search);
+
+    posts_query.column((PostIden::Table, PostIden::Id));
+
+    let (sql, values) = posts_query.build_sqlx(SqliteQueryBuilder);
+    let ids = sqlx::query_scalar_with::<Sqlite, i64, _>(AssertSqlSafe(sql), values)
+        .fetch_all(&mut *conn)
+        .await?;
+
+    Ok(ids)
+}
+
+/// Marks the start of a highlighted match in raw snippets, from the Unicode private use area.
+const HIGHLIGHT_START: char = '\u{E000}';
+/// Marks the end of a highlighted match in raw snippets.
+const HIGHLIGHT_END: char = '\u{E001}';
+/// The number of tokens in a snippet.
+const SNIPPET_TOKENS: i64 = 24;
+
+/// Splits a snippet returned by FTS5 at the highlight markers.
+fn snippet_parts(snippet: &str) -> Vec<SnippetPart> {
+    let mut parts = Vec::new();
+    let mut highlighted = false;
+    for piece in snippet.split([HIGHLIGHT_START, HIGHLIGHT_END]) {
+        if !piece.is_empty() {
+            parts.push(SnippetPart {
+                // snippets are shown on a single line
+                text: piece.replace('\n', " "),
+                highlighted,
+            });
+        }
+        highlighted = !highlighted;
+    }
+    parts
+}
+
+/// The indexed columns of `posts_fts` a snippet may be taken from, leaving out `folded`,
+/// whose text is not what the user wrote.
+const SNIPPET_COLUMNS: [usize; 5] = [0, 1, 2, 3, 4];
+
+/// A post ID, the original text of its index entry and the snippets of [`SNIPPET_COLUMNS`].
+type SnippetRow = (
+    i64,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+);
+
+/// Picks the snippet of a matched index entry from the snippets of its original columns.
+///
+/// The first column holding a highlighted match wins. An entry only matched through its
+/// folded text has none, and gets a plain prefix of its text instead.
+fn pick_snippet(columns: &[Option<String>], text: Option<&str>) -> Vec<SnippetPart> {
+    if let Some(snippet) = columns
+        .iter()
+        .flatten()
+        .find(|snippet| snippet.contains(HIGHLIGHT_START))
+    {
+        return snippet_parts(snippet);
+    }
+    let text = text.unwrap_or_default();
+    let mut prefix: String = text.chars().take(SNIPPET_TOKENS as usize).collect();
+    if prefix.len() < text.len() {
+        prefix.push('…');
+    }
+    snippet_parts(&prefix)
+}
+
+/// Builds highlighted snippets of the given posts for the search term of `query`.
+///
+/// The snippet is taken from the first matching column of the post's own index entry,
+/// or of its retweeted post's entry if its own does not match. Posts only matched by
+/// short terms the index can not rank get no snippet.
+///
+/// # Arguments
+///
+/// * `acquirer` - A database acquirer.
+/// * `query` - The `PostQuery` whose search term is highlighted.
+/// * `folder` - The script folder of the archive, `None` if folding is disabled.
+/// * `ids` - The IDs of the posts to build snippets for.
+///
+/// # Returns
+///
+/// A `Result` containing the snippets by post ID.
+pub async fn search_snippets<'c, A>(
+    acquirer: A,
+    query: &PostQuery,
+    folder: Option<&ScriptFolder>,
+    ids: &[i64],
+) -> Result<HashMap<i64, Vec<SnippetPart>>>
+where
+    A: Acquire<'c, Database = Sqlite>,
+{
+    let mut snippets = HashMap::new();
+    if ids.is_empty() {
+        return Ok(snippets);
+    }
+    let Some(rank_query) = search_query(query, folder)?.rank_query() else {
+        return Ok(snippets);
+    };
+    let mut conn = acquirer.acquire().await?;
+
+    let columns = SNIPPET_COLUMNS
+        .map(|column| {
+            format!(
+                "snippet(posts_fts, {column}, '{HIGHLIGHT_START}', '{HIGHLIGHT_END}', '…', \
+                 {SNIPPET_TOKENS})"
+            )
+        })
+        .join(", ");
+    let placeholders = vec!["?"; ids.len()].join(", ");
+    let own = format!(
+        "SELECT posts_fts.rowid, posts_fts.text, {columns} FROM posts_fts \
+         WHERE posts_fts MATCH ? AND posts_fts.rowid IN ({placeholders})"
+    );
+    let retweeted = format!(
+        "SELECT posts.id, posts_fts.text, {columns} FROM posts \
+         JOIN posts_fts ON posts_fts.rowid = posts.retweeted_id \
+         WHERE posts_fts MATCH ? AND posts.id IN ({placeholders})"
+    );
+
+    for sql in [own, retweeted] {
+        let mut rows = sqlx::query_as::<Sqlite, SnippetRow>(AssertSqlSafe(sql)).bind(&rank_query);
+        for id in ids {
+            rows = rows.bind(id);
+        }
+        for (id, text, c0, c1, c2, c3, c4) in rows.fetch_all(&mut *conn).await? {
+            snippets
+                .entry(id)
+                .or_insert_with(|| pick_snippet(&[c0, c1, c2, c3, c4], text.as_deref()));
+        }
+    }
+    Ok(snippets)
+}
+
+/// Re-extracts the mentions and topics of the given posts from their text.
+///
+/// Posts saved before mentions and topics were indexed are filled in this way.
+///
+/// # Arguments
+///
+/// * `acquirer` - A database acquirer.
+/// * `ids` - The IDs of the posts to re-index.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn reindex_mentions_and_topics<'c, A>(acquirer: A, ids: &[i64]) -> Result<()>
+where
+    A: Acquire<'c, Database = Sqlite>,
+{
+    let (sql, values) = Query::select()
+        .columns([PostIden::Id, PostIden::Text])
+        .from(PostIden::Table)
+        .and_where(Expr::col(PostIden::Id).is_in(ids.iter().copied()))
+        .build_sqlx(SqliteQueryBuilder);
+    let mut tx = acquirer.begin().await?;
+    let posts = sqlx::query_as_with::<Sqlite, (i64, String), _>(AssertSqlSafe(sql), values)
+        .fetch_all(&mut *tx)
+        .await?;
+    for (id, text) in posts {
+        save_mentions_and_topics(&mut *tx, id, &text).await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Rebuilds the `posts_fts` full-text index from the `posts_fts_source` view.
+///
+/// The index is normally kept in sync by triggers; this repairs it if it ever drifts,
+/// e.g. after the database was edited by hand.
+///
+/// # Arguments
+///
+/// * `acquirer` - A database acquirer.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn rebuild_fts<'c, A>(acquirer: A) -> Result<()>
+where
+    A: Acquire<'c, Database = Sqlite>,
+{
+    let mut tx = acquirer.begin().await?;
+    sqlx::query("DELETE FROM posts_fts")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "INSERT INTO posts_fts(rowid, text, screen_name, url_titles, page_title, tag_names, folded) \
+         SELECT id, text, screen_name, url_titles, page_title, tag_names, folded \
+         FROM posts_fts_source",
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod local_tests {
+    use std::collections::HashSet;
+    use std::path::Path;
+
+    use sqlx::SqlitePool;
+    use tokio::fs::read_to_string;
+
+    use super::*;
+    use crate::api::{favorites::FavoritesSucc, profile_statuses::ProfileStatusesSucc};
+    use crate::geo::BoundingBox;
+    use crate::storage::internal::script_folding;
+
+    async fn setup_db() -> SqlitePool {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+        pool
+    }
+
     async fn create_test_posts() -> Vec<Post> {
         let favorites = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/favorites.json");
         let s = read_to_string(favorites).await.unwrap();
@@ -919,12 +1655,13 @@ mod local_tests {
             reverse_order: false,
             page: 1,
             posts_per_page: 2,
+            ..Default::default()
         };
-        let (posts, _sum) = query_posts(&db, query.clone()).await.unwrap();
+        let (posts, _sum) = query_posts(&db, query.clone(), None).await.unwrap();
         assert_eq!(posts.len(), 2);
 
         query.reverse_order = true;
-        let (posts_rev, _sum) = query_posts(&db, query).await.unwrap();
+        let (posts_rev, _sum) = query_posts(&db, query, None).await.unwrap();
         assert_eq!(posts_rev.len(), 2);
     }
 
@@ -968,8 +1705,9 @@ mod local_tests {
             reverse_order: false,
             page: 1,
             posts_per_page: 2,
+            ..Default::default()
         };
-        let (_, sum) = query_posts(&db, query).await.unwrap();
+        let (_, sum) = query_posts(&db, query, None).await.unwrap();
         assert_eq!(sum, favorited_set.len() as u64);
     }
 
@@ -1012,8 +1750,9 @@ mod local_tests {
             reverse_order: false,
             page: 1,
             posts_per_page: 5,
+            ..Default::default()
         };
-        let (fetched_posts, _sum) = query_posts(&db, query.clone()).await.unwrap();
+        let (fetched_posts, _sum) = query_posts(&db, query.clone(), None).await.unwrap();
         assert_eq!(fetched_posts.len(), 5);
         assert_eq!(
             fetched_posts[0].id,
@@ -1021,7 +1760,7 @@ mod local_tests {
         );
 
         query.reverse_order = true;
-        let (fetched_posts_rev, _sum) = query_posts(&db, query).await.unwrap();
+        let (fetched_posts_rev, _sum) = query_posts(&db, query, None).await.unwrap();
         assert_eq!(fetched_posts_rev.len(), 5);
         assert_eq!(
             fetched_posts_rev[0].id,
@@ -1063,15 +1802,16 @@ mod local_tests {
             reverse_order: true,
             page: 1,
             posts_per_page: ones_post_ids.len() as u32,
+            ..Default::default()
         };
-        let (fetched_posts, sum) = query_posts(&db, query.clone()).await.unwrap();
+        let (fetched_posts, sum) = query_posts(&db, query.clone(), None).await.unwrap();
         let fetched_ids = fetched_posts.into_iter().map(|p| p.id).collect::<Vec<_>>();
         assert_eq!(fetched_ids, ones_post_ids);
         assert_eq!(sum as usize, ones_post_ids.len());
 
         query.reverse_order = false;
         ones_post_ids.reverse();
-        let (fetched_posts_rev, sum) = query_posts(&db, query).await.unwrap();
+        let (fetched_posts_rev, sum) = query_posts(&db, query, None).await.unwrap();
         let fetched_ids_rev = fetched_posts_rev
             .into_iter()
             .map(|p| p.id)
@@ -1226,47 +1966,892 @@ mod local_tests {
             reverse_order: false,
             page: 1,
             posts_per_page: 10,
+            ..Default::default()
         };
 
         // Fuzzy search "hello"
-        let (results, total) = query_posts(&db, query.clone()).await.unwrap();
+        let (results, total) = query_posts(&db, query.clone(), None).await.unwrap();
         assert_eq!(total, 1);
         assert_eq!(results[0].id, 1);
 
         // 中文模糊搜索 "备份工" (3个字符)
         query.search_term = Some(SearchTerm::Fuzzy("备份工".to_string()));
-        let (results, total) = query_posts(&db, query.clone()).await.unwrap();
+        let (results, total) = query_posts(&db, query.clone(), None).await.unwrap();
         assert_eq!(total, 1);
         assert_eq!(results[0].id, 4);
 
         // 中文模糊搜索 "备份很" (3个字符)
         query.search_term = Some(SearchTerm::Fuzzy("备份很".to_string()));
-        let (results, total) = query_posts(&db, query.clone()).await.unwrap();
+        let (results, total) = query_posts(&db, query.clone(), None).await.unwrap();
         assert_eq!(total, 1);
         assert_eq!(results[0].id, 5);
 
-        // 中英混合模糊搜索 "你好 rust"
+        // 中英混合模糊搜索 "你好 rust", 两个词都需匹配
         query.search_term = Some(SearchTerm::Fuzzy("你好 rust".to_string()));
-        let (results, total) = query_posts(&db, query.clone()).await.unwrap();
-        assert_eq!(total, 2);
+        let (results, total) = query_posts(&db, query.clone(), None).await.unwrap();
+        assert_eq!(total, 1);
         assert_eq!(results[0].id, 3);
 
         // 纯中文模糊搜索 "微博备" (3个字符)
         query.search_term = Some(SearchTerm::Fuzzy("微博备".to_string()));
-        let (results, total) = query_posts(&db, query.clone()).await.unwrap();
+        let (results, total) = query_posts(&db, query.clone(), None).await.unwrap();
         assert_eq!(total, 1);
         assert_eq!(results[0].id, 4);
 
         // 中英混合精确搜索 "rust 编程语言"
         query.search_term = Some(SearchTerm::Strict("rust 编程语言".to_string()));
-        let (results, total) = query_posts(&db, query.clone()).await.unwrap();
+        let (results, total) = query_posts(&db, query.clone(), None).await.unwrap();
         assert_eq!(total, 1);
         assert_eq!(results[0].id, 2);
 
         // Fuzzy search with no matches
         query.search_term = Some(SearchTerm::Fuzzy("nomatch".to_string()));
-        let (results, total) = query_posts(&db, query).await.unwrap();
+        let (results, total) = query_posts(&db, query, None).await.unwrap();
         assert_eq!(total, 0);
         assert_eq!(results.len(), 0);
     }
+
+    #[tokio::test]
+    async fn test_query_posts_date_range_across_offsets() {
+        let db = setup_db().await;
+        let created_at = [
+            // 2023-12-31T23:30:00Z
+            (3, "2024-01-01T07:30:00+08:00"),
+            // 2024-01-01T00:30:00Z
+            (1, "2023-12-31T19:30:00-05:00"),
+            // 2024-01-01T01:00:00Z
+            (2, "2024-01-01T01:00:00+00:00"),
+        ];
+        for (id, created_at) in created_at {
+            let post = Post {
+                id,
+                created_at: DateTime::parse_from_rfc3339(created_at).unwrap(),
+                ..Default::default()
+            };
+            let internal: PostInternal = post.try_into().unwrap();
+            save_post(&db, &internal).await.unwrap();
+        }
+
+        let new_year = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .unwrap()
+            .timestamp();
+        let mut query = PostQuery {
+            user_id: None,
+            start_date: Some(new_year),
+            end_date: None,
+            search_term: None,
+            is_favorited: false,
+            reverse_order: false,
+            page: 1,
+            posts_per_page: 10,
+            ..Default::default()
+        };
+        let (results, total) = query_posts(&db, query.clone(), None).await.unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(results.iter().map(|p| p.id).collect::<Vec<_>>(), [2, 1]);
+
+        query.start_date = None;
+        query.end_date = Some(new_year);
+        let (results, total) = query_posts(&db, query.clone(), None).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(results[0].id, 3);
+
+        // Ordering follows the actual instant, not the id
+        query.end_date = None;
+        query.reverse_order = true;
+        let ids = query_posts(&db, query, None)
+            .await
+            .unwrap()
+            .0
+            .into_iter()
+            .map(|p| p.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [3, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_query_posts_after() {
+        let db = setup_db().await;
+        // Several posts share a timestamp so that `id` has to break ties
+        for id in 1..=7 {
+            let post = Post {
+                id,
+                created_at: DateTime::from_timestamp(1_700_000_000 + (id % 3) * 60, 0)
+                    .unwrap()
+                    .fixed_offset(),
+                ..Default::default()
+            };
+            let internal: PostInternal = post.try_into().unwrap();
+            save_post(&db, &internal).await.unwrap();
+        }
+
+        let mut query = PostQuery {
+            user_id: None,
+            start_date: None,
+            end_date: None,
+            search_term: None,
+            is_favorited: false,
+            reverse_order: false,
+            page: 1,
+            posts_per_page: 100,
+            ..Default::default()
+        };
+        for reverse_order in [false, true] {
+            query.reverse_order = reverse_order;
+            let expected = query_posts(&db, query.clone(), None)
+                .await
+                .unwrap()
+                .0
+                .into_iter()
+                .map(|p| p.id)
+                .collect::<Vec<_>>();
+
+            let mut ids = Vec::new();
+            let mut after = None;
+            loop {
+                let batch = query_posts_after(&db, &query, None, after, 2)
+                    .await
+                    .unwrap();
+                let Some(&(_, last)) = batch.last() else {
+                    break;
+                };
+                after = Some(last);
+                ids.extend(batch.iter().map(|(p, _)| p.id));
+            }
+            assert_eq!(ids, expected);
+            assert_eq!(ids.len(), 7);
+        }
+        assert_eq!(count_posts(&db, &query, None).await.unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_query_posts_filters_and_sort() {
+        let db = setup_db().await;
+        let video: crate::models::PageInfo = serde_json::from_value(serde_json::json!({
+            "object_type": "video"
+        }))
+        .unwrap();
+        let posts = [
+            Post {
+                id: 1,
+                pic_ids: Some(vec!["pic".to_string()]),
+                source: Some("iPhone 15 Pro".to_string()),
+                region_name: Some("发布于 上海".to_string()),
+                attitudes_count: Some(5),
+                ..Default::default()
+            },
+            Post {
+                id: 2,
+                attitudes_count: Some(50),
+                reposts_count: Some(1),
+                ..Default::default()
+            },
+            Post {
+                id: 3,
+                page_info: Some(video),
+                reposts_count: Some(9),
+                ..Default::default()
+            },
+            Post {
+                id: 4,
+                source: Some("100%_real".to_string()),
+                ..Default::default()
+            },
+        ];
+        // (uid, retweeted_id) of each post; post 2 retweets post 1
+        let authors = [
+            (Some(10), None),
+            (Some(20), Some(1)),
+            (Some(30), None),
+            (None, None),
+        ];
+        for (post, (uid, retweeted_id)) in posts.into_iter().zip(authors) {
+            let mut internal: PostInternal = Post {
+                created_at: DateTime::from_timestamp(1_700_000_000 + post.id * 60, 0)
+                    .unwrap()
+                    .fixed_offset(),
+                ..post
+            }
+            .try_into()
+            .unwrap();
+            internal.uid = uid;
+            internal.retweeted_id = retweeted_id;
+            save_post(&db, &internal).await.unwrap();
+        }
+
+        let base = PostQuery {
+            page: 1,
+            posts_per_page: 10,
+            ..Default::default()
+        };
+        let ids = |query: PostQuery| {
+            let db = db.clone();
+            async move {
+                query_posts(&db, query, None)
+                    .await
+                    .unwrap()
+                    .0
+                    .into_iter()
+                    .map(|p| p.id)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        // Media of the retweeted post counts for the retweet
+        let query = PostQuery {
+            has_pictures: Some(true),
+            ..base.clone()
+        };
+        assert_eq!(ids(query).await, [2, 1]);
+        let query = PostQuery {
+            has_pictures: Some(false),
+            ..base.clone()
+        };
+        assert_eq!(ids(query).await, [4, 3]);
+        let query = PostQuery {
+            has_video: Some(true),
+            ..base.clone()
+        };
+        assert_eq!(ids(query).await, [3]);
+        let query = PostQuery {
+            has_livephoto: Some(true),
+            ..base.clone()
+        };
+        assert!(ids(query).await.is_empty());
+
+        let query = PostQuery {
+            is_retweet: Some(true),
+            ..base.clone()
+        };
+        assert_eq!(ids(query).await, [2]);
+        let query = PostQuery {
+            is_retweet: Some(false),
+            ..base.clone()
+        };
+        assert_eq!(ids(query).await, [4, 3, 1]);
+        let query = PostQuery {
+            retweeted_user_id: Some(10),
+            ..base.clone()
+        };
+        assert_eq!(ids(query).await, [2]);
+
+        let query = PostQuery {
+            source: Some("iphone".to_string()),
+            ..base.clone()
+        };
+        assert_eq!(ids(query).await, [1]);
+        // LIKE wildcards are matched literally
+        let query = PostQuery {
+            source: Some("0%_".to_string()),
+            ..base.clone()
+        };
+        assert_eq!(ids(query).await, [4]);
+        let query = PostQuery {
+            region_name: Some("上海".to_string()),
+            ..base.clone()
+        };
+        assert_eq!(ids(query).await, [1]);
+
+        let query = PostQuery {
+            user_id: Some(10),
+            user_ids: vec![30],
+            ..base.clone()
+        };
+        assert_eq!(ids(query).await, [3, 1]);
+        let query = PostQuery {
+            exclude_user_ids: vec![10, 20],
+            ..base.clone()
+        };
+        assert_eq!(ids(query).await, [4, 3]);
+
+        // Missing counts sort as zero, ties are broken by id
+        let query = PostQuery {
+            sort_by: PostSortBy::AttitudesCount,
+            ..base.clone()
+        };
+        assert_eq!(ids(query.clone()).await, [2, 1, 4, 3]);
+        let query = PostQuery {
+            sort_by: PostSortBy::RepostsCount,
+            reverse_order: true,
+            ..base.clone()
+        };
+        assert_eq!(ids(query.clone()).await, [1, 4, 2, 3]);
+
+        let mut paged = Vec::new();
+        let mut after = None;
+        loop {
+            let batch = query_posts_after(&db, &query, None, after, 1)
+                .await
+                .unwrap();
+            let Some(&(_, last)) = batch.last() else {
+                break;
+            };
+            after = Some(last);
+            paged.extend(batch.iter().map(|(p, _)| p.id));
+        }
+        assert_eq!(paged, [1, 4, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_query_posts_search_language() {
+        let db = setup_db().await;
+        let texts = [
+            (1, Some(10), "今天发现一个 exact phrase 很好用"),
+            (2, Some(10), "推广内容 exact phrase 限时优惠"),
+            (3, Some(20), "别人的 exact phrase 分享"),
+            (4, Some(10), "phrase exact 顺序不同"),
+        ];
+        for (id, uid, text) in texts {
+            let mut internal: PostInternal = Post {
+                id,
+                text: text.to_string(),
+                created_at: DateTime::from_timestamp(1_700_000_000 + id * 60, 0)
+                    .unwrap()
+                    .fixed_offset(),
+                ..Default::default()
+            }
+            .try_into()
+            .unwrap();
+            internal.uid = uid;
+            save_post(&db, &internal).await.unwrap();
+        }
+
+        let query = PostQuery {
+            search_term: Some(SearchTerm::Query(
+                r#"from:10 -推广内容 "exact phrase""#.to_string(),
+            )),
+            page: 1,
+            posts_per_page: 10,
+            ..Default::default()
+        };
+        let (results, total) = query_posts(&db, query, None).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(results[0].id, 1);
+
+        // `from:` narrows the view of a user instead of adding to it
+        let query = PostQuery {
+            user_id: Some(20),
+            search_term: Some(SearchTerm::Query(r#"from:10 "exact phrase""#.to_string())),
+            page: 1,
+            posts_per_page: 10,
+            ..Default::default()
+        };
+        assert_eq!(query_posts(&db, query, None).await.unwrap().1, 0);
+        let query = PostQuery {
+            user_id: Some(10),
+            search_term: Some(SearchTerm::Query(r#"from:10 "exact phrase""#.to_string())),
+            page: 1,
+            posts_per_page: 10,
+            ..Default::default()
+        };
+        assert_eq!(query_posts(&db, query, None).await.unwrap().1, 2);
+
+        let query = PostQuery {
+            search_term: Some(SearchTerm::Query(
+                "-推广内容 顺序不同 OR 别人的".to_string(),
+            )),
+            page: 1,
+            posts_per_page: 10,
+            ..Default::default()
+        };
+        let ids = query_posts(&db, query, None)
+            .await
+            .unwrap()
+            .0
+            .into_iter()
+            .map(|p| p.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [4, 3]);
+
+        let query = PostQuery {
+            search_term: Some(SearchTerm::Query("from:abc".to_string())),
+            page: 1,
+            posts_per_page: 10,
+            ..Default::default()
+        };
+        let err = query_posts(&db, query, None).await.unwrap_err();
+        assert!(err.to_string().contains("位置 5"), "{err}");
+    }
+
+    async fn save_texts(db: &SqlitePool, texts: &[(i64, &str, Option<i64>)]) {
+        for &(id, text, retweeted_id) in texts {
+            let mut internal: PostInternal = Post {
+                id,
+                text: text.to_string(),
+                created_at: DateTime::from_timestamp(1_700_000_000 + id * 60, 0)
+                    .unwrap()
+                    .fixed_offset(),
+                ..Default::default()
+            }
+            .try_into()
+            .unwrap();
+            internal.retweeted_id = retweeted_id;
+            save_post(db, &internal).await.unwrap();
+        }
+    }
+
+    async fn search_ids(db: &SqlitePool, search_term: SearchTerm) -> Vec<i64> {
+        let query = PostQuery {
+            search_term: Some(search_term),
+            page: 1,
+            posts_per_page: 10,
+            ..Default::default()
+        };
+        let folder = script_folding::load_folder(db).await.unwrap();
+        query_posts(db, query, folder.as_ref())
+            .await
+            .unwrap()
+            .0
+            .into_iter()
+            .map(|p| p.id)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_query_posts_short_cjk_terms() {
+        let db = setup_db().await;
+        save_texts(
+            &db,
+            &[
+                (1, "我家的猫很可爱", None),
+                (2, "北京今天下雨了", None),
+                (3, "上海的猫也很可爱", None),
+                (4, "转发微博", Some(2)),
+                (5, "100%_纯天然", None),
+            ],
+        )
+        .await;
+
+        let fuzzy = |s: &str| SearchTerm::Fuzzy(s.to_string());
+        let strict = |s: &str| SearchTerm::Strict(s.to_string());
+        let query = |s: &str| SearchTerm::Query(s.to_string());
+
+        assert_eq!(search_ids(&db, fuzzy("猫")).await, [3, 1]);
+        // the retweet matches through its retweeted post
+        assert_eq!(search_ids(&db, fuzzy("北京")).await, [4, 2]);
+        assert_eq!(search_ids(&db, fuzzy("猫 上海")).await, [3]);
+        // short and trigram-sized terms mix
+        assert_eq!(search_ids(&db, fuzzy("猫 很可爱")).await, [3, 1]);
+        assert_eq!(search_ids(&db, strict("的猫")).await, [3, 1]);
+        assert_eq!(search_ids(&db, query("猫 -上海")).await, [1]);
+        assert_eq!(search_ids(&db, query("上海 OR 北京")).await, [4, 3, 2]);
+        assert_eq!(search_ids(&db, query("-猫 -北京")).await, [5]);
+        // LIKE wildcards in short terms are matched literally
+        assert_eq!(search_ids(&db, strict("%_")).await, [5]);
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_fts() {
+        let db = setup_db().await;
+        save_texts(&db, &[(1, "我家的猫很可爱", None)]).await;
+        let term = || SearchTerm::Fuzzy("猫很可爱".to_string());
+        assert_eq!(search_ids(&db, term()).await, [1]);
+
+        sqlx::query("DELETE FROM posts_fts")
+            .execute(&db)
+            .await
+            .unwrap();
+        assert!(search_ids(&db, term()).await.is_empty());
+
+        rebuild_fts(&db).await.unwrap();
+        assert_eq!(search_ids(&db, term()).await, [1]);
+    }
+
+    #[tokio::test]
+    async fn test_save_same_post_keeps_search_index() {
+        let db = setup_db().await;
+        save_texts(&db, &[(1, "我家的猫很可爱", None)]).await;
+        let indexed = || async {
+            sqlx::query_scalar::<Sqlite, String>("SELECT text FROM posts_fts WHERE rowid = 1")
+                .fetch_one(&db)
+                .await
+                .unwrap()
+        };
+        sqlx::query("UPDATE posts_fts SET text = 'stale' WHERE rowid = 1")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        // saving the post unchanged does not re-index it
+        save_texts(&db, &[(1, "我家的猫很可爱", None)]).await;
+        assert_eq!(indexed().await, "stale");
+
+        save_texts(&db, &[(1, "我家的狗很可爱", None)]).await;
+        assert_eq!(indexed().await, "我家的狗很可爱");
+    }
+
+    #[tokio::test]
+    async fn test_query_posts_relevance() {
+        let db = setup_db().await;
+        save_texts(
+            &db,
+            &[
+                (1, "猫很可爱", None),
+                (2, "今天天气不错，出门散步的时候顺便看到一只猫很可爱", None),
+                (3, "没有相关的内容", None),
+            ],
+        )
+        .await;
+
+        let query = |sort_by, reverse_order| PostQuery {
+            search_term: Some(SearchTerm::Fuzzy("猫很可爱".to_string())),
+            sort_by,
+            reverse_order,
+            page: 1,
+            posts_per_page: 10,
+            ..Default::default()
+        };
+        let ids = |posts: Vec<PostInternal>| posts.into_iter().map(|p| p.id).collect::<Vec<_>>();
+
+        let (posts, total) = query_posts(&db, query(PostSortBy::CreatedAt, false), None)
+            .await
+            .unwrap();
+        assert_eq!((ids(posts), total), (vec![2, 1], 2));
+        // the shorter post is the closer match
+        let (posts, _) = query_posts(&db, query(PostSortBy::Relevance, false), None)
+            .await
+            .unwrap();
+        assert_eq!(ids(posts), [1, 2]);
+        let (posts, _) = query_posts(&db, query(PostSortBy::Relevance, true), None)
+            .await
+            .unwrap();
+        assert_eq!(ids(posts), [2, 1]);
+
+        let query = query(PostSortBy::Relevance, false);
+        let mut after = None;
+        let mut streamed = Vec::new();
+        loop {
+            let batch = query_posts_after(&db, &query, None, after, 1)
+                .await
+                .unwrap();
+            let Some(&(_, last)) = batch.last() else {
+                break;
+            };
+            streamed.extend(batch.iter().map(|(p, _)| p.id));
+            after = Some(last);
+        }
+        assert_eq!(streamed, [1, 2]);
+
+        // without a search term relevance falls back to the post time
+        let (posts, _) = query_posts(
+            &db,
+            PostQuery {
+                sort_by: PostSortBy::Relevance,
+                page: 1,
+                posts_per_page: 10,
+                ..Default::default()
+            },
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(ids(posts), [3, 2, 1]);
+    }
+
+    fn test_user(id: i64, screen_name: &str) -> crate::models::User {
+        let url =
+            url::Url::parse("https://tvax1.sinaimg.cn/default/images/default_avatar.gif").unwrap();
+        crate::models::User {
+            avatar_hd: url.clone(),
+            avatar_large: url.clone(),
+            domain: String::new(),
+            following: false,
+            follow_me: false,
+            id,
+            profile_image_url: url,
+            screen_name: screen_name.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_post_metadata() {
+        use crate::storage::internal::user;
+
+        let db = setup_db().await;
+        save_texts(&db, &[(1, "hello", None), (2, "world", None)]).await;
+        let mut internal = get_post(&db, 1).await.unwrap().unwrap();
+        internal.uid = Some(10);
+        internal.url_struct = Some(serde_json::json!([{"url_title": "北京天气预报"}]));
+        internal.page_info = Some(serde_json::json!({"page_title": "上海美食推荐"}));
+        save_post(&db, &internal).await.unwrap();
+        sqlx::query("UPDATE posts SET tag_struct = ? WHERE id = 2")
+            .bind(r#"[{"tag_name": "旅行攻略分享"}]"#)
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let fuzzy = |s: &str| SearchTerm::Fuzzy(s.to_string());
+        assert_eq!(search_ids(&db, fuzzy("天气预报")).await, [1]);
+        assert_eq!(search_ids(&db, fuzzy("美食推荐")).await, [1]);
+        assert_eq!(search_ids(&db, fuzzy("攻略分享")).await, [2]);
+        // short terms also look at the metadata
+        assert_eq!(search_ids(&db, fuzzy("攻略")).await, [2]);
+
+        // the author is indexed once saved, and follows renames
+        assert!(search_ids(&db, fuzzy("猫咪日记")).await.is_empty());
+        user::save_user(&db, &test_user(10, "猫咪日记"))
+            .await
+            .unwrap();
+        assert_eq!(search_ids(&db, fuzzy("猫咪日记")).await, [1]);
+        user::save_user(&db, &test_user(10, "狗狗日记"))
+            .await
+            .unwrap();
+        assert!(search_ids(&db, fuzzy("猫咪日记")).await.is_empty());
+        assert_eq!(search_ids(&db, fuzzy("狗狗日记")).await, [1]);
+
+        rebuild_fts(&db).await.unwrap();
+        assert_eq!(search_ids(&db, fuzzy("狗狗日记")).await, [1]);
+        assert_eq!(search_ids(&db, fuzzy("攻略分享")).await, [2]);
+    }
+
+    #[tokio::test]
+    async fn test_search_snippets() {
+        let db = setup_db().await;
+        save_texts(
+            &db,
+            &[
+                (1, "今天在公园里看到一只猫很可爱的样子", None),
+                (2, "转发", Some(1)),
+                (3, "无关", None),
+            ],
+        )
+        .await;
+        let query = |term: &str| PostQuery {
+            search_term: Some(SearchTerm::Fuzzy(term.to_string())),
+            ..Default::default()
+        };
+
+        let snippets = search_snippets(&db, &query("猫很可爱"), None, &[1, 2, 3])
+            .await
+            .unwrap();
+        assert_eq!(snippets.len(), 2);
+        // the retweet is highlighted through its retweeted post
+        assert_eq!(snippets[&1], snippets[&2]);
+        let parts = &snippets[&1];
+        assert_eq!(
+            parts.iter().map(|p| p.text.as_str()).collect::<String>(),
+            "今天在公园里看到一只猫很可爱的样子"
+        );
+        assert_eq!(
+            parts
+                .iter()
+                .filter(|p| p.highlighted)
+                .map(|p| p.text.as_str())
+                .collect::<Vec<_>>(),
+            ["猫很可爱"]
+        );
+
+        // short terms are not highlighted
+        assert!(
+            search_snippets(&db, &query("猫"), None, &[1, 2])
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_script_folding() {
+        let db = setup_db().await;
+        save_texts(&db, &[(1, "我愛臺灣", None), (2, "台湾很美", None)]).await;
+        let fuzzy = |s: &str| SearchTerm::Fuzzy(s.to_string());
+
+        assert_eq!(search_ids(&db, fuzzy("台湾")).await, [2]);
+        assert_eq!(search_ids(&db, fuzzy("臺灣")).await, [1]);
+
+        script_folding::set_enabled(&db, true).await.unwrap();
+        assert_eq!(search_ids(&db, fuzzy("台湾")).await, [2, 1]);
+        assert_eq!(search_ids(&db, fuzzy("臺灣")).await, [2, 1]);
+        assert_eq!(search_ids(&db, fuzzy("爱台湾")).await, [1]);
+        assert_eq!(
+            search_ids(&db, SearchTerm::Query("台湾 -愛".to_string())).await,
+            [2]
+        );
+        // new posts are folded by the triggers, the stored text is untouched
+        save_texts(&db, &[(3, "臺灣好", None)]).await;
+        assert_eq!(search_ids(&db, fuzzy("台湾好")).await, [3]);
+        assert_eq!(get_post(&db, 3).await.unwrap().unwrap().text, "臺灣好");
+
+        // snippets show what was written, never the folded text
+        let snippets = |term: &str, id: i64| {
+            let query = PostQuery {
+                search_term: Some(fuzzy(term)),
+                ..Default::default()
+            };
+            let db = db.clone();
+            async move {
+                let folder = script_folding::load_folder(&db).await.unwrap();
+                search_snippets(&db, &query, folder.as_ref(), &[id])
+                    .await
+                    .unwrap()
+            }
+        };
+        assert!(
+            snippets("台湾很", 2).await[&2]
+                .iter()
+                .any(|p| p.highlighted && p.text == "台湾很")
+        );
+        assert_eq!(
+            snippets("爱台湾", 1).await[&1],
+            [SnippetPart {
+                text: "我愛臺灣".to_string(),
+                highlighted: false,
+            }]
+        );
+
+        script_folding::set_enabled(&db, false).await.unwrap();
+        assert_eq!(search_ids(&db, fuzzy("台湾")).await, [2]);
+    }
+
+    #[tokio::test]
+    async fn test_mentions_and_topics() {
+        let db = setup_db().await;
+        save_texts(
+            &db,
+            &[
+                (1, "#春节# 和@小明 一起回家", None),
+                (2, "@小红 @小明 #旅行#", None),
+                (3, "联系 test@example.com", None),
+            ],
+        )
+        .await;
+        let ids = |mentioned: Option<&str>, topic: Option<&str>| {
+            let query = PostQuery {
+                mentioned: mentioned.map(str::to_string),
+                topic: topic.map(str::to_string),
+                ..Default::default()
+            };
+            let db = db.clone();
+            async move {
+                let mut ids = query_all_post_ids(&db, query, None).await.unwrap();
+                ids.sort();
+                ids
+            }
+        };
+
+        assert_eq!(ids(Some("小明"), None).await, [1, 2]);
+        assert_eq!(ids(Some("@小红"), None).await, [2]);
+        assert_eq!(ids(None, Some("#春节#")).await, [1]);
+        assert_eq!(ids(Some("小明"), Some("旅行")).await, [2]);
+        assert!(ids(Some("example.com"), None).await.is_empty());
+
+        // editing a post replaces its entries
+        save_texts(&db, &[(1, "一个人回家", None)]).await;
+        assert_eq!(ids(Some("小明"), None).await, [2]);
+        assert!(ids(None, Some("春节")).await.is_empty());
+
+        sqlx::query("DELETE FROM post_mentions")
+            .execute(&db)
+            .await
+            .unwrap();
+        assert!(ids(Some("小明"), None).await.is_empty());
+        reindex_mentions_and_topics(&db, &[1, 2, 3]).await.unwrap();
+        assert_eq!(ids(Some("小明"), None).await, [2]);
+
+        delete_post(&db, 2).await.unwrap();
+        let mentions: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM post_mentions")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(mentions, 0);
+    }
+
+    #[tokio::test]
+    async fn test_location_filters() {
+        let db = setup_db().await;
+        save_texts(
+            &db,
+            &[(1, "三里屯", None), (2, "外滩", None), (3, "在家", None)],
+        )
+        .await;
+        for (id, lat, lon, place) in [
+            (1, 39.93, 116.45, "北京·三里屯"),
+            (2, 31.24, 121.49, "上海·外滩"),
+        ] {
+            let mut post = get_post(&db, id).await.unwrap().unwrap();
+            post.geo = Some(serde_json::json!({"type": "Point", "coordinates": [lat, lon]}));
+            post.tag_struct = Some(serde_json::json!([{
+                "tag_name": place,
+                "url_type_pic": "https://h5.sinaimg.cn/upload/location.png",
+                "otype": "place",
+            }]));
+            save_post(&db, &post).await.unwrap();
+        }
+        let ids = |query: PostQuery| {
+            let db = db.clone();
+            async move {
+                let mut ids = query_all_post_ids(&db, query, None).await.unwrap();
+                ids.sort();
+                ids
+            }
+        };
+
+        let located = PostQuery {
+            has_location: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(ids(located).await, [1, 2]);
+        let unlocated = PostQuery {
+            has_location: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(ids(unlocated).await, [3]);
+        let beijing = PostQuery {
+            bbox: Some(BoundingBox {
+                min_latitude: 39.4,
+                min_longitude: 115.4,
+                max_latitude: 41.1,
+                max_longitude: 117.5,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(ids(beijing).await, [1]);
+        let bund = PostQuery {
+            place: Some("外滩".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(ids(bund).await, [2]);
+    }
+
+    #[test]
+    fn test_snippet_parts() {
+        let part = |text: &str, highlighted| SnippetPart {
+            text: text.to_string(),
+            highlighted,
+        };
+        assert_eq!(
+            snippet_parts("…看到\u{E000}猫很可爱\u{E001}的\u{E000}样子\u{E001}"),
+            [
+                part("…看到", false),
+                part("猫很可爱", true),
+                part("的", false),
+                part("样子", true),
+            ]
+        );
+        assert_eq!(snippet_parts("\u{E000}全部\u{E001}"), [part("全部", true)]);
+    }
+
+    #[test]
+    fn test_pick_snippet() {
+        let part = |text: &str, highlighted| SnippetPart {
+            text: text.to_string(),
+            highlighted,
+        };
+        let columns = [
+            Some("正文".to_string()),
+            None,
+            Some("\u{E000}链接\u{E001}标题".to_string()),
+            None,
+            None,
+        ];
+        assert_eq!(
+            pick_snippet(&columns, Some("正文")),
+            [part("链接", true), part("标题", false)]
+        );
+        let long = "字".repeat(30);
+        assert_eq!(
+            pick_snippet(&[None, None, None, None, None], Some(&long)),
+            [part(&format!("{}…", "字".repeat(24)), false)]
+        );
+    }
 }
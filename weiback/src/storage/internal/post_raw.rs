@@ -0,0 +1,148 @@
+//! This module provides functions for interacting with the `posts_raw` table in the database.
+//!
+//! It keeps the raw JSON returned by the Weibo API for each post, so that the `posts`
+//! rows can be re-derived offline whenever the models learn to parse more fields.
+//! The JSON is stored gzip-compressed.
+//!
+//! # Table Structure: `posts_raw`
+//!
+//! | Column | Type      | Description                                          |
+//! |--------|-----------|------------------------------------------------------|
+//! | `id`   | `INTEGER` | The ID of the post. **Primary Key.**                 |
+//! | `raw`  | `BLOB`    | Gzip-compressed raw API JSON of the post.            |
+//!
+//! Rows are removed together with their post by the `posts_raw_ad` trigger.
+
+use std::io::{Read, Write};
+
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use sea_query::{Expr, ExprTrait, Iden, OnConflict, Query, SqliteQueryBuilder};
+use sea_query_sqlx::SqlxBinder;
+use serde_json::Value;
+use sqlx::{AssertSqlSafe, Executor, Sqlite};
+
+use crate::error::Result;
+
+#[derive(Iden)]
+#[iden = "posts_raw"]
+enum PostRawIden {
+    Table,
+    Id,
+    Raw,
+}
+
+/// Serializes and gzip-compresses a JSON value.
+fn compress(raw: &Value) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    serde_json::to_writer(&mut encoder, raw)?;
+    encoder.flush()?;
+    Ok(encoder.finish()?)
+}
+
+/// Decompresses and parses a blob produced by [`compress`].
+fn decompress(blob: &[u8]) -> Result<Value> {
+    let mut json = Vec::new();
+    GzDecoder::new(blob).read_to_end(&mut json)?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+/// Saves the raw API JSON of a post, replacing any previous one.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `id` - The ID of the post.
+/// * `raw` - The raw JSON of the post as returned by the API.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn save_post_raw<'e, E>(executor: E, id: i64, raw: &Value) -> Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let (sql, values) = Query::insert()
+        .into_table(PostRawIden::Table)
+        .columns([PostRawIden::Id, PostRawIden::Raw])
+        .values([id.into(), compress(raw)?.into()])?
+        .on_conflict(
+            OnConflict::column(PostRawIden::Id)
+                .update_column(PostRawIden::Raw)
+                .to_owned(),
+        )
+        .build_sqlx(SqliteQueryBuilder);
+    sqlx::query_with(AssertSqlSafe(sql), values)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Retrieves the raw API JSON of a post.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `id` - The ID of the post.
+///
+/// # Returns
+///
+/// A `Result` containing `Some(Value)` if raw JSON was kept for the post, `None` otherwise.
+pub async fn get_post_raw<'e, E>(executor: E, id: i64) -> Result<Option<Value>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let (sql, values) = Query::select()
+        .column(PostRawIden::Raw)
+        .from(PostRawIden::Table)
+        .and_where(Expr::col(PostRawIden::Id).eq(id))
+        .build_sqlx(SqliteQueryBuilder);
+    let blob = sqlx::query_scalar_with::<Sqlite, Vec<u8>, _>(AssertSqlSafe(sql), values)
+        .fetch_optional(executor)
+        .await?;
+    blob.as_deref().map(decompress).transpose()
+}
+
+#[cfg(test)]
+mod local_tests {
+    use serde_json::json;
+    use sqlx::SqlitePool;
+
+    use super::*;
+    use crate::models::Post;
+    use crate::storage::database::create_db_pool_with_url;
+    use crate::storage::internal::post::{self, PostInternal};
+
+    async fn setup_db() -> SqlitePool {
+        create_db_pool_with_url(":memory:").await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_save_and_get_post_raw() {
+        let db = setup_db().await;
+        let raw = json!({"id": 1, "mblogtype": 2, "isTop": 1, "text": "微博备份"});
+
+        assert!(get_post_raw(&db, 1).await.unwrap().is_none());
+        save_post_raw(&db, 1, &raw).await.unwrap();
+        assert_eq!(get_post_raw(&db, 1).await.unwrap(), Some(raw));
+
+        let updated = json!({"id": 1, "title": {"text": "置顶"}});
+        save_post_raw(&db, 1, &updated).await.unwrap();
+        assert_eq!(get_post_raw(&db, 1).await.unwrap(), Some(updated));
+    }
+
+    #[tokio::test]
+    async fn test_post_raw_deleted_with_post() {
+        let db = setup_db().await;
+        let internal: PostInternal = Post {
+            id: 1,
+            ..Default::default()
+        }
+        .try_into()
+        .unwrap();
+        post::save_post(&db, &internal).await.unwrap();
+        save_post_raw(&db, 1, &json!({"id": 1})).await.unwrap();
+
+        post::delete_post(&db, 1).await.unwrap();
+        assert!(get_post_raw(&db, 1).await.unwrap().is_none());
+    }
+}
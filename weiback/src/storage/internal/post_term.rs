@@ -0,0 +1,300 @@
+//! This module provides functions for interacting with the `post_mentions` and
+//! `post_topics` tables in the database.
+//!
+//! They index the terms found in the text of each post: the screen names `@`-mentioned,
+//! as extracted by [`extract_mentions`](crate::utils::extract_mentions), and the topics
+//! (`#话题#`) used, as extracted by [`extract_topics`](crate::utils::extract_topics).
+//! Posts can then be looked up by who they mention or what topic they use, and both
+//! ranked by how often they occur. Both tables have the same shape, and [`PostTerm`]
+//! picks the one to work on.
+//!
+//! # Table Structure: `post_mentions`
+//!
+//! | Column        | Type      | Description                                      |
+//! |---------------|-----------|--------------------------------------------------|
+//! | `post_id`     | `INTEGER` | The ID of the mentioning post. **Primary Key.**  |
+//! | `screen_name` | `TEXT`    | The mentioned screen name. **Primary Key.**      |
+//!
+//! # Table Structure: `post_topics`
+//!
+//! | Column    | Type      | Description                                          |
+//! |-----------|-----------|------------------------------------------------------|
+//! | `post_id` | `INTEGER` | The ID of the post. **Primary Key.**                 |
+//! | `topic`   | `TEXT`    | The topic, without the `#`s. **Primary Key.**        |
+//!
+//! Rows of both tables are removed together with their post by the
+//! `post_mentions_topics_ad` trigger.
+
+use sea_query::{Alias, Expr, ExprTrait, Iden, Order, Query, SelectStatement, SqliteQueryBuilder};
+use sea_query_sqlx::SqlxBinder;
+use sqlx::{Acquire, AssertSqlSafe, Executor, Sqlite};
+
+use crate::core::task::{MentionCount, TopicCount};
+use crate::error::Result;
+
+#[derive(Iden)]
+#[iden = "posts"]
+enum PostIden {
+    Table,
+    Id,
+    Uid,
+}
+
+/// The kind of term indexed for posts, each kind in its own table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostTerm {
+    /// `@` mentions, in `post_mentions`.
+    Mention,
+    /// `#` topics, in `post_topics`.
+    Topic,
+}
+
+impl PostTerm {
+    /// The table the terms are kept in.
+    fn table(self) -> Alias {
+        Alias::new(match self {
+            PostTerm::Mention => "post_mentions",
+            PostTerm::Topic => "post_topics",
+        })
+    }
+
+    /// The column holding the term.
+    fn column(self) -> Alias {
+        Alias::new(match self {
+            PostTerm::Mention => "screen_name",
+            PostTerm::Topic => "topic",
+        })
+    }
+
+    /// The subquery selecting the IDs of the posts with the given term.
+    pub fn posts_with(self, term: &str) -> SelectStatement {
+        Query::select()
+            .column(Alias::new("post_id"))
+            .from(self.table())
+            .and_where(Expr::col(self.column()).eq(term))
+            .take()
+    }
+}
+
+/// Replaces the terms of a kind recorded for a post.
+///
+/// # Arguments
+///
+/// * `acquirer` - A database acquirer.
+/// * `kind` - The kind of the terms.
+/// * `post_id` - The ID of the post.
+/// * `terms` - The terms found in the post.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn save_post_terms<'c, A>(
+    acquirer: A,
+    kind: PostTerm,
+    post_id: i64,
+    terms: &[&str],
+) -> Result<()>
+where
+    A: Acquire<'c, Database = Sqlite>,
+{
+    let post_id_col = Alias::new("post_id");
+    let mut conn = acquirer.acquire().await?;
+    let (sql, values) = Query::delete()
+        .from_table(kind.table())
+        .and_where(Expr::col(post_id_col.clone()).eq(post_id))
+        .build_sqlx(SqliteQueryBuilder);
+    sqlx::query_with(AssertSqlSafe(sql), values)
+        .execute(&mut *conn)
+        .await?;
+    if terms.is_empty() {
+        return Ok(());
+    }
+
+    let mut insert = Query::insert();
+    insert
+        .into_table(kind.table())
+        .columns([post_id_col, kind.column()]);
+    for term in terms {
+        insert.values([post_id.into(), (*term).into()])?;
+    }
+    let (sql, values) = insert.build_sqlx(SqliteQueryBuilder);
+    sqlx::query_with(AssertSqlSafe(sql), values)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Ranks the terms of a kind by the number of posts using them.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `kind` - The kind of the terms.
+/// * `uid` - Only count posts by this user, e.g. to find who they mention most.
+/// * `limit` - The maximum number of terms to return.
+///
+/// # Returns
+///
+/// A `Result` containing the most used terms with their number of posts, most used
+/// first.
+async fn get_top_terms<'e, E>(
+    executor: E,
+    kind: PostTerm,
+    uid: Option<i64>,
+    limit: u64,
+) -> Result<Vec<(String, u64)>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let count = Alias::new("count");
+    let post_id = (kind.table(), Alias::new("post_id"));
+    let term = (kind.table(), kind.column());
+    let mut query = Query::select();
+    query
+        .column(term.clone())
+        .expr_as(Expr::col(post_id.clone()).count(), count.clone())
+        .from(kind.table())
+        .group_by_col(term.clone())
+        .order_by(count, Order::Desc)
+        .order_by(term, Order::Asc)
+        .limit(limit);
+    if let Some(uid) = uid {
+        query.and_where(
+            Expr::col(post_id).in_subquery(
+                Query::select()
+                    .column(PostIden::Id)
+                    .from(PostIden::Table)
+                    .and_where(Expr::col(PostIden::Uid).eq(uid))
+                    .take(),
+            ),
+        );
+    }
+
+    let (sql, values) = query.build_sqlx(SqliteQueryBuilder);
+    let rows = sqlx::query_as_with::<Sqlite, (String, i64), _>(AssertSqlSafe(sql), values)
+        .fetch_all(executor)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(term, count)| (term, count as u64))
+        .collect())
+}
+
+/// Ranks the mentioned screen names by the number of posts mentioning them.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `uid` - Only count posts by this user, e.g. to find who they mention most.
+/// * `limit` - The maximum number of screen names to return.
+///
+/// # Returns
+///
+/// A `Result` containing the most mentioned screen names, most mentioned first.
+pub async fn get_top_mentions<'e, E>(
+    executor: E,
+    uid: Option<i64>,
+    limit: u64,
+) -> Result<Vec<MentionCount>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let terms = get_top_terms(executor, PostTerm::Mention, uid, limit).await?;
+    Ok(terms
+        .into_iter()
+        .map(|(screen_name, count)| MentionCount { screen_name, count })
+        .collect())
+}
+
+/// Ranks the topics by the number of posts using them.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `uid` - Only count posts by this user.
+/// * `limit` - The maximum number of topics to return.
+///
+/// # Returns
+///
+/// A `Result` containing the most used topics, most used first.
+pub async fn get_top_topics<'e, E>(
+    executor: E,
+    uid: Option<i64>,
+    limit: u64,
+) -> Result<Vec<TopicCount>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let terms = get_top_terms(executor, PostTerm::Topic, uid, limit).await?;
+    Ok(terms
+        .into_iter()
+        .map(|(topic, count)| TopicCount { topic, count })
+        .collect())
+}
+
+#[cfg(test)]
+mod local_tests {
+    use sqlx::SqlitePool;
+
+    use super::*;
+    use crate::storage::database::create_db_pool_with_url;
+
+    async fn setup_db() -> SqlitePool {
+        create_db_pool_with_url(":memory:").await.unwrap()
+    }
+
+    fn ranked(terms: &[(&str, u64)]) -> Vec<(String, u64)> {
+        terms
+            .iter()
+            .map(|&(term, count)| (term.to_string(), count))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_save_post_terms_and_rank() {
+        let db = setup_db().await;
+        for kind in [PostTerm::Mention, PostTerm::Topic] {
+            save_post_terms(&db, kind, 1, &["春节", "旅行"])
+                .await
+                .unwrap();
+            save_post_terms(&db, kind, 2, &["春节"]).await.unwrap();
+            save_post_terms(&db, kind, 3, &["美食"]).await.unwrap();
+
+            assert_eq!(
+                get_top_terms(&db, kind, None, 10).await.unwrap(),
+                ranked(&[("春节", 2), ("旅行", 1), ("美食", 1)])
+            );
+            assert_eq!(
+                get_top_terms(&db, kind, None, 1).await.unwrap(),
+                ranked(&[("春节", 2)])
+            );
+
+            // saving again replaces the terms of the post
+            save_post_terms(&db, kind, 1, &["美食"]).await.unwrap();
+            save_post_terms(&db, kind, 2, &[]).await.unwrap();
+            assert_eq!(
+                get_top_terms(&db, kind, None, 10).await.unwrap(),
+                ranked(&[("美食", 2)])
+            );
+        }
+
+        // each kind is kept apart
+        save_post_terms(&db, PostTerm::Mention, 4, &["张三"])
+            .await
+            .unwrap();
+        assert_eq!(
+            get_top_mentions(&db, None, 1).await.unwrap(),
+            [MentionCount {
+                screen_name: "美食".to_string(),
+                count: 2
+            }]
+        );
+        assert_eq!(
+            get_top_topics(&db, None, 10).await.unwrap(),
+            [TopicCount {
+                topic: "美食".to_string(),
+                count: 2
+            }]
+        );
+    }
+}
@@ -0,0 +1,389 @@
+//! This module provides functions for interacting with the `quarantine_batches` and
+//! `quarantine_rows` tables in the database.
+//!
+//! Rows removed by cleanup tasks are copied to `quarantine_rows` before being deleted,
+//! so a batch can be restored; see [`crate::quarantine`] for the files.
+//!
+//! # Table Structure: `quarantine_batches`
+//!
+//! | Column        | Type      | Description                                            |
+//! |---------------|-----------|--------------------------------------------------------|
+//! | `batch`       | `TEXT`    | The name of the batch and its folder. **Primary Key.** |
+//! | `task`        | `TEXT`    | The cleanup task that created the batch.               |
+//! | `created_at`  | `INTEGER` | When the batch was created, as a Unix timestamp.       |
+//! | `restored_at` | `INTEGER` | When the batch was restored, as a Unix timestamp.      |
+//!
+//! # Table Structure: `quarantine_rows`
+//!
+//! | Column       | Type   | Description                                               |
+//! |--------------|--------|-----------------------------------------------------------|
+//! | `batch`      | `TEXT` | The batch that removed the row.                           |
+//! | `table_name` | `TEXT` | The table the row was removed from.                       |
+//! | `row_data`   | `TEXT` | The row as a JSON object of its columns, BLOBs as hex.    |
+
+use itertools::Itertools;
+use sea_query::{Alias, Expr, ExprTrait, Iden, Query, SqliteQueryBuilder, Value};
+use sea_query_sqlx::SqlxBinder;
+use sqlx::{Acquire, AssertSqlSafe, Executor, Sqlite, SqliteConnection};
+
+use crate::error::Result;
+use crate::quarantine::QuarantineBatch;
+
+#[derive(Iden)]
+#[iden = "quarantine_batches"]
+enum QuarantineBatchIden {
+    Table,
+    Batch,
+    Task,
+    CreatedAt,
+    RestoredAt,
+}
+
+#[derive(Iden)]
+#[iden = "quarantine_rows"]
+enum QuarantineRowIden {
+    Table,
+    Batch,
+    TableName,
+    RowData,
+}
+
+/// The tables cleanup tasks remove rows from, in the order they are restored.
+///
+/// `posts` comes first so its triggers index the restored posts for search.
+pub const TABLES: [&str; 7] = [
+    "posts",
+    "posts_raw",
+    "post_mentions",
+    "post_topics",
+    "favorited_posts",
+    "picture",
+    "video",
+];
+
+/// Lists the columns of a table, with whether each holds BLOBs.
+pub(super) async fn get_columns(
+    conn: &mut SqliteConnection,
+    table: &str,
+) -> Result<Vec<(String, bool)>> {
+    let columns = sqlx::query_as::<Sqlite, (String, String)>(
+        "SELECT name, type FROM pragma_table_info(?) ORDER BY cid",
+    )
+    .bind(table)
+    .fetch_all(&mut *conn)
+    .await?;
+    Ok(columns
+        .into_iter()
+        .map(|(name, ty)| (name, ty.eq_ignore_ascii_case("BLOB")))
+        .collect())
+}
+
+/// Records a new quarantine batch.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `batch` - The name of the batch.
+/// * `task` - The cleanup task creating the batch.
+/// * `created_at` - When the batch was created, as a Unix timestamp.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn create_batch<'e, E>(
+    executor: E,
+    batch: &str,
+    task: &str,
+    created_at: i64,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let (sql, values) = Query::insert()
+        .into_table(QuarantineBatchIden::Table)
+        .columns([
+            QuarantineBatchIden::Batch,
+            QuarantineBatchIden::Task,
+            QuarantineBatchIden::CreatedAt,
+        ])
+        .values([batch.into(), task.into(), created_at.into()])?
+        .build_sqlx(SqliteQueryBuilder);
+    sqlx::query_with(AssertSqlSafe(sql), values)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Copies rows of a table to `quarantine_rows`, leaving them in place.
+///
+/// # Arguments
+///
+/// * `acquirer` - A database acquirer.
+/// * `batch` - The batch removing the rows.
+/// * `table` - The table of the rows, one of [`TABLES`].
+/// * `key` - The column the rows are picked by.
+/// * `keys` - The values of `key` to pick.
+///
+/// # Returns
+///
+/// A `Result` containing the number of rows copied.
+pub async fn quarantine_rows<'c, A>(
+    acquirer: A,
+    batch: &str,
+    table: &str,
+    key: &str,
+    keys: Vec<Value>,
+) -> Result<u64>
+where
+    A: Acquire<'c, Database = Sqlite>,
+{
+    if keys.is_empty() {
+        return Ok(0);
+    }
+    let mut conn = acquirer.acquire().await?;
+    let row = get_columns(&mut conn, table)
+        .await?
+        .into_iter()
+        .map(|(name, blob)| {
+            if blob {
+                format!("'{name}', hex(\"{name}\")")
+            } else {
+                format!("'{name}', \"{name}\"")
+            }
+        })
+        .join(", ");
+    let (sql, values) = Query::insert()
+        .into_table(QuarantineRowIden::Table)
+        .columns([
+            QuarantineRowIden::Batch,
+            QuarantineRowIden::TableName,
+            QuarantineRowIden::RowData,
+        ])
+        .select_from(
+            Query::select()
+                .expr(Expr::val(batch))
+                .expr(Expr::val(table))
+                .expr(Expr::cust(format!("json_object({row})")))
+                .from(Alias::new(table))
+                .and_where(Expr::col(Alias::new(key)).is_in(keys))
+                .to_owned(),
+        )?
+        .build_sqlx(SqliteQueryBuilder);
+    let result = sqlx::query_with(AssertSqlSafe(sql), values)
+        .execute(&mut *conn)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Puts the rows of a batch back into their tables and marks the batch as restored.
+///
+/// Rows whose key was saved again since the batch was created are dropped, keeping the
+/// newer row.
+///
+/// # Arguments
+///
+/// * `acquirer` - A database acquirer.
+/// * `batch` - The batch to restore.
+/// * `restored_at` - The current time, as a Unix timestamp.
+///
+/// # Returns
+///
+/// A `Result` containing the number of rows restored and dropped.
+pub async fn restore_rows<'c, A>(acquirer: A, batch: &str, restored_at: i64) -> Result<(u64, u64)>
+where
+    A: Acquire<'c, Database = Sqlite>,
+{
+    let mut tx = acquirer.begin().await?;
+    let mut restored = 0;
+    for table in TABLES {
+        let columns = get_columns(&mut tx, table).await?;
+        let names = columns
+            .iter()
+            .map(|(name, _)| format!("\"{name}\""))
+            .join(", ");
+        let fields = columns
+            .iter()
+            .map(|(name, blob)| {
+                if *blob {
+                    format!("unhex(json_extract(row_data, '$.{name}'))")
+                } else {
+                    format!("json_extract(row_data, '$.{name}')")
+                }
+            })
+            .join(", ");
+        let sql = format!(
+            "INSERT OR IGNORE INTO \"{table}\" ({names}) SELECT {fields} \
+             FROM quarantine_rows WHERE batch = ? AND table_name = ?"
+        );
+        restored += sqlx::query(AssertSqlSafe(sql))
+            .bind(batch)
+            .bind(table)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+    }
+
+    let (sql, values) = Query::delete()
+        .from_table(QuarantineRowIden::Table)
+        .and_where(Expr::col(QuarantineRowIden::Batch).eq(batch))
+        .build_sqlx(SqliteQueryBuilder);
+    let total = sqlx::query_with(AssertSqlSafe(sql), values)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    let (sql, values) = Query::update()
+        .table(QuarantineBatchIden::Table)
+        .value(QuarantineBatchIden::RestoredAt, restored_at)
+        .and_where(Expr::col(QuarantineBatchIden::Batch).eq(batch))
+        .build_sqlx(SqliteQueryBuilder);
+    sqlx::query_with(AssertSqlSafe(sql), values)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok((restored, total - restored))
+}
+
+/// Lists the quarantine batches, the newest first.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+///
+/// # Returns
+///
+/// A `Result` containing the batches with the number of rows kept for each.
+pub async fn list_batches<'e, E>(executor: E) -> Result<Vec<QuarantineBatch>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let (sql, values) = Query::select()
+        .columns([
+            QuarantineBatchIden::Batch,
+            QuarantineBatchIden::Task,
+            QuarantineBatchIden::CreatedAt,
+            QuarantineBatchIden::RestoredAt,
+        ])
+        .expr(Expr::cust(
+            "(SELECT COUNT(*) FROM quarantine_rows \
+             WHERE quarantine_rows.batch = quarantine_batches.batch)",
+        ))
+        .from(QuarantineBatchIden::Table)
+        .order_by(QuarantineBatchIden::CreatedAt, sea_query::Order::Desc)
+        .order_by(QuarantineBatchIden::Batch, sea_query::Order::Desc)
+        .build_sqlx(SqliteQueryBuilder);
+    let rows = sqlx::query_as_with::<Sqlite, (String, String, i64, Option<i64>, i64), _>(
+        AssertSqlSafe(sql),
+        values,
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(
+            |(batch, task, created_at, restored_at, rows)| QuarantineBatch {
+                batch,
+                task,
+                created_at,
+                restored_at,
+                rows,
+            },
+        )
+        .collect())
+}
+
+#[cfg(test)]
+mod local_tests {
+    use sqlx::SqlitePool;
+
+    use super::*;
+    use crate::storage::database::create_db_pool_with_url;
+
+    async fn setup_db() -> SqlitePool {
+        create_db_pool_with_url(":memory:").await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_quarantine_and_restore_rows() {
+        let db = setup_db().await;
+        sqlx::query("INSERT INTO posts (id, text, pic_ids) VALUES (1, 'hello', '[\"p1\"]')")
+            .execute(&db)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO posts_raw (id, raw) VALUES (1, x'00ff10')")
+            .execute(&db)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO picture (id, url, path, post_id) VALUES ('p1', 'u1', 'a/p1.jpg', 1), ('p2', 'u2', 'a/p2.jpg', 1)")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        create_batch(&db, "b1", "cleanup_invalid_posts", 100)
+            .await
+            .unwrap();
+        assert_eq!(
+            quarantine_rows(&db, "b1", "posts", "id", vec![1i64.into()])
+                .await
+                .unwrap(),
+            1
+        );
+        quarantine_rows(&db, "b1", "posts_raw", "id", vec![1i64.into()])
+            .await
+            .unwrap();
+        assert_eq!(
+            quarantine_rows(&db, "b1", "picture", "post_id", vec![1i64.into()])
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            quarantine_rows(&db, "b1", "video", "post_id", vec![])
+                .await
+                .unwrap(),
+            0
+        );
+        let batches = list_batches(&db).await.unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].rows, 4);
+        assert_eq!(batches[0].restored_at, None);
+
+        sqlx::query("DELETE FROM posts WHERE id = 1")
+            .execute(&db)
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM picture")
+            .execute(&db)
+            .await
+            .unwrap();
+        // Saved again since the cleanup, the newer row is kept
+        sqlx::query(
+            "INSERT INTO picture (id, url, path, post_id) VALUES ('p2', 'u2', 'b/p2.jpg', 1)",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        assert_eq!(restore_rows(&db, "b1", 200).await.unwrap(), (3, 1));
+        let post = sqlx::query_as::<Sqlite, (String, String)>(
+            "SELECT text, pic_ids FROM posts WHERE id = 1",
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        assert_eq!(post, ("hello".to_string(), "[\"p1\"]".to_string()));
+        let raw = sqlx::query_scalar::<Sqlite, Vec<u8>>("SELECT raw FROM posts_raw WHERE id = 1")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(raw, [0x00, 0xff, 0x10]);
+        let paths = sqlx::query_scalar::<Sqlite, String>("SELECT path FROM picture ORDER BY url")
+            .fetch_all(&db)
+            .await
+            .unwrap();
+        assert_eq!(paths, ["a/p1.jpg", "b/p2.jpg"]);
+
+        let batches = list_batches(&db).await.unwrap();
+        assert_eq!(batches[0].rows, 0);
+        assert_eq!(batches[0].restored_at, Some(200));
+    }
+}
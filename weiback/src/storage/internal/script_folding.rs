@@ -0,0 +1,138 @@
+//! This module handles folding traditional Chinese characters to simplified ones for search.
+//!
+//! When enabled for an archive, the `posts_fts_source` view fills the shadow `folded`
+//! column of `posts_fts` with the folded text of each post, and search terms are folded
+//! with [`ScriptFolder`] before they are matched, so that a search for "台湾" also finds
+//! "臺灣". The original text is indexed and displayed as it is.
+//!
+//! # Table Structure: `zh_fold_chars`
+//!
+//! | Column | Type   | Description                                          |
+//! |--------|--------|------------------------------------------------------|
+//! | `trad` | `TEXT` | A traditional character. **Primary Key.**            |
+//! | `simp` | `TEXT` | The simplified character it folds to.                |
+//!
+//! The table is filled by migrations and shared by the index triggers and [`load_folder`].
+
+use std::collections::HashMap;
+
+use sqlx::{Acquire, Executor, Sqlite};
+
+use super::{archive_setting, post};
+use crate::error::Result;
+
+/// The `archive_settings` key enabling script folding.
+const SCRIPT_FOLDING_KEY: &str = "script_folding";
+/// The value of [`SCRIPT_FOLDING_KEY`] folding to simplified characters, as checked by the
+/// `posts_fts_source` view.
+const SIMPLIFIED: &str = "simplified";
+
+/// Folds traditional Chinese characters to simplified ones.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptFolder(HashMap<char, char>);
+
+impl ScriptFolder {
+    /// Folds every character of `text` the same way the `posts_fts_source` view does.
+    pub fn fold(&self, text: &str) -> String {
+        text.chars()
+            .map(|c| self.0.get(&c).copied().unwrap_or(c))
+            .collect()
+    }
+}
+
+/// Checks whether script folding is enabled for the archive.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+///
+/// # Returns
+///
+/// A `Result` containing `true` if search text is folded to simplified characters.
+pub async fn is_enabled<'e, E>(executor: E) -> Result<bool>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    Ok(archive_setting::get_setting(executor, SCRIPT_FOLDING_KEY)
+        .await?
+        .is_some_and(|value| value == SIMPLIFIED))
+}
+
+/// Enables or disables script folding for the archive and rebuilds the search index
+/// accordingly, in one transaction.
+///
+/// # Arguments
+///
+/// * `acquirer` - A database acquirer.
+/// * `enabled` - Whether search text should be folded to simplified characters.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn set_enabled<'c, A>(acquirer: A, enabled: bool) -> Result<()>
+where
+    A: Acquire<'c, Database = Sqlite>,
+{
+    let mut tx = acquirer.begin().await?;
+    archive_setting::set_setting(&mut *tx, SCRIPT_FOLDING_KEY, enabled.then_some(SIMPLIFIED))
+        .await?;
+    post::rebuild_fts(&mut *tx).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Loads the folder for search terms if script folding is enabled for the archive.
+///
+/// # Arguments
+///
+/// * `acquirer` - A database acquirer.
+///
+/// # Returns
+///
+/// A `Result` containing `Some(ScriptFolder)` if script folding is enabled, `None` otherwise.
+pub async fn load_folder<'c, A>(acquirer: A) -> Result<Option<ScriptFolder>>
+where
+    A: Acquire<'c, Database = Sqlite>,
+{
+    let mut conn = acquirer.acquire().await?;
+    if !is_enabled(&mut *conn).await? {
+        return Ok(None);
+    }
+    let pairs = sqlx::query_as::<Sqlite, (String, String)>("SELECT trad, simp FROM zh_fold_chars")
+        .fetch_all(&mut *conn)
+        .await?;
+    Ok(Some(ScriptFolder(
+        pairs
+            .into_iter()
+            .filter_map(|(trad, simp)| Some((trad.chars().next()?, simp.chars().next()?)))
+            .collect(),
+    )))
+}
+
+#[cfg(test)]
+mod local_tests {
+    use sqlx::SqlitePool;
+
+    use super::*;
+    use crate::storage::database::create_db_pool_with_url;
+
+    async fn setup_db() -> SqlitePool {
+        create_db_pool_with_url(":memory:").await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_set_enabled_and_load_folder() {
+        let db = setup_db().await;
+        assert!(!is_enabled(&db).await.unwrap());
+        assert!(load_folder(&db).await.unwrap().is_none());
+
+        set_enabled(&db, true).await.unwrap();
+        assert!(is_enabled(&db).await.unwrap());
+        let folder = load_folder(&db).await.unwrap().unwrap();
+        assert_eq!(folder.fold("我愛臺灣, Taiwan!"), "我爱台湾, Taiwan!");
+        assert_eq!(folder.fold("台湾"), "台湾");
+
+        set_enabled(&db, false).await.unwrap();
+        assert!(load_folder(&db).await.unwrap().is_none());
+    }
+}
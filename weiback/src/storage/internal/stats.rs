@@ -0,0 +1,406 @@
+//! This module provides the aggregate queries behind the statistics report of an archive.
+//!
+//! The queries read the `posts`, `users`, `picture` and `video` tables; see their own
+//! modules for the columns. Only valid posts, i.e. those with a `uid` and `created_at`,
+//! are counted. Months, weekdays and hours are taken from `created_at` as written, in the
+//! post's own UTC offset, rather than from the normalized `created_at_ts`.
+
+use sea_query::{Alias, Expr, ExprTrait, Iden, Order, Query, SelectStatement, SqliteQueryBuilder};
+use sea_query_sqlx::SqlxBinder;
+use sqlx::{AssertSqlSafe, Executor, Sqlite};
+
+use crate::error::Result;
+use crate::stats::{HourlyPostCount, MonthlyPostCount, SourceCount};
+
+#[derive(Iden)]
+#[iden = "posts"]
+enum PostIden {
+    Table,
+    Id,
+    Uid,
+    CreatedAt,
+    Source,
+}
+
+#[derive(Iden)]
+#[iden = "users"]
+enum UserIden {
+    Table,
+    Id,
+    ScreenName,
+}
+
+#[derive(Iden)]
+#[iden = "picture"]
+enum PictureIden {
+    Table,
+    Path,
+    PostId,
+}
+
+#[derive(Iden)]
+#[iden = "video"]
+enum VideoIden {
+    Table,
+    Path,
+    PostId,
+}
+
+/// Restricts a query on `posts` to valid posts, optionally by one user.
+fn filter_posts(query: &mut SelectStatement, uid: Option<i64>) {
+    query
+        .and_where(Expr::col((PostIden::Table, PostIden::Uid)).is_not_null())
+        .and_where(Expr::col((PostIden::Table, PostIden::CreatedAt)).is_not_null());
+    if let Some(uid) = uid {
+        query.and_where(Expr::col((PostIden::Table, PostIden::Uid)).eq(uid));
+    }
+}
+
+/// Counts the original posts and retweets of each user per month.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `uid` - Only count posts by this user.
+///
+/// # Returns
+///
+/// A `Result` containing the counts, ordered by user and month.
+pub async fn get_monthly_post_counts<'e, E>(
+    executor: E,
+    uid: Option<i64>,
+) -> Result<Vec<MonthlyPostCount>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let month = Alias::new("month");
+    let mut query = Query::select();
+    query
+        .column((PostIden::Table, PostIden::Uid))
+        .column((UserIden::Table, UserIden::ScreenName))
+        .expr_as(Expr::cust("substr(posts.created_at, 1, 7)"), month.clone())
+        .expr(Expr::cust("SUM(posts.retweeted_id IS NULL)"))
+        .expr(Expr::cust("SUM(posts.retweeted_id IS NOT NULL)"))
+        .from(PostIden::Table)
+        .left_join(
+            UserIden::Table,
+            Expr::col((UserIden::Table, UserIden::Id)).equals((PostIden::Table, PostIden::Uid)),
+        )
+        .group_by_col((PostIden::Table, PostIden::Uid))
+        .group_by_col(month.clone())
+        .order_by((PostIden::Table, PostIden::Uid), Order::Asc)
+        .order_by(month, Order::Asc);
+    filter_posts(&mut query, uid);
+
+    let (sql, values) = query.build_sqlx(SqliteQueryBuilder);
+    let rows = sqlx::query_as_with::<Sqlite, (i64, Option<String>, String, i64, i64), _>(
+        AssertSqlSafe(sql),
+        values,
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(
+            |(uid, screen_name, month, originals, retweets)| MonthlyPostCount {
+                uid,
+                screen_name,
+                month,
+                originals: originals as u64,
+                retweets: retweets as u64,
+            },
+        )
+        .collect())
+}
+
+/// Counts the posts made in each hour of each weekday.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `uid` - Only count posts by this user.
+///
+/// # Returns
+///
+/// A `Result` containing the non-empty cells, ordered by weekday and hour.
+pub async fn get_hourly_post_counts<'e, E>(
+    executor: E,
+    uid: Option<i64>,
+) -> Result<Vec<HourlyPostCount>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let weekday = Alias::new("weekday");
+    let hour = Alias::new("hour");
+    let mut query = Query::select();
+    query
+        .expr_as(
+            Expr::cust("CAST(strftime('%w', substr(posts.created_at, 1, 19)) AS INTEGER)"),
+            weekday.clone(),
+        )
+        .expr_as(
+            Expr::cust("CAST(substr(posts.created_at, 12, 2) AS INTEGER)"),
+            hour.clone(),
+        )
+        .expr(Expr::col((PostIden::Table, PostIden::Id)).count())
+        .from(PostIden::Table)
+        .group_by_col(weekday.clone())
+        .group_by_col(hour.clone())
+        .order_by(weekday, Order::Asc)
+        .order_by(hour, Order::Asc);
+    filter_posts(&mut query, uid);
+
+    let (sql, values) = query.build_sqlx(SqliteQueryBuilder);
+    let rows = sqlx::query_as_with::<Sqlite, (Option<i64>, Option<i64>, i64), _>(
+        AssertSqlSafe(sql),
+        values,
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(weekday, hour, count)| {
+            Some(HourlyPostCount {
+                weekday: u8::try_from(weekday?).ok()?,
+                hour: u8::try_from(hour?).ok()?,
+                count: count as u64,
+            })
+        })
+        .collect())
+}
+
+/// Ranks the clients posts were sent from by the number of posts.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `uid` - Only count posts by this user.
+/// * `limit` - The maximum number of sources to return.
+///
+/// # Returns
+///
+/// A `Result` containing the most used sources, most used first.
+pub async fn get_top_sources<'e, E>(
+    executor: E,
+    uid: Option<i64>,
+    limit: u64,
+) -> Result<Vec<SourceCount>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let count = Alias::new("count");
+    let mut query = Query::select();
+    query
+        .column((PostIden::Table, PostIden::Source))
+        .expr_as(
+            Expr::col((PostIden::Table, PostIden::Id)).count(),
+            count.clone(),
+        )
+        .from(PostIden::Table)
+        .and_where(Expr::col((PostIden::Table, PostIden::Source)).is_not_null())
+        .and_where(Expr::col((PostIden::Table, PostIden::Source)).ne(""))
+        .group_by_col((PostIden::Table, PostIden::Source))
+        .order_by(count, Order::Desc)
+        .order_by((PostIden::Table, PostIden::Source), Order::Asc)
+        .limit(limit);
+    filter_posts(&mut query, uid);
+
+    let (sql, values) = query.build_sqlx(SqliteQueryBuilder);
+    let rows = sqlx::query_as_with::<Sqlite, (String, i64), _>(AssertSqlSafe(sql), values)
+        .fetch_all(executor)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(source, count)| SourceCount {
+            source,
+            count: count as u64,
+        })
+        .collect())
+}
+
+/// Lists the picture files saved for posts, with the author of each post.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `uid` - Only list pictures of posts by this user.
+///
+/// # Returns
+///
+/// A `Result` containing `(uid, path)` pairs, paths relative to the picture directory.
+pub async fn get_picture_paths_by_user<'e, E>(
+    executor: E,
+    uid: Option<i64>,
+) -> Result<Vec<(i64, String)>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let mut query = Query::select();
+    query
+        .column((PostIden::Table, PostIden::Uid))
+        .column((PictureIden::Table, PictureIden::Path))
+        .from(PictureIden::Table)
+        .inner_join(
+            PostIden::Table,
+            Expr::col((PostIden::Table, PostIden::Id))
+                .equals((PictureIden::Table, PictureIden::PostId)),
+        )
+        .and_where(Expr::col((PictureIden::Table, PictureIden::Path)).is_not_null());
+    filter_posts(&mut query, uid);
+
+    let (sql, values) = query.build_sqlx(SqliteQueryBuilder);
+    Ok(
+        sqlx::query_as_with::<Sqlite, (i64, String), _>(AssertSqlSafe(sql), values)
+            .fetch_all(executor)
+            .await?,
+    )
+}
+
+/// Lists the video files saved for posts, with the author of each post.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `uid` - Only list videos of posts by this user.
+///
+/// # Returns
+///
+/// A `Result` containing `(uid, path)` pairs, paths relative to the video directory.
+pub async fn get_video_paths_by_user<'e, E>(
+    executor: E,
+    uid: Option<i64>,
+) -> Result<Vec<(i64, String)>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let mut query = Query::select();
+    query
+        .column((PostIden::Table, PostIden::Uid))
+        .column((VideoIden::Table, VideoIden::Path))
+        .from(VideoIden::Table)
+        .inner_join(
+            PostIden::Table,
+            Expr::col((PostIden::Table, PostIden::Id))
+                .equals((VideoIden::Table, VideoIden::PostId)),
+        )
+        .and_where(Expr::col((VideoIden::Table, VideoIden::Path)).is_not_null());
+    filter_posts(&mut query, uid);
+
+    let (sql, values) = query.build_sqlx(SqliteQueryBuilder);
+    Ok(
+        sqlx::query_as_with::<Sqlite, (i64, String), _>(AssertSqlSafe(sql), values)
+            .fetch_all(executor)
+            .await?,
+    )
+}
+
+#[cfg(test)]
+mod local_tests {
+    use sqlx::SqlitePool;
+
+    use super::*;
+    use crate::storage::database::create_db_pool_with_url;
+
+    async fn setup_db() -> SqlitePool {
+        create_db_pool_with_url(":memory:").await.unwrap()
+    }
+
+    async fn insert_post(
+        db: &SqlitePool,
+        id: i64,
+        uid: i64,
+        created_at: &str,
+        retweeted_id: Option<i64>,
+        source: &str,
+    ) {
+        sqlx::query(
+            "INSERT INTO posts (id, uid, text, created_at, retweeted_id, source) \
+             VALUES (?, ?, '', ?, ?, ?)",
+        )
+        .bind(id)
+        .bind(uid)
+        .bind(created_at)
+        .bind(retweeted_id)
+        .bind(source)
+        .execute(db)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stats_queries() {
+        let db = setup_db().await;
+        sqlx::query("INSERT INTO users (id, screen_name) VALUES (1, '猫')")
+            .execute(&db)
+            .await
+            .unwrap();
+        // 2024-01-07 is a Sunday; the hour is taken in the post's own offset
+        insert_post(&db, 1, 1, "2024-01-07T23:30:00+08:00", None, "iPhone").await;
+        insert_post(&db, 2, 1, "2024-01-07T23:50:00+08:00", Some(9), "iPhone").await;
+        insert_post(&db, 3, 1, "2024-02-01T08:00:00+08:00", None, "Android").await;
+        insert_post(&db, 4, 2, "2024-02-02T08:00:00+08:00", None, "iPhone").await;
+        sqlx::query("INSERT INTO posts (id, text) VALUES (5, 'invalid')")
+            .execute(&db)
+            .await
+            .unwrap();
+        for (url, path, post_id) in [("a", "a.jpg", 1), ("b", "b.jpg", 4), ("c", "c.jpg", 5)] {
+            sqlx::query("INSERT INTO picture (url, path, post_id) VALUES (?, ?, ?)")
+                .bind(url)
+                .bind(path)
+                .bind(post_id)
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+
+        let monthly = get_monthly_post_counts(&db, None).await.unwrap();
+        let monthly = monthly
+            .iter()
+            .map(|m| {
+                (
+                    m.uid,
+                    m.screen_name.as_deref(),
+                    m.month.as_str(),
+                    m.originals,
+                    m.retweets,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            monthly,
+            [
+                (1, Some("猫"), "2024-01", 1, 1),
+                (1, Some("猫"), "2024-02", 1, 0),
+                (2, None, "2024-02", 1, 0),
+            ]
+        );
+        assert_eq!(
+            get_monthly_post_counts(&db, Some(2)).await.unwrap().len(),
+            1
+        );
+
+        let hourly = get_hourly_post_counts(&db, Some(1)).await.unwrap();
+        let hourly = hourly
+            .iter()
+            .map(|h| (h.weekday, h.hour, h.count))
+            .collect::<Vec<_>>();
+        assert_eq!(hourly, [(0, 23, 2), (4, 8, 1)]);
+
+        let sources = get_top_sources(&db, None, 1).await.unwrap();
+        assert_eq!(
+            sources,
+            [SourceCount {
+                source: "iPhone".to_string(),
+                count: 3
+            }]
+        );
+
+        let mut pictures = get_picture_paths_by_user(&db, None).await.unwrap();
+        pictures.sort();
+        assert_eq!(
+            pictures,
+            [(1, "a.jpg".to_string()), (2, "b.jpg".to_string())]
+        );
+        assert!(get_video_paths_by_user(&db, None).await.unwrap().is_empty());
+    }
+}
@@ -14,8 +14,12 @@
 //! | `id`                | `INTEGER` | Unique identifier for the user. **Primary Key.** |
 //! | `profile_image_url` | `TEXT`  | URL of the profile image.                         |
 //! | `screen_name`       | `TEXT`  | User's screen name or nickname.                   |
+//! | `statuses_count`    | `INTEGER` | The number of posts Weibo reports for the user. |
+//! | `statuses_count_at` | `INTEGER` | When `statuses_count` was seen, as a Unix timestamp. |
 //!
-//! The `id` column serves as the primary key for uniqueness.
+//! The `id` column serves as the primary key for uniqueness. `statuses_count` is not part
+//! of the [`User`] model; it is recorded from the raw API JSON of saved posts with
+//! [`save_statuses_count`], to tell how complete the archive of a user is.
 
 use sea_query::{Asterisk, Expr, ExprTrait, OnConflict, Query, SqliteQueryBuilder};
 use sea_query_sqlx::SqlxBinder;
@@ -37,6 +41,8 @@ enum UserIden {
     Id,
     ProfileImageUrl,
     ScreenName,
+    StatusesCount,
+    StatusesCountAt,
 }
 
 /// Represents the internal database structure for a user.
@@ -231,6 +237,80 @@ where
     users.into_iter().map(|u| u.try_into()).collect()
 }
 
+/// Records the number of posts Weibo reports for a user.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `id` - The ID of the user, who must already be saved.
+/// * `statuses_count` - The number of posts of the user.
+/// * `seen_at` - When the number was seen, as a Unix timestamp.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn save_statuses_count<'e, E>(
+    executor: E,
+    id: i64,
+    statuses_count: i64,
+    seen_at: i64,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let (sql, values) = Query::update()
+        .table(UserIden::Table)
+        .values([
+            (UserIden::StatusesCount, statuses_count.into()),
+            (UserIden::StatusesCountAt, seen_at.into()),
+        ])
+        .and_where(Expr::col(UserIden::Id).eq(id))
+        .build_sqlx(SqliteQueryBuilder);
+    sqlx::query_with(AssertSqlSafe(sql), values)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Retrieves the recorded numbers of posts Weibo reports for users.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `id` - Only retrieve the number of this user.
+///
+/// # Returns
+///
+/// A `Result` containing `(id, statuses_count, statuses_count_at)` for the users whose
+/// number has been recorded.
+pub async fn get_statuses_counts<'e, E>(
+    executor: E,
+    id: Option<i64>,
+) -> Result<Vec<(i64, i64, i64)>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let mut query = Query::select();
+    query
+        .columns([
+            UserIden::Id,
+            UserIden::StatusesCount,
+            UserIden::StatusesCountAt,
+        ])
+        .from(UserIden::Table)
+        .and_where(Expr::col(UserIden::StatusesCount).is_not_null())
+        .and_where(Expr::col(UserIden::StatusesCountAt).is_not_null());
+    if let Some(id) = id {
+        query.and_where(Expr::col(UserIden::Id).eq(id));
+    }
+    let (sql, values) = query.build_sqlx(SqliteQueryBuilder);
+    Ok(
+        sqlx::query_as_with::<Sqlite, (i64, i64, i64), _>(AssertSqlSafe(sql), values)
+            .fetch_all(executor)
+            .await?,
+    )
+}
+
 #[cfg(test)]
 mod local_tests {
     use std::path::Path;
@@ -319,4 +399,59 @@ mod local_tests {
             assert_ne!(fetched_user.screen_name, original_name);
         }
     }
+
+    #[tokio::test]
+    async fn test_save_same_user_keeps_search_index() {
+        let db = setup_db().await;
+        let mut user = create_test_users().await.remove(0);
+        let mut conn = db.acquire().await.unwrap();
+        save_user(&mut *conn, &user).await.unwrap();
+        sqlx::query("INSERT INTO posts (id, uid, text) VALUES (1, ?, 'a'), (2, ?, 'b')")
+            .bind(user.id)
+            .bind(user.id)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+
+        // Only the upsert itself counts, no trigger touches posts_fts
+        let total_changes = "SELECT total_changes()";
+        let before: i64 = sqlx::query_scalar(total_changes)
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        save_user(&mut *conn, &user).await.unwrap();
+        let after: i64 = sqlx::query_scalar(total_changes)
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(after - before, 1);
+
+        user.screen_name = format!("{}_renamed", user.screen_name);
+        save_user(&mut *conn, &user).await.unwrap();
+        let indexed: Vec<String> = sqlx::query_scalar("SELECT screen_name FROM posts_fts")
+            .fetch_all(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(indexed, vec![user.screen_name.clone(), user.screen_name]);
+    }
+
+    #[tokio::test]
+    async fn test_save_statuses_count() {
+        let db = setup_db().await;
+        let users = create_test_users().await;
+        let user = &users[0];
+        save_user(&db, user).await.unwrap();
+        assert!(get_statuses_counts(&db, None).await.unwrap().is_empty());
+
+        save_statuses_count(&db, user.id, 503, 1_700_000_000)
+            .await
+            .unwrap();
+        // saving the user again keeps the count
+        save_user(&db, user).await.unwrap();
+        assert_eq!(
+            get_statuses_counts(&db, Some(user.id)).await.unwrap(),
+            [(user.id, 503, 1_700_000_000)]
+        );
+        assert!(get_statuses_counts(&db, Some(1)).await.unwrap().is_empty());
+    }
 }
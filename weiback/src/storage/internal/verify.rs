@@ -0,0 +1,392 @@
+//! This module provides the queries behind the integrity check of an archive.
+//!
+//! The queries read the `posts`, `picture` and `video` tables and the `posts_fts`
+//! full-text index; see their own modules for the columns. Matching the rows against
+//! the files on disk is left to [`crate::verify`] and [`crate::gc`].
+
+use sea_query::{Expr, ExprTrait, Iden, Order, Query, SqliteQueryBuilder};
+use sea_query_sqlx::SqlxBinder;
+use sqlx::{AssertSqlSafe, Executor, Sqlite};
+
+use crate::error::Result;
+use crate::verify::MediaFile;
+
+#[derive(Iden)]
+#[iden = "picture"]
+enum PictureIden {
+    Table,
+    Id,
+    Path,
+    PostId,
+    Url,
+}
+
+#[derive(Iden)]
+#[iden = "video"]
+enum VideoIden {
+    Table,
+    Path,
+    PostId,
+    Url,
+}
+
+/// Lists the pictures recorded with a file.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+///
+/// # Returns
+///
+/// A `Result` containing each picture with its ID, ordered by URL.
+pub async fn get_picture_files<'e, E>(executor: E) -> Result<Vec<(MediaFile, Option<String>)>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let (sql, values) = Query::select()
+        .columns([
+            PictureIden::Url,
+            PictureIden::Path,
+            PictureIden::PostId,
+            PictureIden::Id,
+        ])
+        .from(PictureIden::Table)
+        .and_where(Expr::col(PictureIden::Path).is_not_null())
+        .order_by(PictureIden::Url, Order::Asc)
+        .build_sqlx(SqliteQueryBuilder);
+    let rows = sqlx::query_as_with::<Sqlite, (String, String, Option<i64>, Option<String>), _>(
+        AssertSqlSafe(sql),
+        values,
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(url, path, post_id, id)| (MediaFile { url, path, post_id }, id))
+        .collect())
+}
+
+/// Lists the videos recorded with a file.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+///
+/// # Returns
+///
+/// A `Result` containing the videos, ordered by URL.
+pub async fn get_video_files<'e, E>(executor: E) -> Result<Vec<MediaFile>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let (sql, values) = Query::select()
+        .columns([VideoIden::Url, VideoIden::Path, VideoIden::PostId])
+        .from(VideoIden::Table)
+        .and_where(Expr::col(VideoIden::Path).is_not_null())
+        .order_by(VideoIden::Url, Order::Asc)
+        .build_sqlx(SqliteQueryBuilder);
+    let rows =
+        sqlx::query_as_with::<Sqlite, (String, String, Option<i64>), _>(AssertSqlSafe(sql), values)
+            .fetch_all(executor)
+            .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(url, path, post_id)| MediaFile { url, path, post_id })
+        .collect())
+}
+
+/// Lists the pictures with a file attached to posts that are no longer stored, unless
+/// a stored post still lists them in its `pic_ids`.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+///
+/// # Returns
+///
+/// A `Result` containing the pictures, ordered by URL.
+pub async fn get_orphan_picture_files<'e, E>(executor: E) -> Result<Vec<MediaFile>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let (sql, values) = Query::select()
+        .columns([PictureIden::Url, PictureIden::Path, PictureIden::PostId])
+        .from(PictureIden::Table)
+        .and_where(Expr::col(PictureIden::Path).is_not_null())
+        .and_where(Expr::col(PictureIden::PostId).is_not_null())
+        .and_where(Expr::cust(
+            "NOT EXISTS (SELECT 1 FROM posts WHERE posts.id = picture.post_id)",
+        ))
+        .and_where(Expr::cust(
+            "(picture.id IS NULL OR NOT EXISTS (\
+             SELECT 1 FROM posts, json_each(posts.pic_ids) AS pic \
+             WHERE json_valid(posts.pic_ids) AND pic.value = picture.id))",
+        ))
+        .order_by(PictureIden::Url, Order::Asc)
+        .build_sqlx(SqliteQueryBuilder);
+    let rows =
+        sqlx::query_as_with::<Sqlite, (String, String, Option<i64>), _>(AssertSqlSafe(sql), values)
+            .fetch_all(executor)
+            .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(url, path, post_id)| MediaFile { url, path, post_id })
+        .collect())
+}
+
+/// Lists the videos with a file attached to posts that are no longer stored.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+///
+/// # Returns
+///
+/// A `Result` containing the videos, ordered by URL.
+pub async fn get_orphan_video_files<'e, E>(executor: E) -> Result<Vec<MediaFile>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let (sql, values) = Query::select()
+        .columns([VideoIden::Url, VideoIden::Path, VideoIden::PostId])
+        .from(VideoIden::Table)
+        .and_where(Expr::col(VideoIden::Path).is_not_null())
+        .and_where(Expr::col(VideoIden::PostId).is_not_null())
+        .and_where(Expr::cust(
+            "NOT EXISTS (SELECT 1 FROM posts WHERE posts.id = video.post_id)",
+        ))
+        .order_by(VideoIden::Url, Order::Asc)
+        .build_sqlx(SqliteQueryBuilder);
+    let rows =
+        sqlx::query_as_with::<Sqlite, (String, String, Option<i64>), _>(AssertSqlSafe(sql), values)
+            .fetch_all(executor)
+            .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(url, path, post_id)| MediaFile { url, path, post_id })
+        .collect())
+}
+
+/// Lists the picture IDs in the `pic_ids` of every post.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+///
+/// # Returns
+///
+/// A `Result` containing `(post_id, pic_id)` pairs, ordered by post.
+pub async fn get_post_pic_ids<'e, E>(executor: E) -> Result<Vec<(i64, String)>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    Ok(sqlx::query_as::<Sqlite, (i64, String)>(
+        "SELECT posts.id, pic.value FROM posts, json_each(posts.pic_ids) AS pic \
+         WHERE json_valid(posts.pic_ids) AND pic.type = 'text' \
+         ORDER BY posts.id, pic.key",
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Lists the retweets whose retweeted post is not stored.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+///
+/// # Returns
+///
+/// A `Result` containing `(post_id, retweeted_id)` pairs, ordered by post.
+pub async fn get_dangling_retweets<'e, E>(executor: E) -> Result<Vec<(i64, i64)>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    Ok(sqlx::query_as::<Sqlite, (i64, i64)>(
+        "SELECT p.id, p.retweeted_id FROM posts AS p \
+         WHERE p.retweeted_id IS NOT NULL \
+         AND NOT EXISTS (SELECT 1 FROM posts AS r WHERE r.id = p.retweeted_id) \
+         ORDER BY p.id",
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Lists the posts without an entry in the full-text index.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+pub async fn get_fts_missing<'e, E>(executor: E) -> Result<Vec<i64>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    Ok(sqlx::query_scalar::<Sqlite, i64>(
+        "SELECT id FROM posts WHERE id NOT IN (SELECT rowid FROM posts_fts) ORDER BY id",
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Lists the entries of the full-text index without a post.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+pub async fn get_fts_stale<'e, E>(executor: E) -> Result<Vec<i64>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    Ok(sqlx::query_scalar::<Sqlite, i64>(
+        "SELECT rowid FROM posts_fts WHERE rowid NOT IN (SELECT id FROM posts) ORDER BY rowid",
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Lists the entries of the full-text index whose text or screen name no longer
+/// matches the post, as computed by the `posts_fts_source` view.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+pub async fn get_fts_outdated<'e, E>(executor: E) -> Result<Vec<i64>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    Ok(sqlx::query_scalar::<Sqlite, i64>(
+        "SELECT s.id FROM posts_fts AS f JOIN posts_fts_source AS s ON s.id = f.rowid \
+         WHERE f.text IS NOT s.text OR f.screen_name IS NOT s.screen_name \
+         ORDER BY s.id",
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+#[cfg(test)]
+mod local_tests {
+    use sqlx::SqlitePool;
+
+    use super::*;
+    use crate::storage::database::create_db_pool_with_url;
+
+    async fn setup_db() -> SqlitePool {
+        create_db_pool_with_url(":memory:").await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_verify_queries() {
+        let db = setup_db().await;
+        sqlx::query(
+            "INSERT INTO posts (id, uid, text, pic_ids, retweeted_id) VALUES \
+             (1, 1, 'a', '[\"p1\",\"p2\"]', NULL), \
+             (2, 1, 'b', NULL, 1), \
+             (3, 1, 'c', '[]', 9)",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO picture (id, url, path, post_id) VALUES \
+             ('p1', 'https://wx1.sinaimg.cn/large/p1.jpg', 'wx1.sinaimg.cn/large/p1.jpg', 1), \
+             ('p2', 'https://wx1.sinaimg.cn/large/p2.jpg', NULL, 1)",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO video (url, path, post_id) VALUES ('https://v/1.mp4', 'v/1.mp4', 3)",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        let pictures = get_picture_files(&db).await.unwrap();
+        assert_eq!(pictures.len(), 1);
+        assert_eq!(pictures[0].0.path, "wx1.sinaimg.cn/large/p1.jpg");
+        assert_eq!(pictures[0].1.as_deref(), Some("p1"));
+        let videos = get_video_files(&db).await.unwrap();
+        assert_eq!(videos[0].post_id, Some(3));
+
+        assert_eq!(
+            get_post_pic_ids(&db).await.unwrap(),
+            [(1, "p1".to_string()), (1, "p2".to_string())]
+        );
+        assert_eq!(get_dangling_retweets(&db).await.unwrap(), [(3, 9)]);
+
+        assert!(get_orphan_picture_files(&db).await.unwrap().is_empty());
+        sqlx::query(
+            "INSERT INTO picture (id, url, path, post_id) VALUES \
+             ('p9', 'https://wx1.sinaimg.cn/large/p9.jpg', 'wx1.sinaimg.cn/large/p9.jpg', 9)",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        sqlx::query("DELETE FROM posts WHERE id = 3")
+            .execute(&db)
+            .await
+            .unwrap();
+        let orphans = get_orphan_picture_files(&db).await.unwrap();
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].post_id, Some(9));
+        let orphans = get_orphan_video_files(&db).await.unwrap();
+        assert_eq!(orphans[0].url, "https://v/1.mp4");
+        sqlx::query("INSERT INTO posts (id, uid, text, retweeted_id) VALUES (3, 1, 'c', 9)")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        assert!(get_fts_missing(&db).await.unwrap().is_empty());
+        assert!(get_fts_stale(&db).await.unwrap().is_empty());
+        assert!(get_fts_outdated(&db).await.unwrap().is_empty());
+
+        sqlx::query("DELETE FROM posts_fts WHERE rowid = 1")
+            .execute(&db)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO posts_fts (rowid, text) VALUES (7, 'x')")
+            .execute(&db)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE posts_fts SET text = 'old' WHERE rowid = 2")
+            .execute(&db)
+            .await
+            .unwrap();
+        assert_eq!(get_fts_missing(&db).await.unwrap(), [1]);
+        assert_eq!(get_fts_stale(&db).await.unwrap(), [7]);
+        assert_eq!(get_fts_outdated(&db).await.unwrap(), [2]);
+    }
+
+    #[tokio::test]
+    async fn test_orphan_media_files_skip_referenced() {
+        let db = setup_db().await;
+        sqlx::query("INSERT INTO posts (id, uid, text, pic_ids) VALUES (1, 1, 'a', '[\"p1\"]')")
+            .execute(&db)
+            .await
+            .unwrap();
+        // p1 was saved with a post that is gone, but post 1 still shows it
+        sqlx::query(
+            "INSERT INTO picture (id, url, path, post_id) VALUES \
+             ('p1', 'https://wx1.sinaimg.cn/large/p1.jpg', 'wx1.sinaimg.cn/large/p1.jpg', 8), \
+             ('p8', 'https://wx1.sinaimg.cn/large/p8.jpg', 'wx1.sinaimg.cn/large/p8.jpg', 8)",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO video (url, path, post_id) VALUES \
+             ('https://v/free.mp4', 'v/free.mp4', NULL), \
+             ('https://v/8.mp4', 'v/8.mp4', 8)",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        let orphans = get_orphan_picture_files(&db).await.unwrap();
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].url, "https://wx1.sinaimg.cn/large/p8.jpg");
+        let orphans = get_orphan_video_files(&db).await.unwrap();
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].url, "https://v/8.mp4");
+    }
+}
@@ -122,6 +122,33 @@ where
     Ok(())
 }
 
+/// Deletes the video entries with the given URLs from the database.
+///
+/// # Arguments
+///
+/// * `executor` - A database executor.
+/// * `urls` - A slice of video URLs.
+///
+/// # Returns
+///
+/// A `Result` containing the number of deleted entries.
+pub async fn delete_videos_by_urls<'e, E>(executor: E, urls: &[String]) -> Result<u64>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    if urls.is_empty() {
+        return Ok(0);
+    }
+    let (sql, values) = Query::delete()
+        .from_table(VideoIden::Table)
+        .and_where(Expr::col(VideoIden::Url).is_in(urls.iter().cloned()))
+        .build_sqlx(SqliteQueryBuilder);
+    let result = sqlx::query_with(AssertSqlSafe(sql), values)
+        .execute(executor)
+        .await?;
+    Ok(result.rows_affected())
+}
+
 /// Saves video metadata into the database.
 ///
 /// If a video with the same URL already exists, its metadata will be updated.
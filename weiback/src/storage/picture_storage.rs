@@ -30,6 +30,8 @@ impl FileSystemPictureStorage {
 impl FileSystemPictureStorage {
     /// Retrieves the binary content (blob) of a picture from the file system.
     ///
+    /// Falls back to a copy downloaded from another `sinaimg.cn` mirror.
+    ///
     /// # Arguments
     ///
     /// * `picture_path` - The base directory where pictures are stored.
@@ -48,7 +50,7 @@ impl FileSystemPictureStorage {
     where
         E: Executor<'e, Database = Sqlite>,
     {
-        let Some(relative_path) = picture::get_picture_path(executor, url).await? else {
+        let Some(relative_path) = picture::find_picture_path(executor, url).await? else {
             return Ok(None);
         };
         let path = picture_path.join(&relative_path);
@@ -119,6 +121,8 @@ impl FileSystemPictureStorage {
 
     /// Checks if a picture is saved (both in the database and on the file system).
     ///
+    /// A copy downloaded from another `sinaimg.cn` mirror counts as saved.
+    ///
     /// # Arguments
     ///
     /// * `picture_path` - The base directory where pictures are stored.
@@ -137,7 +141,7 @@ impl FileSystemPictureStorage {
     where
         E: Executor<'e, Database = Sqlite>,
     {
-        let Some(relative_path) = picture::get_picture_path(executor, url).await? else {
+        let Some(relative_path) = picture::find_picture_path(executor, url).await? else {
             return Ok(false);
         };
         let absolute_path = picture_path.join(relative_path);
@@ -376,6 +380,31 @@ mod local_tests {
         assert!(!saved); // Should return false because file doesn't exist
     }
 
+    #[tokio::test]
+    async fn test_picture_saved_across_mirrors() {
+        let temp_dir = tempdir().unwrap();
+        let storage = FileSystemPictureStorage;
+        let picture = create_test_picture("https://wx1.sinaimg.cn/large/mirror.jpg");
+
+        let db = setup_db().await;
+        storage
+            .save_picture(temp_dir.path(), &db, &picture)
+            .await
+            .unwrap();
+
+        let mirror = Url::parse("https://wx3.sinaimg.cn/large/mirror.jpg").unwrap();
+        let saved = storage
+            .picture_saved(temp_dir.path(), &db, &mirror)
+            .await
+            .unwrap();
+        assert!(saved);
+        let blob = storage
+            .get_picture_blob(temp_dir.path(), &db, &mirror)
+            .await
+            .unwrap();
+        assert_eq!(blob, Some(picture.blob));
+    }
+
     #[tokio::test]
     async fn test_delete_picture() {
         let temp_dir = tempdir().unwrap();
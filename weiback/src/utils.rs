@@ -6,10 +6,11 @@
 //! - Extracting complex metadata (like pictures, avatars, and emojis) from `Post` objects.
 
 use std::collections::{HashMap, HashSet};
-use std::path::Path;
+use std::path::{Component, Path};
 
 use once_cell::sync::Lazy;
 use regex::Regex;
+use sha2::{Digest, Sha256};
 use tracing::error;
 use url::Url;
 
@@ -60,6 +61,9 @@ pub static TOPIC_EXPR: Lazy<Regex> = Lazy::new(|| {
 });
 
 /// Normalizes a URL to be used as a stable database key by removing query and fragment parts.
+///
+/// The host is kept as-is, so the same picture fetched from different `sinaimg.cn` mirrors
+/// produces different keys; use [`is_same_picture_url`] to compare pictures across mirrors.
 pub fn pic_url_to_db_key(url: &Url) -> Url {
     let mut url = url.to_owned();
     url.set_fragment(None);
@@ -67,6 +71,42 @@ pub fn pic_url_to_db_key(url: &Url) -> Url {
     url
 }
 
+/// Returns `true` if the URL is served by one of Weibo's `sinaimg.cn` image mirrors.
+pub fn is_sinaimg_url(url: &Url) -> bool {
+    url.host_str()
+        .is_some_and(|host| host == "sinaimg.cn" || host.ends_with(".sinaimg.cn"))
+}
+
+/// Builds a host-agnostic `(pic_id, definition)` key for a picture served by `sinaimg.cn`.
+///
+/// Weibo serves the same file from `wx1`, `wx3`, `tvax1`... hosts, with the definition
+/// as the first path segment, e.g. `https://wx1.sinaimg.cn/large/<pic_id>.jpg`.
+/// Returns `None` for other hosts or unrecognized paths.
+pub fn sinaimg_url_to_pic_key(url: &Url) -> Option<(String, String)> {
+    if !is_sinaimg_url(url) {
+        return None;
+    }
+    let mut segments = url.path_segments()?.filter(|s| !s.is_empty());
+    let definition = segments.next()?.to_string();
+    segments.next()?;
+    let id = pic_url_to_id(url).ok()?;
+    Some((id, definition))
+}
+
+/// Checks whether two picture URLs refer to the same picture.
+///
+/// Query and fragment parts are ignored, and pictures on `sinaimg.cn` mirrors are
+/// compared by their `(pic_id, definition)` key only.
+pub fn is_same_picture_url(a: &Url, b: &Url) -> bool {
+    if pic_url_to_db_key(a) == pic_url_to_db_key(b) {
+        return true;
+    }
+    match (sinaimg_url_to_pic_key(a), sinaimg_url_to_pic_key(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
 /// Converts a picture URL into a file path string for local storage.
 /// e.g., `http://example.com/path/to/file.jpg` -> `example.com/path/to/file.jpg`
 pub fn pic_url_to_path_str(url: &Url) -> String {
@@ -145,6 +185,24 @@ pub fn make_page_name(task_name: &str, index: i32) -> String {
     format!("{task_name}-{index}")
 }
 
+/// The SHA-256 checksum of some data, as lowercase hex.
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex_string(&Sha256::digest(data))
+}
+
+/// Formats bytes as lowercase hex.
+pub fn hex_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Checks that a path read from another archive stays inside the directory it is joined
+/// to: it must be relative and made of plain names only, without `..`, a root or a
+/// drive prefix.
+pub fn is_safe_relative_path(path: &Path) -> bool {
+    let mut components = path.components().peekable();
+    components.peek().is_some() && components.all(|c| matches!(c, Component::Normal(_)))
+}
+
 /// Extracts all unique picture metadata (standalone, emoji, avatar, inline) from a slice of posts.
 ///
 /// # Arguments
@@ -177,6 +235,44 @@ pub fn extract_all_pic_metas(
     pic_metas
 }
 
+/// Builds the definition fallback chain of every attached picture in a slice of posts.
+///
+/// The map is keyed by the picture URL at `definition`. Each value holds the same picture
+/// at each definition of [`PictureDefinition::fallback_chain`], best first, starting with
+/// the key itself. Definitions sharing the same URL appear only once.
+///
+/// # Arguments
+/// * `posts` - The posts to process.
+/// * `definition` - The preferred picture quality.
+pub fn extract_pic_fallback_metas(
+    posts: &[Post],
+    definition: PictureDefinition,
+) -> HashMap<Url, Vec<PictureMeta>> {
+    let mut chains: HashMap<(i64, String), Vec<PictureMeta>> = HashMap::new();
+    for def in definition.fallback_chain() {
+        for post in posts {
+            let metas =
+                extract_standalone_pic_metas(post, def).chain(extract_inline_pic_metas(post, def));
+            for meta in metas {
+                let PictureMeta::Attached { post_id, .. } = meta else {
+                    continue;
+                };
+                let Ok(id) = pic_url_to_id(meta.url()) else {
+                    continue;
+                };
+                let chain = chains.entry((post_id, id)).or_default();
+                if !chain.iter().any(|m| m.url() == meta.url()) {
+                    chain.push(meta);
+                }
+            }
+        }
+    }
+    chains
+        .into_values()
+        .map(|chain| (chain[0].url().clone(), chain))
+        .collect()
+}
+
 /// Extracts picture metadata for images that are referenced via short URLs inside post text.
 fn extract_inline_pic_metas(
     post: &Post,
@@ -230,6 +326,52 @@ pub fn extract_emojis_from_text(text: &str) -> impl Iterator<Item = &str> {
     EMOJI_EXPR.find_iter(text).map(|m| m.as_str())
 }
 
+/// Extracts the screen names `@`-mentioned in a text, without the `@`, in order of first
+/// appearance and without duplicates. The domains of email addresses are not mentions.
+pub fn extract_mentions(text: &str) -> Vec<&str> {
+    let emails = EMAIL_EXPR
+        .find_iter(text)
+        .map(|m| m.range())
+        .collect::<Vec<_>>();
+    let mut seen = HashSet::new();
+    AT_EXPR
+        .find_iter(text)
+        .filter(|m| !emails.iter().any(|email| email.contains(&m.start())))
+        .map(|m| &m.as_str()[1..])
+        .filter(|name| seen.insert(*name))
+        .collect()
+}
+
+/// Extracts the topics (e.g. `#话题#`) of a text, without the `#`s, in order of first
+/// appearance and without duplicates.
+pub fn extract_topics(text: &str) -> Vec<&str> {
+    let mut seen = HashSet::new();
+    TOPIC_EXPR
+        .captures_iter(text)
+        .filter_map(|c| c.get(1))
+        .map(|m| m.as_str().trim())
+        .filter(|topic| !topic.is_empty() && !topic.contains('\n'))
+        .filter(|topic| seen.insert(*topic))
+        .collect()
+}
+
+/// Returns `true` if the URL is a Weibo short link, e.g. `http://t.cn/A6xyz`.
+pub fn is_short_url(url: &Url) -> bool {
+    url.host_str() == Some("t.cn")
+}
+
+/// Extracts the short links (see [`is_short_url`]) in a text, as matched by [`URL_EXPR`],
+/// in order of first appearance and without duplicates.
+pub fn extract_short_urls(text: &str) -> Vec<&str> {
+    let mut seen = HashSet::new();
+    URL_EXPR
+        .find_iter(text)
+        .map(|m| m.as_str())
+        .filter(|url| Url::parse(url).is_ok_and(|url| is_short_url(&url)))
+        .filter(|url| seen.insert(*url))
+        .collect()
+}
+
 /// Extracts avatar URLs for the post author and the author of the retweeted, if present.
 fn extract_avatar_metas(post: &Post) -> impl Iterator<Item = PictureMeta> + '_ {
     let current_user_iter = post
@@ -484,6 +626,47 @@ mod local_tests {
         assert!(pic_url_to_filename(&Url::parse("http://example.com").unwrap()).is_err());
     }
 
+    #[test]
+    fn test_is_safe_relative_path() {
+        assert!(is_safe_relative_path(Path::new(
+            "wx1.sinaimg.cn/large/p1.jpg"
+        )));
+        assert!(!is_safe_relative_path(Path::new("./video/1.mp4")));
+        assert!(!is_safe_relative_path(Path::new("")));
+        assert!(!is_safe_relative_path(Path::new("/etc/passwd")));
+        assert!(!is_safe_relative_path(Path::new("a/../../b.jpg")));
+        assert!(!is_safe_relative_path(Path::new("..")));
+    }
+
+    #[test]
+    fn test_extract_mentions() {
+        assert_eq!(
+            extract_mentions("@张三 你好，@李四_li 和 @张三 ：联系 someone@example.com"),
+            ["张三", "李四_li"]
+        );
+        assert!(extract_mentions("没有提及").is_empty());
+    }
+
+    #[test]
+    fn test_extract_short_urls() {
+        let text = "看 http://t.cn/A6abc 和 https://weibo.com/1 还有 http://t.cn/A6abc \
+                    以及 https://t.cn/R2xyz";
+        assert_eq!(
+            extract_short_urls(text),
+            ["http://t.cn/A6abc", "https://t.cn/R2xyz"]
+        );
+        assert!(extract_short_urls("https://not.t.cn.example.com/x").is_empty());
+    }
+
+    #[test]
+    fn test_extract_topics() {
+        assert_eq!(
+            extract_topics("#春节# 快乐 # 旅行 # #春节# #跨\n行#"),
+            ["春节", "旅行"]
+        );
+        assert!(extract_topics("##").is_empty());
+    }
+
     #[test]
     fn test_pic_url_to_id() {
         assert_eq!(
@@ -516,6 +699,44 @@ mod local_tests {
         );
     }
 
+    #[test]
+    fn test_sinaimg_url_to_pic_key() {
+        assert_eq!(
+            sinaimg_url_to_pic_key(&Url::parse("https://wx1.sinaimg.cn/large/abc123.jpg").unwrap()),
+            Some(("abc123".to_string(), "large".to_string()))
+        );
+        assert_eq!(
+            sinaimg_url_to_pic_key(
+                &Url::parse("https://tvax3.sinaimg.cn/crop.0.0.1080.1080.180/abc.jpg?KID=x")
+                    .unwrap()
+            ),
+            Some(("abc".to_string(), "crop.0.0.1080.1080.180".to_string()))
+        );
+        assert!(
+            sinaimg_url_to_pic_key(&Url::parse("https://example.com/large/abc.jpg").unwrap())
+                .is_none()
+        );
+        assert!(
+            sinaimg_url_to_pic_key(&Url::parse("https://wx1.sinaimg.cn/abc.jpg").unwrap())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_is_same_picture_url() {
+        let wx1 = Url::parse("https://wx1.sinaimg.cn/large/abc.jpg").unwrap();
+        let wx3 = Url::parse("https://wx3.sinaimg.cn/large/abc.jpg?a=1").unwrap();
+        let other_def = Url::parse("https://wx3.sinaimg.cn/orj360/abc.jpg").unwrap();
+        let other_host = Url::parse("https://example.com/large/abc.jpg").unwrap();
+        assert!(is_same_picture_url(&wx1, &wx3));
+        assert!(!is_same_picture_url(&wx1, &other_def));
+        assert!(!is_same_picture_url(&wx1, &other_host));
+        assert!(is_same_picture_url(
+            &other_host,
+            &Url::parse("https://example.com/large/abc.jpg#f").unwrap()
+        ));
+    }
+
     fn create_mock_api(client: &MockClient) -> MockApi {
         let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
         client
@@ -601,4 +822,28 @@ mod local_tests {
         assert!(has_avatar, "Should extract user avatars");
         assert!(has_emoji, "Should extract emoji pictures");
     }
+
+    #[tokio::test]
+    async fn test_extract_pic_fallback_metas() {
+        let client = MockClient::new();
+        let api = create_mock_api(&client);
+        let posts = create_posts(&api).await;
+
+        let chains = extract_pic_fallback_metas(&posts, PictureDefinition::Largest);
+        assert!(!chains.is_empty());
+        for (url, chain) in chains {
+            assert_eq!(chain[0].url(), &url);
+            let id = pic_url_to_id(&url).unwrap();
+            let mut last = PictureDefinition::RealOriginal;
+            for meta in chain {
+                let PictureMeta::Attached { definition, .. } = meta else {
+                    panic!("fallback chain should only hold attached pictures");
+                };
+                assert!(definition <= PictureDefinition::Largest);
+                assert!(definition < last);
+                assert_eq!(pic_url_to_id(meta.url()).unwrap(), id);
+                last = definition;
+            }
+        }
+    }
 }
@@ -0,0 +1,289 @@
+//! This module defines the integrity report of an archive, as produced by the
+//! "verify archive" task.
+//!
+//! A [`VerifyReport`] lists where the database and the media directories disagree:
+//! - `picture`/`video` rows whose file is missing on disk,
+//! - files under the media directories that no row points to,
+//! - posts whose `pic_ids` have no stored picture,
+//! - retweets whose `retweeted_id` is not in `posts`,
+//! - `posts_fts` entries out of sync with `posts`.
+//!
+//! It is assembled by [`Storage::verify_archive`](crate::storage::Storage::verify_archive).
+//! The helpers here do the matching between rows and files, independently of the database.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use walkdir::WalkDir;
+
+use crate::error::{Error, Result};
+
+/// The integrity report of an archive.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VerifyReport {
+    /// When the check was run, as a Unix timestamp.
+    pub checked_at: i64,
+    /// Picture rows whose file is missing.
+    pub missing_picture_files: Vec<MediaFile>,
+    /// Video rows whose file is missing.
+    pub missing_video_files: Vec<MediaFile>,
+    /// Files under the picture directory without a row, relative to it.
+    pub orphan_picture_files: Vec<String>,
+    /// Files under the video directory without a row, relative to it.
+    pub orphan_video_files: Vec<String>,
+    /// Posts with pictures in `pic_ids` that are not stored.
+    pub posts_missing_pictures: Vec<PostMissingPictures>,
+    /// Retweets of posts that are not stored.
+    pub dangling_retweets: Vec<DanglingRetweet>,
+    /// Posts without a search index entry.
+    pub fts_missing: Vec<i64>,
+    /// Search index entries without a post.
+    pub fts_stale: Vec<i64>,
+    /// Search index entries whose text or screen name differs from the post.
+    pub fts_outdated: Vec<i64>,
+    /// What was repaired, if repairing was asked for.
+    pub repair: Option<RepairSummary>,
+}
+
+/// A media file recorded in the database.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MediaFile {
+    pub url: String,
+    /// The path of the file, relative to the media directory.
+    pub path: String,
+    pub post_id: Option<i64>,
+}
+
+/// A post with pictures that are not stored.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PostMissingPictures {
+    pub post_id: i64,
+    pub pic_ids: Vec<String>,
+}
+
+/// A retweet of a post that is not stored.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DanglingRetweet {
+    pub post_id: i64,
+    pub retweeted_id: i64,
+}
+
+/// What a repair did.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepairSummary {
+    /// The quarantine batch the removed rows were moved to, if any were removed.
+    pub batch: Option<String>,
+    /// Picture and video rows moved to quarantine because their file was missing.
+    pub removed_media_rows: u64,
+    /// Whether the search index was rebuilt.
+    pub search_index_rebuilt: bool,
+    /// Posts re-backed up to fetch missing pictures or retweeted posts.
+    pub rebacked_up_posts: u64,
+    /// Posts that failed to be re-backed up.
+    pub failed_posts: u64,
+}
+
+impl VerifyReport {
+    /// The number of problems found.
+    pub fn issue_count(&self) -> usize {
+        self.missing_picture_files.len()
+            + self.missing_video_files.len()
+            + self.orphan_picture_files.len()
+            + self.orphan_video_files.len()
+            + self.posts_missing_pictures.len()
+            + self.dangling_retweets.len()
+            + self.fts_missing.len()
+            + self.fts_stale.len()
+            + self.fts_outdated.len()
+    }
+
+    /// Checks whether no problem was found.
+    pub fn is_clean(&self) -> bool {
+        self.issue_count() == 0
+    }
+
+    /// Checks whether the search index has to be rebuilt.
+    pub fn fts_out_of_sync(&self) -> bool {
+        !(self.fts_missing.is_empty() && self.fts_stale.is_empty() && self.fts_outdated.is_empty())
+    }
+
+    /// The posts to re-back up to fetch their missing pictures or retweeted posts,
+    /// ordered and without duplicates.
+    pub fn posts_to_rebackup(&self) -> Vec<i64> {
+        self.posts_missing_pictures
+            .iter()
+            .map(|p| p.post_id)
+            .chain(self.dangling_retweets.iter().map(|r| r.post_id))
+            .sorted()
+            .dedup()
+            .collect()
+    }
+}
+
+/// Lists the files under a directory, relative to it.
+///
+/// A missing directory has no files. Entries that cannot be read are skipped.
+///
+/// # Arguments
+/// * `dir` - The directory to walk.
+pub async fn list_files(dir: &Path) -> Result<HashSet<PathBuf>> {
+    let dir = dir.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        if !dir.exists() {
+            return HashSet::new();
+        }
+        WalkDir::new(&dir)
+            .into_iter()
+            .filter_map(|entry| {
+                entry
+                    .inspect_err(|e| warn!("walk {dir:?} failed: {e}"))
+                    .ok()
+            })
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| entry.path().strip_prefix(&dir).ok().map(Path::to_path_buf))
+            .collect()
+    })
+    .await
+    .map_err(|e| Error::Io(std::io::Error::other(e)))
+}
+
+/// Matches media rows against the files on disk.
+///
+/// # Arguments
+/// * `rows` - The media files recorded in the database.
+/// * `files` - The files on disk, relative to the media directory. Files referenced by
+///   a row are removed, leaving the orphans.
+///
+/// # Returns
+/// The rows whose file is missing.
+pub fn match_files(rows: Vec<MediaFile>, files: &mut HashSet<PathBuf>) -> Vec<MediaFile> {
+    // Several rows may share a file, e.g. a picture saved from two mirrors
+    let mut referenced = HashSet::new();
+    let mut missing = Vec::new();
+    for row in rows {
+        let path = PathBuf::from(&row.path);
+        if files.remove(&path) {
+            referenced.insert(path);
+        } else if !referenced.contains(&path) {
+            missing.push(row);
+        }
+    }
+    missing
+}
+
+/// Finds the pictures of posts that are not stored.
+///
+/// # Arguments
+/// * `post_pics` - `(post_id, pic_id)` pairs from the `pic_ids` of posts, ordered by post.
+/// * `stored` - The IDs of the pictures with a file on disk.
+pub fn find_posts_missing_pictures(
+    post_pics: Vec<(i64, String)>,
+    stored: &HashSet<String>,
+) -> Vec<PostMissingPictures> {
+    post_pics
+        .into_iter()
+        .filter(|(_, pic_id)| !stored.contains(pic_id))
+        .chunk_by(|(post_id, _)| *post_id)
+        .into_iter()
+        .map(|(post_id, pics)| PostMissingPictures {
+            post_id,
+            pic_ids: pics.map(|(_, pic_id)| pic_id).collect(),
+        })
+        .collect()
+}
+
+/// Formats a relative path the way paths are stored in the database.
+pub fn path_to_string(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .join("/")
+}
+
+#[cfg(test)]
+mod local_tests {
+    use super::*;
+
+    fn media(url: &str, path: &str) -> MediaFile {
+        MediaFile {
+            url: url.to_string(),
+            path: path.to_string(),
+            post_id: Some(1),
+        }
+    }
+
+    #[test]
+    fn test_match_files() {
+        let mut files = ["a/1.jpg", "a/2.jpg", "b/3.jpg"]
+            .into_iter()
+            .map(PathBuf::from)
+            .collect::<HashSet<_>>();
+        let rows = vec![
+            media("https://wx1.sinaimg.cn/a/1.jpg", "a/1.jpg"),
+            media("https://wx3.sinaimg.cn/a/1.jpg", "a/1.jpg"),
+            media("https://wx1.sinaimg.cn/c/4.jpg", "c/4.jpg"),
+        ];
+        let missing = match_files(rows, &mut files);
+        assert_eq!(
+            missing,
+            [media("https://wx1.sinaimg.cn/c/4.jpg", "c/4.jpg")]
+        );
+        let mut orphans = files.iter().map(|p| path_to_string(p)).collect::<Vec<_>>();
+        orphans.sort();
+        assert_eq!(orphans, ["a/2.jpg", "b/3.jpg"]);
+    }
+
+    #[test]
+    fn test_find_posts_missing_pictures() {
+        let stored = HashSet::from(["p1".to_string(), "p3".to_string()]);
+        let post_pics = vec![
+            (1, "p1".to_string()),
+            (1, "p2".to_string()),
+            (2, "p3".to_string()),
+            (3, "p4".to_string()),
+            (3, "p5".to_string()),
+        ];
+        assert_eq!(
+            find_posts_missing_pictures(post_pics, &stored),
+            [
+                PostMissingPictures {
+                    post_id: 1,
+                    pic_ids: vec!["p2".to_string()],
+                },
+                PostMissingPictures {
+                    post_id: 3,
+                    pic_ids: vec!["p4".to_string(), "p5".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_posts_to_rebackup() {
+        let report = VerifyReport {
+            posts_missing_pictures: vec![PostMissingPictures {
+                post_id: 3,
+                pic_ids: vec!["p".to_string()],
+            }],
+            dangling_retweets: vec![
+                DanglingRetweet {
+                    post_id: 3,
+                    retweeted_id: 1,
+                },
+                DanglingRetweet {
+                    post_id: 2,
+                    retweeted_id: 1,
+                },
+            ],
+            fts_stale: vec![9],
+            ..Default::default()
+        };
+        assert_eq!(report.posts_to_rebackup(), [2, 3]);
+        assert_eq!(report.issue_count(), 4);
+        assert!(report.fts_out_of_sync());
+        assert!(!report.is_clean());
+        assert!(VerifyReport::default().is_clean());
+    }
+}